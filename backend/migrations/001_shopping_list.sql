@@ -0,0 +1,13 @@
+-- Items the user needs to buy, either added by hand or from the missing ingredients of a recipe.
+CREATE TABLE IF NOT EXISTS sf_shopping_item (
+    item_id VARCHAR(36) PRIMARY KEY,
+    user_id VARCHAR(36) NOT NULL REFERENCES sf_user(user_id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    category TEXT NOT NULL,
+    quantity REAL NOT NULL,
+    unit TEXT NOT NULL,
+    checked BOOLEAN NOT NULL DEFAULT FALSE,
+    recipe_ids TEXT[] NOT NULL DEFAULT '{}'
+);
+
+CREATE INDEX IF NOT EXISTS sf_shopping_item_user_id_idx ON sf_shopping_item (user_id);
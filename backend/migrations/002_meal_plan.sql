@@ -0,0 +1,16 @@
+-- Copies of the recipes returned by the WorldWide Recipes API, so they can be referenced by id.
+CREATE TABLE IF NOT EXISTS sf_recipe (
+    recipe_id TEXT PRIMARY KEY,
+    recipe JSONB NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Recipes the user plans to cook on a date, one per meal slot.
+CREATE TABLE IF NOT EXISTS sf_meal_plan (
+    plan_id VARCHAR(36) PRIMARY KEY,
+    user_id VARCHAR(36) NOT NULL REFERENCES sf_user(user_id) ON DELETE CASCADE,
+    recipe_id TEXT NOT NULL REFERENCES sf_recipe(recipe_id),
+    date DATE NOT NULL,
+    slot TEXT NOT NULL,
+    UNIQUE (user_id, date, slot)
+);
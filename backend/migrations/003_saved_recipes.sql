@@ -0,0 +1,10 @@
+-- Recipes the user saved, with a copy of the recipe so it survives changes of the upstream feed.
+CREATE TABLE IF NOT EXISTS sf_saved_recipe (
+    user_id VARCHAR(36) NOT NULL REFERENCES sf_user(user_id) ON DELETE CASCADE,
+    recipe_id TEXT NOT NULL,
+    recipe JSONB NOT NULL,
+    notes TEXT,
+    tags TEXT[] NOT NULL DEFAULT '{}',
+    saved_date TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, recipe_id)
+);
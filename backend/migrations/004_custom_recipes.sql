@@ -0,0 +1,12 @@
+-- Recipes written by the users themselves.
+CREATE TABLE IF NOT EXISTS sf_custom_recipe (
+    recipe_id TEXT PRIMARY KEY,
+    user_id VARCHAR(36) NOT NULL REFERENCES sf_user(user_id) ON DELETE CASCADE,
+    title TEXT NOT NULL,
+    image_url TEXT,
+    tags TEXT[] NOT NULL DEFAULT '{}',
+    ingredients JSONB NOT NULL,
+    steps TEXT[] NOT NULL DEFAULT '{}',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
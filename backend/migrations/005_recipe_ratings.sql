@@ -0,0 +1,19 @@
+-- Every time a user cooked a recipe.
+CREATE TABLE IF NOT EXISTS sf_cook_history (
+    cook_id VARCHAR(36) PRIMARY KEY,
+    user_id VARCHAR(36) NOT NULL REFERENCES sf_user(user_id) ON DELETE CASCADE,
+    recipe_id TEXT NOT NULL,
+    cooked_date TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS sf_cook_history_user_recipe ON sf_cook_history (user_id, recipe_id);
+
+-- The rating each user gave to a recipe, a new rating replaces the previous one.
+CREATE TABLE IF NOT EXISTS sf_recipe_rating (
+    user_id VARCHAR(36) NOT NULL REFERENCES sf_user(user_id) ON DELETE CASCADE,
+    recipe_id TEXT NOT NULL,
+    rating SMALLINT NOT NULL CHECK (rating BETWEEN 1 AND 5),
+    comment TEXT,
+    rated_date TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, recipe_id)
+);
@@ -0,0 +1,3 @@
+-- Diets the user follows and ingredients they don't want in their recipes.
+ALTER TABLE sf_settings ADD COLUMN IF NOT EXISTS diets TEXT[] NOT NULL DEFAULT '{}';
+ALTER TABLE sf_settings ADD COLUMN IF NOT EXISTS excluded_ingredients TEXT[] NOT NULL DEFAULT '{}';
@@ -0,0 +1,7 @@
+-- Settings added on version 2 of the settings schema, rows written by older clients keep the defaults.
+ALTER TABLE sf_settings ADD COLUMN IF NOT EXISTS schema_version INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE sf_settings ADD COLUMN IF NOT EXISTS language TEXT NOT NULL DEFAULT 'en';
+ALTER TABLE sf_settings ADD COLUMN IF NOT EXISTS unit_system TEXT NOT NULL DEFAULT 'Metric';
+ALTER TABLE sf_settings ADD COLUMN IF NOT EXISTS expiry_warning_days INTEGER NOT NULL DEFAULT 3;
+ALTER TABLE sf_settings ADD COLUMN IF NOT EXISTS notifications JSONB NOT NULL DEFAULT '{}';
+ALTER TABLE sf_settings ADD COLUMN IF NOT EXISTS default_servings INTEGER NOT NULL DEFAULT 2;
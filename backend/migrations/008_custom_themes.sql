@@ -0,0 +1,15 @@
+-- Color themes defined by the users, they can be copied by other users with the share code.
+CREATE TABLE IF NOT EXISTS sf_theme (
+    theme_id VARCHAR(36) PRIMARY KEY,
+    user_id VARCHAR(36) NOT NULL REFERENCES sf_user(user_id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    primary_color CHAR(7) NOT NULL,
+    secondary_color CHAR(7) NOT NULL,
+    background_color CHAR(7) NOT NULL,
+    surface_color CHAR(7) NOT NULL,
+    share_code TEXT NOT NULL UNIQUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- The custom theme selected when the theme is `Custom`.
+ALTER TABLE sf_settings ADD COLUMN IF NOT EXISTS custom_theme_id VARCHAR(36) REFERENCES sf_theme(theme_id) ON DELETE SET NULL;
@@ -0,0 +1,41 @@
+-- Households own the ingredients of a fridge, users can be members of several of them
+-- and the ingredient routes work on the one they have selected.
+CREATE TABLE IF NOT EXISTS sf_household (
+    household_id VARCHAR(64) PRIMARY KEY,
+    name TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS sf_household_member (
+    household_id VARCHAR(64) NOT NULL REFERENCES sf_household(household_id) ON DELETE CASCADE,
+    user_id VARCHAR(64) NOT NULL REFERENCES sf_user(user_id) ON DELETE CASCADE,
+    role TEXT NOT NULL,
+    joined_date TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (household_id, user_id)
+);
+
+-- Codes to join a household, they can be used until they expire.
+CREATE TABLE IF NOT EXISTS sf_household_invitation (
+    code TEXT PRIMARY KEY,
+    household_id VARCHAR(64) NOT NULL REFERENCES sf_household(household_id) ON DELETE CASCADE,
+    role TEXT NOT NULL,
+    created_by VARCHAR(64) NOT NULL REFERENCES sf_user(user_id) ON DELETE CASCADE,
+    expire_date TIMESTAMPTZ NOT NULL
+);
+
+ALTER TABLE sf_user ADD COLUMN IF NOT EXISTS current_household_id VARCHAR(64) REFERENCES sf_household(household_id);
+ALTER TABLE sf_ingredient ADD COLUMN IF NOT EXISTS household_id VARCHAR(64) REFERENCES sf_household(household_id) ON DELETE CASCADE;
+
+-- Every existing user gets a household of their own with their ingredients,
+-- it uses the id of the user since it's unique too.
+INSERT INTO sf_household (household_id, name)
+    SELECT user_id, username || '''s household' FROM sf_user
+    ON CONFLICT DO NOTHING;
+INSERT INTO sf_household_member (household_id, user_id, role)
+    SELECT user_id, user_id, 'Owner' FROM sf_user
+    ON CONFLICT DO NOTHING;
+UPDATE sf_user SET current_household_id=user_id WHERE current_household_id IS NULL;
+UPDATE sf_ingredient SET household_id=user_id WHERE household_id IS NULL;
+ALTER TABLE sf_ingredient ALTER COLUMN household_id SET NOT NULL;
+
+CREATE INDEX IF NOT EXISTS sf_ingredient_household ON sf_ingredient (household_id);
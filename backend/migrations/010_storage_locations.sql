@@ -0,0 +1,25 @@
+-- Places of a household where ingredients are stored, every household has a
+-- default fridge, freezer and pantry and members can add more.
+CREATE TABLE IF NOT EXISTS sf_storage_location (
+    location_id VARCHAR(64) PRIMARY KEY,
+    household_id VARCHAR(64) NOT NULL REFERENCES sf_household(household_id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    kind TEXT NOT NULL,
+    is_default BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+CREATE INDEX IF NOT EXISTS sf_storage_location_household_id_idx ON sf_storage_location (household_id);
+CREATE UNIQUE INDEX IF NOT EXISTS sf_storage_location_default_idx ON sf_storage_location (household_id, kind) WHERE is_default;
+
+ALTER TABLE sf_ingredient ADD COLUMN IF NOT EXISTS location_id VARCHAR(64) REFERENCES sf_storage_location(location_id);
+
+INSERT INTO sf_storage_location (location_id, household_id, name, kind, is_default)
+    SELECT gen_random_uuid()::TEXT, h.household_id, k.kind, k.kind, TRUE
+    FROM sf_household h CROSS JOIN (VALUES ('Fridge'), ('Freezer'), ('Pantry')) AS k(kind)
+    ON CONFLICT DO NOTHING;
+
+-- Until now everything was in the fridge.
+UPDATE sf_ingredient i SET location_id=l.location_id
+    FROM sf_storage_location l
+    WHERE l.household_id=i.household_id AND l.kind='Fridge' AND l.is_default AND i.location_id IS NULL;
+ALTER TABLE sf_ingredient ALTER COLUMN location_id SET NOT NULL;
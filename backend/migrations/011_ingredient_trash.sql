@@ -0,0 +1,4 @@
+-- Removed ingredients stay in the trash until they are restored or purged.
+ALTER TABLE sf_ingredient ADD COLUMN IF NOT EXISTS deleted_date TIMESTAMPTZ;
+
+CREATE INDEX IF NOT EXISTS sf_ingredient_deleted_date_idx ON sf_ingredient (deleted_date) WHERE deleted_date IS NOT NULL;
@@ -0,0 +1,18 @@
+-- Append-only log of what happened to the ingredients of a household.
+-- Ingredients aren't referenced so the history outlives them when the trash is purged.
+CREATE TABLE IF NOT EXISTS sf_inventory_event (
+    event_id VARCHAR(64) PRIMARY KEY DEFAULT gen_random_uuid()::TEXT,
+    household_id VARCHAR(64) NOT NULL REFERENCES sf_household(household_id) ON DELETE CASCADE,
+    user_id VARCHAR(64) NOT NULL,
+    ingredient_id VARCHAR(64) NOT NULL,
+    name TEXT NOT NULL,
+    category TEXT NOT NULL,
+    quantity REAL NOT NULL,
+    unit TEXT NOT NULL,
+    event_type TEXT NOT NULL,
+    reason TEXT,
+    event_date TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS sf_inventory_event_household_id_idx ON sf_inventory_event (household_id, event_date);
+CREATE INDEX IF NOT EXISTS sf_inventory_event_ingredient_id_idx ON sf_inventory_event (ingredient_id);
@@ -0,0 +1,2 @@
+-- The expire date the ingredient had, used to tell if removals without a reason were expired.
+ALTER TABLE sf_inventory_event ADD COLUMN IF NOT EXISTS expire_date TIMESTAMPTZ;
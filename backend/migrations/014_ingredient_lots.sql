@@ -0,0 +1,12 @@
+-- Batches of an ingredient bought at different times, each one with its own expire date.
+-- The ingredient keeps the total quantity and the earliest expire date of its lots.
+CREATE TABLE IF NOT EXISTS sf_ingredient_lot (
+    lot_id VARCHAR(64) PRIMARY KEY DEFAULT gen_random_uuid()::TEXT,
+    ingredient_id VARCHAR(64) NOT NULL REFERENCES sf_ingredient(ingredient_id) ON DELETE CASCADE,
+    quantity REAL NOT NULL,
+    expire_date TIMESTAMPTZ NOT NULL,
+    -- Unknown for the batch an ingredient had before its first merge.
+    added_date TIMESTAMPTZ DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS sf_ingredient_lot_ingredient_id_idx ON sf_ingredient_lot (ingredient_id, expire_date);
@@ -0,0 +1,4 @@
+-- Every ingredient has at least one lot from now on.
+INSERT INTO sf_ingredient_lot (ingredient_id, quantity, expire_date, added_date)
+    SELECT i.ingredient_id, i.quantity, i.expire_date, NULL FROM sf_ingredient i
+    WHERE NOT EXISTS (SELECT 1 FROM sf_ingredient_lot l WHERE l.ingredient_id=i.ingredient_id);
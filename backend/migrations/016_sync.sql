@@ -0,0 +1,14 @@
+-- When each row last changed, so offline clients can download only what changed since their last sync.
+ALTER TABLE sf_ingredient ADD COLUMN IF NOT EXISTS updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+ALTER TABLE sf_settings ADD COLUMN IF NOT EXISTS updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+
+CREATE INDEX IF NOT EXISTS sf_ingredient_updated_at_idx ON sf_ingredient (household_id, updated_at);
+
+-- Ingredients purged from the trash, trashed ones are still rows with a `deleted_date`.
+CREATE TABLE IF NOT EXISTS sf_ingredient_tombstone (
+    ingredient_id VARCHAR(64) PRIMARY KEY,
+    household_id VARCHAR(64) NOT NULL REFERENCES sf_household(household_id) ON DELETE CASCADE,
+    deleted_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS sf_ingredient_tombstone_household_id_idx ON sf_ingredient_tombstone (household_id, deleted_at);
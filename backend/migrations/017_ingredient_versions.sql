@@ -0,0 +1,2 @@
+-- Incremented on every change of an ingredient, edits and removals must send the version they saw.
+ALTER TABLE sf_ingredient ADD COLUMN IF NOT EXISTS version INTEGER NOT NULL DEFAULT 1;
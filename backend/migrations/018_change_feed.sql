@@ -0,0 +1,32 @@
+-- Notifies the servers listening on `sf_change` of every change to the ingredients and settings,
+-- so they can push it to the connected clients. Every ingredient change logs an inventory event.
+CREATE OR REPLACE FUNCTION sf_notify_inventory_event() RETURNS TRIGGER AS $$
+BEGIN
+    PERFORM pg_notify('sf_change', json_build_object(
+        'Kind', 'Ingredient',
+        'HouseholdId', NEW.household_id,
+        'UserId', NEW.user_id,
+        'IngredientId', NEW.ingredient_id,
+        'EventType', NEW.event_type
+    )::TEXT);
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS sf_inventory_event_notify ON sf_inventory_event;
+CREATE TRIGGER sf_inventory_event_notify AFTER INSERT ON sf_inventory_event
+    FOR EACH ROW EXECUTE FUNCTION sf_notify_inventory_event();
+
+CREATE OR REPLACE FUNCTION sf_notify_settings() RETURNS TRIGGER AS $$
+BEGIN
+    PERFORM pg_notify('sf_change', json_build_object(
+        'Kind', 'Settings',
+        'UserId', NEW.user_id
+    )::TEXT);
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS sf_settings_notify ON sf_settings;
+CREATE TRIGGER sf_settings_notify AFTER UPDATE ON sf_settings
+    FOR EACH ROW EXECUTE FUNCTION sf_notify_settings();
@@ -0,0 +1,12 @@
+-- Responses of the requests sent with an `Idempotency-Key`, replayed when the request is retried.
+-- The status is NULL while the first request is still running.
+CREATE TABLE IF NOT EXISTS sf_idempotency_key (
+    idempotency_key TEXT PRIMARY KEY,
+    request_hash TEXT NOT NULL,
+    status SMALLINT,
+    content_type TEXT,
+    body BYTEA,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS sf_idempotency_key_created_at_idx ON sf_idempotency_key (created_at);
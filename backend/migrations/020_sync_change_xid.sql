@@ -0,0 +1,28 @@
+-- The id of the transaction that last changed each row, so syncs can ask for what changed since the
+-- transactions that were still running on their last sync. Unlike `updated_at`, it can't miss the changes
+-- of a transaction that started before the last sync and committed after it.
+ALTER TABLE sf_ingredient ADD COLUMN IF NOT EXISTS change_xid BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE sf_settings ADD COLUMN IF NOT EXISTS change_xid BIGINT NOT NULL DEFAULT 0;
+ALTER TABLE sf_ingredient_tombstone ADD COLUMN IF NOT EXISTS change_xid BIGINT NOT NULL DEFAULT 0;
+
+CREATE INDEX IF NOT EXISTS sf_ingredient_change_xid_idx ON sf_ingredient (household_id, change_xid);
+CREATE INDEX IF NOT EXISTS sf_ingredient_tombstone_change_xid_idx ON sf_ingredient_tombstone (household_id, change_xid);
+
+CREATE OR REPLACE FUNCTION sf_set_change_xid() RETURNS TRIGGER AS $$
+BEGIN
+    NEW.change_xid := pg_current_xact_id()::TEXT::BIGINT;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS sf_ingredient_change_xid ON sf_ingredient;
+CREATE TRIGGER sf_ingredient_change_xid BEFORE INSERT OR UPDATE ON sf_ingredient
+    FOR EACH ROW EXECUTE FUNCTION sf_set_change_xid();
+
+DROP TRIGGER IF EXISTS sf_settings_change_xid ON sf_settings;
+CREATE TRIGGER sf_settings_change_xid BEFORE INSERT OR UPDATE ON sf_settings
+    FOR EACH ROW EXECUTE FUNCTION sf_set_change_xid();
+
+DROP TRIGGER IF EXISTS sf_ingredient_tombstone_change_xid ON sf_ingredient_tombstone;
+CREATE TRIGGER sf_ingredient_tombstone_change_xid BEFORE INSERT OR UPDATE ON sf_ingredient_tombstone
+    FOR EACH ROW EXECUTE FUNCTION sf_set_change_xid();
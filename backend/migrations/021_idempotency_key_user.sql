@@ -0,0 +1,6 @@
+-- Keys are scoped to the user that sent them, so the keys of different users never collide.
+-- The stored responses are short lived, so they are dropped instead of guessing their user.
+DELETE FROM sf_idempotency_key;
+ALTER TABLE sf_idempotency_key ADD COLUMN IF NOT EXISTS user_id TEXT NOT NULL;
+ALTER TABLE sf_idempotency_key DROP CONSTRAINT IF EXISTS sf_idempotency_key_pkey;
+ALTER TABLE sf_idempotency_key ADD PRIMARY KEY (user_id, idempotency_key);
@@ -0,0 +1,136 @@
+//! Helpers of the routes that change several ingredients at once in a single transaction.
+
+use std::{
+    ops::{Deref, DerefMut},
+    sync::{Arc, Mutex},
+};
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio_postgres::Client;
+
+/// The most ingredients a single bulk request can change.
+pub const MAX_BULK_INGREDIENTS: usize = 100;
+
+/// Connections of their own for the routes that run a transaction.
+///
+/// The shared client is used by every request at the same time,
+/// so a transaction on it would include the statements of other requests.
+/// At most `size` connections are open, when all of them are in use the requests wait for one.
+pub struct TransactionPool {
+    db_connection: String,
+    size: usize,
+    idle: Mutex<Vec<Client>>,
+    permits: Arc<Semaphore>,
+}
+
+impl TransactionPool {
+    pub fn new(db_connection: String, size: usize) -> Self {
+        let size = size.max(1);
+        TransactionPool {
+            db_connection,
+            size,
+            idle: Mutex::new(vec![]),
+            permits: Arc::new(Semaphore::new(size)),
+        }
+    }
+
+    /// Takes an idle connection, or opens one if there's none and the pool isn't full.
+    pub async fn get(self: &Arc<Self>) -> Result<PooledClient, tokio_postgres::Error> {
+        let permit = self
+            .permits
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("The semaphore of the pool is never closed");
+
+        let idle = {
+            let mut idle = self.idle.lock().unwrap_or_else(|err| err.into_inner());
+            // Connections the DB closed while idle are dropped.
+            std::iter::from_fn(|| idle.pop()).find(|client| !client.is_closed())
+        };
+        let client = match idle {
+            Some(client) => client,
+            None => connect(&self.db_connection).await?,
+        };
+
+        Ok(PooledClient {
+            client: Some(client),
+            pool: self.clone(),
+            _permit: permit,
+        })
+    }
+
+    /// Returns how many connections are in use and how many can be.
+    pub fn usage(&self) -> (usize, usize) {
+        (self.size - self.permits.available_permits(), self.size)
+    }
+}
+
+async fn connect(db_connection: &str) -> Result<Client, tokio_postgres::Error> {
+    let (client, connection) =
+        tokio_postgres::connect(db_connection, tokio_postgres::NoTls).await?;
+
+    tokio::spawn(async move {
+        if let Err(err) = connection.await {
+            tracing::error!("An error `{:?}` occurred in a transaction connection!", err);
+        }
+    });
+
+    Ok(client)
+}
+
+/// A connection taken from the `TransactionPool`, it goes back to the pool when dropped.
+///
+/// A transaction that wasn't committed is rolled back before the connection is used again.
+pub struct PooledClient {
+    client: Option<Client>,
+    pool: Arc<TransactionPool>,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl Deref for PooledClient {
+    type Target = Client;
+
+    fn deref(&self) -> &Client {
+        self.client
+            .as_ref()
+            .expect("The client is only taken when dropped")
+    }
+}
+
+impl DerefMut for PooledClient {
+    fn deref_mut(&mut self) -> &mut Client {
+        self.client
+            .as_mut()
+            .expect("The client is only taken when dropped")
+    }
+}
+
+impl Drop for PooledClient {
+    fn drop(&mut self) {
+        if let Some(client) = self.client.take().filter(|client| !client.is_closed()) {
+            self.pool
+                .idle
+                .lock()
+                .unwrap_or_else(|err| err.into_inner())
+                .push(client);
+        }
+    }
+}
+
+/// Returns the name of the first field of an ingredient that can't be stored.
+pub fn invalid_ingredient_field(name: &str, quantity: f32, unit: &str) -> Option<&'static str> {
+    if name.trim().is_empty() {
+        return Some("Name");
+    }
+
+    if !quantity.is_finite() || quantity < 0.0 {
+        return Some("Quantity");
+    }
+
+    if unit.trim().is_empty() {
+        return Some("Unit");
+    }
+
+    None
+}
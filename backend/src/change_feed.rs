@@ -0,0 +1,161 @@
+//! Pushes the changes of the ingredients and settings to the connected clients.
+//!
+//! The DB notifies every change on a channel, see the `018_change_feed` migration,
+//! and a single connection listens to it and broadcasts the changes to the clients.
+
+use std::{future::Future, sync::Arc, time::Duration};
+
+use futures_util::{Stream, StreamExt};
+use tokio::{
+    sync::broadcast::{self, error::RecvError},
+    time::{Instant, MissedTickBehavior},
+};
+use tokio_postgres::AsyncMessage;
+
+use crate::models::ChangeNotification;
+
+/// The channel the DB notifies the changes on.
+const CHANGE_CHANNEL: &str = "sf_change";
+
+/// How many changes are kept for the clients that are behind, older ones are dropped.
+const CHANGE_FEED_CAPACITY: usize = 256;
+
+/// How often a connected client is checked to still have access to the changes.
+const ACCESS_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How long to wait before listening again after the connection is lost.
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Broadcasts the changes notified by the DB to every connected client.
+pub struct ChangeFeed {
+    sender: broadcast::Sender<ChangeNotification>,
+}
+
+/// Represents what is sent to a connected client.
+#[derive(Debug)]
+pub enum ChangeFeedItem {
+    Change(ChangeNotification),
+    /// The client was behind and this many changes were dropped.
+    Lagged(u64),
+}
+
+impl ChangeFeed {
+    /// Streams the changes made from now on to the ingredients of the household and the settings of the user.
+    ///
+    /// The stream ends at `until` or when `has_access`, checked every [`ACCESS_CHECK_INTERVAL`], is false.
+    pub fn subscribe<F, Fut>(
+        &self,
+        household_id: String,
+        user_id: String,
+        until: Instant,
+        has_access: F,
+    ) -> impl Stream<Item = ChangeFeedItem>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = bool>,
+    {
+        let receiver = self.sender.subscribe();
+        let mut access_checks = tokio::time::interval_at(
+            Instant::now() + ACCESS_CHECK_INTERVAL,
+            ACCESS_CHECK_INTERVAL,
+        );
+        access_checks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        futures_util::stream::unfold(
+            (receiver, access_checks, has_access),
+            move |(mut receiver, mut access_checks, has_access)| {
+                let household_id = household_id.clone();
+                let user_id = user_id.clone();
+                async move {
+                    loop {
+                        let change = tokio::select! {
+                            _ = tokio::time::sleep_until(until) => return None,
+                            _ = access_checks.tick() => {
+                                if !has_access().await {
+                                    return None;
+                                }
+                                continue;
+                            }
+                            change = receiver.recv() => change,
+                        };
+
+                        let item = match change {
+                            Ok(change) if change.is_visible_to(&household_id, &user_id) => {
+                                ChangeFeedItem::Change(change)
+                            }
+                            Ok(_) => continue,
+                            Err(RecvError::Lagged(missed)) => ChangeFeedItem::Lagged(missed),
+                            Err(RecvError::Closed) => return None,
+                        };
+                        return Some((item, (receiver, access_checks, has_access)));
+                    }
+                }
+            },
+        )
+    }
+}
+
+/// Spawns a task that listens to the changes on a connection of its own, reconnecting when it's lost.
+pub fn spawn_change_listener(db_connection: String) -> Arc<ChangeFeed> {
+    let (sender, _) = broadcast::channel(CHANGE_FEED_CAPACITY);
+    let feed = Arc::new(ChangeFeed {
+        sender: sender.clone(),
+    });
+
+    tokio::spawn(async move {
+        loop {
+            if let Err(err) = listen_changes(&db_connection, &sender).await {
+                tracing::error!(
+                    "Change feed: An error `{:?}` occurred while listening to changes!",
+                    err
+                );
+            }
+            tokio::time::sleep(RECONNECT_DELAY).await;
+        }
+    });
+
+    feed
+}
+
+/// Listens to the changes until the connection is lost.
+async fn listen_changes(
+    db_connection: &str,
+    sender: &broadcast::Sender<ChangeNotification>,
+) -> Result<(), tokio_postgres::Error> {
+    let (client, mut connection) =
+        tokio_postgres::connect(db_connection, tokio_postgres::NoTls).await?;
+
+    // The notifications come from the connection, so it's polled here instead of being spawned.
+    let sender = sender.clone();
+    let messages = tokio::spawn(async move {
+        let mut messages = futures_util::stream::poll_fn(move |cx| connection.poll_message(cx));
+        while let Some(message) = messages.next().await {
+            let AsyncMessage::Notification(notification) = message? else {
+                continue;
+            };
+
+            match serde_json::from_str::<ChangeNotification>(notification.payload()) {
+                // It only fails when no client is connected.
+                Ok(change) => _ = sender.send(change),
+                Err(err) => tracing::error!(
+                    "Change feed: An error `{:?}` occurred while parsing the change `{}`!",
+                    err,
+                    notification.payload()
+                ),
+            }
+        }
+        Ok::<_, tokio_postgres::Error>(())
+    });
+
+    client
+        .batch_execute(&format!("LISTEN {}", CHANGE_CHANNEL))
+        .await?;
+    tracing::debug!("Change feed: Listening to changes!");
+
+    match messages.await {
+        Ok(result) => result,
+        Err(err) => {
+            tracing::error!("Change feed: The listener task failed with `{:?}`!", err);
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,148 @@
+//! Recipes written by the users themselves.
+//!
+//! They're returned with the same shape as the ones of the WorldWide Recipes API,
+//! their ids start with [CUSTOM_RECIPE_ID_PREFIX] and their source is empty.
+
+use serde::{Deserialize, Serialize};
+use tokio_postgres::Client;
+
+use utoipa::ToSchema;
+
+use crate::{
+    ingredient_parser::normalize_unit,
+    inventory::{missing_ingredients, RequiredIngredient},
+    metrics::timed_query,
+    models::{Ingredient, Recipe, RecipeIngredient},
+    parse_db_custom_recipe,
+};
+
+pub const CUSTOM_RECIPE_ID_PREFIX: &str = "custom:";
+
+/// Represents an ingredient line of a recipe as the user writes it.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CustomRecipeIngredient {
+    pub name: String,
+    pub quantity: Option<f32>,
+    /// The upper bound of the quantity when the user gives a range.
+    pub quantity_max: Option<f32>,
+    pub unit: Option<String>,
+    pub notes: Option<String>,
+}
+
+impl From<CustomRecipeIngredient> for RecipeIngredient {
+    /// Known units are stored with their canonical name and a display line is built,
+    /// like "2-3 cup flour, sifted".
+    fn from(value: CustomRecipeIngredient) -> Self {
+        let unit = value
+            .unit
+            .map(|u| u.trim().to_string())
+            .filter(|u| !u.is_empty())
+            .map(|u| normalize_unit(&u).map(str::to_string).unwrap_or(u));
+
+        let mut display = vec![];
+        match (value.quantity, value.quantity_max) {
+            (Some(quantity), Some(max)) => display.push(format!("{}-{}", quantity, max)),
+            (Some(quantity), None) => display.push(quantity.to_string()),
+            _ => {}
+        }
+        display.extend(unit.clone());
+        display.push(value.name.trim().to_string());
+        let mut display = display.join(" ");
+        if let Some(notes) = &value.notes {
+            display = format!("{}, {}", display, notes);
+        }
+
+        RecipeIngredient {
+            name: value.name.trim().to_string(),
+            display,
+            quantity: value.quantity,
+            quantity_max: value.quantity_max,
+            unit,
+            notes: value.notes,
+        }
+    }
+}
+
+/// Creates the recipe with the fields the user wrote.
+///
+/// Empty steps are dropped, a missing image leaves the banner empty.
+pub fn build_custom_recipe(
+    recipe_id: String,
+    title: String,
+    image_url: Option<String>,
+    tags: Vec<String>,
+    ingredients: Vec<CustomRecipeIngredient>,
+    steps: Vec<String>,
+) -> Recipe {
+    Recipe {
+        recipe_id,
+        title: title.trim().to_string(),
+        banner: image_url.unwrap_or_default(),
+        tags,
+        ingredients: ingredients
+            .into_iter()
+            .map(RecipeIngredient::from)
+            .collect(),
+        source: String::new(),
+        steps: steps
+            .into_iter()
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect(),
+        user_rating: None,
+        last_cooked_date: None,
+        dietary_violations: vec![],
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug)]
+pub enum GetCustomRecipesErrors {
+    InternalDBError(tokio_postgres::Error),
+    InvalidCustomRecipeFormatFromDB,
+}
+
+/// Gets the recipes written by the user.
+///
+/// When a query is given only the recipes with it in their title, tags or ingredients are returned.
+pub async fn find_custom_recipes(
+    conn: &Client,
+    user_id: &str,
+    query: Option<&str>,
+    tracing_prefix: &str,
+) -> Result<Vec<Recipe>, GetCustomRecipesErrors> {
+    let pattern = query.map(|q| {
+        format!(
+            "%{}%",
+            q.replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_")
+        )
+    });
+    timed_query(
+        "find_custom_recipes",
+        conn.query(
+            "SELECT * FROM sf_custom_recipe WHERE user_id=$1 AND ($2::TEXT IS NULL OR title ILIKE $2 OR array_to_string(tags, ' ') ILIKE $2 OR ingredients::TEXT ILIKE $2) ORDER BY updated_at DESC",
+            &[&user_id, &pattern],
+        ),
+    )
+    .await
+    .map_err(GetCustomRecipesErrors::InternalDBError)?
+    .iter()
+    .map(|row| parse_db_custom_recipe(row, tracing_prefix))
+    .collect::<Option<Vec<Recipe>>>()
+    .ok_or(GetCustomRecipesErrors::InvalidCustomRecipeFormatFromDB)
+}
+
+/// Sorts the recipes so the ones the fridge has more ingredients for come first.
+pub fn sort_by_fridge_match(recipes: &mut [Recipe], fridge: &[Ingredient]) {
+    recipes.sort_by_cached_key(|recipe| {
+        let required: Vec<RequiredIngredient> = recipe
+            .ingredients
+            .iter()
+            .map(RequiredIngredient::from)
+            .collect();
+        let missing = missing_ingredients(&required, fridge).len();
+        std::cmp::Reverse(required.len() - missing)
+    });
+}
@@ -0,0 +1,431 @@
+//! Checks of the recipe ingredients against the diets and excluded ingredients of the user.
+//!
+//! The checks only look at ingredient names, so they can't know about hidden ingredients
+//! like the wheat in a store bought sauce.
+
+use std::ops::Range;
+
+use tokio_postgres::Client;
+
+use crate::{
+    ingredient_parser::{contains_ingredient_name, normalize_ingredient_name},
+    metrics::timed_query,
+    models::{DietaryPreference, DietaryViolation, Recipe},
+};
+
+const MEAT: &[&str] = &[
+    "beef",
+    "pork",
+    "chicken",
+    "turkey",
+    "lamb",
+    "mutton",
+    "veal",
+    "duck",
+    "goose",
+    "venison",
+    "bacon",
+    "ham",
+    "sausage",
+    "chorizo",
+    "salami",
+    "pepperoni",
+    "prosciutto",
+    "pancetta",
+    "meat",
+    "steak",
+    "mince",
+    "gelatin",
+    "lard",
+    "bone broth",
+];
+
+const SEAFOOD: &[&str] = &[
+    "fish",
+    "salmon",
+    "tuna",
+    "cod",
+    "tilapia",
+    "trout",
+    "sardine",
+    "anchovy",
+    "mackerel",
+    "halibut",
+    "shrimp",
+    "prawn",
+    "crab",
+    "lobster",
+    "clam",
+    "mussel",
+    "oyster",
+    "scallop",
+    "squid",
+    "octopus",
+    "fish sauce",
+];
+
+const DAIRY: &[&str] = &[
+    "milk",
+    "cheese",
+    "butter",
+    "cream",
+    "yogurt",
+    "ghee",
+    "whey",
+    "buttermilk",
+    "parmesan",
+    "mozzarella",
+    "cheddar",
+    "ricotta",
+    "feta",
+    "mascarpone",
+    "custard",
+];
+
+const ANIMAL_PRODUCTS: &[&str] = &["egg", "honey", "mayonnaise"];
+
+const GLUTEN: &[&str] = &[
+    "wheat",
+    "flour",
+    "bread",
+    "breadcrumb",
+    "pasta",
+    "spaghetti",
+    "noodle",
+    "barley",
+    "rye",
+    "couscous",
+    "semolina",
+    "bulgur",
+    "seitan",
+    "soy sauce",
+    "beer",
+    "tortilla",
+    "cracker",
+];
+
+const NUTS: &[&str] = &[
+    "nut",
+    "almond",
+    "walnut",
+    "pecan",
+    "cashew",
+    "pistachio",
+    "hazelnut",
+    "macadamia",
+    "peanut",
+    "pine nut",
+    "praline",
+    "marzipan",
+];
+
+/// Names that contain a forbidden word but are made of plants.
+const PLANT_BASED: &[&str] = &[
+    "peanut butter",
+    "almond butter",
+    "cashew butter",
+    "cocoa butter",
+    "shea butter",
+    "coconut milk",
+    "almond milk",
+    "soy milk",
+    "oat milk",
+    "rice milk",
+    "coconut cream",
+    "cream of tartar",
+];
+
+/// Words saying the rest of the name is made of plants, like "vegan" in "vegan cheese".
+const PLANT_BASED_QUALIFIERS: &[&str] = &["vegan", "plant based", "dairy free"];
+
+/// Names that contain a forbidden word but are without gluten.
+const GLUTEN_FREE: &[&str] = &[
+    "rice flour",
+    "almond flour",
+    "coconut flour",
+    "corn flour",
+    "cornflour",
+    "rice noodle",
+    "corn tortilla",
+    "tamari",
+    "buckwheat",
+    "rice paper",
+];
+
+/// Words saying the rest of the name is without gluten.
+const GLUTEN_FREE_QUALIFIERS: &[&str] = &["gluten free"];
+
+/// Represents the words a diet forbids and the names that are allowed despite containing them.
+struct DietRules {
+    forbidden: Vec<&'static str>,
+    /// Only the words of the ingredient name covered by the allowed name are allowed,
+    /// so "coconut milk" allows the milk of "chicken in coconut milk" but not the chicken.
+    allowed: &'static [&'static str],
+    /// Allow the words after them until the end of the ingredient name.
+    qualifiers: &'static [&'static str],
+}
+
+impl DietRules {
+    fn new(diet: DietaryPreference) -> Self {
+        let (forbidden, allowed, qualifiers) = match diet {
+            DietaryPreference::Vegetarian => (
+                [MEAT, SEAFOOD].concat(),
+                PLANT_BASED,
+                PLANT_BASED_QUALIFIERS,
+            ),
+            DietaryPreference::Vegan => (
+                [MEAT, SEAFOOD, DAIRY, ANIMAL_PRODUCTS].concat(),
+                PLANT_BASED,
+                PLANT_BASED_QUALIFIERS,
+            ),
+            DietaryPreference::Pescatarian => (MEAT.to_vec(), PLANT_BASED, PLANT_BASED_QUALIFIERS),
+            DietaryPreference::GlutenFree => (GLUTEN.to_vec(), GLUTEN_FREE, GLUTEN_FREE_QUALIFIERS),
+            DietaryPreference::DairyFree => (DAIRY.to_vec(), PLANT_BASED, PLANT_BASED_QUALIFIERS),
+            DietaryPreference::NutFree => (NUTS.to_vec(), &[][..], &[][..]),
+        };
+        DietRules {
+            forbidden,
+            allowed,
+            qualifiers,
+        }
+    }
+
+    /// Checks if a forbidden word appears in the words of the ingredient name
+    /// somewhere no allowed name or qualifier covers.
+    fn forbids(&self, words: &[&str]) -> bool {
+        let allowed: Vec<Range<usize>> = self
+            .allowed
+            .iter()
+            .flat_map(|allowed| find_words(words, allowed))
+            .chain(
+                self.qualifiers
+                    .iter()
+                    .flat_map(|qualifier| find_words(words, qualifier))
+                    .map(|found| found.start..words.len()),
+            )
+            .collect();
+
+        self.forbidden
+            .iter()
+            .flat_map(|forbidden| find_words(words, forbidden))
+            .any(|found| {
+                !allowed
+                    .iter()
+                    .any(|allowed| allowed.start <= found.start && found.end <= allowed.end)
+            })
+    }
+}
+
+/// Returns where all the words of `part` appear together in the words of an ingredient name.
+fn find_words(words: &[&str], part: &str) -> Vec<Range<usize>> {
+    let part = normalize_ingredient_name(part);
+    if part.is_empty() {
+        return vec![];
+    }
+    let part: Vec<&str> = part.split(' ').collect();
+
+    words
+        .windows(part.len())
+        .enumerate()
+        .filter(|(_, window)| *window == part.as_slice())
+        .map(|(start, _)| start..start + part.len())
+        .collect()
+}
+
+/// Represents what the user doesn't want in their recipes.
+#[derive(Debug, Default)]
+pub struct DietaryRestrictions {
+    pub diets: Vec<DietaryPreference>,
+    pub excluded_ingredients: Vec<String>,
+}
+
+impl DietaryRestrictions {
+    pub fn is_empty(&self) -> bool {
+        self.diets.is_empty() && self.excluded_ingredients.is_empty()
+    }
+
+    /// Returns the ingredients of the recipe that go against the restrictions.
+    ///
+    /// Every ingredient is reported once, with the first diet it goes against.
+    pub fn violations(&self, recipe: &Recipe) -> Vec<DietaryViolation> {
+        let rules: Vec<_> = self
+            .diets
+            .iter()
+            .map(|diet| (*diet, DietRules::new(*diet)))
+            .collect();
+
+        recipe
+            .ingredients
+            .iter()
+            .filter_map(|ingredient| self.violation(&ingredient.name, &rules))
+            .collect()
+    }
+
+    fn violation(
+        &self,
+        name: &str,
+        rules: &[(DietaryPreference, DietRules)],
+    ) -> Option<DietaryViolation> {
+        if self
+            .excluded_ingredients
+            .iter()
+            .any(|excluded| contains_ingredient_name(name, excluded))
+        {
+            return Some(DietaryViolation {
+                ingredient: name.to_string(),
+                diet: None,
+            });
+        }
+
+        let normalized = normalize_ingredient_name(name);
+        let words: Vec<&str> = normalized.split(' ').collect();
+        rules
+            .iter()
+            .find(|(_, rules)| rules.forbids(&words))
+            .map(|(diet, _)| DietaryViolation {
+                ingredient: name.to_string(),
+                diet: Some(*diet),
+            })
+    }
+}
+
+/// Gets the diets and excluded ingredients from the settings of the user.
+pub async fn get_dietary_restrictions(
+    conn: &Client,
+    user_id: &str,
+    tracing_prefix: &str,
+) -> Result<DietaryRestrictions, tokio_postgres::Error> {
+    let row = timed_query(
+        "get_dietary_restrictions",
+        conn.query_opt(
+            "SELECT diets, excluded_ingredients FROM sf_settings WHERE user_id=$1",
+            &[&user_id],
+        ),
+    )
+    .await?;
+
+    let Some(row) = row else {
+        return Ok(DietaryRestrictions::default());
+    };
+
+    let diets: Vec<String> = row.try_get("diets")?;
+    let diets = diets
+        .iter()
+        .filter_map(|d| match d.parse() {
+            Ok(diet) => Some(diet),
+            Err(err) => {
+                tracing::error!(
+                    "{} An error `{:?}` occurred while parsing diet `{}`!",
+                    tracing_prefix,
+                    err,
+                    d
+                );
+                None
+            }
+        })
+        .collect();
+
+    Ok(DietaryRestrictions {
+        diets,
+        excluded_ingredients: row.try_get("excluded_ingredients")?,
+    })
+}
+
+/// Flags the ingredients of the recipes that go against the restrictions of the user.
+pub async fn flag_dietary_violations<'a>(
+    conn: &Client,
+    user_id: &str,
+    recipes: impl IntoIterator<Item = &'a mut Recipe>,
+    tracing_prefix: &str,
+) -> Result<(), tokio_postgres::Error> {
+    let restrictions = get_dietary_restrictions(conn, user_id, tracing_prefix).await?;
+    if restrictions.is_empty() {
+        return Ok(());
+    }
+
+    for recipe in recipes {
+        recipe.dietary_violations = restrictions.violations(recipe);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use DietaryPreference::*;
+
+    #[test]
+    fn flags_ingredients_against_diets() {
+        #[rustfmt::skip]
+        let cases = [
+            // Forbidden words
+            ("chicken breast", Vegetarian, true),
+            ("smoked salmon", Pescatarian, false),
+            ("smoked salmon", Vegetarian, true),
+            ("2 eggs", Vegan, true),
+            ("grated parmesan", DairyFree, true),
+            ("salted peanuts", NutFree, true),
+            ("spaghetti", GlutenFree, true),
+            ("eggplant", Vegan, false),
+            ("nutmeg", NutFree, false),
+            // Allowed names
+            ("coconut milk", Vegan, false),
+            ("peanut butter", DairyFree, false),
+            ("peanut butter", NutFree, true),
+            ("cream of tartar", Vegan, false),
+            ("rice flour", GlutenFree, false),
+            ("corn tortillas", GlutenFree, false),
+            // Allowed names only cover their own words
+            ("chicken in coconut milk", Vegan, true),
+            ("chicken in coconut milk", Pescatarian, true),
+            ("wheat bread with rice flour", GlutenFree, true),
+            ("rice flour and butter", DairyFree, true),
+            ("almond milk and cream", Vegan, true),
+            ("coconut milk and almond milk", Vegan, false),
+            // Qualifiers cover the rest of the name
+            ("vegan cream cheese", Vegan, false),
+            ("dairy free butter", DairyFree, false),
+            ("gluten free bread", GlutenFree, false),
+            ("gluten free soy sauce", GlutenFree, false),
+            ("chicken with vegan cheese", Vegan, true),
+            ("wheat bread, gluten free", GlutenFree, true),
+        ];
+
+        for (name, diet, expected) in cases {
+            let restrictions = DietaryRestrictions {
+                diets: vec![diet],
+                excluded_ingredients: vec![],
+            };
+            let rules = [(diet, DietRules::new(diet))];
+            assert_eq!(
+                restrictions.violation(name, &rules).is_some(),
+                expected,
+                "`{}` for {:?}",
+                name,
+                diet
+            );
+        }
+    }
+
+    #[test]
+    fn flags_excluded_ingredients() {
+        let restrictions = DietaryRestrictions {
+            diets: vec![],
+            excluded_ingredients: vec!["peanut".to_string(), "Bell Peppers".to_string()],
+        };
+        let cases = [
+            ("salted peanuts", true),
+            ("peanut butter", true),
+            ("red bell pepper", true),
+            ("peanutty sauce", false),
+            ("black pepper", false),
+        ];
+
+        for (name, expected) in cases {
+            let violation = restrictions.violation(name, &[]);
+            assert_eq!(violation.is_some(), expected, "`{}`", name);
+            assert!(violation.is_none_or(|v| v.diet.is_none()), "`{}`", name);
+        }
+    }
+}
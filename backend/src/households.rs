@@ -0,0 +1,100 @@
+//! Households share the ingredients of a fridge between their members.
+
+use std::str::FromStr;
+
+use tokio_postgres::Client;
+use uuid::Uuid;
+
+use crate::metrics::timed_query;
+use crate::models::{HouseholdRole, StorageKind};
+
+/// Represents the household a user is working on and what they can do in it.
+#[derive(Debug)]
+pub struct HouseholdMembership {
+    pub household_id: String,
+    pub role: HouseholdRole,
+}
+
+#[allow(dead_code)]
+#[derive(Debug)]
+pub enum GetCurrentHouseholdErrors {
+    InternalDBError(tokio_postgres::Error),
+    NoHouseholdSelected,
+    InvalidRoleFromDB(String),
+}
+
+/// Gets the household the user selected and their role in it.
+pub async fn get_current_household(
+    conn: &Client,
+    user_id: &str,
+) -> Result<HouseholdMembership, GetCurrentHouseholdErrors> {
+    let row = timed_query(
+        "get_current_household",
+        conn.query_opt(
+            "SELECT m.household_id, m.role FROM sf_user u JOIN sf_household_member m ON m.household_id=u.current_household_id AND m.user_id=u.user_id WHERE u.user_id=$1",
+            &[&user_id],
+        ),
+    )
+    .await
+    .map_err(GetCurrentHouseholdErrors::InternalDBError)?
+    .ok_or(GetCurrentHouseholdErrors::NoHouseholdSelected)?;
+
+    let role: String = row.get("role");
+    Ok(HouseholdMembership {
+        household_id: row.get("household_id"),
+        role: HouseholdRole::from_str(&role)
+            .map_err(|_| GetCurrentHouseholdErrors::InvalidRoleFromDB(role))?,
+    })
+}
+
+/// Creates a household owned by the user with the default storage locations and selects it.
+pub async fn create_household(
+    conn: &Client,
+    user_id: &str,
+    name: &str,
+) -> Result<String, tokio_postgres::Error> {
+    let household_id = Uuid::new_v4().to_string();
+    timed_query(
+        "create_household",
+        conn.execute(
+            "WITH household AS (INSERT INTO sf_household (household_id, name) VALUES ($1, $2)), owner AS (INSERT INTO sf_household_member (household_id, user_id, role) VALUES ($1, $3, $4)), locations AS (INSERT INTO sf_storage_location (location_id, household_id, name, kind, is_default) SELECT gen_random_uuid()::TEXT, $1, kind, kind, TRUE FROM UNNEST($5::TEXT[]) AS kind) UPDATE sf_user SET current_household_id=$1 WHERE user_id=$3",
+            &[
+                &household_id,
+                &name,
+                &user_id,
+                &format!("{:?}", HouseholdRole::Owner),
+                &StorageKind::ALL
+                    .iter()
+                    .map(|kind| format!("{:?}", kind))
+                    .collect::<Vec<_>>(),
+            ],
+        ),
+    )
+    .await?;
+
+    Ok(household_id)
+}
+
+/// Removes the user from the household, owners can't be removed.
+///
+/// If the user was working on the household they go back to another one, preferably their own.
+pub async fn remove_membership(
+    conn: &Client,
+    household_id: &str,
+    user_id: &str,
+) -> Result<i64, tokio_postgres::Error> {
+    let row = timed_query(
+        "remove_membership",
+        conn.query_one(
+            "WITH removed AS (DELETE FROM sf_household_member WHERE household_id=$1 AND user_id=$2 AND role<>$3 RETURNING user_id), reset AS (UPDATE sf_user SET current_household_id=(SELECT m.household_id FROM sf_household_member m WHERE m.user_id=$2 AND m.household_id<>$1 ORDER BY m.role=$3 DESC, m.joined_date LIMIT 1) WHERE user_id IN (SELECT user_id FROM removed) AND current_household_id=$1) SELECT COUNT(*) AS removed FROM removed",
+            &[
+                &household_id,
+                &user_id,
+                &format!("{:?}", HouseholdRole::Owner),
+            ],
+        ),
+    )
+    .await?;
+
+    Ok(row.get("removed"))
+}
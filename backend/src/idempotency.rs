@@ -0,0 +1,440 @@
+//! Lets clients retry mutating requests safely by sending an `Idempotency-Key` header.
+//!
+//! The response of the first request with a key is stored and replayed to the retries,
+//! so the mutation is only done once. Keys are scoped to the user sending them.
+
+use std::{fmt::Display, sync::Arc, time::Duration};
+
+use axum::{
+    body::{boxed, Body, Full},
+    extract::State,
+    http::{
+        header::{AUTHORIZATION, CONTENT_TYPE},
+        HeaderMap, HeaderValue, Request,
+    },
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use base64::{engine::general_purpose, Engine};
+use hyper::{body::HttpBody, StatusCode};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tokio_postgres::Client;
+
+use crate::{
+    extract_jwt, metrics::record_idempotent_request, metrics::timed_query,
+    responses::ResponseError, APP_SECRET,
+};
+
+pub const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+
+/// Added to the responses that are replayed.
+pub const IDEMPOTENT_REPLAYED_HEADER: &str = "Idempotent-Replayed";
+
+/// The longest key accepted.
+const MAX_KEY_LENGTH: usize = 255;
+
+/// The largest request body read, the same limit the JSON bodies have in the routes.
+const MAX_BODY_SIZE: usize = 2 * 1024 * 1024;
+
+/// How long to wait between purges of the expired keys.
+const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Serialize)]
+pub enum IdempotencyErrors {
+    InvalidIdempotencyKey,
+    InvalidRequestBody,
+    RequestBodyTooLarge,
+    NoDBConnectionFound,
+    ErrorCheckingIdempotencyKey,
+    /// The key was already used for a different request.
+    IdempotencyKeyReused,
+    /// The first request with the key hasn't finished yet.
+    RequestInProgress,
+}
+
+impl Display for IdempotencyErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Clone)]
+pub struct IdempotencyState {
+    pub client: Arc<Option<Client>>,
+    /// How many hours a key is stored, after that it can be used again.
+    pub retention_hours: i64,
+}
+
+/// Middleware that replays the stored response when a request is retried with the same `Idempotency-Key`.
+///
+/// Requests without the header or a session token are run as always. Only successful responses,
+/// conflicts and validation errors are stored, the rest can be retried.
+pub async fn idempotency(
+    State(state): State<IdempotencyState>,
+    request: Request<Body>,
+    next: Next<Body>,
+) -> Response {
+    let Some(key) = request.headers().get(IDEMPOTENCY_KEY_HEADER) else {
+        return next.run(request).await;
+    };
+    let tracing_prefix = format!("{} - Idempotency:", request.uri().path());
+
+    match run_once(&state, key.clone(), request, next, &tracing_prefix).await {
+        Ok(response) => response,
+        Err(error) => error.into_response(),
+    }
+}
+
+async fn run_once(
+    state: &IdempotencyState,
+    key: HeaderValue,
+    request: Request<Body>,
+    next: Next<Body>,
+    tracing_prefix: &str,
+) -> Result<Response, ResponseError<IdempotencyErrors>> {
+    let key = match key.to_str() {
+        Ok(key) if !key.is_empty() && key.len() <= MAX_KEY_LENGTH => key.to_string(),
+        _ => {
+            tracing::error!("{} The key `{:?}` is invalid!", tracing_prefix, key);
+            Err((
+                StatusCode::BAD_REQUEST,
+                IdempotencyErrors::InvalidIdempotencyKey,
+            ))?
+        }
+    };
+
+    let conn = state.client.as_ref().as_ref().ok_or_else(|| {
+        tracing::error!("{} No DB connection found!", tracing_prefix);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            IdempotencyErrors::NoDBConnectionFound,
+        )
+    })?;
+
+    let (parts, body) = request.into_parts();
+    let body = read_body(body, tracing_prefix).await?;
+
+    let Some(user_id) = requesting_user(&parts.headers, &body) else {
+        tracing::debug!(
+            "{} No valid session token, running the request as always...",
+            tracing_prefix
+        );
+        return Ok(next.run(Request::from_parts(parts, Body::from(body))).await);
+    };
+
+    // A retry sends the same request, query string included, anything else with the same key is answered `IdempotencyKeyReused`.
+    let mut hasher = Sha256::new();
+    hasher.update(parts.method.as_str().as_bytes());
+    hasher.update(parts.uri.to_string().as_bytes());
+    if let Some(authorization) = parts.headers.get(AUTHORIZATION) {
+        hasher.update(authorization.as_bytes());
+    }
+    hasher.update(&body);
+    let request_hash = general_purpose::STANDARD_NO_PAD.encode(hasher.finalize());
+
+    let db_error = |err: tokio_postgres::Error| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while checking the key!",
+            tracing_prefix,
+            err
+        );
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            IdempotencyErrors::ErrorCheckingIdempotencyKey,
+        )
+    };
+
+    tracing::debug!("{} Reserving key `{}`...", tracing_prefix, key);
+    let reserved = timed_query(
+        "run_once",
+        conn.query_opt(
+            "INSERT INTO sf_idempotency_key (user_id, idempotency_key, request_hash) VALUES ($1, $2, $3) ON CONFLICT (user_id, idempotency_key) DO UPDATE SET request_hash=EXCLUDED.request_hash, status=NULL, content_type=NULL, body=NULL, created_at=NOW() WHERE sf_idempotency_key.created_at < NOW() - make_interval(hours => $4::INT) RETURNING idempotency_key",
+            &[&user_id, &key, &request_hash, &(state.retention_hours as i32)],
+        ),
+    )
+    .await
+    .map_err(db_error)?
+    .is_some();
+
+    if !reserved {
+        let row = timed_query(
+            "run_once",
+            conn.query_one(
+                "SELECT request_hash, status, content_type, body FROM sf_idempotency_key WHERE user_id=$1 AND idempotency_key=$2",
+                &[&user_id, &key],
+            ),
+        )
+        .await
+        .map_err(db_error)?;
+
+        if row.get::<_, &str>("request_hash") != request_hash {
+            tracing::error!(
+                "{} The key `{}` was used for another request!",
+                tracing_prefix,
+                key
+            );
+            record_idempotent_request("rejected");
+            Err((
+                StatusCode::UNPROCESSABLE_ENTITY,
+                IdempotencyErrors::IdempotencyKeyReused,
+            ))?
+        }
+
+        let Some(status) = row.get::<_, Option<i16>>("status") else {
+            tracing::error!(
+                "{} The first request with key `{}` is still running!",
+                tracing_prefix,
+                key
+            );
+            record_idempotent_request("rejected");
+            Err((StatusCode::CONFLICT, IdempotencyErrors::RequestInProgress))?
+        };
+
+        tracing::debug!(
+            "{} Replaying the response of key `{}`!",
+            tracing_prefix,
+            key
+        );
+        let body: Vec<u8> = row.get::<_, Option<Vec<u8>>>("body").unwrap_or_default();
+        let mut response = Response::new(boxed(Full::from(body)));
+        *response.status_mut() =
+            StatusCode::from_u16(status as u16).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        if let Some(content_type) = row
+            .get::<_, Option<&str>>("content_type")
+            .and_then(|c| HeaderValue::from_str(c).ok())
+        {
+            response.headers_mut().insert(CONTENT_TYPE, content_type);
+        }
+        response
+            .headers_mut()
+            .insert(IDEMPOTENT_REPLAYED_HEADER, HeaderValue::from_static("true"));
+        record_idempotent_request("replayed");
+        return Ok(response);
+    }
+
+    record_idempotent_request("ran");
+    let reservation = KeyReservation {
+        client: state.client.clone(),
+        user_id: user_id.clone(),
+        key: key.clone(),
+        tracing_prefix: tracing_prefix.to_string(),
+        finished: false,
+    };
+    let response = next.run(Request::from_parts(parts, Body::from(body))).await;
+
+    // Errors like an expired session or a missing ingredient may not happen on a retry, so they aren't replayed.
+    let status = response.status();
+    if !(status.is_success()
+        || status == StatusCode::CONFLICT
+        || status == StatusCode::UNPROCESSABLE_ENTITY)
+    {
+        release_key(conn, &user_id, &key, tracing_prefix).await;
+        reservation.finish();
+        return Ok(response);
+    }
+
+    let (parts, body) = response.into_parts();
+    let body = match hyper::body::to_bytes(body).await {
+        Ok(body) => body,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred while reading the response!",
+                tracing_prefix,
+                err
+            );
+            return Ok(StatusCode::INTERNAL_SERVER_ERROR.into_response());
+        }
+    };
+
+    tracing::debug!(
+        "{} Storing the response of key `{}`...",
+        tracing_prefix,
+        key
+    );
+    if let Err(err) = timed_query(
+        "run_once",
+        conn.execute(
+            "UPDATE sf_idempotency_key SET status=$3, content_type=$4, body=$5 WHERE user_id=$1 AND idempotency_key=$2",
+            &[
+                &user_id,
+                &key,
+                &(parts.status.as_u16() as i16),
+                &parts
+                    .headers
+                    .get(CONTENT_TYPE)
+                    .and_then(|c| c.to_str().ok()),
+                &body.as_ref(),
+            ],
+        ),
+    )
+    .await
+    {
+        tracing::error!(
+            "{} An error `{:?}` occurred while storing the response!",
+            tracing_prefix,
+            err
+        );
+        // Otherwise the retries would be answered `RequestInProgress` until the key expires.
+        release_key(conn, &user_id, &key, tracing_prefix).await;
+    }
+    reservation.finish();
+
+    Ok(Response::from_parts(parts, boxed(Full::from(body))))
+}
+
+/// Reads the body of a request, failing when it's larger than [`MAX_BODY_SIZE`].
+async fn read_body(
+    mut body: Body,
+    tracing_prefix: &str,
+) -> Result<Vec<u8>, ResponseError<IdempotencyErrors>> {
+    let mut bytes = vec![];
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.map_err(|err| {
+            tracing::error!(
+                "{} An error `{:?}` occurred while reading the request!",
+                tracing_prefix,
+                err
+            );
+            (
+                StatusCode::BAD_REQUEST,
+                IdempotencyErrors::InvalidRequestBody,
+            )
+        })?;
+        if bytes.len() + chunk.len() > MAX_BODY_SIZE {
+            tracing::error!(
+                "{} The request is larger than {} bytes!",
+                tracing_prefix,
+                MAX_BODY_SIZE
+            );
+            Err((
+                StatusCode::PAYLOAD_TOO_LARGE,
+                IdempotencyErrors::RequestBodyTooLarge,
+            ))?
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(bytes)
+}
+
+/// The user of the session token in the `Authorization` header (v2) or the body (v1) of a request.
+fn requesting_user(headers: &HeaderMap, body: &[u8]) -> Option<String> {
+    let token = match headers.get(AUTHORIZATION) {
+        Some(authorization) => authorization
+            .to_str()
+            .ok()?
+            .strip_prefix("Bearer ")?
+            .trim()
+            .to_string(),
+        None => serde_json::from_slice::<serde_json::Value>(body)
+            .ok()?
+            .get("token")?
+            .as_str()?
+            .to_string(),
+    };
+    extract_jwt(APP_SECRET, &token)
+        .ok()
+        .map(|token| token.user_id)
+}
+
+/// Deletes a reserved key so the request can be retried.
+async fn release_key(conn: &Client, user_id: &str, key: &str, tracing_prefix: &str) {
+    tracing::debug!(
+        "{} Releasing key `{}` so the request can be retried...",
+        tracing_prefix,
+        key
+    );
+    if let Err(err) = timed_query(
+        "release_key",
+        conn.execute(
+            "DELETE FROM sf_idempotency_key WHERE user_id=$1 AND idempotency_key=$2",
+            &[&user_id, &key],
+        ),
+    )
+    .await
+    {
+        tracing::error!(
+            "{} An error `{:?}` occurred while releasing the key!",
+            tracing_prefix,
+            err
+        );
+    }
+}
+
+/// Releases a reserved key when the request doesn't finish, because the route panicked or the client
+/// went away, instead of answering `RequestInProgress` to the retries until the key expires.
+struct KeyReservation {
+    client: Arc<Option<Client>>,
+    user_id: String,
+    key: String,
+    tracing_prefix: String,
+    finished: bool,
+}
+
+impl KeyReservation {
+    fn finish(mut self) {
+        self.finished = true;
+    }
+}
+
+impl Drop for KeyReservation {
+    fn drop(&mut self) {
+        if self.finished {
+            return;
+        }
+
+        let client = self.client.clone();
+        let user_id = std::mem::take(&mut self.user_id);
+        let key = std::mem::take(&mut self.key);
+        let tracing_prefix = std::mem::take(&mut self.tracing_prefix);
+        tokio::spawn(async move {
+            if let Some(conn) = client.as_ref() {
+                release_key(conn, &user_id, &key, &tracing_prefix).await;
+            }
+        });
+    }
+}
+
+/// Deletes the keys stored for longer than `retention_hours`.
+///
+/// Returns how many keys were deleted.
+pub async fn purge_idempotency_keys(
+    conn: &Client,
+    retention_hours: i64,
+) -> Result<u64, tokio_postgres::Error> {
+    timed_query(
+        "purge_idempotency_keys",
+        conn.execute(
+            "DELETE FROM sf_idempotency_key WHERE created_at < NOW() - make_interval(hours => $1::INT)",
+            &[&(retention_hours as i32)],
+        ),
+    )
+    .await
+}
+
+/// Spawns a task that purges the expired keys every hour.
+pub fn spawn_idempotency_purge_job(client: Arc<Option<Client>>, retention_hours: i64) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(PURGE_INTERVAL);
+        loop {
+            interval.tick().await;
+
+            let Some(conn) = client.as_ref() else {
+                tracing::error!("Idempotency keys purge: No DB connection found!");
+                continue;
+            };
+
+            match purge_idempotency_keys(conn, retention_hours).await {
+                Ok(deleted) => tracing::debug!(
+                    "Idempotency keys purge: {} keys older than {} hours deleted!",
+                    deleted,
+                    retention_hours
+                ),
+                Err(err) => tracing::error!(
+                    "Idempotency keys purge: An error `{:?}` occurred while purging the keys!",
+                    err
+                ),
+            }
+        }
+    });
+}
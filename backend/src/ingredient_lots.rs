@@ -0,0 +1,209 @@
+//! Batches of the same ingredient bought at different times, each one with its own expire date.
+//!
+//! The quantity and expire date of an ingredient are always the total and the earliest of its lots.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use tokio_postgres::GenericClient;
+use uuid::Uuid;
+
+use crate::metrics::timed_query;
+use crate::models::{IngredientLot, InventoryEventType, RemovalReason};
+
+/// Quantities smaller than this are considered used up.
+const EPSILON: f32 = 1e-3;
+
+/// Sorts the lots in the order they are used, the oldest first.
+///
+/// Lots without an added date are older than every other one.
+pub fn sort_fifo(lots: &mut [IngredientLot]) {
+    lots.sort_by(|a, b| {
+        a.added_date
+            .cmp(&b.added_date)
+            .then(a.expire_date.cmp(&b.expire_date))
+    });
+}
+
+/// Returns the total quantity and the earliest expire date of the lots.
+pub fn lot_totals(lots: &[IngredientLot]) -> (f32, Option<DateTime<Utc>>) {
+    let quantity = lots.iter().map(|l| l.quantity).sum();
+    let expire_date = lots.iter().map(|l| l.expire_date).min();
+    (quantity, expire_date)
+}
+
+/// Takes `quantity` from the lots, the oldest first, removing the ones that are used up.
+///
+/// Returns the remaining lots and how much couldn't be taken because there wasn't enough.
+pub fn consume_fifo(lots: &[IngredientLot], quantity: f32) -> (Vec<IngredientLot>, f32) {
+    let mut lots = lots.to_vec();
+    sort_fifo(&mut lots);
+
+    let mut missing = quantity;
+    let mut remaining = vec![];
+    for mut lot in lots {
+        if missing > EPSILON {
+            let taken = lot.quantity.min(missing);
+            lot.quantity -= taken;
+            missing -= taken;
+        }
+        if lot.quantity > EPSILON {
+            remaining.push(lot);
+        }
+    }
+
+    (remaining, if missing > EPSILON { missing } else { 0.0 })
+}
+
+/// Applies an edit of the whole ingredient to its lots.
+///
+/// A smaller quantity is consumed from the oldest lots and a bigger one is added as a new lot.
+/// A different expire date changes the lot that expires first, since that's the date the ingredient shows.
+pub fn edit_lots(
+    lots: &[IngredientLot],
+    quantity: f32,
+    expire_date: DateTime<Utc>,
+    edit_date: DateTime<Utc>,
+) -> Vec<IngredientLot> {
+    let (total, _) = lot_totals(lots);
+    let mut lots = if total - quantity > EPSILON {
+        consume_fifo(lots, total - quantity).0
+    } else {
+        lots.to_vec()
+    };
+
+    if quantity - total > EPSILON || lots.is_empty() {
+        lots.push(IngredientLot {
+            lot_id: Uuid::new_v4(),
+            quantity: quantity - lot_totals(&lots).0,
+            expire_date,
+            added_date: Some(edit_date),
+        });
+    }
+
+    if let Some(first) = lots.iter_mut().min_by_key(|l| l.expire_date) {
+        first.expire_date = expire_date;
+    }
+
+    sort_fifo(&mut lots);
+    lots
+}
+
+/// Gets the lots of the ingredients, in the order they are used.
+pub async fn get_lots(
+    conn: &impl GenericClient,
+    ingredient_ids: &[String],
+) -> Result<HashMap<Uuid, Vec<IngredientLot>>, tokio_postgres::Error> {
+    let rows = timed_query(
+        "get_lots",
+        conn.query(
+            "SELECT * FROM sf_ingredient_lot WHERE ingredient_id = ANY($1) ORDER BY added_date NULLS FIRST, expire_date",
+            &[&ingredient_ids],
+        ),
+    )
+    .await?;
+
+    let mut lots: HashMap<Uuid, Vec<IngredientLot>> = HashMap::new();
+    for row in rows {
+        let (Ok(ingredient_id), Ok(lot_id)) = (
+            row.get::<_, &str>("ingredient_id").parse(),
+            row.get::<_, &str>("lot_id").parse(),
+        ) else {
+            tracing::error!("Lot with an invalid id found! {:?}", row);
+            continue;
+        };
+
+        lots.entry(ingredient_id).or_default().push(IngredientLot {
+            lot_id,
+            quantity: row.get("quantity"),
+            expire_date: row.get("expire_date"),
+            added_date: row.get("added_date"),
+        });
+    }
+
+    Ok(lots)
+}
+
+/// Represents a change of the lots of an ingredient and the rest of its fields.
+///
+/// Missing fields are kept as they are.
+#[derive(Debug)]
+pub struct LotsUpdate<'a> {
+    pub ingredient_id: &'a str,
+    pub household_id: &'a str,
+    /// The user making the change.
+    pub user_id: &'a str,
+    pub old_lots: &'a [IngredientLot],
+    pub new_lots: &'a [IngredientLot],
+    pub name: Option<&'a str>,
+    pub category: Option<&'a str>,
+    pub unit: Option<&'a str>,
+    pub location_id: Option<String>,
+    /// Moves the ingredient to the trash, used when all of it was consumed.
+    pub remove: bool,
+    pub event_type: InventoryEventType,
+    pub reason: Option<RemovalReason>,
+    /// The quantity logged in the event, the new quantity of the ingredient when missing.
+    pub event_quantity: Option<f32>,
+    /// The version the client last saw, nothing is changed if the ingredient has another one.
+    pub version: Option<i32>,
+}
+
+/// Replaces the lots of an ingredient, updates its totals and logs the event in a single statement.
+///
+/// The ingredient is locked before its lots are changed, so a concurrent change
+/// makes the version check fail instead of mixing both changes.
+///
+/// Returns how many ingredients were changed, 0 if it wasn't found or its version is different.
+pub async fn save_lots(
+    conn: &impl GenericClient,
+    update: LotsUpdate<'_>,
+) -> Result<u64, tokio_postgres::Error> {
+    let new_ids: Vec<Uuid> = update.new_lots.iter().map(|l| l.lot_id).collect();
+    let old_ids: Vec<Uuid> = update.old_lots.iter().map(|l| l.lot_id).collect();
+
+    let deleted: Vec<String> = old_ids
+        .iter()
+        .filter(|id| !new_ids.contains(id))
+        .map(|id| id.to_string())
+        .collect();
+    let (updated, inserted): (Vec<&IngredientLot>, Vec<&IngredientLot>) = update
+        .new_lots
+        .iter()
+        .filter(|l| !update.old_lots.contains(l))
+        .partition(|l| old_ids.contains(&l.lot_id));
+
+    let (quantity, expire_date) = lot_totals(update.new_lots);
+
+    timed_query(
+        "save_lots",
+        conn.execute(
+            "WITH target AS (SELECT ingredient_id FROM sf_ingredient WHERE ingredient_id=$1 AND household_id=$2 AND deleted_date IS NULL AND ($21::INT IS NULL OR version=$21) FOR UPDATE), deleted AS (DELETE FROM sf_ingredient_lot WHERE ingredient_id IN (SELECT ingredient_id FROM target) AND lot_id = ANY($3)), updated AS (UPDATE sf_ingredient_lot l SET quantity=u.quantity, expire_date=u.expire_date FROM UNNEST($4::TEXT[], $5::REAL[], $6::TIMESTAMPTZ[]) AS u(lot_id, quantity, expire_date) WHERE l.lot_id=u.lot_id AND l.ingredient_id IN (SELECT ingredient_id FROM target)), inserted AS (INSERT INTO sf_ingredient_lot (lot_id, ingredient_id, quantity, expire_date, added_date) SELECT n.lot_id, t.ingredient_id, n.quantity, n.expire_date, n.added_date FROM target t CROSS JOIN UNNEST($7::TEXT[], $8::REAL[], $9::TIMESTAMPTZ[], $10::TIMESTAMPTZ[]) AS n(lot_id, quantity, expire_date, added_date)), changed AS (UPDATE sf_ingredient i SET quantity=$11, expire_date=COALESCE($12, i.expire_date), name=COALESCE($13, i.name), category=COALESCE($14, i.category), unit=COALESCE($15, i.unit), location_id=COALESCE($16, i.location_id), deleted_date=CASE WHEN $17 THEN NOW() ELSE NULL END, updated_at=NOW(), version=version + 1 FROM target t WHERE i.ingredient_id=t.ingredient_id RETURNING i.*) INSERT INTO sf_inventory_event (household_id, user_id, ingredient_id, name, category, quantity, unit, expire_date, event_type, reason) SELECT household_id, $18, ingredient_id, name, category, COALESCE($22::REAL, quantity), unit, expire_date, $19, $20 FROM changed",
+            &[
+                &update.ingredient_id,
+                &update.household_id,
+                &deleted,
+                &updated.iter().map(|l| l.lot_id.to_string()).collect::<Vec<_>>(),
+                &updated.iter().map(|l| l.quantity).collect::<Vec<_>>(),
+                &updated.iter().map(|l| l.expire_date).collect::<Vec<_>>(),
+                &inserted.iter().map(|l| l.lot_id.to_string()).collect::<Vec<_>>(),
+                &inserted.iter().map(|l| l.quantity).collect::<Vec<_>>(),
+                &inserted.iter().map(|l| l.expire_date).collect::<Vec<_>>(),
+                &inserted.iter().map(|l| l.added_date).collect::<Vec<_>>(),
+                &quantity,
+                &expire_date,
+                &update.name,
+                &update.category,
+                &update.unit,
+                &update.location_id,
+                &update.remove,
+                &update.user_id,
+                &format!("{:?}", update.event_type),
+                &update.reason.map(|r| format!("{:?}", r)),
+                &update.version,
+                &update.event_quantity,
+            ],
+        ),
+    )
+    .await
+}
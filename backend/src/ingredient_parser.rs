@@ -0,0 +1,596 @@
+//! Parser for the free text ingredient lines recipes come with.
+//!
+//! The WorldWide Recipes API only gives us lines like "½ cup riced sweet potato",
+//! this module extracts the quantity, unit, ingredient name and preparation notes
+//! so they can be compared against what the user has in the fridge.
+
+/// Represents the structured data extracted from an ingredient line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedIngredientLine {
+    /// The quantity of the ingredient, when the line is a range this is the lower bound.
+    pub quantity: Option<f32>,
+    /// The upper bound of the quantity when the line contains a range like "2-3 cups".
+    pub quantity_max: Option<f32>,
+    /// The canonical name of the unit, see [normalize_unit].
+    pub unit: Option<String>,
+    pub name: String,
+    /// Preparation notes like "finely chopped" or "to taste".
+    pub notes: Option<String>,
+}
+
+/// Words that describe how an ingredient is prepared instead of the ingredient itself.
+const PREPARATIONS: &[&str] = &[
+    "beaten",
+    "chopped",
+    "crushed",
+    "cubed",
+    "diced",
+    "drained",
+    "grated",
+    "halved",
+    "julienned",
+    "mashed",
+    "melted",
+    "minced",
+    "peeled",
+    "pitted",
+    "quartered",
+    "rinsed",
+    "riced",
+    "shredded",
+    "sifted",
+    "sliced",
+    "softened",
+    "toasted",
+    "trimmed",
+    "zested",
+];
+
+/// Adverbs that can go before a preparation word, like "finely chopped".
+const PREPARATION_ADVERBS: &[&str] = &["coarsely", "finely", "freshly", "roughly", "thinly"];
+
+/// Phrases that are notes when they end an ingredient line without a comma.
+const TRAILING_NOTES: &[&str] = &["to taste", "for garnish", "for serving", "optional"];
+
+/// Parses an ingredient line into its quantity, unit, name and notes.
+///
+/// The parser never fails, when something can't be recognized it ends up as part of the name.
+pub fn parse_ingredient_line(line: &str) -> ParsedIngredientLine {
+    let line = normalize_line(line);
+    let mut notes: Vec<String> = vec![];
+
+    let (line, parenthesized) = extract_parenthesized(&line);
+    notes.extend(parenthesized);
+
+    let (line, after_comma) = match line.split_once(',') {
+        Some((head, tail)) => (head.to_string(), Some(tail.trim().to_string())),
+        None => (line, None),
+    };
+
+    let tokens = split_attached_units(&line);
+    let mut tokens: &[String] = &tokens;
+
+    let (quantity, quantity_max) = match parse_quantity(tokens) {
+        Some((min, max, consumed)) => {
+            tokens = &tokens[consumed..];
+            (Some(min), max)
+        }
+        None => (None, None),
+    };
+
+    let mut unit = None;
+    if let Some((found, consumed)) = parse_unit(tokens, quantity.is_some()) {
+        unit = Some(found.to_string());
+        tokens = &tokens[consumed..];
+    }
+
+    if unit.is_some() && tokens.first().map(|t| t.eq_ignore_ascii_case("of")) == Some(true) {
+        tokens = &tokens[1..];
+    }
+
+    let mut preparation = vec![];
+    while let Some(token) = tokens.first() {
+        let lower = token.to_lowercase();
+        let is_adverb = PREPARATION_ADVERBS.contains(&lower.as_str())
+            && tokens
+                .get(1)
+                .map(|next| PREPARATIONS.contains(&next.to_lowercase().as_str()))
+                == Some(true);
+        if is_adverb || PREPARATIONS.contains(&lower.as_str()) {
+            preparation.push(token.to_string());
+            tokens = &tokens[1..];
+        } else {
+            break;
+        }
+    }
+    if !preparation.is_empty() {
+        notes.insert(0, preparation.join(" "));
+    }
+
+    let mut name = tokens.join(" ");
+    let trailing_note = TRAILING_NOTES.iter().find_map(|suffix| {
+        let cut = name.len().checked_sub(suffix.len() + 1)?;
+        let tail = name.get(cut..)?;
+        (tail.starts_with(' ') && tail[1..].eq_ignore_ascii_case(suffix)).then_some(cut)
+    });
+    if let Some(cut) = trailing_note {
+        notes.push(name[cut + 1..].to_string());
+        name.truncate(cut);
+    }
+
+    notes.extend(after_comma.filter(|n| !n.is_empty()));
+    let notes = if notes.is_empty() {
+        None
+    } else {
+        Some(notes.join(", "))
+    };
+
+    ParsedIngredientLine {
+        quantity,
+        quantity_max,
+        unit,
+        name,
+        notes,
+    }
+}
+
+/// Returns the canonical name of a unit, or `None` if the text isn't a known unit.
+///
+/// The check is case insensitive except for the "T" (tablespoon) and "t" (teaspoon) shorthands.
+pub fn normalize_unit(text: &str) -> Option<&'static str> {
+    match text {
+        "T" | "Tbs" | "TB" => return Some("tbsp"),
+        "t" => return Some("tsp"),
+        _ => {}
+    }
+
+    let lower = text.trim_end_matches('.').to_lowercase();
+    let unit = match lower.as_str() {
+        "tsp" | "tsps" | "teaspoon" | "teaspoons" => "tsp",
+        "tbsp" | "tbsps" | "tbs" | "tablespoon" | "tablespoons" => "tbsp",
+        "cup" | "cups" | "c" => "cup",
+        "ml" | "milliliter" | "milliliters" | "millilitre" | "millilitres" => "ml",
+        "l" | "liter" | "liters" | "litre" | "litres" => "l",
+        "g" | "gr" | "gram" | "grams" | "gramme" | "grammes" => "g",
+        "kg" | "kgs" | "kilogram" | "kilograms" => "kg",
+        "oz" | "ounce" | "ounces" => "oz",
+        "fl oz" | "fluid ounce" | "fluid ounces" => "fl oz",
+        "lb" | "lbs" | "pound" | "pounds" => "lb",
+        "pt" | "pint" | "pints" => "pint",
+        "qt" | "quart" | "quarts" => "quart",
+        "gal" | "gallon" | "gallons" => "gallon",
+        "pinch" | "pinches" => "pinch",
+        "dash" | "dashes" => "dash",
+        "clove" | "cloves" => "clove",
+        "can" | "cans" => "can",
+        "package" | "packages" | "pkg" | "packet" | "packets" => "package",
+        "slice" | "slices" => "slice",
+        "piece" | "pieces" => "piece",
+        "stick" | "sticks" => "stick",
+        "bunch" | "bunches" => "bunch",
+        "sprig" | "sprigs" => "sprig",
+        "head" | "heads" => "head",
+        "bag" | "bags" => "bag",
+        "bottle" | "bottles" => "bottle",
+        "jar" | "jars" => "jar",
+        _ => None?,
+    };
+    Some(unit)
+}
+
+/// Normalizes an ingredient name so the same ingredient written differently can be compared.
+///
+/// The name is lowercased and every word is singularized, so "Sweet Potatoes" becomes "sweet potato".
+pub fn normalize_ingredient_name(name: &str) -> String {
+    name.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| singularize(&word.to_lowercase()))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Checks if two ingredient names refer to the same ingredient.
+///
+/// Names match when one of them ends with all the words of the other,
+/// so "milk" matches "whole milk" but not "milk chocolate chips".
+pub fn ingredient_names_match(a: &str, b: &str) -> bool {
+    let a = normalize_ingredient_name(a);
+    let b = normalize_ingredient_name(b);
+    if a.is_empty() || b.is_empty() {
+        return false;
+    }
+
+    let (short, long) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+    long == short || long.ends_with(&format!(" {}", short))
+}
+
+/// Checks if all the words of `part` appear together in the ingredient name,
+/// so "peanut" is contained in "salted peanuts" and "peanut butter" but not in "peanutty".
+pub fn contains_ingredient_name(name: &str, part: &str) -> bool {
+    let name = normalize_ingredient_name(name);
+    let part = normalize_ingredient_name(part);
+    if part.is_empty() {
+        return false;
+    }
+
+    format!(" {} ", name).contains(&format!(" {} ", part))
+}
+
+/// Naive english singularization, good enough for ingredient names.
+fn singularize(word: &str) -> String {
+    if word.len() <= 3 || word.ends_with("ss") {
+        return word.to_string();
+    }
+
+    if let Some(stem) = word.strip_suffix("ies") {
+        format!("{}y", stem)
+    } else if let Some(stem) = word
+        .strip_suffix("oes")
+        .or_else(|| word.strip_suffix("ches"))
+        .or_else(|| word.strip_suffix("shes"))
+    {
+        format!("{}{}", stem, &word[stem.len()..word.len() - 2])
+    } else if let Some(stem) = word.strip_suffix('s') {
+        stem.to_string()
+    } else {
+        word.to_string()
+    }
+}
+
+/// Replaces unicode fractions and dashes with their ASCII equivalents.
+fn normalize_line(line: &str) -> String {
+    let mut normalized = String::with_capacity(line.len());
+    for c in line.trim().chars() {
+        let replacement = match c {
+            '½' => Some("1/2"),
+            '⅓' => Some("1/3"),
+            '⅔' => Some("2/3"),
+            '¼' => Some("1/4"),
+            '¾' => Some("3/4"),
+            '⅕' => Some("1/5"),
+            '⅖' => Some("2/5"),
+            '⅗' => Some("3/5"),
+            '⅘' => Some("4/5"),
+            '⅙' => Some("1/6"),
+            '⅚' => Some("5/6"),
+            '⅛' => Some("1/8"),
+            '⅜' => Some("3/8"),
+            '⅝' => Some("5/8"),
+            '⅞' => Some("7/8"),
+            _ => None,
+        };
+
+        match (replacement, c) {
+            (Some(fraction), _) => {
+                // "1½" should be read as "1 1/2".
+                if normalized.ends_with(|p: char| p.is_ascii_digit()) {
+                    normalized.push(' ');
+                }
+                normalized.push_str(fraction);
+            }
+            (None, '⁄') => normalized.push('/'),
+            (None, '–' | '—') => normalized.push('-'),
+            (None, c) => normalized.push(c),
+        }
+    }
+    normalized
+}
+
+/// Removes every parenthesized section of the line, returning them as notes.
+fn extract_parenthesized(line: &str) -> (String, Vec<String>) {
+    let mut rest = String::with_capacity(line.len());
+    let mut notes = vec![];
+    let mut current = String::new();
+    let mut depth = 0;
+
+    for c in line.chars() {
+        match c {
+            '(' => {
+                if depth > 0 {
+                    current.push(c);
+                }
+                depth += 1;
+            }
+            ')' if depth > 0 => {
+                depth -= 1;
+                if depth == 0 {
+                    let note = current.trim().to_string();
+                    if !note.is_empty() {
+                        notes.push(note);
+                    }
+                    current.clear();
+                } else {
+                    current.push(c);
+                }
+            }
+            _ if depth > 0 => current.push(c),
+            _ => rest.push(c),
+        }
+    }
+
+    (rest, notes)
+}
+
+/// Splits the line into tokens, separating numbers glued to units like "200g"
+/// and ranges glued together like "2-3".
+fn split_attached_units(line: &str) -> Vec<String> {
+    let mut tokens = vec![];
+    for word in line.split_whitespace() {
+        let digits_end = word
+            .char_indices()
+            .find(|(_, c)| !(c.is_ascii_digit() || *c == '.' || *c == '/'))
+            .map(|(i, _)| i);
+
+        match digits_end {
+            Some(i) if i > 0 => {
+                let (number, rest) = word.split_at(i);
+                if let Some(range_end) = rest.strip_prefix('-') {
+                    tokens.push(number.to_string());
+                    tokens.push("-".to_string());
+                    if !range_end.is_empty() {
+                        tokens.extend(split_attached_units(range_end));
+                    }
+                } else if normalize_unit(rest).is_some() {
+                    tokens.push(number.to_string());
+                    tokens.push(rest.to_string());
+                } else {
+                    tokens.push(word.to_string());
+                }
+            }
+            _ => tokens.push(word.to_string()),
+        }
+    }
+    tokens
+}
+
+/// Parses a single number token, like "2", "1.5" or "3/4".
+fn parse_number(token: &str) -> Option<f32> {
+    match token.split_once('/') {
+        Some((numerator, denominator)) => {
+            let numerator: f32 = numerator.parse().ok()?;
+            let denominator: f32 = denominator.parse().ok()?;
+            if denominator == 0.0 {
+                None?
+            }
+            Some(numerator / denominator)
+        }
+        None => token.parse().ok(),
+    }
+}
+
+/// Parses a number that may be a mixed fraction, like "1 1/2".
+///
+/// Returns the value and the amount of tokens used.
+fn parse_mixed_number(tokens: &[String]) -> Option<(f32, usize)> {
+    let first = tokens.first()?;
+    let value = parse_number(first)?;
+
+    if !first.contains('/') {
+        if let Some(fraction) = tokens.get(1).filter(|t| t.contains('/')) {
+            if let Some(fraction) = parse_number(fraction).filter(|f| *f < 1.0) {
+                return Some((value + fraction, 2));
+            }
+        }
+    }
+
+    Some((value, 1))
+}
+
+/// Parses the quantity at the start of the tokens.
+///
+/// Returns the lower bound, the optional upper bound and the amount of tokens used.
+fn parse_quantity(tokens: &[String]) -> Option<(f32, Option<f32>, usize)> {
+    if let Some(first) = tokens.first() {
+        let lower = first.to_lowercase();
+        if (lower == "a" || lower == "an")
+            && tokens
+                .get(1)
+                .and_then(|unit| normalize_unit(unit))
+                .is_some()
+        {
+            return Some((1.0, None, 1));
+        }
+    }
+
+    let (min, mut consumed) = parse_mixed_number(tokens)?;
+
+    let separator = tokens.get(consumed).map(|t| t.to_lowercase());
+    if let Some("-" | "to" | "or") = separator.as_deref() {
+        if let Some((max, used)) = parse_mixed_number(&tokens[consumed + 1..]) {
+            consumed += 1 + used;
+            return Some((min, Some(max), consumed));
+        }
+    }
+
+    Some((min, None, consumed))
+}
+
+/// Parses the unit at the start of the tokens.
+///
+/// Single letter units like "c" or "t" are only accepted after a quantity,
+/// since otherwise they're more likely part of the name.
+///
+/// Returns the canonical unit and the amount of tokens used.
+fn parse_unit(tokens: &[String], after_quantity: bool) -> Option<(&'static str, usize)> {
+    if let (Some(first), Some(second)) = (tokens.first(), tokens.get(1)) {
+        if let Some(unit) = normalize_unit(&format!("{} {}", first, second)) {
+            return Some((unit, 2));
+        }
+    }
+
+    let first = tokens.first()?;
+    if !after_quantity && first.trim_end_matches('.').len() == 1 {
+        None?
+    }
+    // A lone unit word is the ingredient itself, like "1 can" or "Head".
+    if tokens.len() == 1 {
+        None?
+    }
+    normalize_unit(first).map(|unit| (unit, 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// (line, quantity, quantity_max, unit, name, notes)
+    type Case = (
+        &'static str,
+        Option<f32>,
+        Option<f32>,
+        Option<&'static str>,
+        &'static str,
+        Option<&'static str>,
+    );
+
+    #[rustfmt::skip]
+    const CASES: &[Case] = &[
+        // Plain quantities
+        ("2 eggs", Some(2.0), None, None, "eggs", None),
+        ("1 onion", Some(1.0), None, None, "onion", None),
+        ("1.5 cups milk", Some(1.5), None, Some("cup"), "milk", None),
+        ("0.25 tsp salt", Some(0.25), None, Some("tsp"), "salt", None),
+        ("3 cloves garlic", Some(3.0), None, Some("clove"), "garlic", None),
+        ("1 clove garlic", Some(1.0), None, Some("clove"), "garlic", None),
+        // ASCII fractions
+        ("1/2 cup sugar", Some(0.5), None, Some("cup"), "sugar", None),
+        ("3/4 cup flour", Some(0.75), None, Some("cup"), "flour", None),
+        ("1/8 tsp nutmeg", Some(0.125), None, Some("tsp"), "nutmeg", None),
+        // Unicode fractions
+        ("½ cup riced sweet potato", Some(0.5), None, Some("cup"), "sweet potato", Some("riced")),
+        ("¼ teaspoon black pepper", Some(0.25), None, Some("tsp"), "black pepper", None),
+        ("¾ cup rolled oats", Some(0.75), None, Some("cup"), "rolled oats", None),
+        ("⅓ cup honey", Some(1.0 / 3.0), None, Some("cup"), "honey", None),
+        ("1⁄2 cup water", Some(0.5), None, Some("cup"), "water", None),
+        // Mixed fractions
+        ("1 1/2 cups flour", Some(1.5), None, Some("cup"), "flour", None),
+        ("2 3/4 cups broth", Some(2.75), None, Some("cup"), "broth", None),
+        ("1½ cups milk", Some(1.5), None, Some("cup"), "milk", None),
+        ("1 ½ tablespoons olive oil", Some(1.5), None, Some("tbsp"), "olive oil", None),
+        ("2¼ lbs chicken thighs", Some(2.25), None, Some("lb"), "chicken thighs", None),
+        // Ranges
+        ("2-3 cups spinach", Some(2.0), Some(3.0), Some("cup"), "spinach", None),
+        ("2 - 3 tomatoes", Some(2.0), Some(3.0), None, "tomatoes", None),
+        ("1 to 2 tbsp lemon juice", Some(1.0), Some(2.0), Some("tbsp"), "lemon juice", None),
+        ("3 or 4 carrots", Some(3.0), Some(4.0), None, "carrots", None),
+        ("1–2 jalapeños", Some(1.0), Some(2.0), None, "jalapeños", None),
+        ("½-1 tsp chili flakes", Some(0.5), Some(1.0), Some("tsp"), "chili flakes", None),
+        ("1 1/2 - 2 cups rice", Some(1.5), Some(2.0), Some("cup"), "rice", None),
+        // Attached units
+        ("200g spaghetti", Some(200.0), None, Some("g"), "spaghetti", None),
+        ("1kg potatoes", Some(1.0), None, Some("kg"), "potatoes", None),
+        ("500ml chicken stock", Some(500.0), None, Some("ml"), "chicken stock", None),
+        ("2-3g saffron", Some(2.0), Some(3.0), Some("g"), "saffron", None),
+        // Unit aliases
+        ("2 Tbsp. butter", Some(2.0), None, Some("tbsp"), "butter", None),
+        ("1 T soy sauce", Some(1.0), None, Some("tbsp"), "soy sauce", None),
+        ("1 t vanilla extract", Some(1.0), None, Some("tsp"), "vanilla extract", None),
+        ("8 oz cream cheese", Some(8.0), None, Some("oz"), "cream cheese", None),
+        ("4 fluid ounces cream", Some(4.0), None, Some("fl oz"), "cream", None),
+        ("1 fl oz rum", Some(1.0), None, Some("fl oz"), "rum", None),
+        ("2 pounds ground beef", Some(2.0), None, Some("lb"), "ground beef", None),
+        ("1 L water", Some(1.0), None, Some("l"), "water", None),
+        ("1 pint strawberries", Some(1.0), None, Some("pint"), "strawberries", None),
+        ("2 cans black beans", Some(2.0), None, Some("can"), "black beans", None),
+        ("1 package tofu", Some(1.0), None, Some("package"), "tofu", None),
+        ("1 bunch cilantro", Some(1.0), None, Some("bunch"), "cilantro", None),
+        ("2 sprigs thyme", Some(2.0), None, Some("sprig"), "thyme", None),
+        // "of" after the unit
+        ("1 cup of water", Some(1.0), None, Some("cup"), "water", None),
+        ("2 slices of bread", Some(2.0), None, Some("slice"), "bread", None),
+        // Articles as quantities
+        ("a pinch of salt", Some(1.0), None, Some("pinch"), "salt", None),
+        ("A dash hot sauce", Some(1.0), None, Some("dash"), "hot sauce", None),
+        ("an ounce of dark chocolate", Some(1.0), None, Some("oz"), "dark chocolate", None),
+        // Preparation notes
+        ("1 onion, finely chopped", Some(1.0), None, None, "onion", Some("finely chopped")),
+        ("2 cloves garlic, minced", Some(2.0), None, Some("clove"), "garlic", Some("minced")),
+        ("1 cup chopped walnuts", Some(1.0), None, Some("cup"), "walnuts", Some("chopped")),
+        ("2 tbsp finely chopped parsley", Some(2.0), None, Some("tbsp"), "parsley", Some("finely chopped")),
+        ("1/4 cup melted butter", Some(0.25), None, Some("cup"), "butter", Some("melted")),
+        ("3 carrots, peeled and diced", Some(3.0), None, None, "carrots", Some("peeled and diced")),
+        ("1 lb shrimp, peeled, deveined", Some(1.0), None, Some("lb"), "shrimp", Some("peeled, deveined")),
+        // Parenthesized notes
+        ("1 (14 oz) can diced tomatoes", Some(1.0), None, Some("can"), "tomatoes", Some("diced, 14 oz")),
+        ("2 cups rice (uncooked)", Some(2.0), None, Some("cup"), "rice", Some("uncooked")),
+        ("1 lemon (juiced), plus zest", Some(1.0), None, None, "lemon", Some("juiced, plus zest")),
+        // Trailing notes
+        ("salt to taste", None, None, None, "salt", Some("to taste")),
+        ("salt and pepper to taste", None, None, None, "salt and pepper", Some("to taste")),
+        ("fresh basil for garnish", None, None, None, "fresh basil", Some("for garnish")),
+        ("1 tsp red pepper flakes, optional", Some(1.0), None, Some("tsp"), "red pepper flakes", Some("optional")),
+        // No quantity
+        ("olive oil", None, None, None, "olive oil", None),
+        ("Cooking spray", None, None, None, "Cooking spray", None),
+        ("c salt", None, None, None, "c salt", None),
+        ("cups", None, None, None, "cups", None),
+        // Units that are also ingredients
+        ("1 can", Some(1.0), None, None, "can", None),
+        ("2 heads lettuce", Some(2.0), None, Some("head"), "lettuce", None),
+        // Whitespace and casing
+        ("  2   Cups   Milk  ", Some(2.0), None, Some("cup"), "Milk", None),
+        ("3 LARGE eggs", Some(3.0), None, None, "LARGE eggs", None),
+    ];
+
+    #[test]
+    fn parses_ingredient_lines() {
+        for (line, quantity, quantity_max, unit, name, notes) in CASES {
+            let parsed = parse_ingredient_line(line);
+
+            assert_eq!(parsed.name, *name, "name of `{}`", line);
+            assert_eq!(parsed.unit.as_deref(), *unit, "unit of `{}`", line);
+            assert_eq!(parsed.notes.as_deref(), *notes, "notes of `{}`", line);
+            match (parsed.quantity, quantity) {
+                (Some(a), Some(b)) => assert!((a - b).abs() < 1e-4, "quantity of `{}`", line),
+                (a, b) => assert_eq!(a, *b, "quantity of `{}`", line),
+            }
+            match (parsed.quantity_max, quantity_max) {
+                (Some(a), Some(b)) => {
+                    assert!((a - b).abs() < 1e-4, "quantity max of `{}`", line)
+                }
+                (a, b) => assert_eq!(a, *b, "quantity max of `{}`", line),
+            }
+        }
+    }
+
+    #[test]
+    fn normalizes_units() {
+        let cases = [
+            ("Cups", Some("cup")),
+            ("tablespoons", Some("tbsp")),
+            ("T", Some("tbsp")),
+            ("t", Some("tsp")),
+            ("tsp.", Some("tsp")),
+            ("mL", Some("ml")),
+            ("Kg", Some("kg")),
+            ("Lb", Some("lb")),
+            ("Bags", Some("bag")),
+            ("Bottles", Some("bottle")),
+            ("fluid ounce", Some("fl oz")),
+            ("handful", None),
+            ("large", None),
+        ];
+
+        for (text, expected) in cases {
+            assert_eq!(normalize_unit(text), expected, "unit `{}`", text);
+        }
+    }
+
+    #[test]
+    fn matches_ingredient_names() {
+        let cases = [
+            ("Milk", "whole milk", true),
+            ("Sweet Potatoes", "sweet potato", true),
+            ("eggs", "Egg", true),
+            ("peaches", "peach", true),
+            ("berries", "Berry", true),
+            ("milk", "milk chocolate chips", false),
+            ("garlic", "onion", false),
+            ("", "onion", false),
+        ];
+
+        for (a, b, expected) in cases {
+            assert_eq!(ingredient_names_match(a, b), expected, "`{}` vs `{}`", a, b);
+        }
+    }
+}
@@ -0,0 +1,331 @@
+//! Comparison between what recipes need and what the user has in the fridge.
+
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::{
+    ingredient_parser::{ingredient_names_match, normalize_ingredient_name},
+    models::{Ingredient, RecipeIngredient, ShoppingItem},
+    units::{are_units_compatible, convert_quantity},
+};
+
+/// Represents an amount of an ingredient that is needed.
+#[derive(Debug, Clone)]
+pub struct RequiredIngredient {
+    pub name: String,
+    pub quantity: f32,
+    pub unit: String,
+}
+
+impl From<&RecipeIngredient> for RequiredIngredient {
+    /// When the recipe gives a range the upper bound is used,
+    /// lines without a quantity (like "salt to taste") count as one.
+    fn from(value: &RecipeIngredient) -> Self {
+        RequiredIngredient {
+            name: value.name.clone(),
+            quantity: value.quantity_max.or(value.quantity).unwrap_or(1.0),
+            unit: value.unit.clone().unwrap_or_default(),
+        }
+    }
+}
+
+/// Sums the quantities of the same ingredient appearing more than once.
+///
+/// The unit of the first appearance is kept, appearances with incompatible units stay separate.
+pub fn aggregate_required(required: Vec<RequiredIngredient>) -> Vec<RequiredIngredient> {
+    let mut aggregated: Vec<RequiredIngredient> = vec![];
+    for ingredient in required {
+        let existing = aggregated.iter_mut().find(|a| {
+            ingredient_names_match(&a.name, &ingredient.name)
+                && are_units_compatible(&ingredient.unit, &a.unit)
+        });
+
+        match existing {
+            Some(existing) => {
+                // The find above already checked the units can be converted.
+                existing.quantity +=
+                    convert_quantity(ingredient.quantity, &ingredient.unit, &existing.unit)
+                        .unwrap_or_default();
+            }
+            None => aggregated.push(ingredient),
+        }
+    }
+    aggregated
+}
+
+/// Returns the fridge ingredients that can be used for the required one.
+pub fn matching_ingredients<'a>(
+    required: &RequiredIngredient,
+    fridge: &'a [Ingredient],
+) -> Vec<&'a Ingredient> {
+    fridge
+        .iter()
+        .filter(|i| ingredient_names_match(&i.name, &required.name))
+        .collect()
+}
+
+/// Computes how much of the required ingredient the fridge has, in the required unit.
+///
+/// Returns `None` when the fridge has the ingredient only in units that can't be compared
+/// with the required one, like "2 cloves" of garlic vs "1 Bags".
+pub fn available_quantity(required: &RequiredIngredient, fridge: &[Ingredient]) -> Option<f32> {
+    let matching = matching_ingredients(required, fridge);
+    if matching.is_empty() {
+        return Some(0.0);
+    }
+
+    let comparable: Vec<f32> = matching
+        .iter()
+        .filter_map(|i| convert_quantity(i.quantity, &i.unit, &required.unit))
+        .collect();
+    if comparable.is_empty() {
+        None
+    } else {
+        Some(comparable.iter().sum())
+    }
+}
+
+/// Computes how much of each required ingredient is missing from the fridge.
+///
+/// When the available quantity can't be compared we assume there's enough of it.
+pub fn missing_ingredients(
+    required: &[RequiredIngredient],
+    fridge: &[Ingredient],
+) -> Vec<RequiredIngredient> {
+    required
+        .iter()
+        .filter_map(|required| {
+            let missing = required.quantity - available_quantity(required, fridge)?;
+            (missing > 1e-3).then(|| RequiredIngredient {
+                quantity: missing,
+                ..required.clone()
+            })
+        })
+        .collect()
+}
+
+/// Subtracts from each missing ingredient what's already on the shopping list,
+/// dropping the ones the list already covers.
+///
+/// Items in units that can't be compared with the missing ingredient aren't subtracted.
+pub fn not_on_shopping_list(
+    missing: Vec<RequiredIngredient>,
+    shopping_list: &[ShoppingItem],
+) -> Vec<RequiredIngredient> {
+    missing
+        .into_iter()
+        .filter_map(|missing| {
+            let listed: f32 = shopping_list
+                .iter()
+                .filter(|item| !item.checked && ingredient_names_match(&item.name, &missing.name))
+                .filter_map(|item| convert_quantity(item.quantity, &item.unit, &missing.unit))
+                .sum();
+            let quantity = missing.quantity - listed;
+            (quantity > 1e-3).then_some(RequiredIngredient {
+                quantity,
+                ..missing
+            })
+        })
+        .collect()
+}
+
+/// Returns the fridge ingredients the required one relies on that will have expired by `date`.
+///
+/// Nothing is returned if at least one matching ingredient is still good by then.
+pub fn expired_by<'a>(
+    required: &RequiredIngredient,
+    fridge: &'a [Ingredient],
+    date: DateTime<Utc>,
+) -> Vec<&'a Ingredient> {
+    let matching = matching_ingredients(required, fridge);
+    if matching.iter().any(|i| i.expire_date >= date) {
+        return vec![];
+    }
+    matching
+}
+
+/// Returns the ingredients a new one can be merged into.
+///
+/// They must have the same name and category, be stored in the same location
+/// and have a unit the new quantity can be converted to.
+pub fn duplicate_candidates<'a>(
+    name: &str,
+    category: &str,
+    unit: &str,
+    location_id: Uuid,
+    fridge: &'a [Ingredient],
+) -> Vec<&'a Ingredient> {
+    let name = normalize_ingredient_name(name);
+    let category = category.trim().to_lowercase();
+    fridge
+        .iter()
+        .filter(|i| i.location_id == location_id)
+        .filter(|i| i.category.trim().to_lowercase() == category)
+        .filter(|i| normalize_ingredient_name(&i.name) == name)
+        .filter(|i| are_units_compatible(unit, &i.unit))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Duration, TimeZone};
+
+    use super::*;
+
+    /// (name, quantity, unit) of the ingredients expected.
+    type Expected = &'static [(&'static str, f32, &'static str)];
+
+    fn required(name: &str, quantity: f32, unit: &str) -> RequiredIngredient {
+        RequiredIngredient {
+            name: name.to_string(),
+            quantity,
+            unit: unit.to_string(),
+        }
+    }
+
+    fn fridge_ingredient(name: &str, quantity: f32, unit: &str, expire_in_days: i64) -> Ingredient {
+        let now = Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap();
+        Ingredient {
+            ingredient_id: Uuid::new_v4(),
+            user_id: Uuid::nil(),
+            expire_date: now + Duration::days(expire_in_days),
+            name: name.to_string(),
+            category: "Vegetables".to_string(),
+            quantity,
+            unit: unit.to_string(),
+            location_id: Uuid::nil(),
+            updated_at: now,
+            version: 1,
+            lots: vec![],
+        }
+    }
+
+    fn shopping_item(name: &str, quantity: f32, unit: &str, checked: bool) -> ShoppingItem {
+        ShoppingItem {
+            item_id: Uuid::new_v4(),
+            user_id: Uuid::nil(),
+            name: name.to_string(),
+            category: "Vegetables".to_string(),
+            quantity,
+            unit: unit.to_string(),
+            checked,
+            recipe_ids: vec![],
+        }
+    }
+
+    fn assert_quantities(
+        actual: &[RequiredIngredient],
+        expected: &[(&str, f32, &str)],
+        case: &str,
+    ) {
+        let actual: Vec<(&str, f32, &str)> = actual
+            .iter()
+            .map(|i| (i.name.as_str(), i.quantity, i.unit.as_str()))
+            .collect();
+        assert_eq!(actual.len(), expected.len(), "{}: {:?}", case, actual);
+        for ((name, quantity, unit), (e_name, e_quantity, e_unit)) in actual.iter().zip(expected) {
+            assert_eq!((name, unit), (e_name, e_unit), "{}", case);
+            assert!(
+                (quantity - e_quantity).abs() < 1e-2,
+                "{}: {} {} of `{}` instead of {}",
+                case,
+                quantity,
+                unit,
+                name,
+                e_quantity
+            );
+        }
+    }
+
+    #[test]
+    fn aggregates_required_ingredients() {
+        #[rustfmt::skip]
+        let cases: [(&str, Vec<RequiredIngredient>, Expected); 4] = [
+            ("same unit", vec![required("eggs", 2.0, ""), required("Egg", 1.0, "")], &[("eggs", 3.0, "")]),
+            ("compatible units", vec![required("milk", 1.0, "cup"), required("whole milk", 2.0, "tbsp")], &[("milk", 1.125, "cup")]),
+            ("incompatible units", vec![required("garlic", 2.0, "clove"), required("garlic", 10.0, "g")], &[("garlic", 2.0, "clove"), ("garlic", 10.0, "g")]),
+            ("different names", vec![required("milk", 1.0, "cup"), required("milk chocolate chips", 1.0, "cup")], &[("milk", 1.0, "cup"), ("milk chocolate chips", 1.0, "cup")]),
+        ];
+
+        for (case, required, expected) in cases {
+            assert_quantities(&aggregate_required(required), expected, case);
+        }
+    }
+
+    #[test]
+    fn computes_missing_ingredients() {
+        #[rustfmt::skip]
+        let cases: [(&str, RequiredIngredient, Vec<Ingredient>, Expected); 7] = [
+            ("not in the fridge", required("onion", 2.0, ""), vec![], &[("onion", 2.0, "")]),
+            ("not enough", required("onion", 3.0, ""), vec![fridge_ingredient("Onions", 1.0, "", 5)], &[("onion", 2.0, "")]),
+            ("enough", required("onion", 3.0, ""), vec![fridge_ingredient("onion", 3.0, "", 5)], &[]),
+            ("converted", required("flour", 500.0, "g"), vec![fridge_ingredient("flour", 0.25, "kg", 5)], &[("flour", 250.0, "g")]),
+            ("summed", required("milk", 1.0, "l"), vec![fridge_ingredient("milk", 250.0, "ml", 5), fridge_ingredient("whole milk", 500.0, "ml", 5)], &[("milk", 0.25, "l")]),
+            ("incomparable units", required("garlic", 3.0, "clove"), vec![fridge_ingredient("garlic", 1.0, "Bags", 5)], &[]),
+            ("other ingredient", required("milk", 1.0, "cup"), vec![fridge_ingredient("milk chocolate chips", 1.0, "cup", 5)], &[("milk", 1.0, "cup")]),
+        ];
+
+        for (case, required, fridge, expected) in cases {
+            assert_quantities(&missing_ingredients(&[required], &fridge), expected, case);
+        }
+    }
+
+    #[test]
+    fn subtracts_what_the_shopping_list_has() {
+        #[rustfmt::skip]
+        let cases: [(&str, RequiredIngredient, Vec<ShoppingItem>, Expected); 6] = [
+            ("not on the list", required("onion", 2.0, ""), vec![], &[("onion", 2.0, "")]),
+            ("partly on the list", required("onion", 3.0, ""), vec![shopping_item("onions", 1.0, "", false)], &[("onion", 2.0, "")]),
+            ("on the list", required("onion", 3.0, ""), vec![shopping_item("onion", 5.0, "", false)], &[]),
+            ("converted", required("flour", 500.0, "g"), vec![shopping_item("flour", 0.25, "kg", false)], &[("flour", 250.0, "g")]),
+            ("already bought", required("onion", 3.0, ""), vec![shopping_item("onion", 3.0, "", true)], &[("onion", 3.0, "")]),
+            ("incomparable units", required("garlic", 3.0, "clove"), vec![shopping_item("garlic", 1.0, "Bags", false)], &[("garlic", 3.0, "clove")]),
+        ];
+
+        for (case, missing, shopping_list, expected) in cases {
+            assert_quantities(
+                &not_on_shopping_list(vec![missing], &shopping_list),
+                expected,
+                case,
+            );
+        }
+    }
+
+    #[test]
+    fn finds_expired_ingredients() {
+        let date = Utc.with_ymd_and_hms(2024, 3, 4, 0, 0, 0).unwrap();
+        let cases = [
+            ("none matching", vec![], 0),
+            (
+                "all expired",
+                vec![
+                    fridge_ingredient("milk", 1.0, "l", 1),
+                    fridge_ingredient("whole milk", 1.0, "l", 2),
+                ],
+                2,
+            ),
+            (
+                "one still good",
+                vec![
+                    fridge_ingredient("milk", 1.0, "l", 1),
+                    fridge_ingredient("milk", 1.0, "l", 5),
+                ],
+                0,
+            ),
+            (
+                "other ingredient expired",
+                vec![fridge_ingredient("eggs", 1.0, "", 1)],
+                0,
+            ),
+        ];
+
+        for (case, fridge, expected) in cases {
+            assert_eq!(
+                expired_by(&required("milk", 1.0, "l"), &fridge, date).len(),
+                expected,
+                "{}",
+                case
+            );
+        }
+    }
+}
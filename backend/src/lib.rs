@@ -7,16 +7,49 @@ use clap::Parser;
 use hmac::{digest::KeyInit, Hmac};
 
 use jwt::{SignWithKey, VerifyWithKey};
-use models::{Ingredient, JWT_Token, Recipe, RecipeIngredient};
+use metrics::timed_query;
+use models::{
+    CookHistoryEntry, CustomTheme, HouseholdMember, HouseholdRole, Ingredient, InventoryEvent,
+    InventoryEventType, JWT_Token, MealPlanEntry, MealSlot, Recipe, RecipeIngredient, RecipeRating,
+    RemovalReason, SavedRecipe, ShoppingItem, StorageKind, StorageLocation,
+};
 use rand::{thread_rng, Rng};
+use request_id::record_user_id;
 
 use serde_json::{Map, Value};
 use sha2::{Digest, Sha256};
-use tokio_postgres::{types::FromSql, Client, Row};
-
+use tokio_postgres::{
+    types::{FromSql, Json},
+    Client, GenericClient, Row,
+};
+
+pub mod bulk_ingredients;
+pub mod change_feed;
+mod custom_recipes;
+mod dietary;
+mod households;
+pub mod idempotency;
+mod ingredient_lots;
+mod ingredient_parser;
+mod inventory;
+pub mod metrics;
+pub mod migrations;
 mod models;
+pub mod openapi;
+pub mod otlp;
+mod recipe_cache;
+mod recipe_ratings;
+pub mod request_id;
 mod responses;
 pub mod routes;
+mod shelf_life;
+mod stats;
+mod storage;
+mod sync;
+mod themes;
+pub mod trash;
+mod units;
+pub mod v2;
 
 pub const APP_SECRET: &[u8] = b"super-secret-key";
 
@@ -49,6 +82,27 @@ pub struct Params {
     /// https://rapidapi.com/ptwebsolution/api/worldwide-recipes1.
     #[arg(long, env, default_value = "worldwide-recipes1.p.rapidapi.com")]
     pub rapid_api_host: String,
+
+    /// How many days removed ingredients stay in the trash before being purged.
+    #[arg(long, env, default_value_t = 30)]
+    pub trash_retention_days: i64,
+
+    /// How many minutes to wait between purges of the trash.
+    #[arg(long, env, default_value_t = 60)]
+    pub trash_purge_interval_minutes: u64,
+
+    /// How many hours the responses of requests with an `Idempotency-Key` are kept to replay them.
+    #[arg(long, env, default_value_t = 24)]
+    pub idempotency_key_retention_hours: i64,
+
+    /// How many connections of their own the routes that run a transaction can have open at once.
+    #[arg(long, env, default_value_t = 8)]
+    pub transaction_pool_size: usize,
+
+    /// The OpenTelemetry collector to export the spans of the requests to, like `http://localhost:4318`.
+    /// The spans aren't exported when it's missing.
+    #[arg(long, env)]
+    pub otlp_endpoint: Option<String>,
 }
 
 fn resolve_host(host: &str) -> io::Result<SocketAddr> {
@@ -88,9 +142,24 @@ pub enum ExtractJWTErrors {
 pub fn extract_jwt(secret: &[u8], token: &str) -> Result<JWT_Token, ExtractJWTErrors> {
     let secret_key: Hmac<Sha256> =
         Hmac::new_from_slice(secret).map_err(|_| ExtractJWTErrors::ErrorGeneratingHmacKey)?;
-    token
+    let token: JWT_Token = token
         .verify_with_key(&secret_key)
-        .map_err(|_| ExtractJWTErrors::ErrorExtractingWithKey)
+        .map_err(|_| ExtractJWTErrors::ErrorExtractingWithKey)?;
+    record_user_id(&token.user_id);
+    Ok(token)
+}
+
+const SHARE_CODE_LENGTH: usize = 8;
+
+/// Letters and digits that can't be confused with each other when typed by hand.
+const SHARE_CODE_ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+
+/// Generates a random code for users to share something, like a theme or an invitation.
+fn generate_share_code() -> String {
+    let mut rng = thread_rng();
+    (0..SHARE_CODE_LENGTH)
+        .map(|_| SHARE_CODE_ALPHABET[rng.gen_range(0..SHARE_CODE_ALPHABET.len())] as char)
+        .collect()
 }
 
 /// Encrypts the given password with a random 16 bytes salt.
@@ -119,6 +188,7 @@ fn obtain_salt(db_password: &str) -> Result<Vec<u8>, base64::DecodeError> {
     Ok(decoded_bytes[0..16].to_vec())
 }
 
+#[allow(dead_code)]
 #[derive(Debug)]
 enum IsSessionValidErrors {
     InternalDBError(tokio_postgres::Error),
@@ -150,18 +220,20 @@ async fn is_session_valid(
     conn: &Client,
 ) -> Result<(), IsSessionValidErrors> {
     let current_date = Utc::now();
-    let rows = conn
-        .query(
+    let rows = timed_query(
+        "session",
+        conn.query(
             "SELECT user_id, expire_date FROM sf_session WHERE session_id=$1",
             &[&session_id],
-        )
-        .await
-        .map_err(IsSessionValidErrors::InternalDBError)?;
+        ),
+    )
+    .await
+    .map_err(IsSessionValidErrors::InternalDBError)?;
     if rows.is_empty() {
         return Err(IsSessionValidErrors::NoSessionWithId(session_id));
     }
 
-    let row = rows.get(0).unwrap();
+    let row = rows.first().unwrap();
     let db_user_id = row.get::<usize, String>(0);
     let db_expire_date = row.get::<usize, DateTime<Utc>>(1);
 
@@ -244,6 +316,22 @@ fn parse_db_ingredient(row: &Row, tracing_prefix: &str) -> Option<Ingredient> {
 
     let unit = from_db_to_value(row, "unit", tracing_prefix)?;
 
+    let location_id = from_db_to_value::<&str>(row, "location_id", tracing_prefix)?
+        .parse()
+        .map_err(|err| {
+            tracing::error!(
+                "{} An error `{:?}` occurred while parsing row field `{}`",
+                tracing_prefix,
+                err,
+                "location_id"
+            );
+        })
+        .ok()?;
+
+    let updated_at = from_db_to_value(row, "updated_at", tracing_prefix)?;
+
+    let version = from_db_to_value(row, "version", tracing_prefix)?;
+
     Some(Ingredient {
         ingredient_id,
         user_id,
@@ -252,6 +340,430 @@ fn parse_db_ingredient(row: &Row, tracing_prefix: &str) -> Option<Ingredient> {
         category,
         quantity,
         unit,
+        location_id,
+        updated_at,
+        version,
+        lots: vec![],
+    })
+}
+
+#[allow(dead_code)]
+#[derive(Debug)]
+enum GetIngredientErrors {
+    InternalDBError(tokio_postgres::Error),
+    InvalidIngredientFromDB,
+}
+
+/// Gets an ingredient of the household with its lots, `None` if it doesn't exist or it's in the trash.
+async fn get_ingredient(
+    conn: &impl GenericClient,
+    household_id: &str,
+    ingredient_id: &str,
+    tracing_prefix: &str,
+) -> Result<Option<Ingredient>, GetIngredientErrors> {
+    let Some(row) = timed_query(
+        "get_ingredient",
+        conn.query_opt(
+            "SELECT * FROM sf_ingredient WHERE ingredient_id=$1 AND household_id=$2 AND deleted_date IS NULL",
+            &[&ingredient_id, &household_id],
+        ),
+    )
+    .await
+    .map_err(GetIngredientErrors::InternalDBError)?
+    else {
+        return Ok(None);
+    };
+
+    let mut ingredient = parse_db_ingredient(&row, tracing_prefix)
+        .ok_or(GetIngredientErrors::InvalidIngredientFromDB)?;
+    ingredient.lots = ingredient_lots::get_lots(conn, &[ingredient_id.to_string()])
+        .await
+        .map_err(GetIngredientErrors::InternalDBError)?
+        .remove(&ingredient.ingredient_id)
+        .unwrap_or_default();
+
+    Ok(Some(ingredient))
+}
+
+/// Parses an Inventory Event from a DB Row.
+fn parse_db_inventory_event(row: &Row, tracing_prefix: &str) -> Option<InventoryEvent> {
+    let parse_id = |field: &str| -> Option<uuid::Uuid> {
+        from_db_to_value::<&str>(row, field, tracing_prefix)?
+            .parse()
+            .map_err(|err| {
+                tracing::error!(
+                    "{} An error `{:?}` occurred while parsing row field `{}`",
+                    tracing_prefix,
+                    err,
+                    field
+                );
+            })
+            .ok()
+    };
+
+    let event_id = parse_id("event_id")?;
+
+    let ingredient_id = parse_id("ingredient_id")?;
+
+    let user_id = parse_id("user_id")?;
+
+    let name = from_db_to_value(row, "name", tracing_prefix)?;
+
+    let category = from_db_to_value(row, "category", tracing_prefix)?;
+
+    let quantity = from_db_to_value(row, "quantity", tracing_prefix)?;
+
+    let unit = from_db_to_value(row, "unit", tracing_prefix)?;
+
+    let event_type =
+        InventoryEventType::from_str(from_db_to_value::<&str>(row, "event_type", tracing_prefix)?)
+            .map_err(|err| {
+                tracing::error!(
+                    "{} An error `{:?}` occurred while parsing row field `{}`",
+                    tracing_prefix,
+                    err,
+                    "event_type"
+                );
+            })
+            .ok()?;
+
+    let reason = match from_db_to_value::<Option<&str>>(row, "reason", tracing_prefix)? {
+        Some(reason) => Some(
+            RemovalReason::from_str(reason)
+                .map_err(|err| {
+                    tracing::error!(
+                        "{} An error `{:?}` occurred while parsing row field `{}`",
+                        tracing_prefix,
+                        err,
+                        "reason"
+                    );
+                })
+                .ok()?,
+        ),
+        None => None,
+    };
+
+    let expire_date = from_db_to_value(row, "expire_date", tracing_prefix)?;
+
+    let event_date = from_db_to_value(row, "event_date", tracing_prefix)?;
+
+    Some(InventoryEvent {
+        event_id,
+        ingredient_id,
+        user_id,
+        name,
+        category,
+        quantity,
+        unit,
+        event_type,
+        reason,
+        expire_date,
+        event_date,
+    })
+}
+
+/// Parses a Storage Location from a DB Row.
+fn parse_db_storage_location(row: &Row, tracing_prefix: &str) -> Option<StorageLocation> {
+    let location_id = from_db_to_value::<&str>(row, "location_id", tracing_prefix)?
+        .parse()
+        .map_err(|err| {
+            tracing::error!(
+                "{} An error `{:?}` occurred while parsing row field `{}`",
+                tracing_prefix,
+                err,
+                "location_id"
+            );
+        })
+        .ok()?;
+
+    let name = from_db_to_value(row, "name", tracing_prefix)?;
+
+    let kind = StorageKind::from_str(from_db_to_value::<&str>(row, "kind", tracing_prefix)?)
+        .map_err(|err| {
+            tracing::error!(
+                "{} An error `{:?}` occurred while parsing row field `{}`",
+                tracing_prefix,
+                err,
+                "kind"
+            );
+        })
+        .ok()?;
+
+    let is_default = from_db_to_value(row, "is_default", tracing_prefix)?;
+
+    let ingredient_count = from_db_to_value(row, "ingredient_count", tracing_prefix)?;
+
+    Some(StorageLocation {
+        location_id,
+        name,
+        kind,
+        is_default,
+        ingredient_count,
+    })
+}
+
+/// Parses a Shopping Item from a DB Row.
+fn parse_db_shopping_item(row: &Row, tracing_prefix: &str) -> Option<ShoppingItem> {
+    let item_id = from_db_to_value::<&str>(row, "item_id", tracing_prefix)?
+        .parse()
+        .map_err(|err| {
+            tracing::error!(
+                "{} An error `{:?}` occurred while parsing row field `{}`",
+                tracing_prefix,
+                err,
+                "item_id"
+            );
+        })
+        .ok()?;
+
+    let user_id = from_db_to_value::<&str>(row, "user_id", tracing_prefix)?
+        .parse()
+        .map_err(|err| {
+            tracing::error!(
+                "{} An error `{:?}` occurred while parsing row field `{}`",
+                tracing_prefix,
+                err,
+                "user_id"
+            );
+        })
+        .ok()?;
+
+    let name = from_db_to_value(row, "name", tracing_prefix)?;
+
+    let category = from_db_to_value(row, "category", tracing_prefix)?;
+
+    let quantity = from_db_to_value(row, "quantity", tracing_prefix)?;
+
+    let unit = from_db_to_value(row, "unit", tracing_prefix)?;
+
+    let checked = from_db_to_value(row, "checked", tracing_prefix)?;
+
+    let recipe_ids = from_db_to_value(row, "recipe_ids", tracing_prefix)?;
+
+    Some(ShoppingItem {
+        item_id,
+        user_id,
+        name,
+        category,
+        quantity,
+        unit,
+        checked,
+        recipe_ids,
+    })
+}
+
+/// Parses a Meal Plan Entry from a DB Row, the recipe is left empty.
+fn parse_db_meal_plan_entry(row: &Row, tracing_prefix: &str) -> Option<MealPlanEntry> {
+    let plan_id = from_db_to_value::<&str>(row, "plan_id", tracing_prefix)?
+        .parse()
+        .map_err(|err| {
+            tracing::error!(
+                "{} An error `{:?}` occurred while parsing row field `{}`",
+                tracing_prefix,
+                err,
+                "plan_id"
+            );
+        })
+        .ok()?;
+
+    let user_id = from_db_to_value::<&str>(row, "user_id", tracing_prefix)?
+        .parse()
+        .map_err(|err| {
+            tracing::error!(
+                "{} An error `{:?}` occurred while parsing row field `{}`",
+                tracing_prefix,
+                err,
+                "user_id"
+            );
+        })
+        .ok()?;
+
+    let recipe_id = from_db_to_value(row, "recipe_id", tracing_prefix)?;
+
+    let date = from_db_to_value(row, "date", tracing_prefix)?;
+
+    let slot = MealSlot::from_str(from_db_to_value::<&str>(row, "slot", tracing_prefix)?)
+        .map_err(|err| {
+            tracing::error!(
+                "{} An error `{:?}` occurred while parsing row field `{}`",
+                tracing_prefix,
+                err,
+                "slot"
+            );
+        })
+        .ok()?;
+
+    Some(MealPlanEntry {
+        plan_id,
+        user_id,
+        recipe_id,
+        date,
+        slot,
+        recipe: None,
+    })
+}
+
+/// Parses a Saved Recipe from a DB Row.
+fn parse_db_saved_recipe(row: &Row, tracing_prefix: &str) -> Option<SavedRecipe> {
+    let Json(recipe) = from_db_to_value(row, "recipe", tracing_prefix)?;
+
+    let notes = from_db_to_value(row, "notes", tracing_prefix)?;
+
+    let tags = from_db_to_value(row, "tags", tracing_prefix)?;
+
+    let saved_date = from_db_to_value(row, "saved_date", tracing_prefix)?;
+
+    Some(SavedRecipe {
+        recipe,
+        notes,
+        tags,
+        saved_date,
+    })
+}
+
+/// Parses a Cook History Entry from a DB Row, the recipe is left empty.
+fn parse_db_cook_history_entry(row: &Row, tracing_prefix: &str) -> Option<CookHistoryEntry> {
+    let cook_id = from_db_to_value::<&str>(row, "cook_id", tracing_prefix)?
+        .parse()
+        .map_err(|err| {
+            tracing::error!(
+                "{} An error `{:?}` occurred while parsing row field `{}`",
+                tracing_prefix,
+                err,
+                "cook_id"
+            );
+        })
+        .ok()?;
+
+    let recipe_id = from_db_to_value(row, "recipe_id", tracing_prefix)?;
+
+    let cooked_date = from_db_to_value(row, "cooked_date", tracing_prefix)?;
+
+    Some(CookHistoryEntry {
+        cook_id,
+        recipe_id,
+        cooked_date,
+        recipe: None,
+    })
+}
+
+/// Parses a Recipe Rating from a DB Row.
+fn parse_db_recipe_rating(row: &Row, tracing_prefix: &str) -> Option<RecipeRating> {
+    let recipe_id = from_db_to_value(row, "recipe_id", tracing_prefix)?;
+
+    let rating = from_db_to_value(row, "rating", tracing_prefix)?;
+
+    let comment = from_db_to_value(row, "comment", tracing_prefix)?;
+
+    let rated_date = from_db_to_value(row, "rated_date", tracing_prefix)?;
+
+    Some(RecipeRating {
+        recipe_id,
+        rating,
+        comment,
+        rated_date,
+    })
+}
+
+/// Parses a Household Member from a DB Row joined with the user.
+fn parse_db_household_member(row: &Row, tracing_prefix: &str) -> Option<HouseholdMember> {
+    let user_id = from_db_to_value::<&str>(row, "user_id", tracing_prefix)?
+        .parse()
+        .map_err(|err| {
+            tracing::error!(
+                "{} An error `{:?}` occurred while parsing row field `{}`",
+                tracing_prefix,
+                err,
+                "user_id"
+            );
+        })
+        .ok()?;
+
+    let username = from_db_to_value(row, "username", tracing_prefix)?;
+
+    let role = HouseholdRole::from_str(from_db_to_value::<&str>(row, "role", tracing_prefix)?)
+        .map_err(|err| {
+            tracing::error!(
+                "{} An error `{:?}` occurred while parsing row field `{}`",
+                tracing_prefix,
+                err,
+                "role"
+            );
+        })
+        .ok()?;
+
+    let joined_date = from_db_to_value(row, "joined_date", tracing_prefix)?;
+
+    Some(HouseholdMember {
+        user_id,
+        username,
+        role,
+        joined_date,
+    })
+}
+
+/// Parses a Custom Theme from a DB Row.
+fn parse_db_custom_theme(row: &Row, tracing_prefix: &str) -> Option<CustomTheme> {
+    let theme_id = from_db_to_value::<&str>(row, "theme_id", tracing_prefix)?
+        .parse()
+        .map_err(|err| {
+            tracing::error!(
+                "{} An error `{:?}` occurred while parsing row field `{}`",
+                tracing_prefix,
+                err,
+                "theme_id"
+            );
+        })
+        .ok()?;
+
+    let name = from_db_to_value(row, "name", tracing_prefix)?;
+
+    let primary = from_db_to_value(row, "primary_color", tracing_prefix)?;
+
+    let secondary = from_db_to_value(row, "secondary_color", tracing_prefix)?;
+
+    let background = from_db_to_value(row, "background_color", tracing_prefix)?;
+
+    let surface = from_db_to_value(row, "surface_color", tracing_prefix)?;
+
+    let share_code = from_db_to_value(row, "share_code", tracing_prefix)?;
+
+    Some(CustomTheme {
+        theme_id,
+        name,
+        primary,
+        secondary,
+        background,
+        surface,
+        share_code,
+    })
+}
+
+/// Parses a Recipe written by a user from a DB Row.
+fn parse_db_custom_recipe(row: &Row, tracing_prefix: &str) -> Option<Recipe> {
+    let recipe_id = from_db_to_value(row, "recipe_id", tracing_prefix)?;
+
+    let title = from_db_to_value(row, "title", tracing_prefix)?;
+
+    let banner: Option<String> = from_db_to_value(row, "image_url", tracing_prefix)?;
+
+    let tags = from_db_to_value(row, "tags", tracing_prefix)?;
+
+    let Json(ingredients) = from_db_to_value(row, "ingredients", tracing_prefix)?;
+
+    let steps = from_db_to_value(row, "steps", tracing_prefix)?;
+
+    Some(Recipe {
+        recipe_id,
+        title,
+        banner: banner.unwrap_or_default(),
+        tags,
+        ingredients,
+        source: String::new(),
+        steps,
+        user_rating: None,
+        last_cooked_date: None,
+        dietary_violations: vec![],
     })
 }
 
@@ -290,7 +802,7 @@ fn parse_api_recipe_from_value(value: &Map<String, Value>) -> Option<Recipe> {
             }
         })
         .collect();
-        tags.sort_by(|a, b| a.1.cmp(&b.1));
+        tags.sort_by_key(|a| a.1);
         let tags: Vec<String> = tags.into_iter().map(|(tag, _)| tag).take(2).collect();
 
         let title = if let Value::String(a) = details.get("name")? {
@@ -305,7 +817,7 @@ fn parse_api_recipe_from_value(value: &Map<String, Value>) -> Option<Recipe> {
             None?
         };
 
-        let banner = if let Value::Object(a) = images.get(0)? {
+        let banner = if let Value::Object(a) = images.first()? {
             a
         } else {
             None?
@@ -336,13 +848,13 @@ fn parse_api_recipe_from_value(value: &Map<String, Value>) -> Option<Recipe> {
                     None?
                 };
 
-                let name = if let Value::String(a) = json_ingredient.get("ingredient")? {
-                    a.to_string()
+                let name = if let Some(Value::String(a)) = json_ingredient.get("ingredient") {
+                    Some(a.to_string())
                 } else {
-                    None?
+                    None
                 };
 
-                Some(RecipeIngredient { name, display })
+                Some(RecipeIngredient::from_display(name, display))
             })
             .collect();
 
@@ -353,6 +865,10 @@ fn parse_api_recipe_from_value(value: &Map<String, Value>) -> Option<Recipe> {
             tags,
             ingredients,
             source,
+            steps: vec![],
+            user_rating: None,
+            last_cooked_date: None,
+            dietary_violations: vec![],
         })
     } else {
         let content = if let Value::Object(a) = value.get("content")? {
@@ -1,15 +1,55 @@
 #![recursion_limit = "256"]
 use std::{net::SocketAddr, sync::Arc};
 
-use axum::{response::IntoResponse, routing::post, Router};
+use axum::{
+    middleware::{from_fn, from_fn_with_state},
+    response::IntoResponse,
+    routing::{get, post},
+    Router,
+};
 use backend::{
+    bulk_ingredients::TransactionPool,
+    change_feed::{spawn_change_listener, ChangeFeed},
+    idempotency::{idempotency, spawn_idempotency_purge_job, IdempotencyState},
+    metrics::{metrics, spawn_db_probe, track_requests},
+    migrations::run_migrations,
+    openapi::{docs, openapi_json},
+    otlp::OtlpLayer,
+    request_id::request_id,
     routes::{
-        add_ingredient::add_ingredient, edit_ingredient::edit_ingredient,
-        get_ingredients::get_ingredients, get_recipes::get_recipes, login_user::login_user,
-        logout::logout, recipe_details::recipe_details, register_user::register_user,
-        remove_ingredient::remove_ingredient, save_settings::save_settings,
-        search_ingredients::search_ingredients, search_recipes::search_recipes,
+        add_custom_recipe::add_custom_recipe, add_ingredient::add_ingredient,
+        add_recipe_to_shopping_list::add_recipe_to_shopping_list,
+        add_shopping_item::add_shopping_item, add_storage_location::add_storage_location,
+        add_theme::add_theme, assign_meal::assign_meal, bulk_add_ingredients::bulk_add_ingredients,
+        bulk_edit_ingredients::bulk_edit_ingredients,
+        bulk_remove_ingredients::bulk_remove_ingredients, buy_shopping_items::buy_shopping_items,
+        consume_ingredient::consume_ingredient, create_household::create_household_route,
+        edit_custom_recipe::edit_custom_recipe, edit_ingredient::edit_ingredient,
+        edit_shopping_item::edit_shopping_item, get_cook_history::get_cook_history,
+        get_custom_recipes::get_custom_recipes, get_households::get_households,
+        get_ingredient::get_ingredient_route, get_ingredients::get_ingredients,
+        get_inventory_history::get_inventory_history, get_meal_plan::get_meal_plan,
+        get_recipe_ratings::get_recipe_ratings, get_recipes::get_recipes,
+        get_saved_recipes::get_saved_recipes, get_settings::get_settings,
+        get_shopping_list::get_shopping_list, get_stats::get_stats,
+        get_storage_locations::get_storage_locations, get_themes::get_themes, get_trash::get_trash,
+        import_theme::import_theme, invite_to_household::invite_to_household,
+        join_household::join_household, leave_household::leave_household, login_user::login_user,
+        logout::logout, meal_plan_requirements::meal_plan_requirements,
+        move_ingredients::move_ingredients, rate_recipe::rate_recipe,
+        recipe_details::recipe_details, record_cooked_recipe::record_cooked_recipe,
+        register_user::register_user, remove_custom_recipe::remove_custom_recipe,
+        remove_household_member::remove_household_member, remove_ingredient::remove_ingredient,
+        remove_meal::remove_meal, remove_shopping_item::remove_shopping_item,
+        remove_storage_location::remove_storage_location, remove_theme::remove_theme,
+        restore_ingredient::restore_ingredient, save_recipe::save_recipe,
+        save_settings::save_settings, search_ingredients::search_ingredients,
+        search_recipes::search_recipes, set_member_role::set_member_role,
+        stream_changes::stream_changes, switch_household::switch_household,
+        sync_changes::sync_changes, unsave_recipe::unsave_recipe,
     },
+    trash::spawn_purge_job,
+    v2::{self, ApiV2State},
     Params,
 };
 use clap::Parser;
@@ -28,6 +68,7 @@ async fn main() -> Result<(), Error> {
                 .unwrap_or_else(|_| "backend=debug,tower_http=debug".into()),
         )
         .with(tracing_subscriber::fmt::layer())
+        .with(params.otlp_endpoint.clone().map(OtlpLayer::spawn))
         .init();
 
     tracing::debug!("Connecting to DB...");
@@ -47,13 +88,59 @@ async fn main() -> Result<(), Error> {
     });
     tracing::debug!("Connection with DB established!");
 
-    start_server_on(params.server_host, Arc::new(Some(client)), Arc::new(params)).await;
+    tracing::debug!("Applying migrations...");
+    let applied = run_migrations(&client).await?;
+    tracing::debug!("Migrations applied: {:?}", applied);
+
+    let client = Arc::new(Some(client));
+
+    tracing::debug!(
+        "Purging trash every {} minutes...",
+        params.trash_purge_interval_minutes
+    );
+    spawn_purge_job(
+        client.clone(),
+        params.trash_retention_days,
+        params.trash_purge_interval_minutes,
+    );
+
+    tracing::debug!(
+        "Purging idempotency keys older than {} hours...",
+        params.idempotency_key_retention_hours
+    );
+    spawn_idempotency_purge_job(client.clone(), params.idempotency_key_retention_hours);
+
+    tracing::debug!("Listening to changes...");
+    let changes = spawn_change_listener(params.db_connection.clone());
+
+    let transactions = Arc::new(TransactionPool::new(
+        params.db_connection.clone(),
+        params.transaction_pool_size,
+    ));
+
+    tracing::debug!("Probing the DB connection...");
+    spawn_db_probe(client.clone(), transactions.clone());
+
+    start_server_on(
+        params.server_host,
+        client,
+        changes,
+        transactions,
+        Arc::new(params),
+    )
+    .await;
 
     Ok(())
 }
 
 /// Starts a server on the specified address
-async fn start_server_on(addr: SocketAddr, client: Arc<Option<Client>>, params: Arc<Params>) {
+async fn start_server_on(
+    addr: SocketAddr,
+    client: Arc<Option<Client>>,
+    changes: Arc<ChangeFeed>,
+    transactions: Arc<TransactionPool>,
+    params: Arc<Params>,
+) {
     tracing::debug!("Listening on `{}` ...", addr);
 
     let cors = if cfg!(debug_assertions) {
@@ -66,7 +153,11 @@ async fn start_server_on(addr: SocketAddr, client: Arc<Option<Client>>, params:
     };
 
     axum::Server::bind(&addr)
-        .serve(app(client.clone(), params).layer(cors).into_make_service())
+        .serve(
+            app(client.clone(), changes, transactions, params)
+                .layer(cors)
+                .into_make_service(),
+        )
         .await
         .unwrap();
 }
@@ -74,7 +165,12 @@ async fn start_server_on(addr: SocketAddr, client: Arc<Option<Client>>, params:
 /// Having a function that produces our app makes it easy to call it from tests
 /// without having to create an HTTP server.
 #[allow(dead_code)]
-fn app(db_client: Arc<Option<Client>>, params: Arc<Params>) -> Router {
+fn app(
+    db_client: Arc<Option<Client>>,
+    changes: Arc<ChangeFeed>,
+    transactions: Arc<TransactionPool>,
+    params: Arc<Params>,
+) -> Router {
     let db_c_1 = db_client.clone();
     let db_c_2 = db_client.clone();
     let db_c_3 = db_client.clone();
@@ -86,14 +182,132 @@ fn app(db_client: Arc<Option<Client>>, params: Arc<Params>) -> Router {
     let db_c_9 = db_client.clone();
     let db_c_10 = db_client.clone();
     let db_c_11 = db_client.clone();
+    let db_c_12 = db_client.clone();
+    let db_c_13 = db_client.clone();
+    let db_c_14 = db_client.clone();
+    let db_c_15 = db_client.clone();
+    let db_c_16 = db_client.clone();
+    let db_c_17 = db_client.clone();
+    let db_c_18 = db_client.clone();
+    let db_c_19 = db_client.clone();
+    let db_c_20 = db_client.clone();
+    let db_c_21 = db_client.clone();
+    let db_c_22 = db_client.clone();
+    let db_c_23 = db_client.clone();
+    let db_c_24 = db_client.clone();
+    let db_c_25 = db_client.clone();
+    let db_c_26 = db_client.clone();
+    let db_c_27 = db_client.clone();
+    let db_c_28 = db_client.clone();
+    let db_c_29 = db_client.clone();
+    let db_c_30 = db_client.clone();
+    let db_c_31 = db_client.clone();
+    let db_c_32 = db_client.clone();
+    let db_c_33 = db_client.clone();
+    let db_c_34 = db_client.clone();
+    let db_c_35 = db_client.clone();
+    let db_c_36 = db_client.clone();
+    let db_c_37 = db_client.clone();
+    let db_c_38 = db_client.clone();
+    let db_c_39 = db_client.clone();
+    let db_c_40 = db_client.clone();
+    let db_c_41 = db_client.clone();
+    let db_c_42 = db_client.clone();
+    let db_c_43 = db_client.clone();
+    let db_c_44 = db_client.clone();
+    let db_c_45 = db_client.clone();
+    let db_c_46 = db_client.clone();
+    let db_c_47 = db_client.clone();
+    let db_c_48 = db_client.clone();
+    let db_c_49 = db_client.clone();
+    let db_c_50 = db_client.clone();
+    let db_c_51 = db_client.clone();
+    let db_c_52 = db_client.clone();
+    let db_c_53 = db_client.clone();
+    let db_c_54 = db_client.clone();
+    let db_c_55 = db_client.clone();
+    let db_c_56 = db_client.clone();
+    let db_c_57 = db_client.clone();
+    let db_c_58 = db_client.clone();
+    let db_c_59 = db_client.clone();
+    let db_c_60 = db_client.clone();
 
     let params_2 = params.clone();
+    let params_3 = params.clone();
+
+    let transactions_1 = transactions.clone();
+    let transactions_2 = transactions.clone();
+    let transactions_3 = transactions.clone();
+    let transactions_4 = transactions.clone();
+    let transactions_5 = transactions.clone();
+
+    let idempotency_state = IdempotencyState {
+        client: db_client.clone(),
+        retention_hours: params.idempotency_key_retention_hours,
+    };
+    let v2_state = ApiV2State {
+        client: db_client.clone(),
+        changes: changes.clone(),
+        params: params.clone(),
+        transactions: transactions.clone(),
+    };
+
+    // Mutating routes replay their response when retried with the same `Idempotency-Key`.
+    let idempotent = from_fn_with_state(idempotency_state.clone(), idempotency);
 
-    Router::new()
+    let v1 = Router::new()
         .route("/user/register", post(|p| register_user(p, db_client)))
         .route("/user/login", post(|p| login_user(p, db_c_1)))
         .route("/user/logout", post(|p| logout(p, db_c_2)))
-        .route("/settings/save", post(|p| save_settings(p, db_c_7)))
+        .route("/settings", post(|p| get_settings(p, db_c_33)))
+        .route(
+            "/settings/save",
+            post(|p| save_settings(p, db_c_7)).layer(idempotent.clone()),
+        )
+        // Households
+        .route("/households", post(|p| get_households(p, db_c_38)))
+        .route(
+            "/households/create",
+            post(|p| create_household_route(p, db_c_39)).layer(idempotent.clone()),
+        )
+        .route(
+            "/households/invite",
+            post(|p| invite_to_household(p, db_c_40)).layer(idempotent.clone()),
+        )
+        .route(
+            "/households/join",
+            post(|p| join_household(p, db_c_41)).layer(idempotent.clone()),
+        )
+        .route(
+            "/households/switch",
+            post(|p| switch_household(p, db_c_42)).layer(idempotent.clone()),
+        )
+        .route(
+            "/households/leave",
+            post(|p| leave_household(p, db_c_43)).layer(idempotent.clone()),
+        )
+        .route(
+            "/households/members/role",
+            post(|p| set_member_role(p, db_c_44)).layer(idempotent.clone()),
+        )
+        .route(
+            "/households/members/remove",
+            post(|p| remove_household_member(p, db_c_45)).layer(idempotent.clone()),
+        )
+        // Themes
+        .route("/themes", post(|p| get_themes(p, db_c_34)))
+        .route(
+            "/themes/add",
+            post(|p| add_theme(p, db_c_35)).layer(idempotent.clone()),
+        )
+        .route(
+            "/themes/remove",
+            post(|p| remove_theme(p, db_c_36)).layer(idempotent.clone()),
+        )
+        .route(
+            "/themes/import",
+            post(|p| import_theme(p, db_c_37)).layer(idempotent.clone()),
+        )
         // Recipes
         .route("/recipes", post(|p| get_recipes(p, db_c_3, params)))
         .route(
@@ -101,19 +315,163 @@ fn app(db_client: Arc<Option<Client>>, params: Arc<Params>) -> Router {
             post(|p| search_recipes(p, db_c_4, params_2)),
         )
         .route("/recipes/details", post(|p| recipe_details(p, db_c_8)))
+        .route("/recipes/saved", post(|p| get_saved_recipes(p, db_c_22)))
+        .route(
+            "/recipes/save",
+            post(|p| save_recipe(p, db_c_23)).layer(idempotent.clone()),
+        )
+        .route(
+            "/recipes/unsave",
+            post(|p| unsave_recipe(p, db_c_24)).layer(idempotent.clone()),
+        )
+        .route("/recipes/custom", post(|p| get_custom_recipes(p, db_c_25)))
+        .route(
+            "/recipes/custom/add",
+            post(|p| add_custom_recipe(p, db_c_26)).layer(idempotent.clone()),
+        )
+        .route(
+            "/recipes/custom/edit",
+            post(|p| edit_custom_recipe(p, db_c_27)).layer(idempotent.clone()),
+        )
+        .route(
+            "/recipes/custom/remove",
+            post(|p| remove_custom_recipe(p, db_c_28)).layer(idempotent.clone()),
+        )
+        .route(
+            "/recipes/cooked",
+            post(|p| record_cooked_recipe(p, db_c_29)).layer(idempotent.clone()),
+        )
+        .route("/recipes/history", post(|p| get_cook_history(p, db_c_30)))
+        .route(
+            "/recipes/rate",
+            post(|p| rate_recipe(p, db_c_31)).layer(idempotent.clone()),
+        )
+        .route("/recipes/ratings", post(|p| get_recipe_ratings(p, db_c_32)))
         // Ingredients
         .route("/ingredients", post(|p| get_ingredients(p, db_c_5)))
-        .route("/ingredients/add", post(|p| add_ingredient(p, db_c_9)))
-        .route("/ingredients/edit", post(|p| edit_ingredient(p, db_c_10)))
+        .route(
+            "/ingredients/add",
+            post(|p| add_ingredient(p, db_c_9)).layer(idempotent.clone()),
+        )
+        .route(
+            "/ingredients/edit",
+            post(|p| edit_ingredient(p, db_c_10)).layer(idempotent.clone()),
+        )
         .route(
             "/ingredients/remove",
-            post(|p| remove_ingredient(p, db_c_11)),
+            post(|p| remove_ingredient(p, db_c_11)).layer(idempotent.clone()),
+        )
+        .route(
+            "/ingredients/get",
+            post(|p| get_ingredient_route(p, db_c_60)),
         )
         .route(
             "/ingredients/search",
             post(|p| search_ingredients(p, db_c_6)),
         )
+        .route(
+            "/ingredients/move",
+            post(|p| move_ingredients(p, db_c_46, transactions_1)).layer(idempotent.clone()),
+        )
+        .route(
+            "/ingredients/consume",
+            post(|p| consume_ingredient(p, db_c_57)).layer(idempotent.clone()),
+        )
+        .route(
+            "/ingredients/add/bulk",
+            post(|p| bulk_add_ingredients(p, db_c_54, transactions_2)).layer(idempotent.clone()),
+        )
+        .route(
+            "/ingredients/edit/bulk",
+            post(|p| bulk_edit_ingredients(p, db_c_55, transactions_3)).layer(idempotent.clone()),
+        )
+        .route(
+            "/ingredients/remove/bulk",
+            post(|p| bulk_remove_ingredients(p, db_c_56, transactions_4)).layer(idempotent.clone()),
+        )
+        .route(
+            "/ingredients/trash",
+            post(|p| get_trash(p, db_c_50, params_3)),
+        )
+        .route(
+            "/ingredients/history",
+            post(|p| get_inventory_history(p, db_c_52)),
+        )
+        .route(
+            "/ingredients/restore",
+            post(|p| restore_ingredient(p, db_c_51)).layer(idempotent.clone()),
+        )
+        // Storage locations
+        .route("/locations", post(|p| get_storage_locations(p, db_c_47)))
+        .route(
+            "/locations/add",
+            post(|p| add_storage_location(p, db_c_48)).layer(idempotent.clone()),
+        )
+        .route(
+            "/locations/remove",
+            post(|p| remove_storage_location(p, db_c_49)).layer(idempotent.clone()),
+        )
+        // Sync
+        .route(
+            "/sync",
+            post(|p| sync_changes(p, db_c_58, transactions_5)).layer(idempotent.clone()),
+        )
+        .route("/changes", post(|p| stream_changes(p, db_c_59, changes)))
+        // Statistics
+        .route("/stats", post(|p| get_stats(p, db_c_53)))
+        // Shopping list
+        .route("/shopping-list", post(|p| get_shopping_list(p, db_c_12)))
+        .route(
+            "/shopping-list/add",
+            post(|p| add_shopping_item(p, db_c_13)).layer(idempotent.clone()),
+        )
+        .route(
+            "/shopping-list/edit",
+            post(|p| edit_shopping_item(p, db_c_14)).layer(idempotent.clone()),
+        )
+        .route(
+            "/shopping-list/remove",
+            post(|p| remove_shopping_item(p, db_c_15)).layer(idempotent.clone()),
+        )
+        .route(
+            "/shopping-list/recipe",
+            post(|p| add_recipe_to_shopping_list(p, db_c_16)).layer(idempotent.clone()),
+        )
+        .route(
+            "/shopping-list/bought",
+            post(|p| buy_shopping_items(p, db_c_17)).layer(idempotent.clone()),
+        )
+        // Meal plan
+        .route("/meal-plan", post(|p| get_meal_plan(p, db_c_18)))
+        .route(
+            "/meal-plan/assign",
+            post(|p| assign_meal(p, db_c_19)).layer(idempotent.clone()),
+        )
+        .route(
+            "/meal-plan/remove",
+            post(|p| remove_meal(p, db_c_20)).layer(idempotent.clone()),
+        )
+        .route(
+            "/meal-plan/requirements",
+            post(|p| meal_plan_requirements(p, db_c_21)),
+        );
+
+    // The routes without a version are kept for the apps that don't use `/v1` yet.
+    let mut router = Router::new()
+        .merge(v1.clone())
+        .nest("/v1", v1)
+        .nest("/v2", v2::router(v2_state, idempotency_state))
+        .route("/openapi.json", get(openapi_json))
+        .route("/metrics", get(metrics));
+    if cfg!(debug_assertions) {
+        router = router.route("/docs", get(docs));
+    }
+
+    // Added last so they wrap every route, including the fallback.
+    router
         .fallback(handle_404)
+        .layer(from_fn(track_requests))
+        .layer(from_fn(request_id))
 }
 
 async fn handle_404() -> impl IntoResponse {
@@ -0,0 +1,431 @@
+//! Counters and histograms of the server, served at `/metrics` in the Prometheus text format.
+//!
+//! The metrics are kept in a global registry so the routes and helpers can record them
+//! without having it passed around.
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    future::Future,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use axum::{
+    body::Body,
+    extract::MatchedPath,
+    http::{header::CONTENT_TYPE, Request},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use tokio_postgres::Client;
+
+use crate::bulk_ingredients::TransactionPool;
+
+/// How often the DB connection is probed.
+const DB_PROBE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Upper bounds, in seconds, of the buckets of the latency histograms.
+const LATENCY_BUCKETS: [f64; 12] = [
+    0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+const HTTP_REQUESTS: &str = "smartfridge_http_requests_total";
+const HTTP_REQUEST_DURATION: &str = "smartfridge_http_request_duration_seconds";
+const HTTP_REQUESTS_IN_FLIGHT: &str = "smartfridge_http_requests_in_flight";
+const ROUTE_ERRORS: &str = "smartfridge_route_errors_total";
+const DB_QUERY_DURATION: &str = "smartfridge_db_query_duration_seconds";
+const DB_CONNECTION_UP: &str = "smartfridge_db_connection_up";
+const DB_POOL_CONNECTIONS_IN_USE: &str = "smartfridge_db_pool_connections_in_use";
+const DB_POOL_SIZE: &str = "smartfridge_db_pool_size";
+const RECIPE_API_REQUESTS: &str = "smartfridge_recipe_api_requests_total";
+const RECIPE_API_REQUEST_DURATION: &str = "smartfridge_recipe_api_request_duration_seconds";
+const CACHE_REQUESTS: &str = "smartfridge_cache_requests_total";
+const CACHE_HIT_RATIO: &str = "smartfridge_cache_hit_ratio";
+const IDEMPOTENT_REQUESTS: &str = "smartfridge_idempotent_requests_total";
+const OTLP_SPANS_DROPPED: &str = "smartfridge_otlp_spans_dropped_total";
+
+/// The name, type and help of every metric, in the order they're rendered.
+const METRICS: &[(&str, &str, &str)] = &[
+    (
+        HTTP_REQUESTS,
+        "counter",
+        "Requests handled, by route and status.",
+    ),
+    (
+        HTTP_REQUEST_DURATION,
+        "histogram",
+        "Time taken to handle the requests, by route and status.",
+    ),
+    (
+        HTTP_REQUESTS_IN_FLIGHT,
+        "gauge",
+        "Requests being handled right now.",
+    ),
+    (
+        ROUTE_ERRORS,
+        "counter",
+        "Errors returned by the routes, by error enum and variant.",
+    ),
+    (
+        DB_QUERY_DURATION,
+        "histogram",
+        "Time taken by the DB queries, by the function running them, and by the connection probe.",
+    ),
+    (
+        DB_CONNECTION_UP,
+        "gauge",
+        "Whether the DB connection shared by the routes is up, transactions use the connections of the pool instead.",
+    ),
+    (
+        DB_POOL_CONNECTIONS_IN_USE,
+        "gauge",
+        "Connections of the transaction pool in use, sampled with the connection probe.",
+    ),
+    (
+        DB_POOL_SIZE,
+        "gauge",
+        "Connections the transaction pool can have open at once.",
+    ),
+    (
+        RECIPE_API_REQUESTS,
+        "counter",
+        "Requests sent to the WorldWide Recipes API, by endpoint and outcome.",
+    ),
+    (
+        RECIPE_API_REQUEST_DURATION,
+        "histogram",
+        "Time taken by the WorldWide Recipes API, by endpoint.",
+    ),
+    (
+        CACHE_REQUESTS,
+        "counter",
+        "Lookups in the caches, by cache and result.",
+    ),
+    (
+        CACHE_HIT_RATIO,
+        "gauge",
+        "Share of the lookups in the caches that were hits.",
+    ),
+    (
+        IDEMPOTENT_REQUESTS,
+        "counter",
+        "Requests sent with an `Idempotency-Key`, by whether they ran, were replayed or were rejected.",
+    ),
+    (
+        OTLP_SPANS_DROPPED,
+        "counter",
+        "Request spans dropped because the queue of the OTLP export was full.",
+    ),
+];
+
+type Labels = Vec<(&'static str, String)>;
+
+#[derive(Clone, Default)]
+struct Histogram {
+    buckets: [u64; LATENCY_BUCKETS.len()],
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, seconds: f64) {
+        if let Some(bucket) = LATENCY_BUCKETS.iter().position(|le| seconds <= *le) {
+            self.buckets[bucket] += 1;
+        }
+        self.sum += seconds;
+        self.count += 1;
+    }
+}
+
+struct Registry {
+    counters: BTreeMap<(&'static str, Labels), u64>,
+    gauges: BTreeMap<(&'static str, Labels), f64>,
+    histograms: BTreeMap<(&'static str, Labels), Histogram>,
+}
+
+static REGISTRY: Mutex<Registry> = Mutex::new(Registry {
+    counters: BTreeMap::new(),
+    gauges: BTreeMap::new(),
+    histograms: BTreeMap::new(),
+});
+
+fn with_registry(f: impl FnOnce(&mut Registry)) {
+    // A panic while recording can't leave the registry half updated, so a poisoned lock is still usable.
+    let mut registry = REGISTRY.lock().unwrap_or_else(|err| err.into_inner());
+    f(&mut registry)
+}
+
+fn increment(name: &'static str, labels: Labels, by: u64) {
+    with_registry(|registry| *registry.counters.entry((name, labels)).or_default() += by);
+}
+
+fn add_to_gauge(name: &'static str, labels: Labels, value: f64) {
+    with_registry(|registry| *registry.gauges.entry((name, labels)).or_default() += value);
+}
+
+fn set_gauge(name: &'static str, labels: Labels, value: f64) {
+    with_registry(|registry| {
+        registry.gauges.insert((name, labels), value);
+    });
+}
+
+fn observe(name: &'static str, labels: Labels, duration: Duration) {
+    with_registry(|registry| {
+        registry
+            .histograms
+            .entry((name, labels))
+            .or_default()
+            .observe(duration.as_secs_f64())
+    });
+}
+
+/// Counts a request as in flight until it's dropped, even when the client goes away before the response.
+struct InFlightRequest;
+
+impl InFlightRequest {
+    fn start() -> Self {
+        add_to_gauge(HTTP_REQUESTS_IN_FLIGHT, vec![], 1.0);
+        InFlightRequest
+    }
+}
+
+impl Drop for InFlightRequest {
+    fn drop(&mut self) {
+        add_to_gauge(HTTP_REQUESTS_IN_FLIGHT, vec![], -1.0);
+    }
+}
+
+/// Middleware that counts the requests and how long they take, by route and status.
+pub async fn track_requests(request: Request<Body>, next: Next<Body>) -> Response {
+    let method = request.method().to_string();
+    // The path of the route instead of the one requested, so ids don't create new series.
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "fallback".to_string());
+
+    let in_flight = InFlightRequest::start();
+    let start = Instant::now();
+    let response = next.run(request).await;
+    let duration = start.elapsed();
+    drop(in_flight);
+
+    let labels = vec![
+        ("method", method),
+        ("route", route),
+        ("status", response.status().as_u16().to_string()),
+    ];
+    increment(HTTP_REQUESTS, labels.clone(), 1);
+    observe(HTTP_REQUEST_DURATION, labels, duration);
+
+    response
+}
+
+/// Counts an error returned by a route, `message` being the `Display` of the error enum.
+pub fn record_route_error<T>(message: &str) {
+    let error = std::any::type_name::<T>()
+        .rsplit("::")
+        .next()
+        .unwrap_or_default()
+        .to_string();
+    let variant = message
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .next()
+        .unwrap_or_default()
+        .to_string();
+    increment(
+        ROUTE_ERRORS,
+        vec![("error", error), ("variant", variant)],
+        1,
+    );
+}
+
+/// Runs a DB query, recording how long it took under `query`.
+pub async fn timed_query<F: Future>(query: &'static str, future: F) -> F::Output {
+    let start = Instant::now();
+    let output = future.await;
+    observe(
+        DB_QUERY_DURATION,
+        vec![("query", query.to_string())],
+        start.elapsed(),
+    );
+    output
+}
+
+/// Records a request sent to the `endpoint` of the WorldWide Recipes API.
+pub fn record_recipe_api_call(
+    endpoint: &'static str,
+    duration: Duration,
+    response: &Result<reqwest::Response, reqwest::Error>,
+) {
+    let outcome = match response {
+        Ok(response) if response.status().is_success() => "success",
+        _ => "failure",
+    };
+    increment(
+        RECIPE_API_REQUESTS,
+        vec![
+            ("endpoint", endpoint.to_string()),
+            ("outcome", outcome.to_string()),
+        ],
+        1,
+    );
+    observe(
+        RECIPE_API_REQUEST_DURATION,
+        vec![("endpoint", endpoint.to_string())],
+        duration,
+    );
+}
+
+/// Records the lookups in `cache` that found what they looked for and the ones that didn't.
+pub fn record_cache_lookups(cache: &'static str, hits: u64, misses: u64) {
+    for (result, count) in [("hit", hits), ("miss", misses)] {
+        if count > 0 {
+            increment(
+                CACHE_REQUESTS,
+                vec![("cache", cache.to_string()), ("result", result.to_string())],
+                count,
+            );
+        }
+    }
+}
+
+/// Records what happened to a request sent with an `Idempotency-Key`.
+pub fn record_idempotent_request(outcome: &'static str) {
+    increment(
+        IDEMPOTENT_REQUESTS,
+        vec![("outcome", outcome.to_string())],
+        1,
+    );
+}
+
+/// Records a request span that couldn't be queued for the OTLP export.
+pub fn record_dropped_span() {
+    increment(OTLP_SPANS_DROPPED, vec![], 1);
+}
+
+/// Spawns a task that checks the DB connection is up and how long a trivial query takes,
+/// and samples how many connections of the transaction pool are in use.
+pub fn spawn_db_probe(client: Arc<Option<Client>>, transactions: Arc<TransactionPool>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(DB_PROBE_INTERVAL);
+        loop {
+            interval.tick().await;
+
+            let up = match client.as_ref() {
+                Some(conn) if !conn.is_closed() => {
+                    timed_query("probe", conn.execute("SELECT 1", &[]))
+                        .await
+                        .is_ok()
+                }
+                _ => false,
+            };
+            set_gauge(DB_CONNECTION_UP, vec![], if up { 1.0 } else { 0.0 });
+
+            let (in_use, size) = transactions.usage();
+            set_gauge(DB_POOL_CONNECTIONS_IN_USE, vec![], in_use as f64);
+            set_gauge(DB_POOL_SIZE, vec![], size as f64);
+        }
+    });
+}
+
+fn write_labels(out: &mut String, labels: &[(&str, String)], extra: Option<(&str, String)>) {
+    let labels: Vec<String> = labels
+        .iter()
+        .map(|(key, value)| (*key, value.clone()))
+        .chain(extra)
+        .map(|(key, value)| {
+            let value = value
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n");
+            format!("{}=\"{}\"", key, value)
+        })
+        .collect();
+    if !labels.is_empty() {
+        let _ = write!(out, "{{{}}}", labels.join(","));
+    }
+}
+
+/// Renders the metrics in the Prometheus text format.
+pub fn render() -> String {
+    let mut out = String::new();
+    with_registry(|registry| {
+        // The ratios are computed from the counters when they're scraped.
+        let mut lookups: BTreeMap<String, (u64, u64)> = BTreeMap::new();
+        for ((name, labels), count) in &registry.counters {
+            if *name != CACHE_REQUESTS {
+                continue;
+            }
+            let label = |key| {
+                labels
+                    .iter()
+                    .find(|(k, _)| *k == key)
+                    .map(|(_, v)| v.clone())
+                    .unwrap_or_default()
+            };
+            let entry = lookups.entry(label("cache")).or_default();
+            match label("result").as_str() {
+                "hit" => entry.0 += count,
+                _ => entry.1 += count,
+            }
+        }
+        for (cache, (hits, misses)) in lookups {
+            registry.gauges.insert(
+                (CACHE_HIT_RATIO, vec![("cache", cache)]),
+                hits as f64 / (hits + misses) as f64,
+            );
+        }
+
+        for (metric, kind, help) in METRICS {
+            let _ = writeln!(out, "# HELP {} {}", metric, help);
+            let _ = writeln!(out, "# TYPE {} {}", metric, kind);
+
+            for ((name, labels), value) in &registry.counters {
+                if name == metric {
+                    out.push_str(name);
+                    write_labels(&mut out, labels, None);
+                    let _ = writeln!(out, " {}", value);
+                }
+            }
+            for ((name, labels), value) in &registry.gauges {
+                if name == metric {
+                    out.push_str(name);
+                    write_labels(&mut out, labels, None);
+                    let _ = writeln!(out, " {}", value);
+                }
+            }
+            for ((name, labels), histogram) in &registry.histograms {
+                if name != metric {
+                    continue;
+                }
+                let mut cumulative = 0;
+                for (le, count) in LATENCY_BUCKETS.iter().zip(histogram.buckets) {
+                    cumulative += count;
+                    let _ = write!(out, "{}_bucket", name);
+                    write_labels(&mut out, labels, Some(("le", le.to_string())));
+                    let _ = writeln!(out, " {}", cumulative);
+                }
+                let _ = write!(out, "{}_bucket", name);
+                write_labels(&mut out, labels, Some(("le", "+Inf".to_string())));
+                let _ = writeln!(out, " {}", histogram.count);
+                let _ = write!(out, "{}_sum", name);
+                write_labels(&mut out, labels, None);
+                let _ = writeln!(out, " {}", histogram.sum);
+                let _ = write!(out, "{}_count", name);
+                write_labels(&mut out, labels, None);
+                let _ = writeln!(out, " {}", histogram.count);
+            }
+        }
+    });
+    out
+}
+
+pub async fn metrics() -> impl IntoResponse {
+    (
+        [(CONTENT_TYPE, "text/plain; version=0.0.4; charset=utf-8")],
+        render(),
+    )
+}
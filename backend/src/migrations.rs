@@ -0,0 +1,157 @@
+//! Applies the SQL files in `backend/migrations` on top of the schema created by `db.sql`.
+//!
+//! Every migration runs once, in order, in its own transaction, and is recorded in
+//! `sf_schema_migration` so it's skipped the next time the server starts.
+
+use tokio_postgres::Client;
+
+/// The migrations by version, in the order they're applied.
+const MIGRATIONS: &[(&str, &str)] = &[
+    (
+        "001_shopping_list",
+        include_str!("../migrations/001_shopping_list.sql"),
+    ),
+    (
+        "002_meal_plan",
+        include_str!("../migrations/002_meal_plan.sql"),
+    ),
+    (
+        "003_saved_recipes",
+        include_str!("../migrations/003_saved_recipes.sql"),
+    ),
+    (
+        "004_custom_recipes",
+        include_str!("../migrations/004_custom_recipes.sql"),
+    ),
+    (
+        "005_recipe_ratings",
+        include_str!("../migrations/005_recipe_ratings.sql"),
+    ),
+    (
+        "006_dietary_restrictions",
+        include_str!("../migrations/006_dietary_restrictions.sql"),
+    ),
+    (
+        "007_settings_v2",
+        include_str!("../migrations/007_settings_v2.sql"),
+    ),
+    (
+        "008_custom_themes",
+        include_str!("../migrations/008_custom_themes.sql"),
+    ),
+    (
+        "009_households",
+        include_str!("../migrations/009_households.sql"),
+    ),
+    (
+        "010_storage_locations",
+        include_str!("../migrations/010_storage_locations.sql"),
+    ),
+    (
+        "011_ingredient_trash",
+        include_str!("../migrations/011_ingredient_trash.sql"),
+    ),
+    (
+        "012_inventory_events",
+        include_str!("../migrations/012_inventory_events.sql"),
+    ),
+    (
+        "013_inventory_event_expiry",
+        include_str!("../migrations/013_inventory_event_expiry.sql"),
+    ),
+    (
+        "014_ingredient_lots",
+        include_str!("../migrations/014_ingredient_lots.sql"),
+    ),
+    (
+        "015_ingredient_lots_backfill",
+        include_str!("../migrations/015_ingredient_lots_backfill.sql"),
+    ),
+    ("016_sync", include_str!("../migrations/016_sync.sql")),
+    (
+        "017_ingredient_versions",
+        include_str!("../migrations/017_ingredient_versions.sql"),
+    ),
+    (
+        "018_change_feed",
+        include_str!("../migrations/018_change_feed.sql"),
+    ),
+    (
+        "019_idempotency_keys",
+        include_str!("../migrations/019_idempotency_keys.sql"),
+    ),
+    (
+        "020_sync_change_xid",
+        include_str!("../migrations/020_sync_change_xid.sql"),
+    ),
+    (
+        "021_idempotency_key_user",
+        include_str!("../migrations/021_idempotency_key_user.sql"),
+    ),
+];
+
+/// Applies the migrations that haven't been applied yet to the DB.
+///
+/// Returns the versions applied. A migration that fails is rolled back and the ones after it
+/// aren't applied.
+pub async fn run_migrations(conn: &Client) -> Result<Vec<&'static str>, tokio_postgres::Error> {
+    conn.batch_execute(
+        "CREATE TABLE IF NOT EXISTS sf_schema_migration (version TEXT PRIMARY KEY, applied_at TIMESTAMPTZ NOT NULL DEFAULT NOW())",
+    )
+    .await?;
+
+    let applied: Vec<String> = conn
+        .query("SELECT version FROM sf_schema_migration", &[])
+        .await?
+        .iter()
+        .map(|row| row.get(0))
+        .collect();
+
+    let mut newly_applied = vec![];
+    for (version, sql) in MIGRATIONS {
+        if applied.iter().any(|v| v == version) {
+            continue;
+        }
+
+        conn.batch_execute("BEGIN").await?;
+        let result = async {
+            conn.batch_execute(sql).await?;
+            conn.execute(
+                "INSERT INTO sf_schema_migration (version) VALUES ($1)",
+                &[version],
+            )
+            .await
+        }
+        .await;
+
+        match result {
+            Ok(_) => conn.batch_execute("COMMIT").await?,
+            Err(err) => {
+                conn.batch_execute("ROLLBACK").await?;
+                return Err(err);
+            }
+        }
+        newly_applied.push(*version);
+    }
+
+    Ok(newly_applied)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_migration_file_is_applied_in_order() {
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/migrations");
+        let mut files: Vec<String> = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .filter_map(|name| name.strip_suffix(".sql").map(str::to_string))
+            .collect();
+        files.sort();
+
+        let versions: Vec<&str> = MIGRATIONS.iter().map(|(version, _)| *version).collect();
+        assert_eq!(versions, files);
+    }
+}
@@ -1,21 +1,92 @@
-use chrono::{DateTime, Utc};
-use serde::{Deserialize, Serialize};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{de::IgnoredAny, Deserialize, Deserializer, Serialize};
 use std::str::FromStr;
 use strum::EnumString;
+use utoipa::ToSchema;
 use uuid::Uuid;
 
+use crate::ingredient_parser::{parse_ingredient_line, ParsedIngredientLine};
+
 /// Represents the theme of the app the user selected.
-#[derive(Debug, Serialize, Deserialize, Default, EnumString)]
+#[derive(Debug, Serialize, Deserialize, Default, EnumString, ToSchema)]
 pub enum AppThemes {
     #[default]
     Light,
     Dark,
     Foxy,
     DarkOcean,
+    /// One of the themes defined by the users, see [UserSettings::custom_theme_id].
+    Custom,
+}
+
+/// Represents a color theme defined by a user.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CustomTheme {
+    #[serde(rename = "ThemeId")]
+    pub theme_id: Uuid,
+
+    #[serde(rename = "Name")]
+    pub name: String,
+
+    /// All the colors are hex values like "#1A2B3C".
+    #[serde(rename = "Primary")]
+    pub primary: String,
+
+    #[serde(rename = "Secondary")]
+    pub secondary: String,
+
+    #[serde(rename = "Background")]
+    pub background: String,
+
+    #[serde(rename = "Surface")]
+    pub surface: String,
+
+    /// The code other users can use to get a copy of the theme.
+    #[serde(rename = "ShareCode")]
+    pub share_code: String,
+}
+
+/// Represents a diet the user follows, recipes with ingredients it forbids are flagged.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, EnumString, ToSchema)]
+pub enum DietaryPreference {
+    Vegetarian,
+    Vegan,
+    Pescatarian,
+    GlutenFree,
+    DairyFree,
+    NutFree,
+}
+
+/// The version of the settings schema, clients sending older versions only send some fields.
+pub const SETTINGS_SCHEMA_VERSION: i32 = 2;
+
+/// Represents the system of units the client shows quantities in.
+#[derive(Debug, Serialize, Deserialize, Default, Clone, Copy, EnumString, ToSchema)]
+pub enum UnitSystem {
+    #[default]
+    Metric,
+    Imperial,
+}
+
+/// Represents the notifications the user wants to receive.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct NotificationPreferences {
+    #[serde(rename = "ExpiringIngredients", default = "enabled")]
+    pub expiring_ingredients: bool,
+
+    #[serde(rename = "MealPlanReminders", default = "enabled")]
+    pub meal_plan_reminders: bool,
+
+    #[serde(rename = "ShoppingListUpdates", default = "enabled")]
+    pub shopping_list_updates: bool,
+}
+
+fn enabled() -> bool {
+    true
 }
 
 /// Represents the settings the user has for the client app.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct UserSettings {
     #[serde(rename = "SettingsId")]
     settings_id: Uuid,
@@ -25,13 +96,53 @@ pub struct UserSettings {
 
     #[serde(rename = "Theme")]
     theme: AppThemes,
+
+    /// The theme defined by a user that's used when the theme is `Custom`.
+    #[serde(rename = "CustomThemeId")]
+    custom_theme_id: Option<Uuid>,
+
+    #[serde(rename = "Diets")]
+    diets: Vec<DietaryPreference>,
+
+    /// Allergens and other ingredients the user doesn't want in their recipes.
+    #[serde(rename = "ExcludedIngredients")]
+    excluded_ingredients: Vec<String>,
+
+    /// The version of the schema the settings were last saved with.
+    #[serde(rename = "SchemaVersion")]
+    schema_version: i32,
+
+    #[serde(rename = "Language")]
+    language: String,
+
+    #[serde(rename = "UnitSystem")]
+    unit_system: UnitSystem,
+
+    /// How many days before an ingredient expires the user wants to be warned.
+    #[serde(rename = "ExpiryWarningDays")]
+    expiry_warning_days: i32,
+
+    #[serde(rename = "Notifications")]
+    notifications: NotificationPreferences,
+
+    #[serde(rename = "DefaultServings")]
+    default_servings: i32,
+
+    /// When the settings last changed.
+    #[serde(rename = "UpdatedAt")]
+    updated_at: DateTime<Utc>,
 }
 
+#[allow(clippy::enum_variant_names)]
 #[derive(Debug)]
 pub enum FromTokioRowToUserSettingsErrors {
     FailedParsingSettingsId,
     FailedParsingUserId,
     FailedParsingTheme,
+    FailedParsingCustomThemeId,
+    FailedParsingDiets,
+    FailedParsingUnitSystem,
+    FailedParsingNotifications,
 }
 
 impl TryFrom<&tokio_postgres::Row> for UserSettings {
@@ -41,6 +152,16 @@ impl TryFrom<&tokio_postgres::Row> for UserSettings {
         let settings_id: String = value.get("settings_id");
         let user_id: String = value.get("user_id");
         let theme: String = value.get("theme");
+        let custom_theme_id: Option<String> = value.get("custom_theme_id");
+        let diets: Vec<String> = value.get("diets");
+        let excluded_ingredients: Vec<String> = value.get("excluded_ingredients");
+        let schema_version: i32 = value.get("schema_version");
+        let language: String = value.get("language");
+        let unit_system: String = value.get("unit_system");
+        let expiry_warning_days: i32 = value.get("expiry_warning_days");
+        let notifications: serde_json::Value = value.get("notifications");
+        let default_servings: i32 = value.get("default_servings");
+        let updated_at: DateTime<Utc> = value.get("updated_at");
 
         let settings_id = settings_id
             .parse()
@@ -50,17 +171,40 @@ impl TryFrom<&tokio_postgres::Row> for UserSettings {
             .map_err(|_| FromTokioRowToUserSettingsErrors::FailedParsingUserId)?;
         let theme = AppThemes::from_str(&theme)
             .map_err(|_| FromTokioRowToUserSettingsErrors::FailedParsingTheme)?;
+        let custom_theme_id = custom_theme_id
+            .map(|id| id.parse())
+            .transpose()
+            .map_err(|_| FromTokioRowToUserSettingsErrors::FailedParsingCustomThemeId)?;
+        let diets = diets
+            .iter()
+            .map(|d| DietaryPreference::from_str(d))
+            .collect::<Result<_, _>>()
+            .map_err(|_| FromTokioRowToUserSettingsErrors::FailedParsingDiets)?;
+        let unit_system = UnitSystem::from_str(&unit_system)
+            .map_err(|_| FromTokioRowToUserSettingsErrors::FailedParsingUnitSystem)?;
+        let notifications = serde_json::from_value(notifications)
+            .map_err(|_| FromTokioRowToUserSettingsErrors::FailedParsingNotifications)?;
 
         Ok(UserSettings {
             settings_id,
             user_id,
             theme,
+            custom_theme_id,
+            diets,
+            excluded_ingredients,
+            schema_version,
+            language,
+            unit_system,
+            expiry_warning_days,
+            notifications,
+            default_servings,
+            updated_at,
         })
     }
 }
 
 /// Represents an ingredient that the user needs.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct Ingredient {
     #[serde(rename = "IngredientId")]
     pub ingredient_id: Uuid,
@@ -82,10 +226,536 @@ pub struct Ingredient {
 
     #[serde(rename = "Unit")]
     pub unit: String,
+
+    #[serde(rename = "LocationId")]
+    pub location_id: Uuid,
+
+    /// When the ingredient last changed, it's used by the clients to detect conflicting changes.
+    #[serde(rename = "UpdatedAt")]
+    pub updated_at: DateTime<Utc>,
+
+    /// Incremented on every change, edits and removals must send it to detect concurrent changes.
+    #[serde(rename = "Version")]
+    pub version: i32,
+
+    /// The batches of the ingredient, the quantity and expire date of the ingredient
+    /// are the total and the earliest of them. Only filled by the routes that list ingredients.
+    #[serde(rename = "Lots", default, skip_serializing_if = "Vec::is_empty")]
+    pub lots: Vec<IngredientLot>,
+}
+
+/// Represents a batch of an ingredient bought at a given time.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, ToSchema)]
+pub struct IngredientLot {
+    #[serde(rename = "LotId")]
+    pub lot_id: Uuid,
+
+    /// In the unit of the ingredient.
+    #[serde(rename = "Quantity")]
+    pub quantity: f32,
+
+    #[serde(rename = "ExpireDate")]
+    pub expire_date: DateTime<Utc>,
+
+    /// Unknown for the batches ingredients had before lots were stored.
+    #[serde(rename = "AddedDate")]
+    pub added_date: Option<DateTime<Utc>>,
+}
+
+/// Represents an ingredient in the trash.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct TrashedIngredient {
+    #[serde(flatten)]
+    pub ingredient: Ingredient,
+
+    #[serde(rename = "DeletedDate")]
+    pub deleted_date: DateTime<Utc>,
+
+    /// When the ingredient will be deleted for good.
+    #[serde(rename = "PurgeDate")]
+    pub purge_date: DateTime<Utc>,
+}
+
+/// Represents what happened to an ingredient.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, EnumString, ToSchema)]
+pub enum InventoryEventType {
+    Added,
+    Edited,
+    /// Part of the ingredient was used, the quantity of the event is the one used.
+    Consumed,
+    Moved,
+    Removed,
+    Restored,
+}
+
+/// Represents why an ingredient was removed.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, EnumString, ToSchema)]
+pub enum RemovalReason {
+    Eaten,
+    Wasted,
+    Expired,
+}
+
+/// Represents an entry of the history of the ingredients of a household.
+///
+/// The name, category, quantity and unit are the ones the ingredient had after the event.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct InventoryEvent {
+    #[serde(rename = "EventId")]
+    pub event_id: Uuid,
+
+    #[serde(rename = "IngredientId")]
+    pub ingredient_id: Uuid,
+
+    /// The user that made the change.
+    #[serde(rename = "UserId")]
+    pub user_id: Uuid,
+
+    #[serde(rename = "Name")]
+    pub name: String,
+
+    #[serde(rename = "Category")]
+    pub category: String,
+
+    #[serde(rename = "Quantity")]
+    pub quantity: f32,
+
+    #[serde(rename = "Unit")]
+    pub unit: String,
+
+    #[serde(rename = "EventType")]
+    pub event_type: InventoryEventType,
+
+    /// Only removals have a reason, and only when the client supplied it.
+    #[serde(rename = "Reason")]
+    pub reason: Option<RemovalReason>,
+
+    /// Events logged before the expire dates were recorded don't have it.
+    #[serde(rename = "ExpireDate")]
+    pub expire_date: Option<DateTime<Utc>>,
+
+    #[serde(rename = "EventDate")]
+    pub event_date: DateTime<Utc>,
+}
+
+/// Represents the size of the periods the statistics are grouped by.
+#[derive(Debug, Serialize, Deserialize, Default, Clone, Copy, PartialEq, Eq, ToSchema)]
+pub enum StatsPeriod {
+    Week,
+    #[default]
+    Month,
+}
+
+/// Represents what happened to the ingredients of a household in a range of dates.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct WasteStats {
+    #[serde(rename = "FromDate")]
+    pub from_date: DateTime<Utc>,
+
+    #[serde(rename = "ToDate")]
+    pub to_date: DateTime<Utc>,
+
+    #[serde(rename = "Period")]
+    pub period: StatsPeriod,
+
+    #[serde(rename = "ItemsAdded")]
+    pub items_added: u32,
+
+    #[serde(rename = "ItemsConsumed")]
+    pub items_consumed: u32,
+
+    /// Items removed because they were wasted or expired.
+    #[serde(rename = "ItemsDiscarded")]
+    pub items_discarded: u32,
+
+    /// Items removed before expiring without saying why.
+    #[serde(rename = "ItemsUnspecified")]
+    pub items_unspecified: u32,
+
+    #[serde(rename = "WastedByCategory")]
+    pub wasted_by_category: Vec<CategoryWaste>,
+
+    #[serde(rename = "MostWasted")]
+    pub most_wasted: Vec<WastedItem>,
+
+    /// One point per period in the range, oldest first.
+    #[serde(rename = "Trend")]
+    pub trend: Vec<StatsTrendPoint>,
+}
+
+/// Represents how much of a category was discarded.
+///
+/// Quantities are in grams or milliliters, items that are counted keep their unit.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CategoryWaste {
+    #[serde(rename = "Category")]
+    pub category: String,
+
+    #[serde(rename = "Quantity")]
+    pub quantity: f32,
+
+    #[serde(rename = "Unit")]
+    pub unit: String,
+}
+
+/// Represents an ingredient that was discarded several times.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct WastedItem {
+    #[serde(rename = "Name")]
+    pub name: String,
+
+    #[serde(rename = "Times")]
+    pub times: u32,
+}
+
+/// Represents the totals of a single period.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct StatsTrendPoint {
+    #[serde(rename = "PeriodStart")]
+    pub period_start: DateTime<Utc>,
+
+    #[serde(rename = "Added")]
+    pub added: u32,
+
+    #[serde(rename = "Consumed")]
+    pub consumed: u32,
+
+    #[serde(rename = "Discarded")]
+    pub discarded: u32,
+}
+
+/// Represents the result of an ingredient of a bulk request.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct BulkIngredientResult {
+    /// The position of the ingredient in the request.
+    #[serde(rename = "Index")]
+    pub index: usize,
+
+    #[serde(rename = "IngredientId")]
+    pub ingredient_id: Uuid,
+}
+
+/// Represents a change an offline client made to an ingredient.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SyncMutation {
+    /// Chosen by the client to match the mutation with its result.
+    #[serde(rename = "MutationId")]
+    pub mutation_id: Uuid,
+
+    #[serde(rename = "Kind")]
+    pub kind: SyncMutationKind,
+
+    /// Chosen by the client for the ingredients it creates offline.
+    #[serde(rename = "IngredientId")]
+    pub ingredient_id: Uuid,
+
+    /// The `UpdatedAt` of the ingredient when the client changed it, missing for new ingredients.
+    /// The mutation conflicts if the ingredient changed after it.
+    #[serde(rename = "BaseUpdatedAt", default)]
+    pub base_updated_at: Option<DateTime<Utc>>,
+
+    #[serde(rename = "Name", default)]
+    pub name: Option<String>,
+
+    #[serde(rename = "Category", default)]
+    pub category: Option<String>,
+
+    #[serde(rename = "Quantity", default)]
+    pub quantity: Option<f32>,
+
+    #[serde(rename = "Unit", default)]
+    pub unit: Option<String>,
+
+    #[serde(rename = "ExpireDate", default)]
+    pub expire_date: Option<DateTime<Utc>>,
+
+    /// New ingredients without a location go to the default fridge.
+    #[serde(rename = "LocationId", default)]
+    pub location_id: Option<Uuid>,
+
+    /// Why the ingredient was removed when deleting it.
+    #[serde(rename = "Reason", default)]
+    pub reason: Option<RemovalReason>,
+}
+
+/// Represents the kinds of changes a client can make offline.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+pub enum SyncMutationKind {
+    /// Creates the ingredient or replaces all of its fields.
+    Upsert,
+    /// Moves the ingredient to the trash.
+    Delete,
+}
+
+/// Represents what happened to a mutation uploaded by a client.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+pub enum SyncMutationStatus {
+    Applied,
+    /// The ingredient changed on the server after the client last saw it, so nothing was done.
+    Conflict,
+    /// The mutation is missing fields or references something that doesn't exist.
+    Rejected,
+}
+
+/// Represents the result of a mutation uploaded by a client.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SyncMutationResult {
+    #[serde(rename = "MutationId")]
+    pub mutation_id: Uuid,
+
+    #[serde(rename = "Status")]
+    pub status: SyncMutationStatus,
+
+    /// The version of the ingredient on the server when there was a conflict,
+    /// missing if the ingredient was removed.
+    #[serde(rename = "Ingredient", skip_serializing_if = "Option::is_none")]
+    pub ingredient: Option<Ingredient>,
+}
+
+/// Represents everything that changed since the last sync of a client.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SyncChanges {
+    /// Sent on the next sync to get only what changed after this one. Changes the client
+    /// already got may be sent again, the one with the highest `Version` is the current one.
+    /// Cursors are only valid for the household they were returned for.
+    #[serde(rename = "Cursor")]
+    pub cursor: i64,
+
+    #[serde(rename = "HouseholdId")]
+    pub household_id: String,
+
+    /// Ingredients that were created or changed.
+    #[serde(rename = "Ingredients")]
+    pub ingredients: Vec<Ingredient>,
+
+    /// Ingredients that were removed, the client should delete them.
+    #[serde(rename = "DeletedIngredientIds")]
+    pub deleted_ingredient_ids: Vec<Uuid>,
+
+    /// Missing when the settings didn't change.
+    #[serde(rename = "Settings")]
+    pub settings: Option<UserSettings>,
+
+    /// The results of the uploaded mutations, in the same order.
+    #[serde(rename = "Results")]
+    pub results: Vec<SyncMutationResult>,
+}
+
+/// Represents a change pushed to the connected clients as it happens.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+#[serde(tag = "Kind")]
+pub enum ChangeNotification {
+    /// An ingredient of the household was added, edited or removed.
+    Ingredient {
+        #[serde(rename = "HouseholdId")]
+        household_id: String,
+
+        /// The user that made the change.
+        #[serde(rename = "UserId")]
+        user_id: String,
+
+        #[serde(rename = "IngredientId")]
+        ingredient_id: String,
+
+        #[serde(rename = "EventType")]
+        event_type: InventoryEventType,
+    },
+    /// The settings of the user changed.
+    Settings {
+        #[serde(rename = "UserId")]
+        user_id: String,
+    },
+}
+
+impl ChangeNotification {
+    /// Checks if a user working on the household can see the change.
+    pub fn is_visible_to(&self, household_id: &str, user_id: &str) -> bool {
+        match self {
+            ChangeNotification::Ingredient {
+                household_id: change_household_id,
+                ..
+            } => change_household_id == household_id,
+            ChangeNotification::Settings {
+                user_id: change_user_id,
+            } => change_user_id == user_id,
+        }
+    }
+}
+
+/// Represents the kind of place an ingredient is stored in.
+///
+/// The kind decides how long the ingredients stored there last.
+#[derive(
+    Debug, Serialize, Deserialize, Default, Clone, Copy, PartialEq, Eq, EnumString, ToSchema,
+)]
+pub enum StorageKind {
+    #[default]
+    Fridge,
+    Freezer,
+    Pantry,
+}
+
+impl StorageKind {
+    /// The kinds every household has a default location for.
+    pub const ALL: [StorageKind; 3] = [
+        StorageKind::Fridge,
+        StorageKind::Freezer,
+        StorageKind::Pantry,
+    ];
+}
+
+/// Represents a place of a household where ingredients are stored.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct StorageLocation {
+    #[serde(rename = "LocationId")]
+    pub location_id: Uuid,
+
+    #[serde(rename = "Name")]
+    pub name: String,
+
+    #[serde(rename = "Kind")]
+    pub kind: StorageKind,
+
+    /// Default locations are created with the household and can't be removed.
+    #[serde(rename = "IsDefault")]
+    pub is_default: bool,
+
+    #[serde(rename = "IngredientCount")]
+    pub ingredient_count: i64,
+}
+
+/// Represents what a member can do in a household.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, EnumString, ToSchema)]
+pub enum HouseholdRole {
+    /// Manages the members and invitations, every household has one.
+    Owner,
+    /// Can change the ingredients.
+    Member,
+    /// Can only see the ingredients.
+    Viewer,
+}
+
+impl HouseholdRole {
+    pub fn can_edit_ingredients(&self) -> bool {
+        matches!(self, HouseholdRole::Owner | HouseholdRole::Member)
+    }
+}
+
+/// Represents a group of users that share a fridge.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct Household {
+    #[serde(rename = "HouseholdId")]
+    pub household_id: String,
+
+    #[serde(rename = "Name")]
+    pub name: String,
+
+    /// The role of the user requesting the household.
+    #[serde(rename = "Role")]
+    pub role: HouseholdRole,
+
+    /// Whether the ingredient routes are working on this household.
+    #[serde(rename = "Current")]
+    pub current: bool,
+
+    #[serde(rename = "Members")]
+    pub members: Vec<HouseholdMember>,
+}
+
+/// Represents a user that belongs to a household.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct HouseholdMember {
+    #[serde(rename = "UserId")]
+    pub user_id: Uuid,
+
+    #[serde(rename = "Username")]
+    pub username: String,
+
+    #[serde(rename = "Role")]
+    pub role: HouseholdRole,
+
+    #[serde(rename = "JoinedDate")]
+    pub joined_date: DateTime<Utc>,
+}
+
+/// Represents a code to join a household.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct HouseholdInvitation {
+    #[serde(rename = "Code")]
+    pub code: String,
+
+    /// The role the users joining with the code get.
+    #[serde(rename = "Role")]
+    pub role: HouseholdRole,
+
+    #[serde(rename = "ExpireDate")]
+    pub expire_date: DateTime<Utc>,
+}
+
+/// Represents an item of the user's shopping list.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ShoppingItem {
+    #[serde(rename = "ItemId")]
+    pub item_id: Uuid,
+
+    #[serde(rename = "UserId")]
+    pub user_id: Uuid,
+
+    #[serde(rename = "Name")]
+    pub name: String,
+
+    #[serde(rename = "Category")]
+    pub category: String,
+
+    #[serde(rename = "Quantity")]
+    pub quantity: f32,
+
+    #[serde(rename = "Unit")]
+    pub unit: String,
+
+    /// Whether the user already put the item in the cart.
+    #[serde(rename = "Checked")]
+    pub checked: bool,
+
+    /// The ids of the recipes this item was added for.
+    #[serde(rename = "RecipeIds")]
+    pub recipe_ids: Vec<String>,
+}
+
+/// Represents the meal of the day a recipe is planned for.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, EnumString, ToSchema)]
+pub enum MealSlot {
+    Breakfast,
+    Lunch,
+    Dinner,
+    Snack,
+}
+
+/// Represents a recipe the user plans to cook on a date.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct MealPlanEntry {
+    #[serde(rename = "PlanId")]
+    pub plan_id: Uuid,
+
+    #[serde(rename = "UserId")]
+    pub user_id: Uuid,
+
+    #[serde(rename = "RecipeId")]
+    pub recipe_id: String,
+
+    #[serde(rename = "Date")]
+    pub date: NaiveDate,
+
+    #[serde(rename = "Slot")]
+    pub slot: MealSlot,
+
+    /// The stored copy of the recipe, if we still have it.
+    #[serde(rename = "Recipe")]
+    pub recipe: Option<Recipe>,
 }
 
 /// Represents a Food Recipe in the app.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct Recipe {
     /// The tracking Id of the recipe.
     #[serde(rename = "RecipeId")]
@@ -105,18 +775,155 @@ pub struct Recipe {
 
     #[serde(rename = "Source")]
     pub source: String,
+
+    /// The preparation steps, only known for the recipes written by users.
+    #[serde(rename = "Steps", default)]
+    pub steps: Vec<String>,
+
+    /// The rating the user gave to the recipe, they're never read from stored copies.
+    #[serde(rename = "UserRating", default, deserialize_with = "ignore_field")]
+    #[schema(read_only)]
+    pub user_rating: Option<i16>,
+
+    #[serde(rename = "LastCookedDate", default, deserialize_with = "ignore_field")]
+    #[schema(read_only)]
+    pub last_cooked_date: Option<DateTime<Utc>>,
+
+    /// The ingredients that go against the diets or excluded ingredients of the user.
+    #[serde(
+        rename = "DietaryViolations",
+        default,
+        deserialize_with = "ignore_field"
+    )]
+    #[schema(read_only)]
+    pub dietary_violations: Vec<DietaryViolation>,
+}
+
+/// Ignores the value sent by the client, like `skip_deserializing` but the field stays in the API docs.
+fn ignore_field<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Default,
+{
+    IgnoredAny::deserialize(deserializer)?;
+    Ok(T::default())
+}
+
+/// Represents an ingredient of a recipe the user shouldn't eat.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct DietaryViolation {
+    #[serde(rename = "Ingredient")]
+    pub ingredient: String,
+
+    /// The diet the ingredient goes against, `None` when the user excluded it.
+    #[serde(rename = "Diet")]
+    pub diet: Option<DietaryPreference>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Represents a recipe the user saved to come back to later.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SavedRecipe {
+    /// The copy of the recipe at the moment it was saved.
+    #[serde(rename = "Recipe")]
+    pub recipe: Recipe,
+
+    #[serde(rename = "Notes")]
+    pub notes: Option<String>,
+
+    /// Tags the user gave to the recipe, not the ones of the recipe itself.
+    #[serde(rename = "Tags")]
+    pub tags: Vec<String>,
+
+    #[serde(rename = "SavedDate")]
+    pub saved_date: DateTime<Utc>,
+}
+
+/// Represents a time the user cooked a recipe.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CookHistoryEntry {
+    #[serde(rename = "CookId")]
+    pub cook_id: Uuid,
+
+    #[serde(rename = "RecipeId")]
+    pub recipe_id: String,
+
+    #[serde(rename = "CookedDate")]
+    pub cooked_date: DateTime<Utc>,
+
+    /// The stored copy of the recipe, if we still have it.
+    #[serde(rename = "Recipe")]
+    pub recipe: Option<Recipe>,
+}
+
+/// Represents the opinion of the user about a recipe.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RecipeRating {
+    #[serde(rename = "RecipeId")]
+    pub recipe_id: String,
+
+    /// From 1 to 5 stars.
+    #[serde(rename = "Rating")]
+    pub rating: i16,
+
+    #[serde(rename = "Comment")]
+    pub comment: Option<String>,
+
+    #[serde(rename = "RatedDate")]
+    pub rated_date: DateTime<Utc>,
+}
+
+/// Represents an ingredient line of a recipe.
+///
+/// The quantity, unit and notes are extracted from the display line,
+/// see [crate::ingredient_parser::parse_ingredient_line].
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct RecipeIngredient {
     #[serde(rename = "Name")]
     pub name: String,
 
     #[serde(rename = "Display")]
     pub display: String,
+
+    #[serde(rename = "Quantity")]
+    pub quantity: Option<f32>,
+
+    /// The upper bound of the quantity when the recipe gives a range.
+    #[serde(rename = "QuantityMax")]
+    pub quantity_max: Option<f32>,
+
+    #[serde(rename = "Unit")]
+    pub unit: Option<String>,
+
+    #[serde(rename = "Notes")]
+    pub notes: Option<String>,
+}
+
+impl RecipeIngredient {
+    /// Creates a recipe ingredient by parsing its display line.
+    ///
+    /// If no name is supplied the one parsed from the line is used.
+    pub fn from_display(name: Option<String>, display: String) -> Self {
+        let ParsedIngredientLine {
+            quantity,
+            quantity_max,
+            unit,
+            name: parsed_name,
+            notes,
+        } = parse_ingredient_line(&display);
+
+        RecipeIngredient {
+            name: name.unwrap_or(parsed_name),
+            display,
+            quantity,
+            quantity_max,
+            unit,
+            notes,
+        }
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[allow(non_camel_case_types)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JWT_Token {
     pub user_id: String,
     pub session_id: String,
@@ -0,0 +1,449 @@
+//! The OpenAPI document of the API, generated from the handlers and the payloads.
+//!
+//! It's served at `/openapi.json` and, in debug builds, shown at `/docs`.
+//! The copy in `backend/openapi.json` is checked against it by the tests,
+//! run `UPDATE_OPENAPI=1 cargo test` to regenerate it after changing a route or a payload.
+
+use axum::{response::Html, Json};
+use utoipa::{
+    openapi::{
+        security::{HttpAuthScheme, HttpBuilder, SecurityScheme},
+        OpenApi as OpenApiDocument,
+    },
+    Modify, OpenApi,
+};
+
+use crate::{custom_recipes, models, routes, v2};
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "SmartFridge API",
+        description = "The v1 routes take a JSON payload with the session `token` and are all `POST`, they are also served without the `/v1` prefix. The v2 routes take the token in an `Authorization: Bearer` header. Errors are returned as plain text with the name of the error."
+    ),
+    paths(
+        routes::add_custom_recipe::add_custom_recipe,
+        routes::add_ingredient::add_ingredient,
+        routes::add_recipe_to_shopping_list::add_recipe_to_shopping_list,
+        routes::add_shopping_item::add_shopping_item,
+        routes::add_storage_location::add_storage_location,
+        routes::add_theme::add_theme,
+        routes::assign_meal::assign_meal,
+        routes::bulk_add_ingredients::bulk_add_ingredients,
+        routes::bulk_edit_ingredients::bulk_edit_ingredients,
+        routes::bulk_remove_ingredients::bulk_remove_ingredients,
+        routes::buy_shopping_items::buy_shopping_items,
+        routes::consume_ingredient::consume_ingredient,
+        routes::create_household::create_household_route,
+        routes::edit_custom_recipe::edit_custom_recipe,
+        routes::edit_ingredient::edit_ingredient,
+        routes::edit_shopping_item::edit_shopping_item,
+        routes::get_cook_history::get_cook_history,
+        routes::get_custom_recipes::get_custom_recipes,
+        routes::get_households::get_households,
+        routes::get_ingredient::get_ingredient_route,
+        routes::get_ingredients::get_ingredients,
+        routes::get_inventory_history::get_inventory_history,
+        routes::get_meal_plan::get_meal_plan,
+        routes::get_recipe_ratings::get_recipe_ratings,
+        routes::get_recipes::get_recipes,
+        routes::get_saved_recipes::get_saved_recipes,
+        routes::get_settings::get_settings,
+        routes::get_shopping_list::get_shopping_list,
+        routes::get_stats::get_stats,
+        routes::get_storage_locations::get_storage_locations,
+        routes::get_themes::get_themes,
+        routes::get_trash::get_trash,
+        routes::import_theme::import_theme,
+        routes::invite_to_household::invite_to_household,
+        routes::join_household::join_household,
+        routes::leave_household::leave_household,
+        routes::login_user::login_user,
+        routes::logout::logout,
+        routes::meal_plan_requirements::meal_plan_requirements,
+        routes::move_ingredients::move_ingredients,
+        routes::rate_recipe::rate_recipe,
+        routes::recipe_details::recipe_details,
+        routes::record_cooked_recipe::record_cooked_recipe,
+        routes::register_user::register_user,
+        routes::remove_custom_recipe::remove_custom_recipe,
+        routes::remove_household_member::remove_household_member,
+        routes::remove_ingredient::remove_ingredient,
+        routes::remove_meal::remove_meal,
+        routes::remove_shopping_item::remove_shopping_item,
+        routes::remove_storage_location::remove_storage_location,
+        routes::remove_theme::remove_theme,
+        routes::restore_ingredient::restore_ingredient,
+        routes::save_recipe::save_recipe,
+        routes::save_settings::save_settings,
+        routes::search_ingredients::search_ingredients,
+        routes::search_recipes::search_recipes,
+        routes::set_member_role::set_member_role,
+        routes::stream_changes::stream_changes,
+        routes::switch_household::switch_household,
+        routes::sync_changes::sync_changes,
+        routes::unsave_recipe::unsave_recipe,
+        v2::register,
+        v2::login,
+        v2::end_session,
+        v2::read_settings,
+        v2::update_settings,
+        v2::list_households,
+        v2::create_household,
+        v2::select_household,
+        v2::create_invite,
+        v2::accept_invite,
+        v2::update_member,
+        v2::delete_member,
+        v2::leave,
+        v2::list_themes,
+        v2::create_theme,
+        v2::import_shared_theme,
+        v2::delete_theme,
+        v2::list_recipes,
+        v2::find_recipes,
+        v2::read_recipe,
+        v2::list_saved_recipes,
+        v2::save_saved_recipe,
+        v2::delete_saved_recipe,
+        v2::list_custom_recipes,
+        v2::create_custom_recipe,
+        v2::update_custom_recipe,
+        v2::delete_custom_recipe,
+        v2::create_cooked_entry,
+        v2::list_cook_history,
+        v2::replace_rating,
+        v2::list_recipe_ratings,
+        v2::list_ingredients,
+        v2::find_ingredients,
+        v2::create_ingredient,
+        v2::read_ingredient,
+        v2::update_ingredient,
+        v2::delete_ingredient,
+        v2::move_to_location,
+        v2::consume,
+        v2::create_ingredients,
+        v2::update_ingredients,
+        v2::delete_ingredients,
+        v2::list_trash,
+        v2::list_inventory_history,
+        v2::restore,
+        v2::list_locations,
+        v2::create_location,
+        v2::delete_location,
+        v2::sync,
+        v2::changes,
+        v2::stats,
+        v2::list_shopping_items,
+        v2::create_shopping_item,
+        v2::update_shopping_item,
+        v2::delete_shopping_item,
+        v2::create_recipe_items,
+        v2::buy_checked_items,
+        v2::list_meals,
+        v2::create_meal,
+        v2::list_meal_requirements,
+        v2::delete_meal,
+    ),
+    components(schemas(
+            models::AppThemes,
+            models::CustomTheme,
+            models::DietaryPreference,
+            models::UnitSystem,
+            models::NotificationPreferences,
+            models::UserSettings,
+            models::Ingredient,
+            models::IngredientLot,
+            models::TrashedIngredient,
+            models::InventoryEventType,
+            models::RemovalReason,
+            models::InventoryEvent,
+            models::StatsPeriod,
+            models::WasteStats,
+            models::CategoryWaste,
+            models::WastedItem,
+            models::StatsTrendPoint,
+            models::StorageKind,
+            models::BulkIngredientResult,
+            models::SyncMutation,
+            models::SyncMutationKind,
+            models::SyncMutationStatus,
+            models::SyncMutationResult,
+            models::SyncChanges,
+            models::ChangeNotification,
+            models::StorageLocation,
+            models::HouseholdRole,
+            models::Household,
+            models::HouseholdMember,
+            models::HouseholdInvitation,
+            models::ShoppingItem,
+            models::MealSlot,
+            models::MealPlanEntry,
+            models::Recipe,
+            models::DietaryViolation,
+            models::SavedRecipe,
+            models::CookHistoryEntry,
+            models::RecipeRating,
+            models::RecipeIngredient,
+            custom_recipes::CustomRecipeIngredient,
+            routes::add_custom_recipe::AddCustomRecipePayload,
+            routes::add_custom_recipe::AddCustomRecipeResponse,
+            routes::add_ingredient::AddIngredientPayload,
+            routes::add_ingredient::DuplicateIngredientAction,
+            routes::add_ingredient::AddIngredientResponse,
+            routes::add_ingredient::IngredientPayload,
+            routes::add_recipe_to_shopping_list::AddRecipeToShoppingListPayload,
+            routes::add_recipe_to_shopping_list::AddedShoppingItem,
+            routes::add_shopping_item::AddShoppingItemPayload,
+            routes::add_shopping_item::ShoppingItemPayload,
+            routes::add_shopping_item::AddShoppingItemResponse,
+            routes::add_storage_location::AddStorageLocationPayload,
+            routes::add_storage_location::AddStorageLocationResponse,
+            routes::add_theme::AddThemePayload,
+            routes::assign_meal::AssignMealPayload,
+            routes::assign_meal::AssignMealResponse,
+            routes::bulk_add_ingredients::BulkAddIngredientsPayload,
+            routes::bulk_edit_ingredients::BulkEditIngredientsPayload,
+            routes::bulk_remove_ingredients::BulkRemoveIngredientsPayload,
+            routes::bulk_remove_ingredients::RemovedIngredientPayload,
+            routes::buy_shopping_items::BuyShoppingItemsPayload,
+            routes::consume_ingredient::ConsumeIngredientPayload,
+            routes::consume_ingredient::ConsumeIngredientResponse,
+            routes::create_household::CreateHouseholdPayload,
+            routes::create_household::CreateHouseholdResponse,
+            routes::edit_custom_recipe::EditCustomRecipePayload,
+            routes::edit_ingredient::EditIngredientPayload,
+            routes::edit_ingredient::IngredientPayload,
+            routes::edit_shopping_item::EditShoppingItemPayload,
+            routes::edit_shopping_item::ShoppingItemPayload,
+            routes::get_cook_history::GetCookHistoryPayload,
+            routes::get_custom_recipes::GetCustomRecipesPayload,
+            routes::get_households::GetHouseholdsPayload,
+            routes::get_ingredient::GetIngredientPayload,
+            routes::get_ingredients::GetIngredientsPayload,
+            routes::get_inventory_history::GetInventoryHistoryPayload,
+            routes::get_meal_plan::GetMealPlanPayload,
+            routes::get_recipe_ratings::GetRecipeRatingsPayload,
+            routes::get_recipes::GetRecipesPayload,
+            routes::get_saved_recipes::GetSavedRecipesPayload,
+            routes::get_settings::GetSettingsPayload,
+            routes::get_shopping_list::GetShoppingListPayload,
+            routes::get_stats::GetStatsPayload,
+            routes::get_storage_locations::GetStorageLocationsPayload,
+            routes::get_themes::GetThemesPayload,
+            routes::get_trash::GetTrashPayload,
+            routes::import_theme::ImportThemePayload,
+            routes::invite_to_household::InviteToHouseholdPayload,
+            routes::join_household::JoinHouseholdPayload,
+            routes::join_household::JoinHouseholdResponse,
+            routes::leave_household::LeaveHouseholdPayload,
+            routes::login_user::LoginUserResponse,
+            routes::login_user::LoginUserPayload,
+            routes::logout::LogoutUserPayload,
+            routes::meal_plan_requirements::MealPlanRequirementsPayload,
+            routes::meal_plan_requirements::IngredientRequirement,
+            routes::meal_plan_requirements::ExpiryWarning,
+            routes::meal_plan_requirements::MealPlanRequirementsResponse,
+            routes::move_ingredients::MoveIngredientsPayload,
+            routes::move_ingredients::MovedIngredientPayload,
+            routes::rate_recipe::RateRecipePayload,
+            routes::recipe_details::RecipeDetailsPayload,
+            routes::record_cooked_recipe::RecordCookedRecipePayload,
+            routes::record_cooked_recipe::RecordCookedRecipeResponse,
+            routes::register_user::RegisterUserPayload,
+            routes::remove_custom_recipe::RemoveCustomRecipePayload,
+            routes::remove_household_member::RemoveHouseholdMemberPayload,
+            routes::remove_ingredient::RemoveIngredientPayload,
+            routes::remove_meal::RemoveMealPayload,
+            routes::remove_shopping_item::RemoveShoppingItemPayload,
+            routes::remove_storage_location::RemoveStorageLocationPayload,
+            routes::remove_theme::RemoveThemePayload,
+            routes::restore_ingredient::RestoreIngredientPayload,
+            routes::save_recipe::SaveRecipePayload,
+            routes::save_settings::SaveSettingsPayload,
+            routes::save_settings::UserSettingsPayload,
+            routes::save_settings::NotificationPreferencesPayload,
+            routes::search_ingredients::SearchIngredientsPayload,
+            routes::search_recipes::SearchRecipesPayload,
+            routes::set_member_role::SetMemberRolePayload,
+            routes::stream_changes::StreamChangesPayload,
+            routes::switch_household::SwitchHouseholdPayload,
+            routes::sync_changes::SyncChangesPayload,
+            routes::unsave_recipe::UnsaveRecipePayload,
+            v2::Credentials,
+            v2::NewHousehold,
+            v2::CurrentHousehold,
+            v2::NewInvitation,
+            v2::MemberRole,
+            v2::NewTheme,
+            v2::SharedTheme,
+            v2::SavedRecipeChanges,
+            v2::CustomRecipe,
+            v2::CookedRecipe,
+            v2::Rating,
+            v2::IngredientChanges,
+            v2::IngredientsMove,
+            v2::Consumption,
+            v2::NewLocation,
+            v2::SyncRequest,
+            v2::ShoppingItemChanges,
+            v2::RecipeToBuy,
+            v2::NewMeal,
+    )),
+    modifiers(&BearerSecurity),
+    tags(
+        (name = "Users", description = "Registration and sessions"),
+        (name = "Settings", description = "The settings of the user"),
+        (name = "Households", description = "The households sharing the inventory"),
+        (name = "Themes", description = "The custom themes of the app"),
+        (name = "Recipes", description = "Recommended, saved, custom and cooked recipes"),
+        (name = "Ingredients", description = "The inventory of the household"),
+        (name = "Locations", description = "Where the ingredients are stored"),
+        (name = "Sync", description = "Offline sync and live changes"),
+        (name = "Statistics", description = "The waste statistics"),
+        (name = "Shopping list", description = "The shopping list of the household"),
+        (name = "Meal plan", description = "The planned meals"),
+    )
+)]
+pub struct ApiDoc;
+
+/// Adds the `Authorization: Bearer` scheme used by the v2 routes.
+struct BearerSecurity;
+
+impl Modify for BearerSecurity {
+    fn modify(&self, openapi: &mut OpenApiDocument) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .description(Some("The token returned by `POST /v2/sessions`"))
+                    .build(),
+            ),
+        );
+    }
+}
+
+pub async fn openapi_json() -> Json<OpenApiDocument> {
+    Json(ApiDoc::openapi())
+}
+
+/// Page that lists the routes and schemas of `/openapi.json`, only served in debug builds.
+pub async fn docs() -> Html<&'static str> {
+    Html(include_str!("openapi_docs.html"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use regex::Regex;
+    use serde_json::Value;
+
+    use super::*;
+
+    const SPEC_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/openapi.json");
+
+    fn spec() -> Value {
+        serde_json::to_value(ApiDoc::openapi()).unwrap()
+    }
+
+    #[test]
+    fn committed_spec_is_up_to_date() {
+        let generated = ApiDoc::openapi().to_pretty_json().unwrap() + "\n";
+        if std::env::var_os("UPDATE_OPENAPI").is_some() {
+            std::fs::write(SPEC_PATH, &generated).unwrap();
+            return;
+        }
+
+        let committed = std::fs::read_to_string(SPEC_PATH).unwrap_or_default();
+        assert!(
+            committed == generated,
+            "`openapi.json` is out of date, run `UPDATE_OPENAPI=1 cargo test` to regenerate it"
+        );
+    }
+
+    fn collect_refs(value: &Value, refs: &mut BTreeSet<String>) {
+        match value {
+            Value::Object(fields) => {
+                for (key, value) in fields {
+                    match (key.as_str(), value) {
+                        ("$ref", Value::String(reference)) => {
+                            refs.insert(reference.clone());
+                        }
+                        _ => collect_refs(value, refs),
+                    }
+                }
+            }
+            Value::Array(values) => values.iter().for_each(|v| collect_refs(v, refs)),
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn every_reference_has_a_schema() {
+        let spec = spec();
+        let mut refs = BTreeSet::new();
+        collect_refs(&spec, &mut refs);
+
+        let missing: Vec<_> = refs
+            .iter()
+            .filter(|reference| spec.pointer(&reference.replacen('#', "", 1)).is_none())
+            .collect();
+        assert!(
+            missing.is_empty(),
+            "Add these schemas to `ApiDoc`: {:?}",
+            missing
+        );
+    }
+
+    /// Returns the `(method, path)` of the routes in `source`, with the path parameters as `{name}`.
+    fn routes(source: &str, prefix: &str) -> BTreeSet<(String, String)> {
+        let route =
+            Regex::new(r#"\.route\(\s*"([^"]+)",((?s:.*?))(?:\.route\(|\.with_state|;)"#).unwrap();
+        let method = Regex::new(r"\b(get|post|put|patch|delete)\(").unwrap();
+        let param = Regex::new(r":(\w+)").unwrap();
+
+        let mut routes = BTreeSet::new();
+        let mut start = 0;
+        while let Some(captures) = route.captures_at(source, start) {
+            let path = format!("{}{}", prefix, param.replace_all(&captures[1], "{$1}"));
+            for m in method.captures_iter(&captures[2]) {
+                routes.insert((m[1].to_string(), path.clone()));
+            }
+            start = captures.get(2).unwrap().end();
+        }
+        routes
+    }
+
+    #[test]
+    fn every_route_is_documented() {
+        let main = include_str!("main.rs");
+        let v1 = &main[main.find("let v1 = Router::new()").unwrap()..];
+        let v1 = &v1[..=v1.find(";\n").unwrap()];
+        let mut routed = routes(v1, "/v1");
+        routed.extend(routes(include_str!("v2.rs"), "/v2"));
+
+        let spec = spec();
+        let documented: BTreeSet<_> = spec["paths"]
+            .as_object()
+            .unwrap()
+            .iter()
+            .flat_map(|(path, operations)| {
+                operations
+                    .as_object()
+                    .unwrap()
+                    .keys()
+                    .map(move |method| (method.clone(), path.clone()))
+            })
+            .collect();
+
+        assert!(
+            routed == documented,
+            "Routes without docs: {:?}\nDocs without a route: {:?}",
+            routed.difference(&documented).collect::<Vec<_>>(),
+            documented.difference(&routed).collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,262 @@
+//! Exports the spans of the requests to an OpenTelemetry collector, using OTLP over HTTP with JSON.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use rand::{thread_rng, Rng};
+use serde_json::{json, Value};
+use tokio::sync::mpsc::{channel, error::TrySendError, Receiver, Sender};
+use tracing::{
+    field::{Field, Visit},
+    span::{Attributes, Id, Record},
+    Subscriber,
+};
+use tracing_subscriber::{layer::Context, registry::LookupSpan, Layer};
+use uuid::Uuid;
+
+use crate::{metrics::record_dropped_span, request_id::REQUEST_SPAN_NAME};
+
+const SERVICE_NAME: &str = "smart-fridge-backend";
+
+/// How long to wait before sending the spans that haven't filled a batch.
+const EXPORT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The most spans sent at once.
+const MAX_BATCH_SIZE: usize = 512;
+
+/// The most spans waiting to be exported, the ones closing when it's reached are dropped.
+const MAX_QUEUED_SPANS: usize = 4 * MAX_BATCH_SIZE;
+
+/// How long the collector has to answer a batch.
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The attribute each field of the request span is exported as.
+const ATTRIBUTE_NAMES: &[(&str, &str)] = &[
+    ("request_id", "http.request.id"),
+    ("method", "http.request.method"),
+    ("route", "http.route"),
+    ("user_id", "enduser.id"),
+    ("status", "http.response.status_code"),
+    ("latency_ms", "http.server.latency_ms"),
+];
+
+/// Tracing layer that sends the request spans to the collector when they close.
+pub struct OtlpLayer {
+    sender: Sender<Value>,
+}
+
+impl OtlpLayer {
+    /// Starts the task that exports the spans to `endpoint`, like `http://localhost:4318`.
+    pub fn spawn(endpoint: String) -> Self {
+        let (sender, receiver) = channel(MAX_QUEUED_SPANS);
+        let url = format!("{}/v1/traces", endpoint.trim_end_matches('/'));
+        tokio::spawn(export_spans(url, receiver));
+        OtlpLayer { sender }
+    }
+}
+
+/// The fields and start of a request span, kept in its extensions until it closes.
+struct RequestSpan {
+    start: SystemTime,
+    attributes: Vec<(&'static str, Value)>,
+}
+
+impl RequestSpan {
+    fn attribute(&self, name: &str) -> Option<&Value> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value)
+    }
+
+    fn record(&mut self, field: &Field, value: Value) {
+        let Some((_, name)) = ATTRIBUTE_NAMES
+            .iter()
+            .find(|(field_name, _)| *field_name == field.name())
+        else {
+            return;
+        };
+        self.attributes.retain(|(key, _)| key != name);
+        self.attributes.push((name, value));
+    }
+}
+
+impl Visit for RequestSpan {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.record(field, json!({ "stringValue": format!("{:?}", value) }));
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.record(field, json!({ "stringValue": value }));
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.record(field, json!({ "intValue": value.to_string() }));
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.record(field, json!({ "intValue": value.to_string() }));
+    }
+}
+
+impl<S> Layer<S> for OtlpLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        if attrs.metadata().name() != REQUEST_SPAN_NAME {
+            return;
+        }
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+
+        let mut request = RequestSpan {
+            start: SystemTime::now(),
+            attributes: vec![],
+        };
+        attrs.record(&mut request);
+        span.extensions_mut().insert(request);
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut extensions = span.extensions_mut();
+        if let Some(request) = extensions.get_mut::<RequestSpan>() {
+            values.record(request);
+        }
+    }
+
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let Some(request) = span.extensions_mut().remove::<RequestSpan>() else {
+            return;
+        };
+
+        // The span is dropped instead of waiting when the collector is slower than the requests.
+        // A closed channel is ignored, it only happens when the export task stopped.
+        if let Err(TrySendError::Full(_)) =
+            self.sender.try_send(otlp_span(request, SystemTime::now()))
+        {
+            record_dropped_span();
+        }
+    }
+}
+
+fn unix_nanos(time: SystemTime) -> String {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos()
+        .to_string()
+}
+
+/// Builds the OTLP representation of the span.
+///
+/// The request id is used as the trace id when it's a UUID, so the trace can be found from the client logs.
+fn otlp_span(request: RequestSpan, end: SystemTime) -> Value {
+    let mut rng = thread_rng();
+    let trace_id = request
+        .attribute("http.request.id")
+        .and_then(|id| id["stringValue"].as_str())
+        .and_then(|id| Uuid::parse_str(id).ok())
+        .unwrap_or_else(|| Uuid::from_bytes(rng.gen()))
+        .simple()
+        .to_string();
+    let span_id: [u8; 8] = rng.gen();
+
+    let text = |name: &str| {
+        request
+            .attribute(name)
+            .and_then(|value| value["stringValue"].as_str())
+            .unwrap_or_default()
+            .to_string()
+    };
+    let is_server_error = request
+        .attribute("http.response.status_code")
+        .and_then(|status| status["intValue"].as_str())
+        .and_then(|status| status.parse::<u16>().ok())
+        .is_some_and(|status| status >= 500);
+
+    json!({
+        "traceId": trace_id,
+        "spanId": span_id.iter().map(|b| format!("{:02x}", b)).collect::<String>(),
+        "name": format!("{} {}", text("http.request.method"), text("http.route")),
+        // SPAN_KIND_SERVER
+        "kind": 2,
+        "startTimeUnixNano": unix_nanos(request.start),
+        "endTimeUnixNano": unix_nanos(end),
+        "attributes": request
+            .attributes
+            .iter()
+            .map(|(key, value)| json!({ "key": key, "value": value }))
+            .collect::<Vec<_>>(),
+        // STATUS_CODE_ERROR or STATUS_CODE_UNSET
+        "status": { "code": if is_server_error { 2 } else { 0 } },
+    })
+}
+
+/// Sends the spans in batches, when `MAX_BATCH_SIZE` are waiting or every `EXPORT_INTERVAL`.
+async fn export_spans(url: String, mut receiver: Receiver<Value>) {
+    let client = match reqwest::Client::builder().timeout(EXPORT_TIMEOUT).build() {
+        Ok(client) => client,
+        Err(err) => {
+            tracing::error!(
+                "OTLP export: An error `{:?}` occurred while building the HTTP client!",
+                err
+            );
+            return;
+        }
+    };
+    let mut interval = tokio::time::interval(EXPORT_INTERVAL);
+    let mut batch = vec![];
+
+    loop {
+        tokio::select! {
+            span = receiver.recv() => match span {
+                Some(span) => {
+                    batch.push(span);
+                    if batch.len() < MAX_BATCH_SIZE {
+                        continue;
+                    }
+                }
+                None => break,
+            },
+            _ = interval.tick() => {}
+        }
+        if batch.is_empty() {
+            continue;
+        }
+
+        let body = json!({
+            "resourceSpans": [{
+                "resource": {
+                    "attributes": [
+                        { "key": "service.name", "value": { "stringValue": SERVICE_NAME } },
+                    ],
+                },
+                "scopeSpans": [{
+                    "scope": { "name": env!("CARGO_PKG_NAME"), "version": env!("CARGO_PKG_VERSION") },
+                    "spans": std::mem::take(&mut batch),
+                }],
+            }],
+        });
+
+        let result = client
+            .post(&url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body.to_string())
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        if let Err(err) = result {
+            tracing::error!(
+                "OTLP export: An error `{:?}` occurred while sending the spans to `{}`!",
+                err,
+                url
+            );
+        }
+    }
+}
@@ -0,0 +1,72 @@
+//! Copies of the recipes we've received from the WorldWide Recipes API.
+//!
+//! The API doesn't let us fetch a recipe by its id, so every recipe we return
+//! to a client is stored and other features can look it up by `recipe_id` later.
+
+use tokio_postgres::{types::Json, Client};
+
+use crate::{
+    metrics::{record_cache_lookups, timed_query},
+    models::Recipe,
+};
+
+/// Stores the given recipes, replacing older copies of them.
+pub async fn cache_recipes(
+    conn: &Client,
+    recipes: &[Recipe],
+) -> Result<u64, tokio_postgres::Error> {
+    if recipes.is_empty() {
+        return Ok(0);
+    }
+
+    let ids: Vec<&str> = recipes.iter().map(|r| r.recipe_id.as_str()).collect();
+    let recipes: Vec<Json<&Recipe>> = recipes.iter().map(Json).collect();
+    timed_query(
+        "recipe_cache_write",
+        conn.execute(
+            "INSERT INTO sf_recipe (recipe_id, recipe) SELECT * FROM UNNEST($1::TEXT[], $2::JSONB[]) ON CONFLICT (recipe_id) DO UPDATE SET recipe=EXCLUDED.recipe, updated_at=NOW()",
+            &[&ids, &recipes],
+        ),
+    )
+    .await
+}
+
+/// Gets the stored copies of the given recipes.
+///
+/// Recipes that were never cached are missing from the result.
+pub async fn get_cached_recipes(
+    conn: &Client,
+    recipe_ids: &[String],
+    tracing_prefix: &str,
+) -> Result<Vec<Recipe>, tokio_postgres::Error> {
+    let rows = timed_query(
+        "recipe_cache_read",
+        conn.query(
+            "SELECT recipe FROM sf_recipe WHERE recipe_id = ANY($1)",
+            &[&recipe_ids],
+        ),
+    )
+    .await?;
+    record_cache_lookups(
+        "recipes",
+        rows.len() as u64,
+        recipe_ids.len().saturating_sub(rows.len()) as u64,
+    );
+
+    let recipes = rows
+        .iter()
+        .filter_map(|row| match row.try_get::<_, Json<Recipe>>("recipe") {
+            Ok(Json(recipe)) => Some(recipe),
+            Err(err) => {
+                tracing::error!(
+                    "{} An error `{:?}` occurred while parsing a cached recipe!",
+                    tracing_prefix,
+                    err
+                );
+                None
+            }
+        })
+        .collect();
+
+    Ok(recipes)
+}
@@ -0,0 +1,54 @@
+//! What the user thinks of the recipes and when they cooked them.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use tokio_postgres::Client;
+
+use crate::metrics::timed_query;
+use crate::models::Recipe;
+
+/// Fills the rating the user gave to each recipe and the last time they cooked it.
+pub async fn fill_user_ratings<'a>(
+    conn: &Client,
+    user_id: &str,
+    recipes: impl IntoIterator<Item = &'a mut Recipe>,
+) -> Result<(), tokio_postgres::Error> {
+    let mut recipes: Vec<&mut Recipe> = recipes.into_iter().collect();
+    if recipes.is_empty() {
+        return Ok(());
+    }
+
+    let recipe_ids: Vec<&str> = recipes.iter().map(|r| r.recipe_id.as_str()).collect();
+    let rows = timed_query(
+        "fill_user_ratings",
+        conn.query(
+            "SELECT recipe_id, MAX(rating) AS rating, MAX(cooked_date) AS last_cooked_date FROM (
+                SELECT recipe_id, rating, NULL::TIMESTAMPTZ AS cooked_date FROM sf_recipe_rating WHERE user_id=$1 AND recipe_id = ANY($2)
+                UNION ALL
+                SELECT recipe_id, NULL, cooked_date FROM sf_cook_history WHERE user_id=$1 AND recipe_id = ANY($2)
+            ) AS r GROUP BY recipe_id",
+            &[&user_id, &recipe_ids],
+        ),
+    )
+    .await?;
+
+    let found: HashMap<String, (Option<i16>, Option<DateTime<Utc>>)> = rows
+        .iter()
+        .map(|row| {
+            (
+                row.get("recipe_id"),
+                (row.get("rating"), row.get("last_cooked_date")),
+            )
+        })
+        .collect();
+
+    for recipe in recipes.iter_mut() {
+        if let Some((rating, last_cooked_date)) = found.get(&recipe.recipe_id) {
+            recipe.user_rating = *rating;
+            recipe.last_cooked_date = *last_cooked_date;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,94 @@
+//! Gives every request an id, sent back in the `X-Request-Id` header, and runs it inside a tracing span.
+//!
+//! Clients can send their own id in the header so their logs can be matched with the ones of the server.
+//! The span records the method, the route, the user and how long the request took.
+
+use std::time::Instant;
+
+use axum::{
+    body::Body,
+    extract::MatchedPath,
+    http::{HeaderValue, Request},
+    middleware::Next,
+    response::Response,
+};
+use tracing::{field::Empty, Instrument};
+use uuid::Uuid;
+
+pub const REQUEST_ID_HEADER: &str = "X-Request-Id";
+
+/// The longest id accepted from clients, longer ones are replaced.
+const MAX_REQUEST_ID_LENGTH: usize = 128;
+
+/// Name of the span the requests run in.
+pub const REQUEST_SPAN_NAME: &str = "request";
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// Returns the id of the request being handled, or `-` outside of a request.
+pub fn current_request_id() -> String {
+    REQUEST_ID
+        .try_with(Clone::clone)
+        .unwrap_or_else(|_| "-".to_string())
+}
+
+/// Middleware that assigns or propagates the `X-Request-Id` of the request.
+pub async fn request_id(request: Request<Body>, next: Next<Body>) -> Response {
+    let id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|id| id.to_str().ok())
+        .filter(|id| {
+            !id.is_empty()
+                && id.len() <= MAX_REQUEST_ID_LENGTH
+                && id.bytes().all(|b| b.is_ascii_graphic())
+        })
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "fallback".to_string());
+
+    let span = tracing::info_span!(
+        REQUEST_SPAN_NAME,
+        request_id = %id,
+        method = %request.method(),
+        route = %route,
+        user_id = Empty,
+        status = Empty,
+        latency_ms = Empty,
+    );
+
+    let start = Instant::now();
+    let mut response = REQUEST_ID
+        .scope(id.clone(), next.run(request))
+        .instrument(span.clone())
+        .await;
+    let latency_ms = start.elapsed().as_millis() as u64;
+
+    span.record("status", response.status().as_u16());
+    span.record("latency_ms", latency_ms);
+    span.in_scope(|| {
+        tracing::info!(
+            "{} finished with {} in {}ms",
+            route,
+            response.status(),
+            latency_ms
+        )
+    });
+
+    if let Ok(id) = HeaderValue::from_str(&id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, id);
+    }
+    response
+}
+
+/// Records the user of the request in its span, once the session token is read.
+pub fn record_user_id(user_id: &str) {
+    tracing::Span::current().record("user_id", user_id);
+}
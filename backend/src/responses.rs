@@ -1,9 +1,16 @@
-use axum::response::{IntoResponse, Response};
+use axum::{
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+
+use crate::metrics::record_route_error;
 
 #[derive(Debug)]
 pub struct ResponseError<T: ToString> {
     status: hyper::StatusCode,
     message: T,
+    body: Option<serde_json::Value>,
 }
 
 impl<T> From<(hyper::StatusCode, T)> for ResponseError<T>
@@ -13,7 +20,29 @@ where
     fn from(value: (hyper::StatusCode, T)) -> Self {
         let (status, message) = value;
 
-        ResponseError { status, message }
+        ResponseError {
+            status,
+            message,
+            body: None,
+        }
+    }
+}
+
+impl<T> ResponseError<T>
+where
+    T: ToString,
+{
+    /// Answers `body` as JSON instead of the message, for the errors the client has to act on like conflicts.
+    pub fn with_json(mut self, body: &impl Serialize) -> Self {
+        match serde_json::to_value(body) {
+            Ok(body) => self.body = Some(body),
+            Err(err) => tracing::error!(
+                "An error `{:?}` occurred while serializing the body of `{}`!",
+                err,
+                self.message.to_string()
+            ),
+        }
+        self
     }
 }
 
@@ -22,7 +51,16 @@ where
     T: ToString,
 {
     fn into_response(self) -> Response {
-        let ResponseError { status, message } = self;
-        (status, message.to_string()).into_response()
+        let ResponseError {
+            status,
+            message,
+            body,
+        } = self;
+        let message = message.to_string();
+        record_route_error::<T>(&message);
+        match body {
+            Some(body) => (status, Json(body)).into_response(),
+            None => (status, message).into_response(),
+        }
     }
 }
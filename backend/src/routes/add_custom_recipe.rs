@@ -0,0 +1,237 @@
+use std::{fmt::Display, sync::Arc};
+
+use axum::{response::IntoResponse, Json};
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::Client;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    custom_recipes::{build_custom_recipe, CustomRecipeIngredient, CUSTOM_RECIPE_ID_PREFIX},
+    extract_jwt, is_session_valid,
+    metrics::timed_query,
+    recipe_cache::cache_recipes,
+    request_id::current_request_id,
+    responses::ResponseError,
+    APP_SECRET,
+};
+
+#[derive(Debug, Serialize)]
+pub enum AddCustomRecipeErrors {
+    InvalidPayload { payload: String },
+    InvalidJWT,
+    NoDBConnectionFound,
+    ErrorCheckingIfSessionIsValid,
+    JWTExpired,
+    EmptyTitle,
+    ErrorAddingCustomRecipe,
+}
+
+impl Display for AddCustomRecipeErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AddCustomRecipePayload {
+    token: String,
+    title: String,
+    image_url: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    ingredients: Vec<CustomRecipeIngredient>,
+    #[serde(default)]
+    steps: Vec<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AddCustomRecipeResponse {
+    #[serde(rename = "RecipeId")]
+    pub recipe_id: String,
+}
+
+/// Route to add a recipe written by the user.
+///
+/// A copy is also stored with the recipes returned by the API
+/// so it can be planned, saved and added to the shopping list like any other.
+#[utoipa::path(
+    post,
+    path = "/v1/recipes/custom/add",
+    tag = "Recipes",
+    request_body = AddCustomRecipePayload,
+    responses(
+        (status = 200, description = "The recipe was added", body = AddCustomRecipeResponse),
+        (status = "4XX", description = "The `AddCustomRecipeErrors` that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The `AddCustomRecipeErrors` that made the request fail", body = String, content_type = "text/plain"),
+    )
+)]
+pub async fn add_custom_recipe(
+    payload: Json<serde_json::Value>,
+    client: Arc<Option<Client>>,
+) -> Result<impl IntoResponse, ResponseError<AddCustomRecipeErrors>> {
+    let tracing_prefix = format!("/recipes/custom/add - {}:", current_request_id());
+
+    tracing::debug!("{} START", tracing_prefix);
+
+    tracing::debug!("{} Parsing payload...", tracing_prefix);
+    let AddCustomRecipePayload {
+        token,
+        title,
+        image_url,
+        tags,
+        ingredients,
+        steps,
+    } = match serde_json::from_value(payload.0.clone()) {
+        Ok(p) => p,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred parsing payload `{}`",
+                tracing_prefix,
+                err,
+                payload.0
+            );
+            let error: ResponseError<_> = (
+                StatusCode::BAD_REQUEST,
+                AddCustomRecipeErrors::InvalidPayload {
+                    payload: payload.0.to_string(),
+                },
+            )
+                .into();
+            Err(error)?
+        }
+    };
+    tracing::debug!("{} Payload parsed successfully!", tracing_prefix);
+
+    tracing::debug!("{} Extracting JWT...", tracing_prefix);
+    let token_info = match extract_jwt(APP_SECRET, &token) {
+        Ok(t) => t,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred while extracting the JWT `{}`",
+                tracing_prefix,
+                err,
+                token
+            );
+            let error: ResponseError<_> =
+                (StatusCode::BAD_REQUEST, AddCustomRecipeErrors::InvalidJWT).into();
+            Err(error)?
+        }
+    };
+    let user_id = token_info.user_id.clone();
+    tracing::debug!("{} JWT extracted successfully!", tracing_prefix);
+
+    tracing::debug!("{} Checking for DB connection...", tracing_prefix);
+    let conn = client.as_ref().as_ref().ok_or_else(|| {
+        tracing::error!("{} No DB connection found!", tracing_prefix);
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            AddCustomRecipeErrors::NoDBConnectionFound,
+        )
+            .into();
+        error
+    })?;
+    tracing::debug!("{} DB Connection found!", tracing_prefix);
+
+    tracing::debug!("{} Checking if session is valid...", tracing_prefix);
+    if let Err(err) = is_session_valid(token_info, conn).await {
+        tracing::error!(
+            "{} An error `{:?}` occurred while checking if session is valid!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = match err {
+            crate::IsSessionValidErrors::InternalDBError(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                AddCustomRecipeErrors::ErrorCheckingIfSessionIsValid,
+            ),
+            crate::IsSessionValidErrors::InvalidSessionData {
+                current_date: _,
+                db_expire_date: _,
+            } => (StatusCode::UNAUTHORIZED, AddCustomRecipeErrors::JWTExpired),
+            _ => (
+                StatusCode::BAD_REQUEST,
+                AddCustomRecipeErrors::ErrorCheckingIfSessionIsValid,
+            ),
+        }
+        .into();
+
+        Err(error)?
+    }
+    tracing::debug!("{} Session is valid!", tracing_prefix);
+
+    if title.trim().is_empty() {
+        tracing::error!("{} The recipe has no title!", tracing_prefix);
+        let error: ResponseError<_> =
+            (StatusCode::BAD_REQUEST, AddCustomRecipeErrors::EmptyTitle).into();
+        Err(error)?
+    }
+
+    let recipe_id = format!("{}{}", CUSTOM_RECIPE_ID_PREFIX, Uuid::new_v4());
+    let recipe = build_custom_recipe(
+        recipe_id.clone(),
+        title,
+        image_url.clone(),
+        tags,
+        ingredients,
+        steps,
+    );
+
+    tracing::debug!(
+        "{} Storing copy of recipe `{}`...",
+        tracing_prefix,
+        recipe_id
+    );
+    if let Err(err) = cache_recipes(conn, std::slice::from_ref(&recipe)).await {
+        tracing::error!(
+            "{} An error `{:?}` occurred while storing a copy of recipe `{}`!",
+            tracing_prefix,
+            err,
+            recipe_id
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            AddCustomRecipeErrors::ErrorAddingCustomRecipe,
+        )
+            .into();
+        Err(error)?
+    }
+    tracing::debug!("{} Copy stored!", tracing_prefix);
+
+    tracing::debug!("{} Adding recipe `{}`...", tracing_prefix, recipe_id);
+    if let Err(err) = timed_query(
+        "add_custom_recipe",
+        conn.execute(
+            "INSERT INTO sf_custom_recipe (recipe_id, user_id, title, image_url, tags, ingredients, steps) VALUES ($1, $2, $3, $4, $5, $6, $7)",
+            &[
+                &recipe_id,
+                &user_id,
+                &recipe.title,
+                &image_url,
+                &recipe.tags,
+                &tokio_postgres::types::Json(&recipe.ingredients),
+                &recipe.steps,
+            ],
+        ),
+    )
+    .await
+    {
+        tracing::error!(
+            "{} An error `{:?}` occurred while adding recipe `{}`!",
+            tracing_prefix,
+            err,
+            recipe_id
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            AddCustomRecipeErrors::ErrorAddingCustomRecipe,
+        )
+            .into();
+        Err(error)?
+    }
+    tracing::debug!("{} Recipe added!", tracing_prefix);
+
+    tracing::debug!("{} DONE", tracing_prefix);
+    Ok(Json(AddCustomRecipeResponse { recipe_id }))
+}
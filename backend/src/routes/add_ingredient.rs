@@ -1,15 +1,26 @@
-use std::{
-    fmt::Display,
-    sync::{atomic::AtomicUsize, Arc},
-};
+use std::{fmt::Display, sync::Arc};
 
-use axum::{response::IntoResponse, Json};
+use axum::Json;
 use hyper::StatusCode;
 use serde::{Deserialize, Serialize};
 use tokio_postgres::Client;
+use utoipa::ToSchema;
 use uuid::Uuid;
 
-use crate::{extract_jwt, is_session_valid, responses::ResponseError, APP_SECRET};
+use crate::{
+    extract_jwt,
+    households::{get_current_household, GetCurrentHouseholdErrors},
+    inventory::duplicate_candidates,
+    is_session_valid,
+    metrics::timed_query,
+    models::{Ingredient, InventoryEventType},
+    parse_db_ingredient,
+    request_id::current_request_id,
+    responses::ResponseError,
+    storage::{get_storage_location, GetStorageLocationErrors},
+    units::convert_quantity,
+    APP_SECRET,
+};
 
 #[derive(Debug, Serialize)]
 pub enum AddIngredientErrors {
@@ -20,6 +31,15 @@ pub enum AddIngredientErrors {
     DBConnectionNotFound,
     ErrorInsertingIngredientIntoDB,
     NoIngredientInserted,
+    ErrorGettingHousehold,
+    NoHouseholdSelected,
+    NotAllowedInHousehold,
+    LocationNotFound,
+    ErrorGettingStorageLocation,
+    CouldntRetrieveIngredientsFromDB,
+    InvalidIngredientFormatFromDB,
+    DuplicateIngredient { candidates: Vec<Uuid> },
+    ErrorMergingIngredient,
 }
 
 impl Display for AddIngredientErrors {
@@ -28,17 +48,43 @@ impl Display for AddIngredientErrors {
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct AddIngredientPayload {
     token: String,
+    #[schema(value_type = NewIngredientPayload)]
     ingredient: IngredientPayload,
+    #[serde(default)]
+    on_duplicate: DuplicateIngredientAction,
+}
+
+/// What to do when the household already has the ingredient in the same location.
+#[derive(Debug, Serialize, Deserialize, Default, Clone, Copy, PartialEq, Eq, ToSchema)]
+pub enum DuplicateIngredientAction {
+    /// Add it as a different ingredient anyway.
+    #[default]
+    Create,
+    /// Add the quantity to the existing ingredient as a new lot with its own expire date.
+    Merge,
+    /// Fail listing the ingredients it could be merged into.
+    Conflict,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AddIngredientResponse {
+    #[serde(rename = "IngredientId")]
+    pub ingredient_id: Uuid,
+
+    /// Whether the ingredient was merged into an existing one.
+    #[serde(rename = "Merged")]
+    pub merged: bool,
 }
 
 /// Represents an ingredient that will be created.
 ///
 /// Creating the ingredient id shouldn't be a responsibility of the client.
 /// That's why this object doesn't have that.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[schema(as = NewIngredientPayload)]
 pub struct IngredientPayload {
     #[serde(rename = "UserId")]
     pub user_id: Uuid,
@@ -57,22 +103,42 @@ pub struct IngredientPayload {
 
     #[serde(rename = "Unit")]
     pub unit: String,
-}
 
-static ID: AtomicUsize = AtomicUsize::new(0);
+    /// When missing the ingredient is stored in the default fridge.
+    #[serde(rename = "LocationId", default)]
+    pub location_id: Option<Uuid>,
+}
 
+/// Route to add an ingredient to the current household.
+///
+/// When asked to, ingredients with the same name and category stored in the same location
+/// are merged into a single ingredient or reported as a conflict.
+#[utoipa::path(
+    post,
+    path = "/v1/ingredients/add",
+    tag = "Ingredients",
+    request_body = AddIngredientPayload,
+    responses(
+        (status = 200, description = "The ingredient was added or merged", body = AddIngredientResponse),
+        (status = 409, description = "The ingredient is a duplicate and `on_duplicate` is `Conflict`, the ingredients it could be merged into are returned", body = [Ingredient]),
+        (status = "4XX", description = "The `AddIngredientErrors` that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The `AddIngredientErrors` that made the request fail", body = String, content_type = "text/plain"),
+    )
+)]
 pub async fn add_ingredient(
     payload: Json<serde_json::Value>,
     client: Arc<Option<Client>>,
-) -> Result<impl IntoResponse, ResponseError<AddIngredientErrors>> {
-    let id = ID.fetch_add(1, std::sync::atomic::Ordering::AcqRel);
-    let tracing_prefix = format!("/ingredients/add - {}:", id);
+) -> Result<Json<AddIngredientResponse>, ResponseError<AddIngredientErrors>> {
+    let tracing_prefix = format!("/ingredients/add - {}:", current_request_id());
 
     tracing::debug!("{} START", tracing_prefix);
 
     tracing::debug!("{} Parsing payload...", tracing_prefix);
-    let AddIngredientPayload { token, ingredient } = match serde_json::from_value(payload.0.clone())
-    {
+    let AddIngredientPayload {
+        token,
+        ingredient,
+        on_duplicate,
+    } = match serde_json::from_value(payload.0.clone()) {
         Ok(p) => p,
         Err(err) => {
             tracing::error!(
@@ -108,6 +174,7 @@ pub async fn add_ingredient(
             Err(error)?
         }
     };
+    let user_id = token_info.user_id.clone();
     tracing::debug!("{} JWT extracted successfully!", tracing_prefix);
 
     let conn = client.as_ref().as_ref().ok_or_else(|| {
@@ -154,22 +221,230 @@ pub async fn add_ingredient(
         }
     }
 
+    tracing::debug!("{} Getting current household...", tracing_prefix);
+    let household = get_current_household(conn, &user_id).await.map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while getting the household of user `{}`!",
+            tracing_prefix,
+            err,
+            user_id
+        );
+        let error: ResponseError<_> = match err {
+            GetCurrentHouseholdErrors::NoHouseholdSelected => (
+                StatusCode::BAD_REQUEST,
+                AddIngredientErrors::NoHouseholdSelected,
+            ),
+            _ => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                AddIngredientErrors::ErrorGettingHousehold,
+            ),
+        }
+        .into();
+        error
+    })?;
+    if !household.role.can_edit_ingredients() {
+        tracing::error!(
+            "{} The user can't change the ingredients of household `{}`!",
+            tracing_prefix,
+            household.household_id
+        );
+        let error: ResponseError<_> = (
+            StatusCode::FORBIDDEN,
+            AddIngredientErrors::NotAllowedInHousehold,
+        )
+            .into();
+        Err(error)?
+    }
+    tracing::debug!(
+        "{} Working on household `{}`!",
+        tracing_prefix,
+        household.household_id
+    );
+
+    tracing::debug!("{} Getting storage location...", tracing_prefix);
+    let location = get_storage_location(
+        conn,
+        &household.household_id,
+        ingredient.location_id.map(|id| id.to_string()).as_deref(),
+    )
+    .await
+    .map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while getting the storage location!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = match err {
+            GetStorageLocationErrors::LocationNotFound => {
+                (StatusCode::NOT_FOUND, AddIngredientErrors::LocationNotFound)
+            }
+            _ => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                AddIngredientErrors::ErrorGettingStorageLocation,
+            ),
+        }
+        .into();
+        error
+    })?;
+    tracing::debug!(
+        "{} Got location `{}`!",
+        tracing_prefix,
+        location.location_id
+    );
+
+    let mut duplicate = None;
+    if on_duplicate != DuplicateIngredientAction::Create {
+        tracing::debug!("{} Looking for duplicates...", tracing_prefix);
+        let fridge = timed_query(
+            "add_ingredient",
+            conn.query(
+                "SELECT * FROM sf_ingredient WHERE household_id=$1 AND location_id=$2 AND deleted_date IS NULL ORDER BY expire_date",
+                &[&household.household_id, &location.location_id.to_string()],
+            ),
+        )
+        .await
+        .map_err(|err| {
+            tracing::error!(
+                "{} An error `{:?}` occurred while looking for duplicates!",
+                tracing_prefix,
+                err
+            );
+            let error: ResponseError<_> = (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                AddIngredientErrors::CouldntRetrieveIngredientsFromDB,
+            )
+                .into();
+            error
+        })?
+        .iter()
+        .map(|row| {
+            parse_db_ingredient(row, &tracing_prefix).ok_or_else(|| {
+                let error: ResponseError<_> = (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    AddIngredientErrors::InvalidIngredientFormatFromDB,
+                )
+                    .into();
+                error
+            })
+        })
+        .collect::<Result<Vec<Ingredient>, ResponseError<AddIngredientErrors>>>()?;
+
+        let candidates = duplicate_candidates(
+            &ingredient.name,
+            &ingredient.category,
+            &ingredient.unit,
+            location.location_id,
+            &fridge,
+        );
+        tracing::debug!("{} Found {} duplicates!", tracing_prefix, candidates.len());
+
+        if on_duplicate == DuplicateIngredientAction::Conflict && !candidates.is_empty() {
+            let candidate_ids: Vec<Uuid> = candidates.iter().map(|i| i.ingredient_id).collect();
+            tracing::error!(
+                "{} The ingredient is a duplicate of `{:?}`!",
+                tracing_prefix,
+                candidate_ids
+            );
+            let error = ResponseError::from((
+                StatusCode::CONFLICT,
+                AddIngredientErrors::DuplicateIngredient {
+                    candidates: candidate_ids,
+                },
+            ))
+            .with_json(&candidates);
+            Err(error)?
+        }
+
+        // The one that expires first is the one that should be used first. A quantity that can't
+        // be converted to the unit of the candidate isn't merged into it.
+        duplicate = candidates.iter().find_map(|i| {
+            convert_quantity(ingredient.quantity, &ingredient.unit, &i.unit)
+                .map(|quantity| (i.ingredient_id, i.unit.clone(), quantity))
+        });
+    }
+
+    if let Some((ingredient_id, unit, quantity)) = duplicate {
+        tracing::debug!(
+            "{} Merging {} {} into ingredient `{}`...",
+            tracing_prefix,
+            quantity,
+            unit,
+            ingredient_id
+        );
+        let rows_modified = timed_query(
+            "add_ingredient",
+            conn.execute(
+                "WITH target AS (SELECT ingredient_id, quantity, expire_date FROM sf_ingredient WHERE ingredient_id=$1 AND household_id=$2 AND deleted_date IS NULL), new_lot AS (INSERT INTO sf_ingredient_lot (ingredient_id, quantity, expire_date) SELECT ingredient_id, $3, $4 FROM target), merged AS (UPDATE sf_ingredient i SET quantity=i.quantity + $3, expire_date=LEAST(i.expire_date, $4), updated_at=NOW(), version=version + 1 FROM target t WHERE i.ingredient_id=t.ingredient_id RETURNING i.*) INSERT INTO sf_inventory_event (household_id, user_id, ingredient_id, name, category, quantity, unit, expire_date, event_type, reason) SELECT household_id, $5, ingredient_id, name, category, $3, unit, $4, $6, NULL FROM merged",
+                &[
+                    &ingredient_id.to_string(),
+                    &household.household_id,
+                    &quantity,
+                    &ingredient.expire_date,
+                    &user_id,
+                    &format!("{:?}", InventoryEventType::Added),
+                ],
+            ),
+        )
+        .await
+        .map_err(|err| {
+            tracing::error!(
+                "{} An error `{:?}` occurred while merging into ingredient `{}`!",
+                tracing_prefix,
+                err,
+                ingredient_id
+            );
+            let error: ResponseError<_> = (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                AddIngredientErrors::ErrorMergingIngredient,
+            )
+                .into();
+            error
+        })?;
+
+        if rows_modified == 0 {
+            tracing::error!(
+                "{} Ingredient `{}` was removed while merging!",
+                tracing_prefix,
+                ingredient_id
+            );
+            let error: ResponseError<_> = (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                AddIngredientErrors::NoIngredientInserted,
+            )
+                .into();
+            Err(error)?
+        }
+        tracing::debug!("{} Ingredient merged!", tracing_prefix);
+
+        tracing::debug!("{} DONE!", tracing_prefix);
+        return Ok(Json(AddIngredientResponse {
+            ingredient_id,
+            merged: true,
+        }));
+    }
+
     tracing::debug!("{} Inserting ingredient `{:?}`", tracing_prefix, ingredient);
-    let ingredient_id = Uuid::new_v4().to_string();
-    match conn
-        .execute(
-            "INSERT INTO sf_ingredient (ingredient_id, user_id, name, expire_date, category, quantity, unit) VALUES ($1, $2, $3, $4, $5, $6, $7)",
+    let ingredient_id = Uuid::new_v4();
+    match timed_query(
+        "add_ingredient",
+        conn.execute(
+            "WITH added AS (INSERT INTO sf_ingredient (ingredient_id, user_id, name, expire_date, category, quantity, unit, household_id, location_id) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9) RETURNING *), lot AS (INSERT INTO sf_ingredient_lot (ingredient_id, quantity, expire_date) SELECT ingredient_id, quantity, expire_date FROM added) INSERT INTO sf_inventory_event (household_id, user_id, ingredient_id, name, category, quantity, unit, expire_date, event_type, reason) SELECT household_id, $10, ingredient_id, name, category, quantity, unit, expire_date, $11, NULL FROM added",
             &[
-                &ingredient_id,
+                &ingredient_id.to_string(),
                 &ingredient.user_id.to_string(),
                 &ingredient.name,
                 &ingredient.expire_date,
                 &ingredient.category,
                 &ingredient.quantity,
                 &ingredient.unit,
+                &household.household_id,
+                &location.location_id.to_string(),
+                &user_id,
+                &format!("{:?}", InventoryEventType::Added),
             ],
-        )
-        .await
+        ),
+    )
+    .await
     {
         Ok(rows_modified) => {
             if rows_modified == 0 {
@@ -208,5 +483,8 @@ pub async fn add_ingredient(
     }
 
     tracing::debug!("{} DONE!", tracing_prefix);
-    Ok(StatusCode::OK)
+    Ok(Json(AddIngredientResponse {
+        ingredient_id,
+        merged: false,
+    }))
 }
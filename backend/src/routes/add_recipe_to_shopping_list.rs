@@ -0,0 +1,346 @@
+use std::{fmt::Display, sync::Arc};
+
+use axum::{response::IntoResponse, Json};
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::Client;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    extract_jwt,
+    households::{get_current_household, GetCurrentHouseholdErrors},
+    inventory::{
+        aggregate_required, matching_ingredients, missing_ingredients, not_on_shopping_list,
+        RequiredIngredient,
+    },
+    is_session_valid,
+    metrics::timed_query,
+    models::{Ingredient, Recipe, RecipeIngredient, ShoppingItem},
+    parse_db_ingredient, parse_db_shopping_item,
+    request_id::current_request_id,
+    responses::ResponseError,
+    shelf_life::DEFAULT_CATEGORY,
+    APP_SECRET,
+};
+
+use super::add_shopping_item::{add_to_shopping_list, ShoppingItemPayload};
+
+#[derive(Debug, Serialize)]
+pub enum AddRecipeToShoppingListErrors {
+    InvalidPayload { payload: String },
+    InvalidJWT,
+    DBConnectionNotFound,
+    ErrorCheckingIfSessionIsValid,
+    JWTExpired,
+    CouldntRetrieveIngredientsFromDB,
+    InvalidIngredientFormatFromDB,
+    CouldntRetrieveShoppingListFromDB,
+    ErrorAddingItemToShoppingList,
+    ErrorGettingHousehold,
+    NoHouseholdSelected,
+}
+
+impl Display for AddRecipeToShoppingListErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AddRecipeToShoppingListPayload {
+    token: String,
+    recipe: Recipe,
+}
+
+/// Represents an item added to the shopping list because the fridge didn't have enough of it.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AddedShoppingItem {
+    #[serde(rename = "ItemId")]
+    pub item_id: Uuid,
+
+    #[serde(rename = "Name")]
+    pub name: String,
+
+    /// The quantity added, what was missing minus what the list already had, not the total of the item.
+    #[serde(rename = "Quantity")]
+    pub quantity: f32,
+
+    #[serde(rename = "Unit")]
+    pub unit: String,
+
+    /// Whether the item was merged into one that was already on the list.
+    #[serde(rename = "Merged")]
+    pub merged: bool,
+}
+
+/// Route to add the ingredients of a recipe that are missing from the fridge to the shopping list.
+///
+/// What the shopping list already has of an ingredient isn't added again.
+///
+/// The ingredient lines of the recipe are parsed again, so the client can send
+/// the recipe exactly as it received it.
+#[utoipa::path(
+    post,
+    path = "/v1/shopping-list/recipe",
+    tag = "Shopping list",
+    request_body = AddRecipeToShoppingListPayload,
+    responses(
+        (status = 200, description = "The items that were missing", body = [AddedShoppingItem]),
+        (status = "4XX", description = "The `AddRecipeToShoppingListErrors` that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The `AddRecipeToShoppingListErrors` that made the request fail", body = String, content_type = "text/plain"),
+    )
+)]
+pub async fn add_recipe_to_shopping_list(
+    payload: Json<serde_json::Value>,
+    client: Arc<Option<Client>>,
+) -> Result<impl IntoResponse, ResponseError<AddRecipeToShoppingListErrors>> {
+    let tracing_prefix = format!("/shopping-list/recipe - {}:", current_request_id());
+
+    tracing::debug!("{} START", tracing_prefix);
+
+    tracing::debug!("{} Parsing payload...", tracing_prefix);
+    let AddRecipeToShoppingListPayload { token, recipe } =
+        match serde_json::from_value(payload.0.clone()) {
+            Ok(p) => p,
+            Err(err) => {
+                tracing::error!(
+                    "{} An error `{:?}` occurred parsing payload `{}`",
+                    tracing_prefix,
+                    err,
+                    payload.0
+                );
+                let error: ResponseError<_> = (
+                    StatusCode::BAD_REQUEST,
+                    AddRecipeToShoppingListErrors::InvalidPayload {
+                        payload: payload.0.to_string(),
+                    },
+                )
+                    .into();
+                Err(error)?
+            }
+        };
+    tracing::debug!("{} Payload parsed successfully!", tracing_prefix);
+
+    tracing::debug!("{} Extracting JWT...", tracing_prefix);
+    let token_info = match extract_jwt(APP_SECRET, &token) {
+        Ok(t) => t,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred while extracting the JWT `{}`",
+                tracing_prefix,
+                err,
+                token
+            );
+            let error: ResponseError<_> = (
+                StatusCode::BAD_REQUEST,
+                AddRecipeToShoppingListErrors::InvalidJWT,
+            )
+                .into();
+            Err(error)?
+        }
+    };
+    let user_id = token_info.user_id.clone();
+    tracing::debug!("{} JWT extracted successfully!", tracing_prefix);
+
+    let conn = client.as_ref().as_ref().ok_or_else(|| {
+        tracing::error!("{} DB Connection not found!", tracing_prefix);
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            AddRecipeToShoppingListErrors::DBConnectionNotFound,
+        )
+            .into();
+        error
+    })?;
+    tracing::debug!("{} DB Connection found!", tracing_prefix);
+
+    tracing::debug!("{} Checking if session is valid...", tracing_prefix);
+    if let Err(err) = is_session_valid(token_info, conn).await {
+        tracing::error!(
+            "{} An error `{:?}` occurred while checking if session is valid!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = match err {
+            crate::IsSessionValidErrors::InternalDBError(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                AddRecipeToShoppingListErrors::ErrorCheckingIfSessionIsValid,
+            ),
+            crate::IsSessionValidErrors::InvalidSessionData {
+                current_date: _,
+                db_expire_date: _,
+            } => (
+                StatusCode::UNAUTHORIZED,
+                AddRecipeToShoppingListErrors::JWTExpired,
+            ),
+            _ => (
+                StatusCode::BAD_REQUEST,
+                AddRecipeToShoppingListErrors::ErrorCheckingIfSessionIsValid,
+            ),
+        }
+        .into();
+
+        Err(error)?
+    }
+    tracing::debug!("{} Session is valid!", tracing_prefix);
+
+    tracing::debug!("{} Getting current household...", tracing_prefix);
+    let household = get_current_household(conn, &user_id).await.map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while getting the household of user `{}`!",
+            tracing_prefix,
+            err,
+            user_id
+        );
+        let error: ResponseError<_> = match err {
+            GetCurrentHouseholdErrors::NoHouseholdSelected => (
+                StatusCode::BAD_REQUEST,
+                AddRecipeToShoppingListErrors::NoHouseholdSelected,
+            ),
+            _ => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                AddRecipeToShoppingListErrors::ErrorGettingHousehold,
+            ),
+        }
+        .into();
+        error
+    })?;
+    tracing::debug!(
+        "{} Working on household `{}`!",
+        tracing_prefix,
+        household.household_id
+    );
+
+    tracing::debug!("{} Getting ingredients from DB...", tracing_prefix);
+    let fridge = timed_query(
+        "add_recipe_to_shopping_list",
+        conn.query(
+            "SELECT * FROM sf_ingredient WHERE household_id=$1 AND deleted_date IS NULL",
+            &[&household.household_id],
+        ),
+    )
+    .await
+    .map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` while trying to get ingredients for user `{}`",
+            tracing_prefix,
+            err,
+            user_id
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            AddRecipeToShoppingListErrors::CouldntRetrieveIngredientsFromDB,
+        )
+            .into();
+        error
+    })?
+    .iter()
+    .map(|row| {
+        parse_db_ingredient(row, &tracing_prefix).ok_or_else(|| {
+            let error: ResponseError<_> = (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                AddRecipeToShoppingListErrors::InvalidIngredientFormatFromDB,
+            )
+                .into();
+            error
+        })
+    })
+    .collect::<Result<Vec<Ingredient>, ResponseError<AddRecipeToShoppingListErrors>>>()?;
+    tracing::debug!("{} Got ingredients from user!", tracing_prefix);
+
+    tracing::debug!("{} Computing missing ingredients...", tracing_prefix);
+    let required = recipe
+        .ingredients
+        .into_iter()
+        .map(|i| RecipeIngredient::from_display(Some(i.name), i.display))
+        .map(|i| RequiredIngredient::from(&i))
+        .collect();
+    let missing = missing_ingredients(&aggregate_required(required), &fridge);
+    tracing::debug!(
+        "{} {} ingredients are missing!",
+        tracing_prefix,
+        missing.len()
+    );
+
+    tracing::debug!("{} Getting shopping list from DB...", tracing_prefix);
+    let shopping_list: Vec<ShoppingItem> = timed_query(
+        "add_recipe_to_shopping_list",
+        conn.query(
+            "SELECT * FROM sf_shopping_item WHERE user_id=$1 AND checked=FALSE",
+            &[&user_id],
+        ),
+    )
+    .await
+    .map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` while trying to get the shopping list of user `{}`",
+            tracing_prefix,
+            err,
+            user_id
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            AddRecipeToShoppingListErrors::CouldntRetrieveShoppingListFromDB,
+        )
+            .into();
+        error
+    })?
+    .iter()
+    .filter_map(|row| parse_db_shopping_item(row, &tracing_prefix))
+    .collect();
+    let missing = not_on_shopping_list(missing, &shopping_list);
+    tracing::debug!(
+        "{} {} ingredients are missing from the shopping list!",
+        tracing_prefix,
+        missing.len()
+    );
+
+    let mut added = vec![];
+    for ingredient in missing {
+        let category = matching_ingredients(&ingredient, &fridge)
+            .first()
+            .map(|i| i.category.clone())
+            .unwrap_or_else(|| DEFAULT_CATEGORY.to_string());
+        let item = ShoppingItemPayload {
+            name: ingredient.name,
+            category,
+            quantity: ingredient.quantity,
+            unit: ingredient.unit,
+        };
+
+        let (item_id, merged) = add_to_shopping_list(
+            conn,
+            &user_id,
+            &item,
+            Some(&recipe.recipe_id),
+            &tracing_prefix,
+        )
+        .await
+        .map_err(|err| {
+            tracing::error!(
+                "{} An error `{:?}` occurred while adding item `{:?}` to the shopping list!",
+                tracing_prefix,
+                err,
+                item
+            );
+            let error: ResponseError<_> = (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                AddRecipeToShoppingListErrors::ErrorAddingItemToShoppingList,
+            )
+                .into();
+            error
+        })?;
+
+        added.push(AddedShoppingItem {
+            item_id,
+            name: item.name,
+            quantity: item.quantity,
+            unit: item.unit,
+            merged,
+        });
+    }
+    tracing::debug!("{} Missing ingredients added!", tracing_prefix);
+
+    tracing::debug!("{} DONE", tracing_prefix);
+    Ok(Json(added))
+}
@@ -0,0 +1,275 @@
+use std::{fmt::Display, sync::Arc};
+
+use axum::{response::IntoResponse, Json};
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::Client;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    extract_jwt, ingredient_parser::ingredient_names_match, is_session_valid, metrics::timed_query,
+    parse_db_shopping_item, request_id::current_request_id, responses::ResponseError,
+    shelf_life::DEFAULT_CATEGORY, units::convert_quantity, APP_SECRET,
+};
+
+#[derive(Debug, Serialize)]
+pub enum AddShoppingItemErrors {
+    InvalidPayload { payload: String },
+    InvalidJWT,
+    DBConnectionNotFound,
+    ErrorCheckingIfSessionIsValid,
+    JWTExpired,
+    ErrorAddingItemToShoppingList,
+}
+
+impl Display for AddShoppingItemErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AddShoppingItemPayload {
+    token: String,
+    #[schema(value_type = NewShoppingItemPayload)]
+    item: ShoppingItemPayload,
+}
+
+/// Represents an item that will be added to the shopping list.
+///
+/// If the list already has the same item with a compatible unit the quantities are merged.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[schema(as = NewShoppingItemPayload)]
+pub struct ShoppingItemPayload {
+    #[serde(rename = "Name")]
+    pub name: String,
+
+    #[serde(rename = "Category", default = "default_category")]
+    pub category: String,
+
+    #[serde(rename = "Quantity")]
+    pub quantity: f32,
+
+    #[serde(rename = "Unit")]
+    pub unit: String,
+}
+
+fn default_category() -> String {
+    DEFAULT_CATEGORY.to_string()
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AddShoppingItemResponse {
+    /// The id of the item that now contains the added quantity.
+    #[serde(rename = "ItemId")]
+    pub item_id: Uuid,
+
+    /// Whether the item was merged into one that was already on the list.
+    #[serde(rename = "Merged")]
+    pub merged: bool,
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/shopping-list/add",
+    tag = "Shopping list",
+    request_body = AddShoppingItemPayload,
+    responses(
+        (status = 200, description = "The item was added or merged", body = AddShoppingItemResponse),
+        (status = "4XX", description = "The `AddShoppingItemErrors` that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The `AddShoppingItemErrors` that made the request fail", body = String, content_type = "text/plain"),
+    )
+)]
+pub async fn add_shopping_item(
+    payload: Json<serde_json::Value>,
+    client: Arc<Option<Client>>,
+) -> Result<impl IntoResponse, ResponseError<AddShoppingItemErrors>> {
+    let tracing_prefix = format!("/shopping-list/add - {}:", current_request_id());
+
+    tracing::debug!("{} START", tracing_prefix);
+
+    tracing::debug!("{} Parsing payload...", tracing_prefix);
+    let AddShoppingItemPayload { token, item } = match serde_json::from_value(payload.0.clone()) {
+        Ok(p) => p,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred parsing payload `{}`",
+                tracing_prefix,
+                err,
+                payload.0
+            );
+            let error: ResponseError<_> = (
+                StatusCode::BAD_REQUEST,
+                AddShoppingItemErrors::InvalidPayload {
+                    payload: payload.0.to_string(),
+                },
+            )
+                .into();
+            Err(error)?
+        }
+    };
+    tracing::debug!("{} Payload parsed successfully!", tracing_prefix);
+
+    tracing::debug!("{} Extracting JWT...", tracing_prefix);
+    let token_info = match extract_jwt(APP_SECRET, &token) {
+        Ok(t) => t,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred while extracting the JWT `{}`",
+                tracing_prefix,
+                err,
+                token
+            );
+            let error: ResponseError<_> =
+                (StatusCode::BAD_REQUEST, AddShoppingItemErrors::InvalidJWT).into();
+            Err(error)?
+        }
+    };
+    let user_id = token_info.user_id.clone();
+    tracing::debug!("{} JWT extracted successfully!", tracing_prefix);
+
+    let conn = client.as_ref().as_ref().ok_or_else(|| {
+        tracing::error!(
+            "{} DB Connection not found! Couldn't add item `{:?}` into DB!",
+            tracing_prefix,
+            item
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            AddShoppingItemErrors::DBConnectionNotFound,
+        )
+            .into();
+        error
+    })?;
+    tracing::debug!("{} DB Connection found!", tracing_prefix);
+
+    tracing::debug!("{} Checking if session is valid...", tracing_prefix);
+    if let Err(err) = is_session_valid(token_info, conn).await {
+        tracing::error!(
+            "{} An error `{:?}` occurred while checking if session is valid!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = match err {
+            crate::IsSessionValidErrors::InternalDBError(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                AddShoppingItemErrors::ErrorCheckingIfSessionIsValid,
+            ),
+            crate::IsSessionValidErrors::InvalidSessionData {
+                current_date: _,
+                db_expire_date: _,
+            } => (StatusCode::UNAUTHORIZED, AddShoppingItemErrors::JWTExpired),
+            _ => (
+                StatusCode::BAD_REQUEST,
+                AddShoppingItemErrors::ErrorCheckingIfSessionIsValid,
+            ),
+        }
+        .into();
+
+        Err(error)?
+    }
+    tracing::debug!("{} Session is valid!", tracing_prefix);
+
+    tracing::debug!("{} Adding item `{:?}`...", tracing_prefix, item);
+    let (item_id, merged) = add_to_shopping_list(conn, &user_id, &item, None, &tracing_prefix)
+        .await
+        .map_err(|err| {
+            tracing::error!(
+                "{} An error `{:?}` occurred while adding item `{:?}` to the shopping list!",
+                tracing_prefix,
+                err,
+                item
+            );
+            let error: ResponseError<_> = (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                AddShoppingItemErrors::ErrorAddingItemToShoppingList,
+            )
+                .into();
+            error
+        })?;
+    tracing::debug!(
+        "{} Item with ID `{}` added! Merged: {}",
+        tracing_prefix,
+        item_id,
+        merged
+    );
+
+    tracing::debug!("{} DONE", tracing_prefix);
+    Ok(Json(AddShoppingItemResponse { item_id, merged }))
+}
+
+/// Adds an item to the user's shopping list.
+///
+/// When an unchecked item with the same name and a compatible unit already exists,
+/// the quantity is converted and added to it instead of creating a new row.
+///
+/// Returns the id of the item and whether it was merged.
+pub(crate) async fn add_to_shopping_list(
+    conn: &Client,
+    user_id: &str,
+    item: &ShoppingItemPayload,
+    recipe_id: Option<&str>,
+    tracing_prefix: &str,
+) -> Result<(Uuid, bool), tokio_postgres::Error> {
+    let rows = timed_query(
+        "add_to_shopping_list",
+        conn.query(
+            "SELECT * FROM sf_shopping_item WHERE user_id=$1 AND checked=FALSE",
+            &[&user_id],
+        ),
+    )
+    .await?;
+
+    let existing = rows
+        .iter()
+        .filter_map(|row| parse_db_shopping_item(row, tracing_prefix))
+        .find_map(|existing| {
+            if !ingredient_names_match(&existing.name, &item.name) {
+                return None;
+            }
+            let quantity = convert_quantity(item.quantity, &item.unit, &existing.unit)?;
+            Some((existing, quantity))
+        });
+
+    let recipe_ids: Vec<String> = recipe_id.into_iter().map(str::to_string).collect();
+    match existing {
+        Some((existing, quantity)) => {
+            tracing::debug!(
+                "{} Merging `{}` into item `{}`...",
+                tracing_prefix,
+                item.name,
+                existing.item_id
+            );
+            timed_query(
+                "add_to_shopping_list",
+                conn.execute(
+                    "UPDATE sf_shopping_item SET quantity=quantity + $2, recipe_ids=ARRAY(SELECT DISTINCT UNNEST(recipe_ids || $3::TEXT[])) WHERE item_id=$1",
+                    &[&existing.item_id.to_string(), &quantity, &recipe_ids],
+                ),
+            )
+            .await?;
+            Ok((existing.item_id, true))
+        }
+        None => {
+            let item_id = Uuid::new_v4();
+            timed_query(
+                "add_to_shopping_list",
+                conn.execute(
+                    "INSERT INTO sf_shopping_item (item_id, user_id, name, category, quantity, unit, checked, recipe_ids) VALUES ($1, $2, $3, $4, $5, $6, FALSE, $7)",
+                    &[
+                        &item_id.to_string(),
+                        &user_id,
+                        &item.name,
+                        &item.category,
+                        &item.quantity,
+                        &item.unit,
+                        &recipe_ids,
+                    ],
+                ),
+            )
+            .await?;
+            Ok((item_id, false))
+        }
+    }
+}
@@ -0,0 +1,243 @@
+use std::{fmt::Display, sync::Arc};
+
+use axum::{response::IntoResponse, Json};
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::Client;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    extract_jwt,
+    households::{get_current_household, GetCurrentHouseholdErrors},
+    is_session_valid,
+    metrics::timed_query,
+    models::StorageKind,
+    request_id::current_request_id,
+    responses::ResponseError,
+    APP_SECRET,
+};
+
+#[derive(Debug, Serialize)]
+pub enum AddStorageLocationErrors {
+    InvalidPayload { payload: String },
+    InvalidJWT,
+    NoDBConnectionFound,
+    ErrorCheckingIfSessionIsValid,
+    JWTExpired,
+    ErrorGettingHousehold,
+    NoHouseholdSelected,
+    NotAllowedInHousehold,
+    EmptyName,
+    ErrorAddingLocation,
+}
+
+impl Display for AddStorageLocationErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AddStorageLocationPayload {
+    token: String,
+    name: String,
+    kind: StorageKind,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AddStorageLocationResponse {
+    #[serde(rename = "LocationId")]
+    pub location_id: Uuid,
+}
+
+/// Route to add a storage location to the current household.
+///
+/// The kind decides how the expire date of the ingredients moved there changes.
+#[utoipa::path(
+    post,
+    path = "/v1/locations/add",
+    tag = "Locations",
+    request_body = AddStorageLocationPayload,
+    responses(
+        (status = 200, description = "The location was added", body = AddStorageLocationResponse),
+        (status = "4XX", description = "The `AddStorageLocationErrors` that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The `AddStorageLocationErrors` that made the request fail", body = String, content_type = "text/plain"),
+    )
+)]
+pub async fn add_storage_location(
+    payload: Json<serde_json::Value>,
+    client: Arc<Option<Client>>,
+) -> Result<impl IntoResponse, ResponseError<AddStorageLocationErrors>> {
+    let tracing_prefix = format!("/locations/add - {}:", current_request_id());
+
+    tracing::debug!("{} START", tracing_prefix);
+
+    tracing::debug!("{} Parsing payload...", tracing_prefix);
+    let AddStorageLocationPayload { token, name, kind } =
+        match serde_json::from_value(payload.0.clone()) {
+            Ok(p) => p,
+            Err(err) => {
+                tracing::error!(
+                    "{} An error `{:?}` occurred parsing payload `{}`",
+                    tracing_prefix,
+                    err,
+                    payload.0
+                );
+                let error: ResponseError<_> = (
+                    StatusCode::BAD_REQUEST,
+                    AddStorageLocationErrors::InvalidPayload {
+                        payload: payload.0.to_string(),
+                    },
+                )
+                    .into();
+                Err(error)?
+            }
+        };
+    tracing::debug!("{} Payload parsed successfully!", tracing_prefix);
+
+    tracing::debug!("{} Extracting JWT...", tracing_prefix);
+    let token_info = match extract_jwt(APP_SECRET, &token) {
+        Ok(t) => t,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred while extracting the JWT `{}`",
+                tracing_prefix,
+                err,
+                token
+            );
+            let error: ResponseError<_> = (
+                StatusCode::BAD_REQUEST,
+                AddStorageLocationErrors::InvalidJWT,
+            )
+                .into();
+            Err(error)?
+        }
+    };
+    let user_id = token_info.user_id.clone();
+    tracing::debug!("{} JWT extracted successfully!", tracing_prefix);
+
+    tracing::debug!("{} Checking for DB connection...", tracing_prefix);
+    let conn = client.as_ref().as_ref().ok_or_else(|| {
+        tracing::error!("{} No DB connection found!", tracing_prefix);
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            AddStorageLocationErrors::NoDBConnectionFound,
+        )
+            .into();
+        error
+    })?;
+    tracing::debug!("{} DB Connection found!", tracing_prefix);
+
+    tracing::debug!("{} Checking if session is valid...", tracing_prefix);
+    if let Err(err) = is_session_valid(token_info, conn).await {
+        tracing::error!(
+            "{} An error `{:?}` occurred while checking if session is valid!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = match err {
+            crate::IsSessionValidErrors::InternalDBError(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                AddStorageLocationErrors::ErrorCheckingIfSessionIsValid,
+            ),
+            crate::IsSessionValidErrors::InvalidSessionData {
+                current_date: _,
+                db_expire_date: _,
+            } => (
+                StatusCode::UNAUTHORIZED,
+                AddStorageLocationErrors::JWTExpired,
+            ),
+            _ => (
+                StatusCode::BAD_REQUEST,
+                AddStorageLocationErrors::ErrorCheckingIfSessionIsValid,
+            ),
+        }
+        .into();
+
+        Err(error)?
+    }
+    tracing::debug!("{} Session is valid!", tracing_prefix);
+
+    tracing::debug!("{} Getting current household...", tracing_prefix);
+    let household = get_current_household(conn, &user_id).await.map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while getting the household of user `{}`!",
+            tracing_prefix,
+            err,
+            user_id
+        );
+        let error: ResponseError<_> = match err {
+            GetCurrentHouseholdErrors::NoHouseholdSelected => (
+                StatusCode::BAD_REQUEST,
+                AddStorageLocationErrors::NoHouseholdSelected,
+            ),
+            _ => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                AddStorageLocationErrors::ErrorGettingHousehold,
+            ),
+        }
+        .into();
+        error
+    })?;
+    if !household.role.can_edit_ingredients() {
+        tracing::error!(
+            "{} The user can't change the ingredients of household `{}`!",
+            tracing_prefix,
+            household.household_id
+        );
+        let error: ResponseError<_> = (
+            StatusCode::FORBIDDEN,
+            AddStorageLocationErrors::NotAllowedInHousehold,
+        )
+            .into();
+        Err(error)?
+    }
+    tracing::debug!(
+        "{} Working on household `{}`!",
+        tracing_prefix,
+        household.household_id
+    );
+
+    let name = name.trim();
+    if name.is_empty() {
+        tracing::error!("{} The location has no name!", tracing_prefix);
+        let error: ResponseError<_> =
+            (StatusCode::BAD_REQUEST, AddStorageLocationErrors::EmptyName).into();
+        Err(error)?
+    }
+
+    tracing::debug!("{} Adding location `{}`...", tracing_prefix, name);
+    let location_id = Uuid::new_v4();
+    if let Err(err) = timed_query(
+        "add_storage_location",
+        conn.execute(
+            "INSERT INTO sf_storage_location (location_id, household_id, name, kind) VALUES ($1, $2, $3, $4)",
+            &[
+                &location_id.to_string(),
+                &household.household_id,
+                &name,
+                &format!("{:?}", kind),
+            ],
+        ),
+    )
+    .await
+    {
+        tracing::error!(
+            "{} An error `{:?}` occurred while adding location `{}`!",
+            tracing_prefix,
+            err,
+            name
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            AddStorageLocationErrors::ErrorAddingLocation,
+        )
+            .into();
+        Err(error)?
+    }
+    tracing::debug!("{} Location `{}` added!", tracing_prefix, location_id);
+
+    tracing::debug!("{} DONE", tracing_prefix);
+    Ok(Json(AddStorageLocationResponse { location_id }))
+}
@@ -0,0 +1,243 @@
+use std::{fmt::Display, sync::Arc};
+
+use axum::{response::IntoResponse, Json};
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::Client;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    extract_jwt, generate_share_code, is_session_valid,
+    metrics::timed_query,
+    models::CustomTheme,
+    request_id::current_request_id,
+    responses::ResponseError,
+    themes::{ThemePalette, ThemeValidationErrors},
+    APP_SECRET,
+};
+
+#[derive(Debug, Serialize)]
+pub enum AddThemeErrors {
+    InvalidPayload { payload: String },
+    InvalidJWT,
+    NoDBConnectionFound,
+    ErrorCheckingIfSessionIsValid,
+    JWTExpired,
+    EmptyName,
+    InvalidColor { color: String },
+    LowContrast { colors: String, ratio: f32 },
+    ErrorAddingTheme,
+}
+
+impl Display for AddThemeErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AddThemePayload {
+    token: String,
+    name: String,
+    primary: String,
+    secondary: String,
+    background: String,
+    surface: String,
+}
+
+/// Route to define a color theme.
+///
+/// The primary and secondary colors must contrast with the background and surface colors.
+#[utoipa::path(
+    post,
+    path = "/v1/themes/add",
+    tag = "Themes",
+    request_body = AddThemePayload,
+    responses(
+        (status = 200, description = "The theme was added", body = CustomTheme),
+        (status = "4XX", description = "The `AddThemeErrors` that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The `AddThemeErrors` that made the request fail", body = String, content_type = "text/plain"),
+    )
+)]
+pub async fn add_theme(
+    payload: Json<serde_json::Value>,
+    client: Arc<Option<Client>>,
+) -> Result<impl IntoResponse, ResponseError<AddThemeErrors>> {
+    let tracing_prefix = format!("/themes/add - {}:", current_request_id());
+
+    tracing::debug!("{} START", tracing_prefix);
+
+    tracing::debug!("{} Parsing payload...", tracing_prefix);
+    let AddThemePayload {
+        token,
+        name,
+        primary,
+        secondary,
+        background,
+        surface,
+    } = match serde_json::from_value(payload.0.clone()) {
+        Ok(p) => p,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred parsing payload `{}`",
+                tracing_prefix,
+                err,
+                payload.0
+            );
+            let error: ResponseError<_> = (
+                StatusCode::BAD_REQUEST,
+                AddThemeErrors::InvalidPayload {
+                    payload: payload.0.to_string(),
+                },
+            )
+                .into();
+            Err(error)?
+        }
+    };
+    tracing::debug!("{} Payload parsed successfully!", tracing_prefix);
+
+    tracing::debug!("{} Extracting JWT...", tracing_prefix);
+    let token_info = match extract_jwt(APP_SECRET, &token) {
+        Ok(t) => t,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred while extracting the JWT `{}`",
+                tracing_prefix,
+                err,
+                token
+            );
+            let error: ResponseError<_> =
+                (StatusCode::BAD_REQUEST, AddThemeErrors::InvalidJWT).into();
+            Err(error)?
+        }
+    };
+    let user_id = token_info.user_id.clone();
+    tracing::debug!("{} JWT extracted successfully!", tracing_prefix);
+
+    tracing::debug!("{} Checking for DB connection...", tracing_prefix);
+    let conn = client.as_ref().as_ref().ok_or_else(|| {
+        tracing::error!("{} No DB connection found!", tracing_prefix);
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            AddThemeErrors::NoDBConnectionFound,
+        )
+            .into();
+        error
+    })?;
+    tracing::debug!("{} DB Connection found!", tracing_prefix);
+
+    tracing::debug!("{} Checking if session is valid...", tracing_prefix);
+    if let Err(err) = is_session_valid(token_info, conn).await {
+        tracing::error!(
+            "{} An error `{:?}` occurred while checking if session is valid!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = match err {
+            crate::IsSessionValidErrors::InternalDBError(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                AddThemeErrors::ErrorCheckingIfSessionIsValid,
+            ),
+            crate::IsSessionValidErrors::InvalidSessionData {
+                current_date: _,
+                db_expire_date: _,
+            } => (StatusCode::UNAUTHORIZED, AddThemeErrors::JWTExpired),
+            _ => (
+                StatusCode::BAD_REQUEST,
+                AddThemeErrors::ErrorCheckingIfSessionIsValid,
+            ),
+        }
+        .into();
+
+        Err(error)?
+    }
+    tracing::debug!("{} Session is valid!", tracing_prefix);
+
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        tracing::error!("{} The theme has no name!", tracing_prefix);
+        let error: ResponseError<_> = (StatusCode::BAD_REQUEST, AddThemeErrors::EmptyName).into();
+        Err(error)?
+    }
+
+    tracing::debug!("{} Validating colors...", tracing_prefix);
+    let palette = ThemePalette {
+        primary,
+        secondary,
+        background,
+        surface,
+    }
+    .validate()
+    .map_err(|err| {
+        tracing::error!(
+            "{} The colors of the theme aren't valid `{:?}`!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = match err {
+            ThemeValidationErrors::InvalidColor { color } => (
+                StatusCode::BAD_REQUEST,
+                AddThemeErrors::InvalidColor { color },
+            ),
+            ThemeValidationErrors::LowContrast { colors, ratio } => (
+                StatusCode::BAD_REQUEST,
+                AddThemeErrors::LowContrast { colors, ratio },
+            ),
+        }
+        .into();
+        error
+    })?;
+    tracing::debug!("{} Colors are valid!", tracing_prefix);
+
+    let theme = CustomTheme {
+        theme_id: Uuid::new_v4(),
+        name,
+        primary: palette.primary,
+        secondary: palette.secondary,
+        background: palette.background,
+        surface: palette.surface,
+        share_code: generate_share_code(),
+    };
+
+    tracing::debug!("{} Saving theme `{}`...", tracing_prefix, theme.name);
+    if let Err(err) = timed_query(
+        "add_theme",
+        conn.execute(
+            "INSERT INTO sf_theme (theme_id, user_id, name, primary_color, secondary_color, background_color, surface_color, share_code) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+            &[
+                &theme.theme_id.to_string(),
+                &user_id,
+                &theme.name,
+                &theme.primary,
+                &theme.secondary,
+                &theme.background,
+                &theme.surface,
+                &theme.share_code,
+            ],
+        ),
+    )
+    .await
+    {
+        tracing::error!(
+            "{} An error `{:?}` occurred while saving theme `{}`!",
+            tracing_prefix,
+            err,
+            theme.name
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            AddThemeErrors::ErrorAddingTheme,
+        )
+            .into();
+        Err(error)?
+    }
+    tracing::debug!(
+        "{} Theme saved with id `{}`!",
+        tracing_prefix,
+        theme.theme_id
+    );
+
+    tracing::debug!("{} DONE", tracing_prefix);
+    Ok(Json(theme))
+}
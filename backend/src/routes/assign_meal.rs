@@ -0,0 +1,249 @@
+use std::{fmt::Display, sync::Arc};
+
+use axum::{response::IntoResponse, Json};
+use chrono::NaiveDate;
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::Client;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    extract_jwt, is_session_valid, metrics::timed_query, models::MealSlot,
+    request_id::current_request_id, responses::ResponseError, APP_SECRET,
+};
+
+#[derive(Debug, Serialize)]
+pub enum AssignMealErrors {
+    InvalidPayload { payload: String },
+    InvalidJWT,
+    NoDBConnectionFound,
+    ErrorCheckingIfSessionIsValid,
+    JWTExpired,
+    ErrorCheckingIfRecipeExists,
+    RecipeNotFound,
+    ErrorAssigningMeal,
+}
+
+impl Display for AssignMealErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct AssignMealPayload {
+    token: String,
+    recipe_id: String,
+    date: NaiveDate,
+    slot: MealSlot,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AssignMealResponse {
+    #[serde(rename = "PlanId")]
+    pub plan_id: Uuid,
+}
+
+/// Route to plan a recipe for a date and meal slot.
+///
+/// The recipe must be one the backend has already returned to a client.
+/// If the slot already had a recipe it's replaced.
+#[utoipa::path(
+    post,
+    path = "/v1/meal-plan/assign",
+    tag = "Meal plan",
+    request_body = AssignMealPayload,
+    responses(
+        (status = 200, description = "The meal was planned", body = AssignMealResponse),
+        (status = "4XX", description = "The `AssignMealErrors` that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The `AssignMealErrors` that made the request fail", body = String, content_type = "text/plain"),
+    )
+)]
+pub async fn assign_meal(
+    payload: Json<serde_json::Value>,
+    client: Arc<Option<Client>>,
+) -> Result<impl IntoResponse, ResponseError<AssignMealErrors>> {
+    let tracing_prefix = format!("/meal-plan/assign - {}:", current_request_id());
+
+    tracing::debug!("{} START", tracing_prefix);
+
+    tracing::debug!("{} Parsing payload...", tracing_prefix);
+    let AssignMealPayload {
+        token,
+        recipe_id,
+        date,
+        slot,
+    } = match serde_json::from_value(payload.0.clone()) {
+        Ok(p) => p,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred parsing payload `{}`",
+                tracing_prefix,
+                err,
+                payload.0
+            );
+            let error: ResponseError<_> = (
+                StatusCode::BAD_REQUEST,
+                AssignMealErrors::InvalidPayload {
+                    payload: payload.0.to_string(),
+                },
+            )
+                .into();
+            Err(error)?
+        }
+    };
+    tracing::debug!("{} Payload parsed successfully!", tracing_prefix);
+
+    tracing::debug!("{} Extracting JWT...", tracing_prefix);
+    let token_info = match extract_jwt(APP_SECRET, &token) {
+        Ok(t) => t,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred while extracting the JWT `{}`",
+                tracing_prefix,
+                err,
+                token
+            );
+            let error: ResponseError<_> =
+                (StatusCode::BAD_REQUEST, AssignMealErrors::InvalidJWT).into();
+            Err(error)?
+        }
+    };
+    let user_id = token_info.user_id.clone();
+    tracing::debug!("{} JWT extracted successfully!", tracing_prefix);
+
+    tracing::debug!("{} Checking for DB connection...", tracing_prefix);
+    let conn = client.as_ref().as_ref().ok_or_else(|| {
+        tracing::error!("{} No DB connection found!", tracing_prefix);
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            AssignMealErrors::NoDBConnectionFound,
+        )
+            .into();
+        error
+    })?;
+    tracing::debug!("{} DB Connection found!", tracing_prefix);
+
+    tracing::debug!("{} Checking if session is valid...", tracing_prefix);
+    if let Err(err) = is_session_valid(token_info, conn).await {
+        tracing::error!(
+            "{} An error `{:?}` occurred while checking if session is valid!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = match err {
+            crate::IsSessionValidErrors::InternalDBError(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                AssignMealErrors::ErrorCheckingIfSessionIsValid,
+            ),
+            crate::IsSessionValidErrors::InvalidSessionData {
+                current_date: _,
+                db_expire_date: _,
+            } => (StatusCode::UNAUTHORIZED, AssignMealErrors::JWTExpired),
+            _ => (
+                StatusCode::BAD_REQUEST,
+                AssignMealErrors::ErrorCheckingIfSessionIsValid,
+            ),
+        }
+        .into();
+
+        Err(error)?
+    }
+    tracing::debug!("{} Session is valid!", tracing_prefix);
+
+    tracing::debug!(
+        "{} Checking if recipe `{}` exists...",
+        tracing_prefix,
+        recipe_id
+    );
+    let recipe_exists = !timed_query(
+        "assign_meal",
+        conn.query(
+            "SELECT recipe_id FROM sf_recipe WHERE recipe_id=$1",
+            &[&recipe_id],
+        ),
+    )
+    .await
+    .map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while checking if recipe `{}` exists!",
+            tracing_prefix,
+            err,
+            recipe_id
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            AssignMealErrors::ErrorCheckingIfRecipeExists,
+        )
+            .into();
+        error
+    })?
+    .is_empty();
+
+    if !recipe_exists {
+        tracing::error!(
+            "{} No recipe found with id `{}`!",
+            tracing_prefix,
+            recipe_id
+        );
+        let error: ResponseError<_> =
+            (StatusCode::NOT_FOUND, AssignMealErrors::RecipeNotFound).into();
+        Err(error)?
+    }
+    tracing::debug!("{} Recipe found!", tracing_prefix);
+
+    tracing::debug!(
+        "{} Assigning recipe to {:?} of {}...",
+        tracing_prefix,
+        slot,
+        date
+    );
+    let plan_id: String = timed_query(
+        "assign_meal",
+        conn.query_one(
+            "INSERT INTO sf_meal_plan (plan_id, user_id, recipe_id, date, slot) VALUES ($1, $2, $3, $4, $5) ON CONFLICT (user_id, date, slot) DO UPDATE SET recipe_id=EXCLUDED.recipe_id RETURNING plan_id",
+            &[
+                &Uuid::new_v4().to_string(),
+                &user_id,
+                &recipe_id,
+                &date,
+                &format!("{:?}", slot),
+            ],
+        ),
+    )
+    .await
+    .map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while assigning recipe `{}`!",
+            tracing_prefix,
+            err,
+            recipe_id
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            AssignMealErrors::ErrorAssigningMeal,
+        )
+            .into();
+        error
+    })?
+    .get("plan_id");
+    let plan_id = plan_id.parse().map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while parsing plan id `{}`!",
+            tracing_prefix,
+            err,
+            plan_id
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            AssignMealErrors::ErrorAssigningMeal,
+        )
+            .into();
+        error
+    })?;
+    tracing::debug!("{} Recipe assigned on plan `{}`!", tracing_prefix, plan_id);
+
+    tracing::debug!("{} DONE", tracing_prefix);
+    Ok(Json(AssignMealResponse { plan_id }))
+}
@@ -0,0 +1,380 @@
+use std::{collections::HashMap, fmt::Display, sync::Arc};
+
+use axum::{response::IntoResponse, Json};
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::Client;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    bulk_ingredients::{invalid_ingredient_field, TransactionPool, MAX_BULK_INGREDIENTS},
+    extract_jwt,
+    households::{get_current_household, GetCurrentHouseholdErrors},
+    is_session_valid,
+    metrics::timed_query,
+    models::{BulkIngredientResult, InventoryEventType},
+    request_id::current_request_id,
+    responses::ResponseError,
+    routes::add_ingredient::IngredientPayload,
+    storage::{get_storage_location, GetStorageLocationErrors},
+    APP_SECRET,
+};
+
+#[derive(Debug, Serialize)]
+pub enum BulkAddIngredientsErrors {
+    InvalidPayload { payload: String },
+    InvalidJWT,
+    NoDBConnectionFound,
+    ErrorCheckingIfSessionIsValid,
+    JWTExpired,
+    ErrorGettingHousehold,
+    NoHouseholdSelected,
+    NotAllowedInHousehold,
+    NoIngredients,
+    TooManyIngredients,
+    InvalidIngredient { index: usize, field: String },
+    LocationNotFound { index: usize },
+    ErrorGettingStorageLocation,
+    ErrorStartingTransaction,
+    ErrorInsertingIngredient { index: usize },
+    ErrorCommittingTransaction,
+}
+
+impl Display for BulkAddIngredientsErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct BulkAddIngredientsPayload {
+    token: String,
+    #[schema(value_type = Vec<NewIngredientPayload>)]
+    ingredients: Vec<IngredientPayload>,
+}
+
+/// Route to add several ingredients at once, like when unpacking the groceries.
+///
+/// Every ingredient is validated before adding any of them and they are added in a single transaction,
+/// so either all of them are added or none. The id of each ingredient is returned with its position.
+#[utoipa::path(
+    post,
+    path = "/v1/ingredients/add/bulk",
+    tag = "Ingredients",
+    request_body = BulkAddIngredientsPayload,
+    responses(
+        (status = 200, description = "The ingredients were added", body = [BulkIngredientResult]),
+        (status = "4XX", description = "The `BulkAddIngredientsErrors` that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The `BulkAddIngredientsErrors` that made the request fail", body = String, content_type = "text/plain"),
+    )
+)]
+pub async fn bulk_add_ingredients(
+    payload: Json<serde_json::Value>,
+    client: Arc<Option<Client>>,
+    transactions: Arc<TransactionPool>,
+) -> Result<impl IntoResponse, ResponseError<BulkAddIngredientsErrors>> {
+    let tracing_prefix = format!("/ingredients/add/bulk - {}:", current_request_id());
+
+    tracing::debug!("{} START", tracing_prefix);
+
+    tracing::debug!("{} Parsing payload...", tracing_prefix);
+    let BulkAddIngredientsPayload { token, ingredients } =
+        match serde_json::from_value(payload.0.clone()) {
+            Ok(p) => p,
+            Err(err) => {
+                tracing::error!(
+                    "{} An error `{:?}` occurred parsing payload `{}`",
+                    tracing_prefix,
+                    err,
+                    payload.0
+                );
+                let error: ResponseError<_> = (
+                    StatusCode::BAD_REQUEST,
+                    BulkAddIngredientsErrors::InvalidPayload {
+                        payload: payload.0.to_string(),
+                    },
+                )
+                    .into();
+                Err(error)?
+            }
+        };
+    tracing::debug!("{} Payload parsed successfully!", tracing_prefix);
+
+    tracing::debug!("{} Extracting JWT...", tracing_prefix);
+    let token_info = match extract_jwt(APP_SECRET, &token) {
+        Ok(t) => t,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred while extracting the JWT `{}`",
+                tracing_prefix,
+                err,
+                token
+            );
+            let error: ResponseError<_> = (
+                StatusCode::BAD_REQUEST,
+                BulkAddIngredientsErrors::InvalidJWT,
+            )
+                .into();
+            Err(error)?
+        }
+    };
+    let user_id = token_info.user_id.clone();
+    tracing::debug!("{} JWT extracted successfully!", tracing_prefix);
+
+    tracing::debug!("{} Checking for DB connection...", tracing_prefix);
+    let conn = client.as_ref().as_ref().ok_or_else(|| {
+        tracing::error!("{} No DB connection found!", tracing_prefix);
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            BulkAddIngredientsErrors::NoDBConnectionFound,
+        )
+            .into();
+        error
+    })?;
+    tracing::debug!("{} DB Connection found!", tracing_prefix);
+
+    tracing::debug!("{} Checking if session is valid...", tracing_prefix);
+    if let Err(err) = is_session_valid(token_info, conn).await {
+        tracing::error!(
+            "{} An error `{:?}` occurred while checking if session is valid!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = match err {
+            crate::IsSessionValidErrors::InternalDBError(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                BulkAddIngredientsErrors::ErrorCheckingIfSessionIsValid,
+            ),
+            crate::IsSessionValidErrors::InvalidSessionData {
+                current_date: _,
+                db_expire_date: _,
+            } => (
+                StatusCode::UNAUTHORIZED,
+                BulkAddIngredientsErrors::JWTExpired,
+            ),
+            _ => (
+                StatusCode::BAD_REQUEST,
+                BulkAddIngredientsErrors::ErrorCheckingIfSessionIsValid,
+            ),
+        }
+        .into();
+
+        Err(error)?
+    }
+    tracing::debug!("{} Session is valid!", tracing_prefix);
+
+    tracing::debug!("{} Getting current household...", tracing_prefix);
+    let household = get_current_household(conn, &user_id).await.map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while getting the household of user `{}`!",
+            tracing_prefix,
+            err,
+            user_id
+        );
+        let error: ResponseError<_> = match err {
+            GetCurrentHouseholdErrors::NoHouseholdSelected => (
+                StatusCode::BAD_REQUEST,
+                BulkAddIngredientsErrors::NoHouseholdSelected,
+            ),
+            _ => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                BulkAddIngredientsErrors::ErrorGettingHousehold,
+            ),
+        }
+        .into();
+        error
+    })?;
+    if !household.role.can_edit_ingredients() {
+        tracing::error!(
+            "{} The user can't change the ingredients of household `{}`!",
+            tracing_prefix,
+            household.household_id
+        );
+        let error: ResponseError<_> = (
+            StatusCode::FORBIDDEN,
+            BulkAddIngredientsErrors::NotAllowedInHousehold,
+        )
+            .into();
+        Err(error)?
+    }
+    tracing::debug!(
+        "{} Working on household `{}`!",
+        tracing_prefix,
+        household.household_id
+    );
+
+    if ingredients.is_empty() {
+        tracing::error!("{} No ingredients were sent!", tracing_prefix);
+        let error: ResponseError<_> = (
+            StatusCode::BAD_REQUEST,
+            BulkAddIngredientsErrors::NoIngredients,
+        )
+            .into();
+        Err(error)?
+    }
+    if ingredients.len() > MAX_BULK_INGREDIENTS {
+        tracing::error!(
+            "{} {} ingredients were sent, the max is {}!",
+            tracing_prefix,
+            ingredients.len(),
+            MAX_BULK_INGREDIENTS
+        );
+        let error: ResponseError<_> = (
+            StatusCode::BAD_REQUEST,
+            BulkAddIngredientsErrors::TooManyIngredients,
+        )
+            .into();
+        Err(error)?
+    }
+
+    tracing::debug!("{} Validating ingredients...", tracing_prefix);
+    for (index, ingredient) in ingredients.iter().enumerate() {
+        if let Some(field) =
+            invalid_ingredient_field(&ingredient.name, ingredient.quantity, &ingredient.unit)
+        {
+            tracing::error!(
+                "{} The field `{}` of ingredient {} is invalid!",
+                tracing_prefix,
+                field,
+                index
+            );
+            let error: ResponseError<_> = (
+                StatusCode::BAD_REQUEST,
+                BulkAddIngredientsErrors::InvalidIngredient {
+                    index,
+                    field: field.to_string(),
+                },
+            )
+                .into();
+            Err(error)?
+        }
+    }
+    let mut locations: HashMap<Option<Uuid>, Uuid> = HashMap::new();
+    for (index, ingredient) in ingredients.iter().enumerate() {
+        if locations.contains_key(&ingredient.location_id) {
+            continue;
+        }
+
+        let location = get_storage_location(
+            conn,
+            &household.household_id,
+            ingredient.location_id.map(|id| id.to_string()).as_deref(),
+        )
+        .await
+        .map_err(|err| {
+            tracing::error!(
+                "{} An error `{:?}` occurred while getting the location of ingredient {}!",
+                tracing_prefix,
+                err,
+                index
+            );
+            let error: ResponseError<_> = match err {
+                GetStorageLocationErrors::LocationNotFound => (
+                    StatusCode::NOT_FOUND,
+                    BulkAddIngredientsErrors::LocationNotFound { index },
+                ),
+                _ => (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    BulkAddIngredientsErrors::ErrorGettingStorageLocation,
+                ),
+            }
+            .into();
+            error
+        })?;
+        locations.insert(ingredient.location_id, location.location_id);
+    }
+    tracing::debug!("{} Ingredients are valid!", tracing_prefix);
+
+    tracing::debug!("{} Starting transaction...", tracing_prefix);
+    let mut tx_client = transactions.get().await.map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while getting a connection for the transaction!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            BulkAddIngredientsErrors::ErrorStartingTransaction,
+        )
+            .into();
+        error
+    })?;
+    let transaction = tx_client.transaction().await.map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while starting the transaction!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            BulkAddIngredientsErrors::ErrorStartingTransaction,
+        )
+            .into();
+        error
+    })?;
+    tracing::debug!("{} Transaction started!", tracing_prefix);
+
+    let mut results = vec![];
+    for (index, ingredient) in ingredients.iter().enumerate() {
+        let ingredient_id = Uuid::new_v4();
+        timed_query(
+            "bulk_add_ingredients",
+            transaction.execute(
+                "WITH added AS (INSERT INTO sf_ingredient (ingredient_id, user_id, name, expire_date, category, quantity, unit, household_id, location_id) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9) RETURNING *), lot AS (INSERT INTO sf_ingredient_lot (ingredient_id, quantity, expire_date) SELECT ingredient_id, quantity, expire_date FROM added) INSERT INTO sf_inventory_event (household_id, user_id, ingredient_id, name, category, quantity, unit, expire_date, event_type, reason) SELECT household_id, $10, ingredient_id, name, category, quantity, unit, expire_date, $11, NULL FROM added",
+                &[
+                    &ingredient_id.to_string(),
+                    &ingredient.user_id.to_string(),
+                    &ingredient.name,
+                    &ingredient.expire_date,
+                    &ingredient.category,
+                    &ingredient.quantity,
+                    &ingredient.unit,
+                    &household.household_id,
+                    &locations[&ingredient.location_id].to_string(),
+                    &user_id,
+                    &format!("{:?}", InventoryEventType::Added),
+                ],
+            ),
+        )
+        .await
+        .map_err(|err| {
+            tracing::error!(
+                "{} An error `{:?}` occurred while inserting ingredient {}! Rolling back...",
+                tracing_prefix,
+                err,
+                index
+            );
+            let error: ResponseError<_> = (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                BulkAddIngredientsErrors::ErrorInsertingIngredient { index },
+            )
+                .into();
+            error
+        })?;
+
+        results.push(BulkIngredientResult {
+            index,
+            ingredient_id,
+        });
+    }
+
+    tracing::debug!("{} Committing transaction...", tracing_prefix);
+    transaction.commit().await.map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while committing the transaction!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            BulkAddIngredientsErrors::ErrorCommittingTransaction,
+        )
+            .into();
+        error
+    })?;
+    tracing::debug!("{} Transaction committed!", tracing_prefix);
+
+    tracing::debug!("{} DONE", tracing_prefix);
+    Ok(Json(results))
+}
@@ -0,0 +1,505 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Display,
+    sync::Arc,
+};
+
+use axum::{response::IntoResponse, Json};
+use chrono::Utc;
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::Client;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    bulk_ingredients::{invalid_ingredient_field, TransactionPool, MAX_BULK_INGREDIENTS},
+    extract_jwt, get_ingredient,
+    households::{get_current_household, GetCurrentHouseholdErrors},
+    ingredient_lots::{edit_lots, get_lots, save_lots, LotsUpdate},
+    is_session_valid,
+    models::{BulkIngredientResult, Ingredient, InventoryEventType},
+    request_id::current_request_id,
+    responses::ResponseError,
+    routes::edit_ingredient::IngredientPayload,
+    storage::{get_storage_location, GetStorageLocationErrors},
+    APP_SECRET,
+};
+
+#[derive(Debug, Serialize)]
+pub enum BulkEditIngredientsErrors {
+    InvalidPayload {
+        payload: String,
+    },
+    InvalidJWT,
+    NoDBConnectionFound,
+    ErrorCheckingIfSessionIsValid,
+    JWTExpired,
+    ErrorGettingHousehold,
+    NoHouseholdSelected,
+    NotAllowedInHousehold,
+    NoIngredients,
+    TooManyIngredients,
+    InvalidIngredient {
+        index: usize,
+        field: String,
+    },
+    RepeatedIngredient {
+        index: usize,
+    },
+    LocationNotFound {
+        index: usize,
+    },
+    ErrorGettingStorageLocation,
+    ErrorStartingTransaction,
+    ErrorGettingLots,
+    ErrorUpdatingIngredient {
+        index: usize,
+    },
+    IngredientNotFound {
+        index: usize,
+    },
+    /// The ingredient changed since the client got it, the current version is returned.
+    VersionConflict {
+        index: usize,
+        current: Box<Ingredient>,
+    },
+    ErrorCommittingTransaction,
+}
+
+impl Display for BulkEditIngredientsErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct BulkEditIngredientsPayload {
+    token: String,
+    #[schema(value_type = Vec<EditedIngredientPayload>)]
+    ingredients: Vec<IngredientPayload>,
+}
+
+/// Route to edit several ingredients at once.
+///
+/// Every ingredient is validated before editing any of them and they are edited in a single transaction,
+/// if one of them isn't found or changed since the client got it none of them are changed.
+#[utoipa::path(
+    post,
+    path = "/v1/ingredients/edit/bulk",
+    tag = "Ingredients",
+    request_body = BulkEditIngredientsPayload,
+    responses(
+        (status = 200, description = "The ingredients were edited", body = [BulkIngredientResult]),
+        (status = 409, description = "An ingredient changed since the client got it, it's returned as it is now", body = Ingredient),
+        (status = "4XX", description = "The `BulkEditIngredientsErrors` that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The `BulkEditIngredientsErrors` that made the request fail", body = String, content_type = "text/plain"),
+    )
+)]
+pub async fn bulk_edit_ingredients(
+    payload: Json<serde_json::Value>,
+    client: Arc<Option<Client>>,
+    transactions: Arc<TransactionPool>,
+) -> Result<impl IntoResponse, ResponseError<BulkEditIngredientsErrors>> {
+    let tracing_prefix = format!("/ingredients/edit/bulk - {}:", current_request_id());
+
+    tracing::debug!("{} START", tracing_prefix);
+
+    tracing::debug!("{} Parsing payload...", tracing_prefix);
+    let BulkEditIngredientsPayload { token, ingredients } =
+        match serde_json::from_value(payload.0.clone()) {
+            Ok(p) => p,
+            Err(err) => {
+                tracing::error!(
+                    "{} An error `{:?}` occurred parsing payload `{}`",
+                    tracing_prefix,
+                    err,
+                    payload.0
+                );
+                let error: ResponseError<_> = (
+                    StatusCode::BAD_REQUEST,
+                    BulkEditIngredientsErrors::InvalidPayload {
+                        payload: payload.0.to_string(),
+                    },
+                )
+                    .into();
+                Err(error)?
+            }
+        };
+    tracing::debug!("{} Payload parsed successfully!", tracing_prefix);
+
+    tracing::debug!("{} Extracting JWT...", tracing_prefix);
+    let token_info = match extract_jwt(APP_SECRET, &token) {
+        Ok(t) => t,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred while extracting the JWT `{}`",
+                tracing_prefix,
+                err,
+                token
+            );
+            let error: ResponseError<_> = (
+                StatusCode::BAD_REQUEST,
+                BulkEditIngredientsErrors::InvalidJWT,
+            )
+                .into();
+            Err(error)?
+        }
+    };
+    let user_id = token_info.user_id.clone();
+    tracing::debug!("{} JWT extracted successfully!", tracing_prefix);
+
+    tracing::debug!("{} Checking for DB connection...", tracing_prefix);
+    let conn = client.as_ref().as_ref().ok_or_else(|| {
+        tracing::error!("{} No DB connection found!", tracing_prefix);
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            BulkEditIngredientsErrors::NoDBConnectionFound,
+        )
+            .into();
+        error
+    })?;
+    tracing::debug!("{} DB Connection found!", tracing_prefix);
+
+    tracing::debug!("{} Checking if session is valid...", tracing_prefix);
+    if let Err(err) = is_session_valid(token_info, conn).await {
+        tracing::error!(
+            "{} An error `{:?}` occurred while checking if session is valid!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = match err {
+            crate::IsSessionValidErrors::InternalDBError(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                BulkEditIngredientsErrors::ErrorCheckingIfSessionIsValid,
+            ),
+            crate::IsSessionValidErrors::InvalidSessionData {
+                current_date: _,
+                db_expire_date: _,
+            } => (
+                StatusCode::UNAUTHORIZED,
+                BulkEditIngredientsErrors::JWTExpired,
+            ),
+            _ => (
+                StatusCode::BAD_REQUEST,
+                BulkEditIngredientsErrors::ErrorCheckingIfSessionIsValid,
+            ),
+        }
+        .into();
+
+        Err(error)?
+    }
+    tracing::debug!("{} Session is valid!", tracing_prefix);
+
+    tracing::debug!("{} Getting current household...", tracing_prefix);
+    let household = get_current_household(conn, &user_id).await.map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while getting the household of user `{}`!",
+            tracing_prefix,
+            err,
+            user_id
+        );
+        let error: ResponseError<_> = match err {
+            GetCurrentHouseholdErrors::NoHouseholdSelected => (
+                StatusCode::BAD_REQUEST,
+                BulkEditIngredientsErrors::NoHouseholdSelected,
+            ),
+            _ => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                BulkEditIngredientsErrors::ErrorGettingHousehold,
+            ),
+        }
+        .into();
+        error
+    })?;
+    if !household.role.can_edit_ingredients() {
+        tracing::error!(
+            "{} The user can't change the ingredients of household `{}`!",
+            tracing_prefix,
+            household.household_id
+        );
+        let error: ResponseError<_> = (
+            StatusCode::FORBIDDEN,
+            BulkEditIngredientsErrors::NotAllowedInHousehold,
+        )
+            .into();
+        Err(error)?
+    }
+    tracing::debug!(
+        "{} Working on household `{}`!",
+        tracing_prefix,
+        household.household_id
+    );
+
+    if ingredients.is_empty() {
+        tracing::error!("{} No ingredients were sent!", tracing_prefix);
+        let error: ResponseError<_> = (
+            StatusCode::BAD_REQUEST,
+            BulkEditIngredientsErrors::NoIngredients,
+        )
+            .into();
+        Err(error)?
+    }
+    if ingredients.len() > MAX_BULK_INGREDIENTS {
+        tracing::error!(
+            "{} {} ingredients were sent, the max is {}!",
+            tracing_prefix,
+            ingredients.len(),
+            MAX_BULK_INGREDIENTS
+        );
+        let error: ResponseError<_> = (
+            StatusCode::BAD_REQUEST,
+            BulkEditIngredientsErrors::TooManyIngredients,
+        )
+            .into();
+        Err(error)?
+    }
+
+    tracing::debug!("{} Validating ingredients...", tracing_prefix);
+    for (index, ingredient) in ingredients.iter().enumerate() {
+        if let Some(field) =
+            invalid_ingredient_field(&ingredient.name, ingredient.quantity, &ingredient.unit)
+        {
+            tracing::error!(
+                "{} The field `{}` of ingredient {} is invalid!",
+                tracing_prefix,
+                field,
+                index
+            );
+            let error: ResponseError<_> = (
+                StatusCode::BAD_REQUEST,
+                BulkEditIngredientsErrors::InvalidIngredient {
+                    index,
+                    field: field.to_string(),
+                },
+            )
+                .into();
+            Err(error)?
+        }
+    }
+    let mut seen_ids = HashSet::new();
+    for (index, ingredient) in ingredients.iter().enumerate() {
+        if !seen_ids.insert(ingredient.ingredient_id) {
+            tracing::error!("{} Ingredient {} is repeated!", tracing_prefix, index);
+            let error: ResponseError<_> = (
+                StatusCode::BAD_REQUEST,
+                BulkEditIngredientsErrors::RepeatedIngredient { index },
+            )
+                .into();
+            Err(error)?
+        }
+    }
+    let mut locations: HashMap<Option<Uuid>, Uuid> = HashMap::new();
+    for (index, ingredient) in ingredients.iter().enumerate() {
+        if ingredient.location_id.is_none() || locations.contains_key(&ingredient.location_id) {
+            continue;
+        }
+
+        let location = get_storage_location(
+            conn,
+            &household.household_id,
+            ingredient.location_id.map(|id| id.to_string()).as_deref(),
+        )
+        .await
+        .map_err(|err| {
+            tracing::error!(
+                "{} An error `{:?}` occurred while getting the location of ingredient {}!",
+                tracing_prefix,
+                err,
+                index
+            );
+            let error: ResponseError<_> = match err {
+                GetStorageLocationErrors::LocationNotFound => (
+                    StatusCode::NOT_FOUND,
+                    BulkEditIngredientsErrors::LocationNotFound { index },
+                ),
+                _ => (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    BulkEditIngredientsErrors::ErrorGettingStorageLocation,
+                ),
+            }
+            .into();
+            error
+        })?;
+        locations.insert(ingredient.location_id, location.location_id);
+    }
+    tracing::debug!("{} Ingredients are valid!", tracing_prefix);
+
+    tracing::debug!("{} Starting transaction...", tracing_prefix);
+    let mut tx_client = transactions.get().await.map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while getting a connection for the transaction!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            BulkEditIngredientsErrors::ErrorStartingTransaction,
+        )
+            .into();
+        error
+    })?;
+    let transaction = tx_client.transaction().await.map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while starting the transaction!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            BulkEditIngredientsErrors::ErrorStartingTransaction,
+        )
+            .into();
+        error
+    })?;
+    tracing::debug!("{} Transaction started!", tracing_prefix);
+
+    tracing::debug!("{} Getting lots...", tracing_prefix);
+    let ingredient_ids: Vec<String> = ingredients
+        .iter()
+        .map(|i| i.ingredient_id.to_string())
+        .collect();
+    let mut lots = get_lots(&transaction, &ingredient_ids)
+        .await
+        .map_err(|err| {
+            tracing::error!(
+                "{} An error `{:?}` occurred while getting the lots! Rolling back...",
+                tracing_prefix,
+                err
+            );
+            let error: ResponseError<_> = (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                BulkEditIngredientsErrors::ErrorGettingLots,
+            )
+                .into();
+            error
+        })?;
+    let edit_date = Utc::now();
+
+    let mut results = vec![];
+    for (index, ingredient) in ingredients.iter().enumerate() {
+        let location_id = ingredient
+            .location_id
+            .map(|id| locations[&Some(id)].to_string());
+        let old_lots = lots.remove(&ingredient.ingredient_id).unwrap_or_default();
+        let new_lots = edit_lots(
+            &old_lots,
+            ingredient.quantity,
+            ingredient.expire_date,
+            edit_date,
+        );
+        let rows_modified = save_lots(
+            &transaction,
+            LotsUpdate {
+                ingredient_id: &ingredient_ids[index],
+                household_id: &household.household_id,
+                user_id: &user_id,
+                old_lots: &old_lots,
+                new_lots: &new_lots,
+                name: Some(&ingredient.name),
+                category: Some(&ingredient.category),
+                unit: Some(&ingredient.unit),
+                location_id,
+                remove: false,
+                event_type: InventoryEventType::Edited,
+                reason: None,
+                event_quantity: None,
+                version: Some(ingredient.version),
+            },
+        )
+        .await
+        .map_err(|err| {
+            tracing::error!(
+                "{} An error `{:?}` occurred while updating ingredient {}! Rolling back...",
+                tracing_prefix,
+                err,
+                index
+            );
+            let error: ResponseError<_> = (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                BulkEditIngredientsErrors::ErrorUpdatingIngredient { index },
+            )
+                .into();
+            error
+        })?;
+        if rows_modified == 0 {
+            let current = get_ingredient(
+                &transaction,
+                &household.household_id,
+                &ingredient_ids[index],
+                &tracing_prefix,
+            )
+            .await
+            .map_err(|err| {
+                tracing::error!(
+                    "{} An error `{:?}` occurred while getting ingredient {}! Rolling back...",
+                    tracing_prefix,
+                    err,
+                    index
+                );
+                let error: ResponseError<_> = (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    BulkEditIngredientsErrors::ErrorUpdatingIngredient { index },
+                )
+                    .into();
+                error
+            })?;
+            let error: ResponseError<_> = match current {
+                Some(current) => {
+                    tracing::error!(
+                        "{} Ingredient {} is on version {} but version {} was edited! Rolling back...",
+                        tracing_prefix,
+                        index,
+                        current.version,
+                        ingredient.version
+                    );
+                    ResponseError::from((
+                        StatusCode::CONFLICT,
+                        BulkEditIngredientsErrors::VersionConflict {
+                            index,
+                            current: Box::new(current.clone()),
+                        },
+                    ))
+                    .with_json(&current)
+                }
+                None => {
+                    tracing::error!(
+                        "{} Ingredient {} wasn't found! Rolling back...",
+                        tracing_prefix,
+                        index
+                    );
+                    (
+                        StatusCode::NOT_FOUND,
+                        BulkEditIngredientsErrors::IngredientNotFound { index },
+                    )
+                        .into()
+                }
+            };
+            Err(error)?
+        }
+
+        results.push(BulkIngredientResult {
+            index,
+            ingredient_id: ingredient.ingredient_id,
+        });
+    }
+
+    tracing::debug!("{} Committing transaction...", tracing_prefix);
+    transaction.commit().await.map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while committing the transaction!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            BulkEditIngredientsErrors::ErrorCommittingTransaction,
+        )
+            .into();
+        error
+    })?;
+    tracing::debug!("{} Transaction committed!", tracing_prefix);
+
+    tracing::debug!("{} DONE", tracing_prefix);
+    Ok(Json(results))
+}
@@ -0,0 +1,409 @@
+use std::{collections::HashSet, fmt::Display, sync::Arc};
+
+use axum::{response::IntoResponse, Json};
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::Client;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    bulk_ingredients::{TransactionPool, MAX_BULK_INGREDIENTS},
+    extract_jwt, get_ingredient,
+    households::{get_current_household, GetCurrentHouseholdErrors},
+    is_session_valid,
+    metrics::timed_query,
+    models::{BulkIngredientResult, Ingredient, InventoryEventType, RemovalReason},
+    request_id::current_request_id,
+    responses::ResponseError,
+    APP_SECRET,
+};
+
+#[derive(Debug, Serialize)]
+pub enum BulkRemoveIngredientsErrors {
+    InvalidPayload {
+        payload: String,
+    },
+    InvalidJWT,
+    NoDBConnectionFound,
+    ErrorCheckingIfSessionIsValid,
+    JWTExpired,
+    ErrorGettingHousehold,
+    NoHouseholdSelected,
+    NotAllowedInHousehold,
+    NoIngredients,
+    TooManyIngredients,
+    RepeatedIngredient {
+        index: usize,
+    },
+    ErrorStartingTransaction,
+    ErrorRemovingIngredient {
+        index: usize,
+    },
+    IngredientNotFound {
+        index: usize,
+    },
+    /// The ingredient changed since the client got it, the current version is returned.
+    VersionConflict {
+        index: usize,
+        current: Box<Ingredient>,
+    },
+    ErrorCommittingTransaction,
+}
+
+impl Display for BulkRemoveIngredientsErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct BulkRemoveIngredientsPayload {
+    token: String,
+    ingredients: Vec<RemovedIngredientPayload>,
+}
+
+/// Represents an ingredient that will be moved to the trash.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RemovedIngredientPayload {
+    #[serde(rename = "IngredientId")]
+    pub ingredient_id: Uuid,
+
+    /// Why the ingredient was removed, it's used for the waste statistics.
+    #[serde(rename = "Reason")]
+    pub reason: Option<RemovalReason>,
+
+    /// The version of the ingredient the client removed.
+    #[serde(rename = "Version")]
+    pub version: i32,
+}
+
+/// Route to move several ingredients to the trash at once.
+///
+/// The ingredients are removed in a single transaction,
+/// if one of them isn't found or changed since the client got it none of them are removed.
+#[utoipa::path(
+    post,
+    path = "/v1/ingredients/remove/bulk",
+    tag = "Ingredients",
+    request_body = BulkRemoveIngredientsPayload,
+    responses(
+        (status = 200, description = "The ingredients were moved to the trash", body = [BulkIngredientResult]),
+        (status = 409, description = "An ingredient changed since the client got it, it's returned as it is now", body = Ingredient),
+        (status = "4XX", description = "The `BulkRemoveIngredientsErrors` that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The `BulkRemoveIngredientsErrors` that made the request fail", body = String, content_type = "text/plain"),
+    )
+)]
+pub async fn bulk_remove_ingredients(
+    payload: Json<serde_json::Value>,
+    client: Arc<Option<Client>>,
+    transactions: Arc<TransactionPool>,
+) -> Result<impl IntoResponse, ResponseError<BulkRemoveIngredientsErrors>> {
+    let tracing_prefix = format!("/ingredients/remove/bulk - {}:", current_request_id());
+
+    tracing::debug!("{} START", tracing_prefix);
+
+    tracing::debug!("{} Parsing payload...", tracing_prefix);
+    let BulkRemoveIngredientsPayload { token, ingredients } =
+        match serde_json::from_value(payload.0.clone()) {
+            Ok(p) => p,
+            Err(err) => {
+                tracing::error!(
+                    "{} An error `{:?}` occurred parsing payload `{}`",
+                    tracing_prefix,
+                    err,
+                    payload.0
+                );
+                let error: ResponseError<_> = (
+                    StatusCode::BAD_REQUEST,
+                    BulkRemoveIngredientsErrors::InvalidPayload {
+                        payload: payload.0.to_string(),
+                    },
+                )
+                    .into();
+                Err(error)?
+            }
+        };
+    tracing::debug!("{} Payload parsed successfully!", tracing_prefix);
+
+    tracing::debug!("{} Extracting JWT...", tracing_prefix);
+    let token_info = match extract_jwt(APP_SECRET, &token) {
+        Ok(t) => t,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred while extracting the JWT `{}`",
+                tracing_prefix,
+                err,
+                token
+            );
+            let error: ResponseError<_> = (
+                StatusCode::BAD_REQUEST,
+                BulkRemoveIngredientsErrors::InvalidJWT,
+            )
+                .into();
+            Err(error)?
+        }
+    };
+    let user_id = token_info.user_id.clone();
+    tracing::debug!("{} JWT extracted successfully!", tracing_prefix);
+
+    tracing::debug!("{} Checking for DB connection...", tracing_prefix);
+    let conn = client.as_ref().as_ref().ok_or_else(|| {
+        tracing::error!("{} No DB connection found!", tracing_prefix);
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            BulkRemoveIngredientsErrors::NoDBConnectionFound,
+        )
+            .into();
+        error
+    })?;
+    tracing::debug!("{} DB Connection found!", tracing_prefix);
+
+    tracing::debug!("{} Checking if session is valid...", tracing_prefix);
+    if let Err(err) = is_session_valid(token_info, conn).await {
+        tracing::error!(
+            "{} An error `{:?}` occurred while checking if session is valid!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = match err {
+            crate::IsSessionValidErrors::InternalDBError(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                BulkRemoveIngredientsErrors::ErrorCheckingIfSessionIsValid,
+            ),
+            crate::IsSessionValidErrors::InvalidSessionData {
+                current_date: _,
+                db_expire_date: _,
+            } => (
+                StatusCode::UNAUTHORIZED,
+                BulkRemoveIngredientsErrors::JWTExpired,
+            ),
+            _ => (
+                StatusCode::BAD_REQUEST,
+                BulkRemoveIngredientsErrors::ErrorCheckingIfSessionIsValid,
+            ),
+        }
+        .into();
+
+        Err(error)?
+    }
+    tracing::debug!("{} Session is valid!", tracing_prefix);
+
+    tracing::debug!("{} Getting current household...", tracing_prefix);
+    let household = get_current_household(conn, &user_id).await.map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while getting the household of user `{}`!",
+            tracing_prefix,
+            err,
+            user_id
+        );
+        let error: ResponseError<_> = match err {
+            GetCurrentHouseholdErrors::NoHouseholdSelected => (
+                StatusCode::BAD_REQUEST,
+                BulkRemoveIngredientsErrors::NoHouseholdSelected,
+            ),
+            _ => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                BulkRemoveIngredientsErrors::ErrorGettingHousehold,
+            ),
+        }
+        .into();
+        error
+    })?;
+    if !household.role.can_edit_ingredients() {
+        tracing::error!(
+            "{} The user can't change the ingredients of household `{}`!",
+            tracing_prefix,
+            household.household_id
+        );
+        let error: ResponseError<_> = (
+            StatusCode::FORBIDDEN,
+            BulkRemoveIngredientsErrors::NotAllowedInHousehold,
+        )
+            .into();
+        Err(error)?
+    }
+    tracing::debug!(
+        "{} Working on household `{}`!",
+        tracing_prefix,
+        household.household_id
+    );
+
+    if ingredients.is_empty() {
+        tracing::error!("{} No ingredients were sent!", tracing_prefix);
+        let error: ResponseError<_> = (
+            StatusCode::BAD_REQUEST,
+            BulkRemoveIngredientsErrors::NoIngredients,
+        )
+            .into();
+        Err(error)?
+    }
+    if ingredients.len() > MAX_BULK_INGREDIENTS {
+        tracing::error!(
+            "{} {} ingredients were sent, the max is {}!",
+            tracing_prefix,
+            ingredients.len(),
+            MAX_BULK_INGREDIENTS
+        );
+        let error: ResponseError<_> = (
+            StatusCode::BAD_REQUEST,
+            BulkRemoveIngredientsErrors::TooManyIngredients,
+        )
+            .into();
+        Err(error)?
+    }
+
+    let mut seen_ids = HashSet::new();
+    for (index, ingredient) in ingredients.iter().enumerate() {
+        if !seen_ids.insert(ingredient.ingredient_id) {
+            tracing::error!("{} Ingredient {} is repeated!", tracing_prefix, index);
+            let error: ResponseError<_> = (
+                StatusCode::BAD_REQUEST,
+                BulkRemoveIngredientsErrors::RepeatedIngredient { index },
+            )
+                .into();
+            Err(error)?
+        }
+    }
+
+    tracing::debug!("{} Starting transaction...", tracing_prefix);
+    let mut tx_client = transactions.get().await.map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while getting a connection for the transaction!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            BulkRemoveIngredientsErrors::ErrorStartingTransaction,
+        )
+            .into();
+        error
+    })?;
+    let transaction = tx_client.transaction().await.map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while starting the transaction!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            BulkRemoveIngredientsErrors::ErrorStartingTransaction,
+        )
+            .into();
+        error
+    })?;
+    tracing::debug!("{} Transaction started!", tracing_prefix);
+
+    let mut results = vec![];
+    for (index, ingredient) in ingredients.iter().enumerate() {
+        let rows_modified = timed_query(
+            "bulk_remove_ingredients",
+            transaction.execute(
+                "WITH removed AS (UPDATE sf_ingredient SET deleted_date=NOW(), updated_at=NOW(), version=version + 1 WHERE ingredient_id=$1 AND household_id=$2 AND deleted_date IS NULL AND version=$6 RETURNING *) INSERT INTO sf_inventory_event (household_id, user_id, ingredient_id, name, category, quantity, unit, expire_date, event_type, reason) SELECT household_id, $3, ingredient_id, name, category, quantity, unit, expire_date, $4, $5 FROM removed",
+                &[
+                    &ingredient.ingredient_id.to_string(),
+                    &household.household_id,
+                    &user_id,
+                    &format!("{:?}", InventoryEventType::Removed),
+                    &ingredient.reason.map(|r| format!("{:?}", r)),
+                    &ingredient.version,
+                ],
+            ),
+        )
+        .await
+        .map_err(|err| {
+            tracing::error!(
+                "{} An error `{:?}` occurred while removing ingredient {}! Rolling back...",
+                tracing_prefix,
+                err,
+                index
+            );
+            let error: ResponseError<_> = (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                BulkRemoveIngredientsErrors::ErrorRemovingIngredient { index },
+            )
+                .into();
+            error
+        })?;
+        if rows_modified == 0 {
+            let current = get_ingredient(
+                &transaction,
+                &household.household_id,
+                &ingredient.ingredient_id.to_string(),
+                &tracing_prefix,
+            )
+            .await
+            .map_err(|err| {
+                tracing::error!(
+                    "{} An error `{:?}` occurred while getting ingredient {}! Rolling back...",
+                    tracing_prefix,
+                    err,
+                    index
+                );
+                let error: ResponseError<_> = (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    BulkRemoveIngredientsErrors::ErrorRemovingIngredient { index },
+                )
+                    .into();
+                error
+            })?;
+            let error: ResponseError<_> = match current {
+                Some(current) => {
+                    tracing::error!(
+                        "{} Ingredient {} is on version {} but version {} was removed! Rolling back...",
+                        tracing_prefix,
+                        index,
+                        current.version,
+                        ingredient.version
+                    );
+                    ResponseError::from((
+                        StatusCode::CONFLICT,
+                        BulkRemoveIngredientsErrors::VersionConflict {
+                            index,
+                            current: Box::new(current.clone()),
+                        },
+                    ))
+                    .with_json(&current)
+                }
+                None => {
+                    tracing::error!(
+                        "{} Ingredient {} wasn't found! Rolling back...",
+                        tracing_prefix,
+                        index
+                    );
+                    (
+                        StatusCode::NOT_FOUND,
+                        BulkRemoveIngredientsErrors::IngredientNotFound { index },
+                    )
+                        .into()
+                }
+            };
+            Err(error)?
+        }
+
+        results.push(BulkIngredientResult {
+            index,
+            ingredient_id: ingredient.ingredient_id,
+        });
+    }
+
+    tracing::debug!("{} Committing transaction...", tracing_prefix);
+    transaction.commit().await.map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while committing the transaction!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            BulkRemoveIngredientsErrors::ErrorCommittingTransaction,
+        )
+            .into();
+        error
+    })?;
+    tracing::debug!("{} Transaction committed!", tracing_prefix);
+
+    tracing::debug!("{} DONE", tracing_prefix);
+    Ok(Json(results))
+}
@@ -0,0 +1,328 @@
+use std::{fmt::Display, sync::Arc};
+
+use axum::{response::IntoResponse, Json};
+use chrono::Utc;
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::Client;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    extract_jwt,
+    households::{get_current_household, GetCurrentHouseholdErrors},
+    is_session_valid,
+    metrics::timed_query,
+    models::{Ingredient, IngredientLot, InventoryEventType, ShoppingItem},
+    parse_db_shopping_item,
+    request_id::current_request_id,
+    responses::ResponseError,
+    shelf_life::estimate_expire_date,
+    storage::get_storage_location,
+    APP_SECRET,
+};
+
+#[derive(Debug, Serialize)]
+pub enum BuyShoppingItemsErrors {
+    InvalidPayload { payload: String },
+    InvalidJWT,
+    NoDBConnectionFound,
+    ErrorCheckingIfSessionIsValid,
+    JWTExpired,
+    CouldntRetrieveItemsFromDB,
+    InvalidItemFormatFromDB,
+    ErrorMovingItemToFridge,
+    ErrorGettingHousehold,
+    NoHouseholdSelected,
+    NotAllowedInHousehold,
+    ErrorGettingStorageLocation,
+}
+
+impl Display for BuyShoppingItemsErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct BuyShoppingItemsPayload {
+    token: String,
+}
+
+/// Route to move all checked items of the shopping list into the fridge.
+///
+/// The expire date of each new ingredient is estimated from its category.
+/// Returns the ingredients that were created.
+#[utoipa::path(
+    post,
+    path = "/v1/shopping-list/bought",
+    tag = "Shopping list",
+    request_body = BuyShoppingItemsPayload,
+    responses(
+        (status = 200, description = "The ingredients created from the checked items", body = [Ingredient]),
+        (status = "4XX", description = "The `BuyShoppingItemsErrors` that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The `BuyShoppingItemsErrors` that made the request fail", body = String, content_type = "text/plain"),
+    )
+)]
+pub async fn buy_shopping_items(
+    payload: Json<serde_json::Value>,
+    client: Arc<Option<Client>>,
+) -> Result<impl IntoResponse, ResponseError<BuyShoppingItemsErrors>> {
+    let tracing_prefix = format!("/shopping-list/bought - {}:", current_request_id());
+
+    tracing::debug!("{} START", tracing_prefix);
+
+    tracing::debug!("{} Parsing payload...", tracing_prefix);
+    let BuyShoppingItemsPayload { token } = match serde_json::from_value(payload.0.clone()) {
+        Ok(p) => p,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred parsing payload `{}`",
+                tracing_prefix,
+                err,
+                payload.0
+            );
+            let error: ResponseError<_> = (
+                StatusCode::BAD_REQUEST,
+                BuyShoppingItemsErrors::InvalidPayload {
+                    payload: payload.0.to_string(),
+                },
+            )
+                .into();
+            Err(error)?
+        }
+    };
+    tracing::debug!("{} Payload parsed successfully!", tracing_prefix);
+
+    tracing::debug!("{} Extracting JWT...", tracing_prefix);
+    let token_info = match extract_jwt(APP_SECRET, &token) {
+        Ok(t) => t,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred while extracting the JWT `{}`",
+                tracing_prefix,
+                err,
+                token
+            );
+            let error: ResponseError<_> =
+                (StatusCode::BAD_REQUEST, BuyShoppingItemsErrors::InvalidJWT).into();
+            Err(error)?
+        }
+    };
+    let user_id = token_info.user_id.clone();
+    tracing::debug!("{} JWT extracted successfully!", tracing_prefix);
+
+    let conn = client.as_ref().as_ref().ok_or_else(|| {
+        tracing::error!("{} DB connection not found!", tracing_prefix);
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            BuyShoppingItemsErrors::NoDBConnectionFound,
+        )
+            .into();
+        error
+    })?;
+    tracing::debug!("{} DB Connection found!", tracing_prefix);
+
+    tracing::debug!("{} Checking if session is valid...", tracing_prefix);
+    if let Err(err) = is_session_valid(token_info, conn).await {
+        tracing::error!(
+            "{} An error `{:?}` occurred while checking if session is valid!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = match err {
+            crate::IsSessionValidErrors::InternalDBError(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                BuyShoppingItemsErrors::ErrorCheckingIfSessionIsValid,
+            ),
+            crate::IsSessionValidErrors::InvalidSessionData {
+                current_date: _,
+                db_expire_date: _,
+            } => (StatusCode::UNAUTHORIZED, BuyShoppingItemsErrors::JWTExpired),
+            _ => (
+                StatusCode::BAD_REQUEST,
+                BuyShoppingItemsErrors::ErrorCheckingIfSessionIsValid,
+            ),
+        }
+        .into();
+
+        Err(error)?
+    }
+    tracing::debug!("{} Session is valid!", tracing_prefix);
+
+    tracing::debug!("{} Getting current household...", tracing_prefix);
+    let household = get_current_household(conn, &user_id).await.map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while getting the household of user `{}`!",
+            tracing_prefix,
+            err,
+            user_id
+        );
+        let error: ResponseError<_> = match err {
+            GetCurrentHouseholdErrors::NoHouseholdSelected => (
+                StatusCode::BAD_REQUEST,
+                BuyShoppingItemsErrors::NoHouseholdSelected,
+            ),
+            _ => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                BuyShoppingItemsErrors::ErrorGettingHousehold,
+            ),
+        }
+        .into();
+        error
+    })?;
+    if !household.role.can_edit_ingredients() {
+        tracing::error!(
+            "{} The user can't change the ingredients of household `{}`!",
+            tracing_prefix,
+            household.household_id
+        );
+        let error: ResponseError<_> = (
+            StatusCode::FORBIDDEN,
+            BuyShoppingItemsErrors::NotAllowedInHousehold,
+        )
+            .into();
+        Err(error)?
+    }
+    tracing::debug!(
+        "{} Working on household `{}`!",
+        tracing_prefix,
+        household.household_id
+    );
+
+    tracing::debug!("{} Getting the default fridge...", tracing_prefix);
+    let fridge = get_storage_location(conn, &household.household_id, None)
+        .await
+        .map_err(|err| {
+            tracing::error!(
+                "{} An error `{:?}` occurred while getting the default fridge of household `{}`!",
+                tracing_prefix,
+                err,
+                household.household_id
+            );
+            let error: ResponseError<_> = (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                BuyShoppingItemsErrors::ErrorGettingStorageLocation,
+            )
+                .into();
+            error
+        })?;
+    tracing::debug!("{} Got fridge `{}`!", tracing_prefix, fridge.location_id);
+
+    tracing::debug!("{} Getting checked items from DB...", tracing_prefix);
+    let items = timed_query(
+        "buy_shopping_items",
+        conn.query(
+            "SELECT * FROM sf_shopping_item WHERE user_id=$1 AND checked=TRUE",
+            &[&user_id],
+        ),
+    )
+    .await
+    .map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` while trying to get the checked items for user `{}`",
+            tracing_prefix,
+            err,
+            user_id
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            BuyShoppingItemsErrors::CouldntRetrieveItemsFromDB,
+        )
+            .into();
+        error
+    })?
+    .iter()
+    .map(|row| {
+        parse_db_shopping_item(row, &tracing_prefix).ok_or_else(|| {
+            let error: ResponseError<_> = (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                BuyShoppingItemsErrors::InvalidItemFormatFromDB,
+            )
+                .into();
+            error
+        })
+    })
+    .collect::<Result<Vec<ShoppingItem>, ResponseError<BuyShoppingItemsErrors>>>()?;
+    tracing::debug!("{} {} checked items found!", tracing_prefix, items.len());
+
+    let bought_date = Utc::now();
+    let mut ingredients = vec![];
+    for item in items {
+        let expire_date = estimate_expire_date(&item.category, bought_date);
+        let ingredient = Ingredient {
+            ingredient_id: Uuid::new_v4(),
+            user_id: item.user_id,
+            expire_date,
+            name: item.name,
+            category: item.category,
+            quantity: item.quantity,
+            unit: item.unit,
+            location_id: fridge.location_id,
+            updated_at: bought_date,
+            version: 1,
+            lots: vec![IngredientLot {
+                lot_id: Uuid::new_v4(),
+                quantity: item.quantity,
+                expire_date,
+                added_date: Some(bought_date),
+            }],
+        };
+
+        tracing::debug!(
+            "{} Moving item `{}` into the fridge...",
+            tracing_prefix,
+            item.item_id
+        );
+        // Deleting and inserting in the same statement makes the move atomic.
+        let rows_modified = timed_query(
+            "buy_shopping_items",
+            conn.execute(
+                "WITH bought AS (DELETE FROM sf_shopping_item WHERE item_id=$1 AND checked=TRUE RETURNING user_id), added AS (INSERT INTO sf_ingredient (ingredient_id, user_id, name, expire_date, category, quantity, unit, household_id, location_id, updated_at) SELECT $2, user_id, $3, $4, $5, $6, $7, $8, $9, $13 FROM bought RETURNING *), lot AS (INSERT INTO sf_ingredient_lot (lot_id, ingredient_id, quantity, expire_date, added_date) SELECT $12, ingredient_id, quantity, expire_date, $13 FROM added) INSERT INTO sf_inventory_event (household_id, user_id, ingredient_id, name, category, quantity, unit, expire_date, event_type, reason) SELECT household_id, $10, ingredient_id, name, category, quantity, unit, expire_date, $11, NULL FROM added",
+                &[
+                    &item.item_id.to_string(),
+                    &ingredient.ingredient_id.to_string(),
+                    &ingredient.name,
+                    &ingredient.expire_date,
+                    &ingredient.category,
+                    &ingredient.quantity,
+                    &ingredient.unit,
+                    &household.household_id,
+                    &ingredient.location_id.to_string(),
+                    &user_id,
+                    &format!("{:?}", InventoryEventType::Added),
+                    &ingredient.lots[0].lot_id.to_string(),
+                    &bought_date,
+                ],
+            ),
+        )
+        .await
+        .map_err(|err| {
+            tracing::error!(
+                "{} An error `{:?}` occurred while moving item `{}` into the fridge!",
+                tracing_prefix,
+                err,
+                item.item_id
+            );
+            let error: ResponseError<_> = (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                BuyShoppingItemsErrors::ErrorMovingItemToFridge,
+            )
+                .into();
+            error
+        })?;
+
+        // Another request may have moved or unchecked it in the meantime.
+        if rows_modified > 0 {
+            ingredients.push(ingredient);
+        }
+    }
+    tracing::debug!(
+        "{} {} items moved into the fridge!",
+        tracing_prefix,
+        ingredients.len()
+    );
+
+    tracing::debug!("{} DONE", tracing_prefix);
+    Ok(Json(ingredients))
+}
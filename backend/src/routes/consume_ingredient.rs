@@ -0,0 +1,429 @@
+use std::{fmt::Display, sync::Arc};
+
+use axum::{response::IntoResponse, Json};
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::Client;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    extract_jwt, get_ingredient,
+    households::{get_current_household, GetCurrentHouseholdErrors},
+    ingredient_lots::{consume_fifo, get_lots, lot_totals, save_lots, LotsUpdate},
+    is_session_valid,
+    metrics::timed_query,
+    models::{Ingredient, InventoryEventType, RemovalReason},
+    request_id::current_request_id,
+    responses::ResponseError,
+    units::convert_quantity,
+    APP_SECRET,
+};
+
+#[derive(Debug, Serialize)]
+pub enum ConsumeIngredientErrors {
+    InvalidPayload {
+        payload: String,
+    },
+    InvalidJWT,
+    NoDBConnectionFound,
+    ErrorCheckingIfSessionIsValid,
+    JWTExpired,
+    ErrorGettingHousehold,
+    NoHouseholdSelected,
+    NotAllowedInHousehold,
+    IngredientNotFound,
+    ErrorGettingIngredient,
+    IncompatibleUnits,
+    NotEnoughIngredient {
+        available: f32,
+    },
+    ErrorConsumingIngredient,
+    /// The ingredient changed since the client got it, the current version is returned.
+    VersionConflict {
+        current: Box<Ingredient>,
+    },
+}
+
+impl Display for ConsumeIngredientErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ConsumeIngredientPayload {
+    token: String,
+    ingredient_id: Uuid,
+    quantity: f32,
+    /// The unit of the quantity, the one of the ingredient when missing.
+    unit: Option<String>,
+    /// The version of the ingredient the client consumed.
+    version: i32,
+}
+
+/// The quantity of the ingredient left after consuming it, in the unit of the ingredient.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ConsumeIngredientResponse {
+    #[serde(rename = "Quantity")]
+    quantity: f32,
+
+    #[serde(rename = "Unit")]
+    unit: String,
+
+    /// The ingredient was used up and moved to the trash.
+    #[serde(rename = "Removed")]
+    removed: bool,
+}
+
+/// Route to use some quantity of an ingredient, taken from its oldest lots first.
+///
+/// When all of it is used the ingredient is moved to the trash as eaten.
+/// The consumption is rejected with a conflict if the ingredient changed since the client got it.
+#[utoipa::path(
+    post,
+    path = "/v1/ingredients/consume",
+    tag = "Ingredients",
+    request_body = ConsumeIngredientPayload,
+    responses(
+        (status = 200, description = "What is left of the ingredient", body = ConsumeIngredientResponse),
+        (status = 409, description = "The ingredient changed since the client got it, it's returned as it is now", body = Ingredient),
+        (status = "4XX", description = "The `ConsumeIngredientErrors` that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The `ConsumeIngredientErrors` that made the request fail", body = String, content_type = "text/plain"),
+    )
+)]
+pub async fn consume_ingredient(
+    payload: Json<serde_json::Value>,
+    client: Arc<Option<Client>>,
+) -> Result<impl IntoResponse, ResponseError<ConsumeIngredientErrors>> {
+    let tracing_prefix = format!("/ingredients/consume - {}:", current_request_id());
+
+    tracing::debug!("{} START", tracing_prefix);
+
+    tracing::debug!("{} Parsing payload...", tracing_prefix);
+    let ConsumeIngredientPayload {
+        token,
+        ingredient_id,
+        quantity,
+        unit,
+        version,
+    } = match serde_json::from_value(payload.0.clone()) {
+        Ok(p) => p,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred parsing payload `{}`",
+                tracing_prefix,
+                err,
+                payload.0
+            );
+            let error: ResponseError<_> = (
+                StatusCode::BAD_REQUEST,
+                ConsumeIngredientErrors::InvalidPayload {
+                    payload: payload.0.to_string(),
+                },
+            )
+                .into();
+            Err(error)?
+        }
+    };
+    tracing::debug!("{} Payload parsed successfully!", tracing_prefix);
+
+    tracing::debug!("{} Extracting JWT...", tracing_prefix);
+    let token_info = match extract_jwt(APP_SECRET, &token) {
+        Ok(t) => t,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred while extracting the JWT `{}`",
+                tracing_prefix,
+                err,
+                token
+            );
+            let error: ResponseError<_> =
+                (StatusCode::BAD_REQUEST, ConsumeIngredientErrors::InvalidJWT).into();
+            Err(error)?
+        }
+    };
+    let user_id = token_info.user_id.clone();
+    tracing::debug!("{} JWT extracted successfully!", tracing_prefix);
+
+    tracing::debug!("{} Checking for DB connection...", tracing_prefix);
+    let conn = client.as_ref().as_ref().ok_or_else(|| {
+        tracing::error!("{} No DB connection found!", tracing_prefix);
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ConsumeIngredientErrors::NoDBConnectionFound,
+        )
+            .into();
+        error
+    })?;
+    tracing::debug!("{} DB Connection found!", tracing_prefix);
+
+    tracing::debug!("{} Checking if session is valid...", tracing_prefix);
+    if let Err(err) = is_session_valid(token_info, conn).await {
+        tracing::error!(
+            "{} An error `{:?}` occurred while checking if session is valid!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = match err {
+            crate::IsSessionValidErrors::InternalDBError(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ConsumeIngredientErrors::ErrorCheckingIfSessionIsValid,
+            ),
+            crate::IsSessionValidErrors::InvalidSessionData {
+                current_date: _,
+                db_expire_date: _,
+            } => (
+                StatusCode::UNAUTHORIZED,
+                ConsumeIngredientErrors::JWTExpired,
+            ),
+            _ => (
+                StatusCode::BAD_REQUEST,
+                ConsumeIngredientErrors::ErrorCheckingIfSessionIsValid,
+            ),
+        }
+        .into();
+
+        Err(error)?
+    }
+    tracing::debug!("{} Session is valid!", tracing_prefix);
+
+    tracing::debug!("{} Getting current household...", tracing_prefix);
+    let household = get_current_household(conn, &user_id).await.map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while getting the household of user `{}`!",
+            tracing_prefix,
+            err,
+            user_id
+        );
+        let error: ResponseError<_> = match err {
+            GetCurrentHouseholdErrors::NoHouseholdSelected => (
+                StatusCode::BAD_REQUEST,
+                ConsumeIngredientErrors::NoHouseholdSelected,
+            ),
+            _ => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ConsumeIngredientErrors::ErrorGettingHousehold,
+            ),
+        }
+        .into();
+        error
+    })?;
+    if !household.role.can_edit_ingredients() {
+        tracing::error!(
+            "{} The user can't change the ingredients of household `{}`!",
+            tracing_prefix,
+            household.household_id
+        );
+        let error: ResponseError<_> = (
+            StatusCode::FORBIDDEN,
+            ConsumeIngredientErrors::NotAllowedInHousehold,
+        )
+            .into();
+        Err(error)?
+    }
+    tracing::debug!(
+        "{} Working on household `{}`!",
+        tracing_prefix,
+        household.household_id
+    );
+
+    tracing::debug!("{} Getting ingredient from DB...", tracing_prefix);
+    let ingredient_id_str = ingredient_id.to_string();
+    let ingredient = timed_query(
+        "consume_ingredient",
+        conn.query_opt(
+            "SELECT unit FROM sf_ingredient WHERE ingredient_id=$1 AND household_id=$2 AND deleted_date IS NULL",
+            &[&ingredient_id_str, &household.household_id],
+        ),
+    )
+    .await
+    .map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while getting the ingredient!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ConsumeIngredientErrors::ErrorGettingIngredient,
+        )
+            .into();
+        error
+    })?
+    .ok_or_else(|| {
+        tracing::error!(
+            "{} The ingredient `{}` wasn't found!",
+            tracing_prefix,
+            ingredient_id
+        );
+        let error: ResponseError<_> = (
+            StatusCode::NOT_FOUND,
+            ConsumeIngredientErrors::IngredientNotFound,
+        )
+            .into();
+        error
+    })?;
+    let ingredient_unit: String = ingredient.get("unit");
+
+    let quantity = match unit {
+        Some(unit) => convert_quantity(quantity, &unit, &ingredient_unit).ok_or_else(|| {
+            tracing::error!(
+                "{} Can't consume `{}` of an ingredient measured in `{}`!",
+                tracing_prefix,
+                unit,
+                ingredient_unit
+            );
+            let error: ResponseError<_> = (
+                StatusCode::BAD_REQUEST,
+                ConsumeIngredientErrors::IncompatibleUnits,
+            )
+                .into();
+            error
+        })?,
+        None => quantity,
+    };
+
+    tracing::debug!("{} Getting lots...", tracing_prefix);
+    let old_lots = get_lots(conn, std::slice::from_ref(&ingredient_id_str))
+        .await
+        .map_err(|err| {
+            tracing::error!(
+                "{} An error `{:?}` occurred while getting the lots!",
+                tracing_prefix,
+                err
+            );
+            let error: ResponseError<_> = (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ConsumeIngredientErrors::ErrorGettingIngredient,
+            )
+                .into();
+            error
+        })?
+        .remove(&ingredient_id)
+        .unwrap_or_default();
+    let (new_lots, missing) = consume_fifo(&old_lots, quantity);
+    if missing > 0.0 {
+        let (available, _) = lot_totals(&old_lots);
+        tracing::error!(
+            "{} Can't consume {} when only {} is available!",
+            tracing_prefix,
+            quantity,
+            available
+        );
+        let error: ResponseError<_> = (
+            StatusCode::BAD_REQUEST,
+            ConsumeIngredientErrors::NotEnoughIngredient { available },
+        )
+            .into();
+        Err(error)?
+    }
+
+    // A used up ingredient keeps its lots in the trash, so it's logged like a removal and can be restored.
+    let removed = new_lots.is_empty();
+    let (saved_lots, event_type, reason) = if removed {
+        (
+            &old_lots,
+            InventoryEventType::Removed,
+            Some(RemovalReason::Eaten),
+        )
+    } else {
+        (&new_lots, InventoryEventType::Consumed, None)
+    };
+
+    tracing::debug!("{} Consuming ingredient...", tracing_prefix);
+    let rows_modified = save_lots(
+        conn,
+        LotsUpdate {
+            ingredient_id: &ingredient_id_str,
+            household_id: &household.household_id,
+            user_id: &user_id,
+            old_lots: &old_lots,
+            new_lots: saved_lots,
+            name: None,
+            category: None,
+            unit: None,
+            location_id: None,
+            remove: removed,
+            event_type,
+            reason,
+            event_quantity: (!removed).then_some(quantity),
+            version: Some(version),
+        },
+    )
+    .await
+    .map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while consuming the ingredient!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ConsumeIngredientErrors::ErrorConsumingIngredient,
+        )
+            .into();
+        error
+    })?;
+    if rows_modified == 0 {
+        let current = get_ingredient(
+            conn,
+            &household.household_id,
+            &ingredient_id_str,
+            &tracing_prefix,
+        )
+        .await
+        .map_err(|err| {
+            tracing::error!(
+                "{} An error `{:?}` occurred while getting the ingredient `{}`!",
+                tracing_prefix,
+                err,
+                ingredient_id
+            );
+            let error: ResponseError<_> = (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ConsumeIngredientErrors::ErrorConsumingIngredient,
+            )
+                .into();
+            error
+        })?;
+        let error: ResponseError<_> = match current {
+            Some(current) => {
+                tracing::error!(
+                    "{} The ingredient is on version {} but version {} was consumed!",
+                    tracing_prefix,
+                    current.version,
+                    version
+                );
+                ResponseError::from((
+                    StatusCode::CONFLICT,
+                    ConsumeIngredientErrors::VersionConflict {
+                        current: Box::new(current.clone()),
+                    },
+                ))
+                .with_json(&current)
+            }
+            None => {
+                tracing::error!(
+                    "{} The ingredient `{}` wasn't found!",
+                    tracing_prefix,
+                    ingredient_id
+                );
+                (
+                    StatusCode::NOT_FOUND,
+                    ConsumeIngredientErrors::IngredientNotFound,
+                )
+                    .into()
+            }
+        };
+        Err(error)?
+    }
+    tracing::debug!("{} Ingredient consumed!", tracing_prefix);
+
+    tracing::debug!("{} DONE", tracing_prefix);
+    Ok(Json(ConsumeIngredientResponse {
+        quantity: lot_totals(&new_lots).0,
+        unit: ingredient_unit,
+        removed,
+    }))
+}
@@ -0,0 +1,174 @@
+use std::{fmt::Display, sync::Arc};
+
+use axum::{response::IntoResponse, Json};
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::Client;
+
+use utoipa::ToSchema;
+
+use crate::{
+    extract_jwt, households::create_household, is_session_valid, request_id::current_request_id,
+    responses::ResponseError, APP_SECRET,
+};
+
+#[derive(Debug, Serialize)]
+pub enum CreateHouseholdErrors {
+    InvalidPayload { payload: String },
+    InvalidJWT,
+    NoDBConnectionFound,
+    ErrorCheckingIfSessionIsValid,
+    JWTExpired,
+    EmptyName,
+    ErrorCreatingHousehold,
+}
+
+impl Display for CreateHouseholdErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct CreateHouseholdPayload {
+    token: String,
+    name: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CreateHouseholdResponse {
+    #[serde(rename = "HouseholdId")]
+    pub household_id: String,
+}
+
+/// Route to create a household owned by the user.
+///
+/// The new household becomes the one the ingredient routes work on.
+#[utoipa::path(
+    post,
+    path = "/v1/households/create",
+    tag = "Households",
+    request_body = CreateHouseholdPayload,
+    responses(
+        (status = 200, description = "The household was created", body = CreateHouseholdResponse),
+        (status = "4XX", description = "The `CreateHouseholdErrors` that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The `CreateHouseholdErrors` that made the request fail", body = String, content_type = "text/plain"),
+    )
+)]
+pub async fn create_household_route(
+    payload: Json<serde_json::Value>,
+    client: Arc<Option<Client>>,
+) -> Result<impl IntoResponse, ResponseError<CreateHouseholdErrors>> {
+    let tracing_prefix = format!("/households/create - {}:", current_request_id());
+
+    tracing::debug!("{} START", tracing_prefix);
+
+    tracing::debug!("{} Parsing payload...", tracing_prefix);
+    let CreateHouseholdPayload { token, name } = match serde_json::from_value(payload.0.clone()) {
+        Ok(p) => p,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred parsing payload `{}`",
+                tracing_prefix,
+                err,
+                payload.0
+            );
+            let error: ResponseError<_> = (
+                StatusCode::BAD_REQUEST,
+                CreateHouseholdErrors::InvalidPayload {
+                    payload: payload.0.to_string(),
+                },
+            )
+                .into();
+            Err(error)?
+        }
+    };
+    tracing::debug!("{} Payload parsed successfully!", tracing_prefix);
+
+    tracing::debug!("{} Extracting JWT...", tracing_prefix);
+    let token_info = match extract_jwt(APP_SECRET, &token) {
+        Ok(t) => t,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred while extracting the JWT `{}`",
+                tracing_prefix,
+                err,
+                token
+            );
+            let error: ResponseError<_> =
+                (StatusCode::BAD_REQUEST, CreateHouseholdErrors::InvalidJWT).into();
+            Err(error)?
+        }
+    };
+    let user_id = token_info.user_id.clone();
+    tracing::debug!("{} JWT extracted successfully!", tracing_prefix);
+
+    tracing::debug!("{} Checking for DB connection...", tracing_prefix);
+    let conn = client.as_ref().as_ref().ok_or_else(|| {
+        tracing::error!("{} No DB connection found!", tracing_prefix);
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            CreateHouseholdErrors::NoDBConnectionFound,
+        )
+            .into();
+        error
+    })?;
+    tracing::debug!("{} DB Connection found!", tracing_prefix);
+
+    tracing::debug!("{} Checking if session is valid...", tracing_prefix);
+    if let Err(err) = is_session_valid(token_info, conn).await {
+        tracing::error!(
+            "{} An error `{:?}` occurred while checking if session is valid!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = match err {
+            crate::IsSessionValidErrors::InternalDBError(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                CreateHouseholdErrors::ErrorCheckingIfSessionIsValid,
+            ),
+            crate::IsSessionValidErrors::InvalidSessionData {
+                current_date: _,
+                db_expire_date: _,
+            } => (StatusCode::UNAUTHORIZED, CreateHouseholdErrors::JWTExpired),
+            _ => (
+                StatusCode::BAD_REQUEST,
+                CreateHouseholdErrors::ErrorCheckingIfSessionIsValid,
+            ),
+        }
+        .into();
+
+        Err(error)?
+    }
+    tracing::debug!("{} Session is valid!", tracing_prefix);
+
+    let name = name.trim();
+    if name.is_empty() {
+        tracing::error!("{} The household has no name!", tracing_prefix);
+        let error: ResponseError<_> =
+            (StatusCode::BAD_REQUEST, CreateHouseholdErrors::EmptyName).into();
+        Err(error)?
+    }
+
+    tracing::debug!("{} Creating household `{}`...", tracing_prefix, name);
+    let household_id = create_household(conn, &user_id, name)
+        .await
+        .map_err(|err| {
+            tracing::error!(
+                "{} An error `{:?}` occurred while creating household `{}`!",
+                tracing_prefix,
+                err,
+                name
+            );
+            let error: ResponseError<_> = (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                CreateHouseholdErrors::ErrorCreatingHousehold,
+            )
+                .into();
+            error
+        })?;
+    tracing::debug!("{} Household `{}` created!", tracing_prefix, household_id);
+
+    tracing::debug!("{} DONE", tracing_prefix);
+    Ok(Json(CreateHouseholdResponse { household_id }))
+}
@@ -0,0 +1,245 @@
+use std::{fmt::Display, sync::Arc};
+
+use axum::{response::IntoResponse, Json};
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::Client;
+
+use utoipa::ToSchema;
+
+use crate::{
+    custom_recipes::{build_custom_recipe, CustomRecipeIngredient},
+    extract_jwt, is_session_valid,
+    metrics::timed_query,
+    recipe_cache::cache_recipes,
+    request_id::current_request_id,
+    responses::ResponseError,
+    APP_SECRET,
+};
+
+#[derive(Debug, Serialize)]
+pub enum EditCustomRecipeErrors {
+    InvalidPayload { payload: String },
+    InvalidJWT,
+    NoDBConnectionFound,
+    ErrorCheckingIfSessionIsValid,
+    JWTExpired,
+    EmptyTitle,
+    RecipeNotFound,
+    ErrorEditingCustomRecipe,
+}
+
+impl Display for EditCustomRecipeErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// All the fields of the recipe are replaced.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct EditCustomRecipePayload {
+    token: String,
+    recipe_id: String,
+    title: String,
+    image_url: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    ingredients: Vec<CustomRecipeIngredient>,
+    #[serde(default)]
+    steps: Vec<String>,
+}
+
+/// Route to edit a recipe written by the user.
+#[utoipa::path(
+    post,
+    path = "/v1/recipes/custom/edit",
+    tag = "Recipes",
+    request_body = EditCustomRecipePayload,
+    responses(
+        (status = 200, description = "The recipe was edited"),
+        (status = "4XX", description = "The `EditCustomRecipeErrors` that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The `EditCustomRecipeErrors` that made the request fail", body = String, content_type = "text/plain"),
+    )
+)]
+pub async fn edit_custom_recipe(
+    payload: Json<serde_json::Value>,
+    client: Arc<Option<Client>>,
+) -> Result<impl IntoResponse, ResponseError<EditCustomRecipeErrors>> {
+    let tracing_prefix = format!("/recipes/custom/edit - {}:", current_request_id());
+
+    tracing::debug!("{} START", tracing_prefix);
+
+    tracing::debug!("{} Parsing payload...", tracing_prefix);
+    let EditCustomRecipePayload {
+        token,
+        recipe_id,
+        title,
+        image_url,
+        tags,
+        ingredients,
+        steps,
+    } = match serde_json::from_value(payload.0.clone()) {
+        Ok(p) => p,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred parsing payload `{}`",
+                tracing_prefix,
+                err,
+                payload.0
+            );
+            let error: ResponseError<_> = (
+                StatusCode::BAD_REQUEST,
+                EditCustomRecipeErrors::InvalidPayload {
+                    payload: payload.0.to_string(),
+                },
+            )
+                .into();
+            Err(error)?
+        }
+    };
+    tracing::debug!("{} Payload parsed successfully!", tracing_prefix);
+
+    tracing::debug!("{} Extracting JWT...", tracing_prefix);
+    let token_info = match extract_jwt(APP_SECRET, &token) {
+        Ok(t) => t,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred while extracting the JWT `{}`",
+                tracing_prefix,
+                err,
+                token
+            );
+            let error: ResponseError<_> =
+                (StatusCode::BAD_REQUEST, EditCustomRecipeErrors::InvalidJWT).into();
+            Err(error)?
+        }
+    };
+    let user_id = token_info.user_id.clone();
+    tracing::debug!("{} JWT extracted successfully!", tracing_prefix);
+
+    tracing::debug!("{} Checking for DB connection...", tracing_prefix);
+    let conn = client.as_ref().as_ref().ok_or_else(|| {
+        tracing::error!("{} No DB connection found!", tracing_prefix);
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            EditCustomRecipeErrors::NoDBConnectionFound,
+        )
+            .into();
+        error
+    })?;
+    tracing::debug!("{} DB Connection found!", tracing_prefix);
+
+    tracing::debug!("{} Checking if session is valid...", tracing_prefix);
+    if let Err(err) = is_session_valid(token_info, conn).await {
+        tracing::error!(
+            "{} An error `{:?}` occurred while checking if session is valid!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = match err {
+            crate::IsSessionValidErrors::InternalDBError(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                EditCustomRecipeErrors::ErrorCheckingIfSessionIsValid,
+            ),
+            crate::IsSessionValidErrors::InvalidSessionData {
+                current_date: _,
+                db_expire_date: _,
+            } => (StatusCode::UNAUTHORIZED, EditCustomRecipeErrors::JWTExpired),
+            _ => (
+                StatusCode::BAD_REQUEST,
+                EditCustomRecipeErrors::ErrorCheckingIfSessionIsValid,
+            ),
+        }
+        .into();
+
+        Err(error)?
+    }
+    tracing::debug!("{} Session is valid!", tracing_prefix);
+
+    if title.trim().is_empty() {
+        tracing::error!("{} The recipe has no title!", tracing_prefix);
+        let error: ResponseError<_> =
+            (StatusCode::BAD_REQUEST, EditCustomRecipeErrors::EmptyTitle).into();
+        Err(error)?
+    }
+
+    let recipe = build_custom_recipe(
+        recipe_id.clone(),
+        title,
+        image_url.clone(),
+        tags,
+        ingredients,
+        steps,
+    );
+
+    tracing::debug!("{} Editing recipe `{}`...", tracing_prefix, recipe_id);
+    let updated = timed_query(
+        "edit_custom_recipe",
+        conn.execute(
+            "UPDATE sf_custom_recipe SET title=$3, image_url=$4, tags=$5, ingredients=$6, steps=$7, updated_at=NOW() WHERE recipe_id=$1 AND user_id=$2",
+            &[
+                &recipe_id,
+                &user_id,
+                &recipe.title,
+                &image_url,
+                &recipe.tags,
+                &tokio_postgres::types::Json(&recipe.ingredients),
+                &recipe.steps,
+            ],
+        ),
+    )
+    .await
+    .map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while editing recipe `{}`!",
+            tracing_prefix,
+            err,
+            recipe_id
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            EditCustomRecipeErrors::ErrorEditingCustomRecipe,
+        )
+            .into();
+        error
+    })?;
+
+    if updated == 0 {
+        tracing::error!(
+            "{} No recipe `{}` found for the user!",
+            tracing_prefix,
+            recipe_id
+        );
+        let error: ResponseError<_> = (
+            StatusCode::NOT_FOUND,
+            EditCustomRecipeErrors::RecipeNotFound,
+        )
+            .into();
+        Err(error)?
+    }
+    tracing::debug!("{} Recipe edited!", tracing_prefix);
+
+    tracing::debug!(
+        "{} Storing copy of recipe `{}`...",
+        tracing_prefix,
+        recipe_id
+    );
+    if let Err(err) = cache_recipes(conn, std::slice::from_ref(&recipe)).await {
+        tracing::error!(
+            "{} An error `{:?}` occurred while storing a copy of recipe `{}`!",
+            tracing_prefix,
+            err,
+            recipe_id
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            EditCustomRecipeErrors::ErrorEditingCustomRecipe,
+        )
+            .into();
+        Err(error)?
+    }
+    tracing::debug!("{} Copy stored!", tracing_prefix);
+
+    tracing::debug!("{} DONE", tracing_prefix);
+    Ok(StatusCode::OK)
+}
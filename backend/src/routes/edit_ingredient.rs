@@ -1,24 +1,45 @@
-use std::{
-    fmt::Display,
-    sync::{atomic::AtomicUsize, Arc},
-};
+use std::{fmt::Display, sync::Arc};
 
 use axum::{response::IntoResponse, Json};
+use chrono::Utc;
 use hyper::StatusCode;
 use serde::{Deserialize, Serialize};
 use tokio_postgres::Client;
+use utoipa::ToSchema;
 use uuid::Uuid;
 
-use crate::{extract_jwt, is_session_valid, responses::ResponseError, APP_SECRET};
+use crate::{
+    extract_jwt, get_ingredient,
+    households::{get_current_household, GetCurrentHouseholdErrors},
+    ingredient_lots::{edit_lots, get_lots, save_lots, LotsUpdate},
+    is_session_valid,
+    models::{Ingredient, InventoryEventType},
+    request_id::current_request_id,
+    responses::ResponseError,
+    storage::{get_storage_location, GetStorageLocationErrors},
+    APP_SECRET,
+};
 
 #[derive(Debug, Serialize)]
 pub enum EditIngredientErrors {
-    InvalidPayload { payload: String },
+    InvalidPayload {
+        payload: String,
+    },
     InvalidJWT,
     NoDBConnectionFound,
     JWTExpired,
     ErrorCheckingIfSessionIsValid,
     ErrorUpdatingIngredientInDB,
+    ErrorGettingHousehold,
+    NoHouseholdSelected,
+    NotAllowedInHousehold,
+    LocationNotFound,
+    ErrorGettingStorageLocation,
+    IngredientNotFound,
+    /// The ingredient changed since the client got it, the current version is returned.
+    VersionConflict {
+        current: Box<Ingredient>,
+    },
 }
 
 impl Display for EditIngredientErrors {
@@ -27,9 +48,10 @@ impl Display for EditIngredientErrors {
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct EditIngredientPayload {
     token: String,
+    #[schema(value_type = EditedIngredientPayload)]
     ingredient: IngredientPayload,
 }
 
@@ -37,7 +59,8 @@ pub struct EditIngredientPayload {
 ///
 /// Creating the ingredient id shouldn't be a responsibility of the client.
 /// That's why this object doesn't have that.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[schema(as = EditedIngredientPayload)]
 pub struct IngredientPayload {
     #[serde(rename = "IngredientId")]
     pub ingredient_id: Uuid,
@@ -56,19 +79,38 @@ pub struct IngredientPayload {
 
     #[serde(rename = "Unit")]
     pub unit: String,
-}
 
-static ID: AtomicUsize = AtomicUsize::new(0);
+    /// When missing the ingredient stays where it is.
+    #[serde(rename = "LocationId", default)]
+    pub location_id: Option<Uuid>,
+
+    /// The version of the ingredient the client edited.
+    #[serde(rename = "Version")]
+    pub version: i32,
+}
 
 /// Route to edit the data contained inside an ingredient.
 ///
 /// All elements from the ingredient are updated except for id's.
+/// A smaller quantity is taken from the oldest lots and a bigger one is added as a new lot.
+/// The edit is rejected with a conflict if the ingredient changed since the client got it.
+#[utoipa::path(
+    post,
+    path = "/v1/ingredients/edit",
+    tag = "Ingredients",
+    request_body = EditIngredientPayload,
+    responses(
+        (status = 200, description = "The ingredient was edited"),
+        (status = 409, description = "The ingredient changed since the client got it, it's returned as it is now", body = Ingredient),
+        (status = "4XX", description = "The `EditIngredientErrors` that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The `EditIngredientErrors` that made the request fail", body = String, content_type = "text/plain"),
+    )
+)]
 pub async fn edit_ingredient(
     payload: Json<serde_json::Value>,
     client: Arc<Option<Client>>,
 ) -> Result<impl IntoResponse, ResponseError<EditIngredientErrors>> {
-    let id = ID.fetch_add(1, std::sync::atomic::Ordering::AcqRel);
-    let tracing_prefix = format!("/ingredients/edit - {}:", id);
+    let tracing_prefix = format!("/ingredients/edit - {}:", current_request_id());
 
     tracing::debug!("{} START", tracing_prefix);
 
@@ -110,6 +152,7 @@ pub async fn edit_ingredient(
             Err(error)?
         }
     };
+    let user_id = token_info.user_id.clone();
     tracing::debug!("{} JWT extracted successfully!", tracing_prefix);
 
     tracing::debug!("{} Checking for DB connection...", tracing_prefix);
@@ -151,18 +194,203 @@ pub async fn edit_ingredient(
     }
     tracing::debug!("{} Session is valid!", tracing_prefix);
 
+    tracing::debug!("{} Getting current household...", tracing_prefix);
+    let household = get_current_household(conn, &user_id).await.map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while getting the household of user `{}`!",
+            tracing_prefix,
+            err,
+            user_id
+        );
+        let error: ResponseError<_> = match err {
+            GetCurrentHouseholdErrors::NoHouseholdSelected => (
+                StatusCode::BAD_REQUEST,
+                EditIngredientErrors::NoHouseholdSelected,
+            ),
+            _ => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                EditIngredientErrors::ErrorGettingHousehold,
+            ),
+        }
+        .into();
+        error
+    })?;
+    if !household.role.can_edit_ingredients() {
+        tracing::error!(
+            "{} The user can't change the ingredients of household `{}`!",
+            tracing_prefix,
+            household.household_id
+        );
+        let error: ResponseError<_> = (
+            StatusCode::FORBIDDEN,
+            EditIngredientErrors::NotAllowedInHousehold,
+        )
+            .into();
+        Err(error)?
+    }
+    tracing::debug!(
+        "{} Working on household `{}`!",
+        tracing_prefix,
+        household.household_id
+    );
+
+    let location_id = match ingredient.location_id {
+        Some(location_id) => {
+            tracing::debug!("{} Getting storage location...", tracing_prefix);
+            let location = get_storage_location(
+                conn,
+                &household.household_id,
+                Some(&location_id.to_string()),
+            )
+            .await
+            .map_err(|err| {
+                tracing::error!(
+                    "{} An error `{:?}` occurred while getting the storage location!",
+                    tracing_prefix,
+                    err
+                );
+                let error: ResponseError<_> = match err {
+                    GetStorageLocationErrors::LocationNotFound => (
+                        StatusCode::NOT_FOUND,
+                        EditIngredientErrors::LocationNotFound,
+                    ),
+                    _ => (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        EditIngredientErrors::ErrorGettingStorageLocation,
+                    ),
+                }
+                .into();
+                error
+            })?;
+            tracing::debug!(
+                "{} Got location `{}`!",
+                tracing_prefix,
+                location.location_id
+            );
+            Some(location.location_id.to_string())
+        }
+        None => None,
+    };
+
+    tracing::debug!("{} Getting lots...", tracing_prefix);
+    let ingredient_id = ingredient.ingredient_id.to_string();
+    let old_lots = get_lots(conn, std::slice::from_ref(&ingredient_id))
+        .await
+        .map_err(|err| {
+            tracing::error!(
+                "{} An error `{:?}` occurred while getting the lots of ingredient `{}`!",
+                tracing_prefix,
+                err,
+                ingredient_id
+            );
+            let error: ResponseError<_> = (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                EditIngredientErrors::ErrorUpdatingIngredientInDB,
+            )
+                .into();
+            error
+        })?
+        .remove(&ingredient.ingredient_id)
+        .unwrap_or_default();
+    let new_lots = edit_lots(
+        &old_lots,
+        ingredient.quantity,
+        ingredient.expire_date,
+        Utc::now(),
+    );
+    tracing::debug!(
+        "{} The ingredient goes from {} to {} lots!",
+        tracing_prefix,
+        old_lots.len(),
+        new_lots.len()
+    );
+
     tracing::debug!("{} Updating ingredient in DB...", tracing_prefix);
-    let IngredientPayload {
-        ingredient_id,
-        expire_date,
-        name,
-        category,
-        quantity,
-        unit,
-    } = &ingredient;
-    if let Err(err) = conn.execute("UPDATE sf_ingredient SET expire_date=$2, name=$3, category=$4, quantity=$5, unit=$6 WHERE ingredient_id=$1", &[&ingredient_id.to_string(), expire_date, name, category, quantity, unit]).await {
-        tracing::error!("{} An error `{:?}` occurred while trying to update the ingredient `{:?}`", tracing_prefix, err, ingredient);
-        let error: ResponseError<_> = (StatusCode::INTERNAL_SERVER_ERROR, EditIngredientErrors::ErrorUpdatingIngredientInDB).into();
+    let rows_modified = save_lots(
+        conn,
+        LotsUpdate {
+            ingredient_id: &ingredient_id,
+            household_id: &household.household_id,
+            user_id: &user_id,
+            old_lots: &old_lots,
+            new_lots: &new_lots,
+            name: Some(&ingredient.name),
+            category: Some(&ingredient.category),
+            unit: Some(&ingredient.unit),
+            location_id,
+            remove: false,
+            event_type: InventoryEventType::Edited,
+            reason: None,
+            event_quantity: None,
+            version: Some(ingredient.version),
+        },
+    )
+    .await
+    .map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while trying to update the ingredient `{:?}`",
+            tracing_prefix,
+            err,
+            ingredient
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            EditIngredientErrors::ErrorUpdatingIngredientInDB,
+        )
+            .into();
+        error
+    })?;
+    if rows_modified == 0 {
+        let current = get_ingredient(
+            conn,
+            &household.household_id,
+            &ingredient_id,
+            &tracing_prefix,
+        )
+        .await
+        .map_err(|err| {
+            tracing::error!(
+                "{} An error `{:?}` occurred while getting the ingredient `{}`!",
+                tracing_prefix,
+                err,
+                ingredient_id
+            );
+            let error: ResponseError<_> = (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                EditIngredientErrors::ErrorUpdatingIngredientInDB,
+            )
+                .into();
+            error
+        })?;
+        let error: ResponseError<_> = match current {
+            Some(current) => {
+                tracing::error!(
+                    "{} The ingredient is on version {} but version {} was edited!",
+                    tracing_prefix,
+                    current.version,
+                    ingredient.version
+                );
+                ResponseError::from((
+                    StatusCode::CONFLICT,
+                    EditIngredientErrors::VersionConflict {
+                        current: Box::new(current.clone()),
+                    },
+                ))
+                .with_json(&current)
+            }
+            None => {
+                tracing::error!(
+                    "{} The ingredient `{}` wasn't found!",
+                    tracing_prefix,
+                    ingredient_id
+                );
+                (
+                    StatusCode::NOT_FOUND,
+                    EditIngredientErrors::IngredientNotFound,
+                )
+                    .into()
+            }
+        };
         Err(error)?
     }
     tracing::debug!("{} Ingredient updated!", tracing_prefix);
@@ -0,0 +1,210 @@
+use std::{fmt::Display, sync::Arc};
+
+use axum::{response::IntoResponse, Json};
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::Client;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    extract_jwt, is_session_valid, metrics::timed_query, request_id::current_request_id,
+    responses::ResponseError, APP_SECRET,
+};
+
+#[derive(Debug, Serialize)]
+pub enum EditShoppingItemErrors {
+    InvalidPayload { payload: String },
+    InvalidJWT,
+    NoDBConnectionFound,
+    JWTExpired,
+    ErrorCheckingIfSessionIsValid,
+    ErrorUpdatingItemInDB,
+    ItemNotFound,
+}
+
+impl Display for EditShoppingItemErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct EditShoppingItemPayload {
+    token: String,
+    #[schema(value_type = EditedShoppingItemPayload)]
+    item: ShoppingItemPayload,
+}
+
+/// Represents the new values of a shopping list item.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[schema(as = EditedShoppingItemPayload)]
+pub struct ShoppingItemPayload {
+    #[serde(rename = "ItemId")]
+    pub item_id: Uuid,
+
+    #[serde(rename = "Name")]
+    pub name: String,
+
+    #[serde(rename = "Category")]
+    pub category: String,
+
+    #[serde(rename = "Quantity")]
+    pub quantity: f32,
+
+    #[serde(rename = "Unit")]
+    pub unit: String,
+
+    #[serde(rename = "Checked")]
+    pub checked: bool,
+}
+
+/// Route to edit an item of the shopping list, used to check items as well.
+///
+/// All elements from the item are updated except for id's and the recipes it was added for.
+#[utoipa::path(
+    post,
+    path = "/v1/shopping-list/edit",
+    tag = "Shopping list",
+    request_body = EditShoppingItemPayload,
+    responses(
+        (status = 200, description = "The item was edited"),
+        (status = "4XX", description = "The `EditShoppingItemErrors` that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The `EditShoppingItemErrors` that made the request fail", body = String, content_type = "text/plain"),
+    )
+)]
+pub async fn edit_shopping_item(
+    payload: Json<serde_json::Value>,
+    client: Arc<Option<Client>>,
+) -> Result<impl IntoResponse, ResponseError<EditShoppingItemErrors>> {
+    let tracing_prefix = format!("/shopping-list/edit - {}:", current_request_id());
+
+    tracing::debug!("{} START", tracing_prefix);
+
+    tracing::debug!("{} Parsing payload...", tracing_prefix);
+    let EditShoppingItemPayload { token, item } = match serde_json::from_value(payload.0.clone()) {
+        Ok(p) => p,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred parsing payload `{}`",
+                tracing_prefix,
+                err,
+                payload.0
+            );
+            let error: ResponseError<_> = (
+                StatusCode::BAD_REQUEST,
+                EditShoppingItemErrors::InvalidPayload {
+                    payload: payload.0.to_string(),
+                },
+            )
+                .into();
+            Err(error)?
+        }
+    };
+    tracing::debug!("{} Payload parsed successfully!", tracing_prefix);
+
+    tracing::debug!("{} Extracting JWT...", tracing_prefix);
+    let token_info = match extract_jwt(APP_SECRET, &token) {
+        Ok(t) => t,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred while extracting the JWT `{}`",
+                tracing_prefix,
+                err,
+                token
+            );
+            let error: ResponseError<_> =
+                (StatusCode::BAD_REQUEST, EditShoppingItemErrors::InvalidJWT).into();
+            Err(error)?
+        }
+    };
+    let user_id = token_info.user_id.clone();
+    tracing::debug!("{} JWT extracted successfully!", tracing_prefix);
+
+    tracing::debug!("{} Checking for DB connection...", tracing_prefix);
+    let conn = client.as_ref().as_ref().ok_or_else(|| {
+        tracing::error!("{} No DB connection found!", tracing_prefix);
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            EditShoppingItemErrors::NoDBConnectionFound,
+        )
+            .into();
+        error
+    })?;
+    tracing::debug!("{} DB Connection found!", tracing_prefix);
+
+    tracing::debug!("{} Checking if session is valid...", tracing_prefix);
+    if let Err(err) = is_session_valid(token_info, conn).await {
+        tracing::error!(
+            "{} An error `{:?}` occurred while checking if session is valid!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = match err {
+            crate::IsSessionValidErrors::InternalDBError(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                EditShoppingItemErrors::ErrorCheckingIfSessionIsValid,
+            ),
+            crate::IsSessionValidErrors::InvalidSessionData {
+                current_date: _,
+                db_expire_date: _,
+            } => (StatusCode::UNAUTHORIZED, EditShoppingItemErrors::JWTExpired),
+            _ => (
+                StatusCode::BAD_REQUEST,
+                EditShoppingItemErrors::ErrorCheckingIfSessionIsValid,
+            ),
+        }
+        .into();
+
+        Err(error)?
+    }
+    tracing::debug!("{} Session is valid!", tracing_prefix);
+
+    tracing::debug!("{} Updating item in DB...", tracing_prefix);
+    let ShoppingItemPayload {
+        item_id,
+        name,
+        category,
+        quantity,
+        unit,
+        checked,
+    } = &item;
+    let rows_modified = timed_query(
+        "edit_shopping_item",
+        conn.execute(
+            "UPDATE sf_shopping_item SET name=$3, category=$4, quantity=$5, unit=$6, checked=$7 WHERE item_id=$1 AND user_id=$2",
+            &[&item_id.to_string(), &user_id, name, category, quantity, unit, checked],
+        ),
+    )
+    .await
+    .map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while trying to update the item `{:?}`",
+            tracing_prefix,
+            err,
+            item
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            EditShoppingItemErrors::ErrorUpdatingItemInDB,
+        )
+            .into();
+        error
+    })?;
+
+    if rows_modified == 0 {
+        tracing::error!(
+            "{} No item with id `{}` found for user `{}`!",
+            tracing_prefix,
+            item_id,
+            user_id
+        );
+        let error: ResponseError<_> =
+            (StatusCode::NOT_FOUND, EditShoppingItemErrors::ItemNotFound).into();
+        Err(error)?
+    }
+    tracing::debug!("{} Item updated!", tracing_prefix);
+
+    tracing::debug!("{} DONE", tracing_prefix);
+    Ok(StatusCode::OK)
+}
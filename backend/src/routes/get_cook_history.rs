@@ -0,0 +1,246 @@
+use std::{fmt::Display, sync::Arc};
+
+use axum::{response::IntoResponse, Json};
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::Client;
+
+use utoipa::ToSchema;
+
+use crate::{
+    dietary::flag_dietary_violations, extract_jwt, is_session_valid, metrics::timed_query,
+    models::CookHistoryEntry, parse_db_cook_history_entry, recipe_cache::get_cached_recipes,
+    recipe_ratings::fill_user_ratings, request_id::current_request_id, responses::ResponseError,
+    APP_SECRET,
+};
+
+#[derive(Debug, Serialize)]
+pub enum GetCookHistoryErrors {
+    InvalidPayload { payload: String },
+    InvalidJWT,
+    NoDBConnectionFound,
+    ErrorCheckingIfSessionIsValid,
+    JWTExpired,
+    CouldntRetrieveCookHistoryFromDB,
+    InvalidCookHistoryFormatFromDB,
+    CouldntCheckDietaryRestrictions,
+}
+
+impl Display for GetCookHistoryErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// When a recipe is supplied only the times it was cooked are returned.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct GetCookHistoryPayload {
+    token: String,
+    recipe_id: Option<String>,
+}
+
+/// Route to get the times the user cooked recipes, the most recent first.
+#[utoipa::path(
+    post,
+    path = "/v1/recipes/history",
+    tag = "Recipes",
+    request_body = GetCookHistoryPayload,
+    responses(
+        (status = 200, description = "The recipes the user cooked", body = [CookHistoryEntry]),
+        (status = "4XX", description = "The `GetCookHistoryErrors` that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The `GetCookHistoryErrors` that made the request fail", body = String, content_type = "text/plain"),
+    )
+)]
+pub async fn get_cook_history(
+    payload: Json<serde_json::Value>,
+    client: Arc<Option<Client>>,
+) -> Result<impl IntoResponse, ResponseError<GetCookHistoryErrors>> {
+    let tracing_prefix = format!("/recipes/history - {}:", current_request_id());
+
+    tracing::debug!("{} START", tracing_prefix);
+
+    tracing::debug!("{} Parsing payload...", tracing_prefix);
+    let GetCookHistoryPayload { token, recipe_id } = match serde_json::from_value(payload.0.clone())
+    {
+        Ok(p) => p,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred parsing payload `{}`",
+                tracing_prefix,
+                err,
+                payload.0
+            );
+            let error: ResponseError<_> = (
+                StatusCode::BAD_REQUEST,
+                GetCookHistoryErrors::InvalidPayload {
+                    payload: payload.0.to_string(),
+                },
+            )
+                .into();
+            Err(error)?
+        }
+    };
+    tracing::debug!("{} Payload parsed successfully!", tracing_prefix);
+
+    tracing::debug!("{} Extracting JWT...", tracing_prefix);
+    let token_info = match extract_jwt(APP_SECRET, &token) {
+        Ok(t) => t,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred while extracting the JWT `{}`",
+                tracing_prefix,
+                err,
+                token
+            );
+            let error: ResponseError<_> =
+                (StatusCode::BAD_REQUEST, GetCookHistoryErrors::InvalidJWT).into();
+            Err(error)?
+        }
+    };
+    let user_id = token_info.user_id.clone();
+    tracing::debug!("{} JWT extracted successfully!", tracing_prefix);
+
+    tracing::debug!("{} Checking for DB connection...", tracing_prefix);
+    let conn = client.as_ref().as_ref().ok_or_else(|| {
+        tracing::error!("{} No DB connection found!", tracing_prefix);
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            GetCookHistoryErrors::NoDBConnectionFound,
+        )
+            .into();
+        error
+    })?;
+    tracing::debug!("{} DB Connection found!", tracing_prefix);
+
+    tracing::debug!("{} Checking if session is valid...", tracing_prefix);
+    if let Err(err) = is_session_valid(token_info, conn).await {
+        tracing::error!(
+            "{} An error `{:?}` occurred while checking if session is valid!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = match err {
+            crate::IsSessionValidErrors::InternalDBError(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                GetCookHistoryErrors::ErrorCheckingIfSessionIsValid,
+            ),
+            crate::IsSessionValidErrors::InvalidSessionData {
+                current_date: _,
+                db_expire_date: _,
+            } => (StatusCode::UNAUTHORIZED, GetCookHistoryErrors::JWTExpired),
+            _ => (
+                StatusCode::BAD_REQUEST,
+                GetCookHistoryErrors::ErrorCheckingIfSessionIsValid,
+            ),
+        }
+        .into();
+
+        Err(error)?
+    }
+    tracing::debug!("{} Session is valid!", tracing_prefix);
+
+    tracing::debug!("{} Getting cook history from DB...", tracing_prefix);
+    let mut entries = timed_query(
+        "get_cook_history",
+        conn.query(
+            "SELECT * FROM sf_cook_history WHERE user_id=$1 AND ($2::TEXT IS NULL OR recipe_id=$2) ORDER BY cooked_date DESC",
+            &[&user_id, &recipe_id],
+        ),
+    )
+    .await
+    .map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` while trying to get cook history for user `{}`",
+            tracing_prefix,
+            err,
+            user_id
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            GetCookHistoryErrors::CouldntRetrieveCookHistoryFromDB,
+        )
+            .into();
+        error
+    })?
+    .iter()
+    .map(|row| {
+        parse_db_cook_history_entry(row, &tracing_prefix).ok_or_else(|| {
+            let error: ResponseError<_> = (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                GetCookHistoryErrors::InvalidCookHistoryFormatFromDB,
+            )
+                .into();
+            error
+        })
+    })
+    .collect::<Result<Vec<CookHistoryEntry>, ResponseError<GetCookHistoryErrors>>>()?;
+    tracing::debug!(
+        "{} Got {} cook history entries!",
+        tracing_prefix,
+        entries.len()
+    );
+
+    tracing::debug!("{} Getting stored copies of the recipes...", tracing_prefix);
+    let recipe_ids: Vec<String> = entries.iter().map(|e| e.recipe_id.clone()).collect();
+    let recipes = get_cached_recipes(conn, &recipe_ids, &tracing_prefix)
+        .await
+        .map_err(|err| {
+            tracing::error!(
+                "{} An error `{:?}` occurred while getting the stored recipes!",
+                tracing_prefix,
+                err
+            );
+            let error: ResponseError<_> = (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                GetCookHistoryErrors::CouldntRetrieveCookHistoryFromDB,
+            )
+                .into();
+            error
+        })?;
+    for entry in entries.iter_mut() {
+        entry.recipe = recipes
+            .iter()
+            .find(|r| r.recipe_id == entry.recipe_id)
+            .cloned();
+    }
+
+    tracing::debug!("{} Checking dietary restrictions...", tracing_prefix);
+    flag_dietary_violations(
+        conn,
+        &user_id,
+        entries.iter_mut().filter_map(|e| e.recipe.as_mut()),
+        &tracing_prefix,
+    )
+    .await
+    .map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while checking the user's dietary restrictions!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            GetCookHistoryErrors::CouldntCheckDietaryRestrictions,
+        )
+            .into();
+        error
+    })?;
+
+    tracing::debug!("{} Adding the user's ratings...", tracing_prefix);
+    if let Err(err) = fill_user_ratings(
+        conn,
+        &user_id,
+        entries.iter_mut().filter_map(|e| e.recipe.as_mut()),
+    )
+    .await
+    {
+        tracing::error!(
+            "{} An error `{:?}` occurred while getting the user's ratings!",
+            tracing_prefix,
+            err
+        );
+    }
+
+    tracing::debug!("{} DONE", tracing_prefix);
+    Ok(Json(entries))
+}
@@ -0,0 +1,195 @@
+use std::{fmt::Display, sync::Arc};
+
+use axum::{response::IntoResponse, Json};
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::Client;
+
+use utoipa::ToSchema;
+
+use crate::{
+    custom_recipes::{self, find_custom_recipes},
+    dietary::flag_dietary_violations,
+    extract_jwt, is_session_valid,
+    recipe_ratings::fill_user_ratings,
+    request_id::current_request_id,
+    responses::ResponseError,
+    APP_SECRET,
+};
+
+#[derive(Debug, Serialize)]
+pub enum GetCustomRecipesErrors {
+    InvalidPayload { payload: String },
+    InvalidJWT,
+    NoDBConnectionFound,
+    ErrorCheckingIfSessionIsValid,
+    JWTExpired,
+    CouldntRetrieveCustomRecipesFromDB,
+    InvalidCustomRecipeFormatFromDB,
+    CouldntCheckDietaryRestrictions,
+}
+
+impl Display for GetCustomRecipesErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct GetCustomRecipesPayload {
+    token: String,
+}
+
+/// Route to get the recipes written by the user, the last edited first.
+#[utoipa::path(
+    post,
+    path = "/v1/recipes/custom",
+    tag = "Recipes",
+    request_body = GetCustomRecipesPayload,
+    responses(
+        (status = 200, description = "The recipes written by the user", body = [Recipe]),
+        (status = "4XX", description = "The `GetCustomRecipesErrors` that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The `GetCustomRecipesErrors` that made the request fail", body = String, content_type = "text/plain"),
+    )
+)]
+pub async fn get_custom_recipes(
+    payload: Json<serde_json::Value>,
+    client: Arc<Option<Client>>,
+) -> Result<impl IntoResponse, ResponseError<GetCustomRecipesErrors>> {
+    let tracing_prefix = format!("/recipes/custom - {}:", current_request_id());
+
+    tracing::debug!("{} START", tracing_prefix);
+
+    tracing::debug!("{} Parsing payload...", tracing_prefix);
+    let GetCustomRecipesPayload { token } = match serde_json::from_value(payload.0.clone()) {
+        Ok(p) => p,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred parsing payload `{}`",
+                tracing_prefix,
+                err,
+                payload.0
+            );
+            let error: ResponseError<_> = (
+                StatusCode::BAD_REQUEST,
+                GetCustomRecipesErrors::InvalidPayload {
+                    payload: payload.0.to_string(),
+                },
+            )
+                .into();
+            Err(error)?
+        }
+    };
+    tracing::debug!("{} Payload parsed successfully!", tracing_prefix);
+
+    tracing::debug!("{} Extracting JWT...", tracing_prefix);
+    let token_info = match extract_jwt(APP_SECRET, &token) {
+        Ok(t) => t,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred while extracting the JWT `{}`",
+                tracing_prefix,
+                err,
+                token
+            );
+            let error: ResponseError<_> =
+                (StatusCode::BAD_REQUEST, GetCustomRecipesErrors::InvalidJWT).into();
+            Err(error)?
+        }
+    };
+    let user_id = token_info.user_id.clone();
+    tracing::debug!("{} JWT extracted successfully!", tracing_prefix);
+
+    tracing::debug!("{} Checking for DB connection...", tracing_prefix);
+    let conn = client.as_ref().as_ref().ok_or_else(|| {
+        tracing::error!("{} No DB connection found!", tracing_prefix);
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            GetCustomRecipesErrors::NoDBConnectionFound,
+        )
+            .into();
+        error
+    })?;
+    tracing::debug!("{} DB Connection found!", tracing_prefix);
+
+    tracing::debug!("{} Checking if session is valid...", tracing_prefix);
+    if let Err(err) = is_session_valid(token_info, conn).await {
+        tracing::error!(
+            "{} An error `{:?}` occurred while checking if session is valid!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = match err {
+            crate::IsSessionValidErrors::InternalDBError(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                GetCustomRecipesErrors::ErrorCheckingIfSessionIsValid,
+            ),
+            crate::IsSessionValidErrors::InvalidSessionData {
+                current_date: _,
+                db_expire_date: _,
+            } => (StatusCode::UNAUTHORIZED, GetCustomRecipesErrors::JWTExpired),
+            _ => (
+                StatusCode::BAD_REQUEST,
+                GetCustomRecipesErrors::ErrorCheckingIfSessionIsValid,
+            ),
+        }
+        .into();
+
+        Err(error)?
+    }
+    tracing::debug!("{} Session is valid!", tracing_prefix);
+
+    tracing::debug!("{} Getting custom recipes from DB...", tracing_prefix);
+    let mut recipes = find_custom_recipes(conn, &user_id, None, &tracing_prefix)
+        .await
+        .map_err(|err| {
+            tracing::error!(
+                "{} An error `{:?}` while trying to get custom recipes for user `{}`",
+                tracing_prefix,
+                err,
+                user_id
+            );
+            let error: ResponseError<_> = match err {
+                custom_recipes::GetCustomRecipesErrors::InternalDBError(_) => (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    GetCustomRecipesErrors::CouldntRetrieveCustomRecipesFromDB,
+                ),
+                custom_recipes::GetCustomRecipesErrors::InvalidCustomRecipeFormatFromDB => (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    GetCustomRecipesErrors::InvalidCustomRecipeFormatFromDB,
+                ),
+            }
+            .into();
+            error
+        })?;
+    tracing::debug!("{} Got {} custom recipes!", tracing_prefix, recipes.len());
+
+    tracing::debug!("{} Checking dietary restrictions...", tracing_prefix);
+    flag_dietary_violations(conn, &user_id, recipes.iter_mut(), &tracing_prefix)
+        .await
+        .map_err(|err| {
+            tracing::error!(
+                "{} An error `{:?}` occurred while checking the user's dietary restrictions!",
+                tracing_prefix,
+                err
+            );
+            let error: ResponseError<_> = (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                GetCustomRecipesErrors::CouldntCheckDietaryRestrictions,
+            )
+                .into();
+            error
+        })?;
+
+    tracing::debug!("{} Adding the user's ratings...", tracing_prefix);
+    if let Err(err) = fill_user_ratings(conn, &user_id, recipes.iter_mut()).await {
+        tracing::error!(
+            "{} An error `{:?}` occurred while getting the user's ratings!",
+            tracing_prefix,
+            err
+        );
+    }
+
+    tracing::debug!("{} DONE", tracing_prefix);
+    Ok(Json(recipes))
+}
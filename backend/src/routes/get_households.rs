@@ -0,0 +1,239 @@
+use std::{fmt::Display, sync::Arc};
+
+use axum::{response::IntoResponse, Json};
+use std::str::FromStr;
+
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::Client;
+
+use utoipa::ToSchema;
+
+use crate::{
+    extract_jwt, is_session_valid,
+    metrics::timed_query,
+    models::{Household, HouseholdMember, HouseholdRole},
+    parse_db_household_member,
+    request_id::current_request_id,
+    responses::ResponseError,
+    APP_SECRET,
+};
+
+#[derive(Debug, Serialize)]
+pub enum GetHouseholdsErrors {
+    InvalidPayload { payload: String },
+    InvalidJWT,
+    NoDBConnectionFound,
+    ErrorCheckingIfSessionIsValid,
+    JWTExpired,
+    CouldntRetrieveHouseholdsFromDB,
+    InvalidHouseholdFormatFromDB,
+}
+
+impl Display for GetHouseholdsErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct GetHouseholdsPayload {
+    token: String,
+}
+
+/// Route to get the households the user belongs to with their members.
+#[utoipa::path(
+    post,
+    path = "/v1/households",
+    tag = "Households",
+    request_body = GetHouseholdsPayload,
+    responses(
+        (status = 200, description = "The households of the user", body = [Household]),
+        (status = "4XX", description = "The `GetHouseholdsErrors` that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The `GetHouseholdsErrors` that made the request fail", body = String, content_type = "text/plain"),
+    )
+)]
+pub async fn get_households(
+    payload: Json<serde_json::Value>,
+    client: Arc<Option<Client>>,
+) -> Result<impl IntoResponse, ResponseError<GetHouseholdsErrors>> {
+    let tracing_prefix = format!("/households - {}:", current_request_id());
+
+    tracing::debug!("{} START", tracing_prefix);
+
+    tracing::debug!("{} Parsing payload...", tracing_prefix);
+    let GetHouseholdsPayload { token } = match serde_json::from_value(payload.0.clone()) {
+        Ok(p) => p,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred parsing payload `{}`",
+                tracing_prefix,
+                err,
+                payload.0
+            );
+            let error: ResponseError<_> = (
+                StatusCode::BAD_REQUEST,
+                GetHouseholdsErrors::InvalidPayload {
+                    payload: payload.0.to_string(),
+                },
+            )
+                .into();
+            Err(error)?
+        }
+    };
+    tracing::debug!("{} Payload parsed successfully!", tracing_prefix);
+
+    tracing::debug!("{} Extracting JWT...", tracing_prefix);
+    let token_info = match extract_jwt(APP_SECRET, &token) {
+        Ok(t) => t,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred while extracting the JWT `{}`",
+                tracing_prefix,
+                err,
+                token
+            );
+            let error: ResponseError<_> =
+                (StatusCode::BAD_REQUEST, GetHouseholdsErrors::InvalidJWT).into();
+            Err(error)?
+        }
+    };
+    let user_id = token_info.user_id.clone();
+    tracing::debug!("{} JWT extracted successfully!", tracing_prefix);
+
+    tracing::debug!("{} Checking for DB connection...", tracing_prefix);
+    let conn = client.as_ref().as_ref().ok_or_else(|| {
+        tracing::error!("{} No DB connection found!", tracing_prefix);
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            GetHouseholdsErrors::NoDBConnectionFound,
+        )
+            .into();
+        error
+    })?;
+    tracing::debug!("{} DB Connection found!", tracing_prefix);
+
+    tracing::debug!("{} Checking if session is valid...", tracing_prefix);
+    if let Err(err) = is_session_valid(token_info, conn).await {
+        tracing::error!(
+            "{} An error `{:?}` occurred while checking if session is valid!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = match err {
+            crate::IsSessionValidErrors::InternalDBError(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                GetHouseholdsErrors::ErrorCheckingIfSessionIsValid,
+            ),
+            crate::IsSessionValidErrors::InvalidSessionData {
+                current_date: _,
+                db_expire_date: _,
+            } => (StatusCode::UNAUTHORIZED, GetHouseholdsErrors::JWTExpired),
+            _ => (
+                StatusCode::BAD_REQUEST,
+                GetHouseholdsErrors::ErrorCheckingIfSessionIsValid,
+            ),
+        }
+        .into();
+
+        Err(error)?
+    }
+    tracing::debug!("{} Session is valid!", tracing_prefix);
+
+    tracing::debug!("{} Getting households from DB...", tracing_prefix);
+    let mut households = timed_query(
+        "get_households",
+        conn.query(
+            "SELECT h.household_id, h.name, m.role, u.current_household_id IS NOT DISTINCT FROM h.household_id AS current FROM sf_household_member m JOIN sf_household h ON h.household_id=m.household_id JOIN sf_user u ON u.user_id=m.user_id WHERE m.user_id=$1 ORDER BY h.created_at",
+            &[&user_id],
+        ),
+    )
+    .await
+    .map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` while trying to get households for user `{}`",
+            tracing_prefix,
+            err,
+            user_id
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            GetHouseholdsErrors::CouldntRetrieveHouseholdsFromDB,
+        )
+            .into();
+        error
+    })?
+    .iter()
+    .map(|row| {
+        let role: String = row.get("role");
+        let role = HouseholdRole::from_str(&role).map_err(|err| {
+            tracing::error!(
+                "{} An error `{:?}` occurred while parsing role `{}`!",
+                tracing_prefix,
+                err,
+                role
+            );
+            let error: ResponseError<_> = (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                GetHouseholdsErrors::InvalidHouseholdFormatFromDB,
+            )
+                .into();
+            error
+        })?;
+        Ok(Household {
+            household_id: row.get("household_id"),
+            name: row.get("name"),
+            role,
+            current: row.get("current"),
+            members: vec![],
+        })
+    })
+    .collect::<Result<Vec<Household>, ResponseError<GetHouseholdsErrors>>>()?;
+    tracing::debug!("{} Got {} households!", tracing_prefix, households.len());
+
+    tracing::debug!("{} Getting members from DB...", tracing_prefix);
+    let household_ids: Vec<&str> = households.iter().map(|h| h.household_id.as_str()).collect();
+    let rows = timed_query(
+        "get_households",
+        conn.query(
+            "SELECT m.household_id, m.user_id, u.username, m.role, m.joined_date FROM sf_household_member m JOIN sf_user u ON u.user_id=m.user_id WHERE m.household_id = ANY($1) ORDER BY m.joined_date",
+            &[&household_ids],
+        ),
+    )
+    .await
+    .map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` while trying to get the household members!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            GetHouseholdsErrors::CouldntRetrieveHouseholdsFromDB,
+        )
+            .into();
+        error
+    })?;
+    for row in rows {
+        let household_id: &str = row.get("household_id");
+        let member: HouseholdMember =
+            parse_db_household_member(&row, &tracing_prefix).ok_or_else(|| {
+                let error: ResponseError<_> = (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    GetHouseholdsErrors::InvalidHouseholdFormatFromDB,
+                )
+                    .into();
+                error
+            })?;
+        if let Some(household) = households
+            .iter_mut()
+            .find(|h| h.household_id == household_id)
+        {
+            household.members.push(member);
+        }
+    }
+    tracing::debug!("{} Got members!", tracing_prefix);
+
+    tracing::debug!("{} DONE", tracing_prefix);
+    Ok(Json(households))
+}
@@ -0,0 +1,216 @@
+use std::{fmt::Display, sync::Arc};
+
+use axum::{response::IntoResponse, Json};
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::Client;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    extract_jwt, get_ingredient,
+    households::{get_current_household, GetCurrentHouseholdErrors},
+    is_session_valid,
+    request_id::current_request_id,
+    responses::ResponseError,
+    APP_SECRET,
+};
+
+#[derive(Debug, Serialize)]
+pub enum GetIngredientErrors {
+    InvalidPayload { payload: String },
+    InvalidJWT,
+    NoDBConnectionFound,
+    ErrorCheckingIfSessionIsValid,
+    JWTExpired,
+    ErrorGettingHousehold,
+    NoHouseholdSelected,
+    IngredientNotFound,
+    ErrorGettingIngredient,
+}
+
+impl Display for GetIngredientErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct GetIngredientPayload {
+    token: String,
+    ingredient_id: Uuid,
+}
+
+/// Route to get an ingredient of the current household with its lots.
+#[utoipa::path(
+    post,
+    path = "/v1/ingredients/get",
+    tag = "Ingredients",
+    request_body = GetIngredientPayload,
+    responses(
+        (status = 200, description = "The ingredient", body = Ingredient),
+        (status = "4XX", description = "The `GetIngredientErrors` that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The `GetIngredientErrors` that made the request fail", body = String, content_type = "text/plain"),
+    )
+)]
+pub async fn get_ingredient_route(
+    payload: Json<serde_json::Value>,
+    client: Arc<Option<Client>>,
+) -> Result<impl IntoResponse, ResponseError<GetIngredientErrors>> {
+    let tracing_prefix = format!("/ingredients/get - {}:", current_request_id());
+
+    tracing::debug!("{} START", tracing_prefix);
+
+    tracing::debug!("{} Parsing payload...", tracing_prefix);
+    let GetIngredientPayload {
+        token,
+        ingredient_id,
+    } = match serde_json::from_value(payload.0.clone()) {
+        Ok(p) => p,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred parsing payload `{}`",
+                tracing_prefix,
+                err,
+                payload.0
+            );
+            let error: ResponseError<_> = (
+                StatusCode::BAD_REQUEST,
+                GetIngredientErrors::InvalidPayload {
+                    payload: payload.0.to_string(),
+                },
+            )
+                .into();
+            Err(error)?
+        }
+    };
+    tracing::debug!("{} Payload parsed successfully!", tracing_prefix);
+
+    tracing::debug!("{} Extracting JWT...", tracing_prefix);
+    let token_info = match extract_jwt(APP_SECRET, &token) {
+        Ok(t) => t,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred while extracting the JWT `{}`",
+                tracing_prefix,
+                err,
+                token
+            );
+            let error: ResponseError<_> =
+                (StatusCode::BAD_REQUEST, GetIngredientErrors::InvalidJWT).into();
+            Err(error)?
+        }
+    };
+    let user_id = token_info.user_id.clone();
+    tracing::debug!("{} JWT extracted successfully!", tracing_prefix);
+
+    tracing::debug!("{} Checking for DB connection...", tracing_prefix);
+    let conn = client.as_ref().as_ref().ok_or_else(|| {
+        tracing::error!("{} No DB connection found!", tracing_prefix);
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            GetIngredientErrors::NoDBConnectionFound,
+        )
+            .into();
+        error
+    })?;
+    tracing::debug!("{} DB Connection found!", tracing_prefix);
+
+    tracing::debug!("{} Checking if session is valid...", tracing_prefix);
+    if let Err(err) = is_session_valid(token_info, conn).await {
+        tracing::error!(
+            "{} An error `{:?}` occurred while checking if session is valid!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = match err {
+            crate::IsSessionValidErrors::InternalDBError(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                GetIngredientErrors::ErrorCheckingIfSessionIsValid,
+            ),
+            crate::IsSessionValidErrors::InvalidSessionData {
+                current_date: _,
+                db_expire_date: _,
+            } => (StatusCode::UNAUTHORIZED, GetIngredientErrors::JWTExpired),
+            _ => (
+                StatusCode::BAD_REQUEST,
+                GetIngredientErrors::ErrorCheckingIfSessionIsValid,
+            ),
+        }
+        .into();
+
+        Err(error)?
+    }
+    tracing::debug!("{} Session is valid!", tracing_prefix);
+
+    tracing::debug!("{} Getting current household...", tracing_prefix);
+    let household = get_current_household(conn, &user_id).await.map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while getting the household of user `{}`!",
+            tracing_prefix,
+            err,
+            user_id
+        );
+        let error: ResponseError<_> = match err {
+            GetCurrentHouseholdErrors::NoHouseholdSelected => (
+                StatusCode::BAD_REQUEST,
+                GetIngredientErrors::NoHouseholdSelected,
+            ),
+            _ => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                GetIngredientErrors::ErrorGettingHousehold,
+            ),
+        }
+        .into();
+        error
+    })?;
+    tracing::debug!(
+        "{} Working on household `{}`!",
+        tracing_prefix,
+        household.household_id
+    );
+
+    tracing::debug!(
+        "{} Getting ingredient `{}`...",
+        tracing_prefix,
+        ingredient_id
+    );
+    let ingredient = get_ingredient(
+        conn,
+        &household.household_id,
+        &ingredient_id.to_string(),
+        &tracing_prefix,
+    )
+    .await
+    .map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while getting the ingredient `{}`!",
+            tracing_prefix,
+            err,
+            ingredient_id
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            GetIngredientErrors::ErrorGettingIngredient,
+        )
+            .into();
+        error
+    })?
+    .ok_or_else(|| {
+        tracing::error!(
+            "{} Ingredient `{}` wasn't found!",
+            tracing_prefix,
+            ingredient_id
+        );
+        let error: ResponseError<_> = (
+            StatusCode::NOT_FOUND,
+            GetIngredientErrors::IngredientNotFound,
+        )
+            .into();
+        error
+    })?;
+    tracing::debug!("{} Ingredient found!", tracing_prefix);
+
+    tracing::debug!("{} DONE", tracing_prefix);
+    Ok(Json(ingredient))
+}
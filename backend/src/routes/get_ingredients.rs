@@ -1,18 +1,24 @@
-use std::{
-    fmt::Display,
-    sync::{atomic::AtomicUsize, Arc},
-};
+use std::{fmt::Display, sync::Arc};
 
 use axum::{response::IntoResponse, Json};
 
 use hyper::StatusCode;
 use serde::Deserialize;
 use tokio_postgres::Client;
+use utoipa::ToSchema;
 use uuid::Uuid;
 
 use crate::{
-    extract_jwt, is_session_valid, models::Ingredient, parse_db_ingredient,
-    responses::ResponseError, APP_SECRET,
+    extract_jwt,
+    households::{get_current_household, GetCurrentHouseholdErrors},
+    ingredient_lots::get_lots,
+    is_session_valid,
+    metrics::timed_query,
+    models::{Ingredient, StorageKind},
+    parse_db_ingredient,
+    request_id::current_request_id,
+    responses::ResponseError,
+    APP_SECRET,
 };
 
 #[derive(Debug)]
@@ -25,6 +31,9 @@ pub enum GetIngredientsErrors {
     JWTExpired,
     CouldntRetrieveRecipesFromDB,
     InvalidIngredientFormatFromDB,
+    ErrorGettingHousehold,
+    NoHouseholdSelected,
+    ErrorGettingLots,
 }
 
 impl Display for GetIngredientsErrors {
@@ -33,24 +42,41 @@ impl Display for GetIngredientsErrors {
     }
 }
 
-#[derive(Debug, Deserialize)]
-struct GetIngredientsPayload {
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct GetIngredientsPayload {
     token: String,
+    /// Only returns the ingredients stored in this location.
+    location_id: Option<Uuid>,
+    /// Only returns the ingredients stored in locations of this kind.
+    kind: Option<StorageKind>,
 }
 
-static ID: AtomicUsize = AtomicUsize::new(0);
-
+/// Route to get the ingredients of the current household grouped by storage location.
+#[utoipa::path(
+    post,
+    path = "/v1/ingredients",
+    tag = "Ingredients",
+    request_body = GetIngredientsPayload,
+    responses(
+        (status = 200, description = "The ingredients of the household", body = [Ingredient]),
+        (status = "4XX", description = "The `GetIngredientsErrors` that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The `GetIngredientsErrors` that made the request fail", body = String, content_type = "text/plain"),
+    )
+)]
 pub async fn get_ingredients(
     payload: Json<serde_json::Value>,
     client: Arc<Option<Client>>,
 ) -> Result<impl IntoResponse, ResponseError<GetIngredientsErrors>> {
-    let id = ID.fetch_add(1, std::sync::atomic::Ordering::AcqRel);
-    let tracing_prefix = format!("/ingredients - {}:", id);
+    let tracing_prefix = format!("/ingredients - {}:", current_request_id());
 
     tracing::debug!("{} START", tracing_prefix);
 
     tracing::debug!("{} Parsing payload...", tracing_prefix);
-    let GetIngredientsPayload { token } = match serde_json::from_value(payload.0.clone()) {
+    let GetIngredientsPayload {
+        token,
+        location_id,
+        kind,
+    } = match serde_json::from_value(payload.0.clone()) {
         Ok(p) => p,
         Err(err) => {
             tracing::error!(
@@ -134,31 +160,64 @@ pub async fn get_ingredients(
     }
     tracing::debug!("{} Session is valid!", tracing_prefix);
 
+    tracing::debug!("{} Getting current household...", tracing_prefix);
+    let household = get_current_household(conn, &user_id).await.map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while getting the household of user `{}`!",
+            tracing_prefix,
+            err,
+            user_id
+        );
+        let error: ResponseError<_> = match err {
+            GetCurrentHouseholdErrors::NoHouseholdSelected => (
+                StatusCode::BAD_REQUEST,
+                GetIngredientsErrors::NoHouseholdSelected,
+            ),
+            _ => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                GetIngredientsErrors::ErrorGettingHousehold,
+            ),
+        }
+        .into();
+        error
+    })?;
+    tracing::debug!(
+        "{} Working on household `{}`!",
+        tracing_prefix,
+        household.household_id
+    );
+
     tracing::debug!("{} Getting ingredients from DB...", tracing_prefix);
-    let db_result = conn
-        .query(
-            "SELECT * FROM sf_ingredient WHERE user_id=$1",
-            &[&user_id.to_string()],
+    let db_result = timed_query(
+        "get_ingredients",
+        conn.query(
+            "SELECT i.* FROM sf_ingredient i JOIN sf_storage_location l ON l.location_id=i.location_id WHERE i.household_id=$1 AND i.deleted_date IS NULL AND ($2::TEXT IS NULL OR i.location_id=$2) AND ($3::TEXT IS NULL OR l.kind=$3) ORDER BY l.is_default DESC, l.name, i.expire_date",
+            &[
+                &household.household_id,
+                &location_id.map(|id| id.to_string()),
+                &kind.map(|k| format!("{:?}", k)),
+            ],
+        ),
+    )
+    .await
+    .map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` while trying to get ingredients for user `{}`",
+            tracing_prefix,
+            err,
+            user_id
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            GetIngredientsErrors::CouldntRetrieveRecipesFromDB,
         )
-        .await
-        .map_err(|err| {
-            tracing::error!(
-                "{} An error `{:?}` while trying to get ingredients for user `{}`",
-                tracing_prefix,
-                err,
-                user_id
-            );
-            let error: ResponseError<_> = (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                GetIngredientsErrors::CouldntRetrieveRecipesFromDB,
-            )
-                .into();
-            error
-        })?;
+            .into();
+        error
+    })?;
     tracing::debug!("{} Got ingredients from user!", tracing_prefix);
 
     tracing::debug!("{} Parsing ingredients from db...", tracing_prefix);
-    let ingredients = db_result
+    let mut ingredients = db_result
         .iter()
         .map(|row| {
             parse_db_ingredient(row, &tracing_prefix).ok_or_else(|| {
@@ -173,6 +232,28 @@ pub async fn get_ingredients(
         .collect::<Result<Vec<Ingredient>, ResponseError<GetIngredientsErrors>>>()?;
     tracing::debug!("{} Ingredients parsed!", tracing_prefix);
 
+    tracing::debug!("{} Getting lots...", tracing_prefix);
+    let ingredient_ids: Vec<String> = ingredients
+        .iter()
+        .map(|i| i.ingredient_id.to_string())
+        .collect();
+    let mut lots = get_lots(conn, &ingredient_ids).await.map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while getting the lots!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            GetIngredientsErrors::ErrorGettingLots,
+        )
+            .into();
+        error
+    })?;
+    for ingredient in ingredients.iter_mut() {
+        ingredient.lots = lots.remove(&ingredient.ingredient_id).unwrap_or_default();
+    }
+
     tracing::debug!("{} DONE", tracing_prefix);
     Ok(Json(ingredients))
 }
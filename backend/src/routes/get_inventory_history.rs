@@ -0,0 +1,258 @@
+use std::{fmt::Display, sync::Arc};
+
+use axum::{response::IntoResponse, Json};
+use chrono::{DateTime, Utc};
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::Client;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    extract_jwt,
+    households::{get_current_household, GetCurrentHouseholdErrors},
+    is_session_valid,
+    metrics::timed_query,
+    models::InventoryEvent,
+    parse_db_inventory_event,
+    request_id::current_request_id,
+    responses::ResponseError,
+    APP_SECRET,
+};
+
+#[derive(Debug, Serialize)]
+pub enum GetInventoryHistoryErrors {
+    InvalidPayload { payload: String },
+    InvalidJWT,
+    NoDBConnectionFound,
+    ErrorCheckingIfSessionIsValid,
+    JWTExpired,
+    ErrorGettingHousehold,
+    NoHouseholdSelected,
+    InvalidDateRange,
+    CouldntRetrieveEventsFromDB,
+    InvalidEventFormatFromDB,
+}
+
+impl Display for GetInventoryHistoryErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct GetInventoryHistoryPayload {
+    token: String,
+    /// Only returns the events of this ingredient.
+    ingredient_id: Option<Uuid>,
+    /// Only returns the events of ingredients of this category.
+    category: Option<String>,
+    /// Only returns the events that happened on or after this date.
+    from_date: Option<DateTime<Utc>>,
+    /// Only returns the events that happened before this date.
+    to_date: Option<DateTime<Utc>>,
+}
+
+/// Route to get what happened to the ingredients of the current household, the most recent first.
+///
+/// All the filters are optional and can be combined.
+#[utoipa::path(
+    post,
+    path = "/v1/ingredients/history",
+    tag = "Ingredients",
+    request_body = GetInventoryHistoryPayload,
+    responses(
+        (status = 200, description = "The history of the ingredients", body = [InventoryEvent]),
+        (status = "4XX", description = "The `GetInventoryHistoryErrors` that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The `GetInventoryHistoryErrors` that made the request fail", body = String, content_type = "text/plain"),
+    )
+)]
+pub async fn get_inventory_history(
+    payload: Json<serde_json::Value>,
+    client: Arc<Option<Client>>,
+) -> Result<impl IntoResponse, ResponseError<GetInventoryHistoryErrors>> {
+    let tracing_prefix = format!("/ingredients/history - {}:", current_request_id());
+
+    tracing::debug!("{} START", tracing_prefix);
+
+    tracing::debug!("{} Parsing payload...", tracing_prefix);
+    let GetInventoryHistoryPayload {
+        token,
+        ingredient_id,
+        category,
+        from_date,
+        to_date,
+    } = match serde_json::from_value(payload.0.clone()) {
+        Ok(p) => p,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred parsing payload `{}`",
+                tracing_prefix,
+                err,
+                payload.0
+            );
+            let error: ResponseError<_> = (
+                StatusCode::BAD_REQUEST,
+                GetInventoryHistoryErrors::InvalidPayload {
+                    payload: payload.0.to_string(),
+                },
+            )
+                .into();
+            Err(error)?
+        }
+    };
+    tracing::debug!("{} Payload parsed successfully!", tracing_prefix);
+
+    tracing::debug!("{} Extracting JWT...", tracing_prefix);
+    let token_info = match extract_jwt(APP_SECRET, &token) {
+        Ok(t) => t,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred while extracting the JWT `{}`",
+                tracing_prefix,
+                err,
+                token
+            );
+            let error: ResponseError<_> = (
+                StatusCode::BAD_REQUEST,
+                GetInventoryHistoryErrors::InvalidJWT,
+            )
+                .into();
+            Err(error)?
+        }
+    };
+    let user_id = token_info.user_id.clone();
+    tracing::debug!("{} JWT extracted successfully!", tracing_prefix);
+
+    tracing::debug!("{} Checking for DB connection...", tracing_prefix);
+    let conn = client.as_ref().as_ref().ok_or_else(|| {
+        tracing::error!("{} No DB connection found!", tracing_prefix);
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            GetInventoryHistoryErrors::NoDBConnectionFound,
+        )
+            .into();
+        error
+    })?;
+    tracing::debug!("{} DB Connection found!", tracing_prefix);
+
+    tracing::debug!("{} Checking if session is valid...", tracing_prefix);
+    if let Err(err) = is_session_valid(token_info, conn).await {
+        tracing::error!(
+            "{} An error `{:?}` occurred while checking if session is valid!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = match err {
+            crate::IsSessionValidErrors::InternalDBError(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                GetInventoryHistoryErrors::ErrorCheckingIfSessionIsValid,
+            ),
+            crate::IsSessionValidErrors::InvalidSessionData {
+                current_date: _,
+                db_expire_date: _,
+            } => (
+                StatusCode::UNAUTHORIZED,
+                GetInventoryHistoryErrors::JWTExpired,
+            ),
+            _ => (
+                StatusCode::BAD_REQUEST,
+                GetInventoryHistoryErrors::ErrorCheckingIfSessionIsValid,
+            ),
+        }
+        .into();
+
+        Err(error)?
+    }
+    tracing::debug!("{} Session is valid!", tracing_prefix);
+
+    tracing::debug!("{} Getting current household...", tracing_prefix);
+    let household = get_current_household(conn, &user_id).await.map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while getting the household of user `{}`!",
+            tracing_prefix,
+            err,
+            user_id
+        );
+        let error: ResponseError<_> = match err {
+            GetCurrentHouseholdErrors::NoHouseholdSelected => (
+                StatusCode::BAD_REQUEST,
+                GetInventoryHistoryErrors::NoHouseholdSelected,
+            ),
+            _ => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                GetInventoryHistoryErrors::ErrorGettingHousehold,
+            ),
+        }
+        .into();
+        error
+    })?;
+    tracing::debug!(
+        "{} Working on household `{}`!",
+        tracing_prefix,
+        household.household_id
+    );
+
+    if let (Some(from_date), Some(to_date)) = (from_date, to_date) {
+        if from_date > to_date {
+            tracing::error!(
+                "{} The range `{}` - `{}` is invalid!",
+                tracing_prefix,
+                from_date,
+                to_date
+            );
+            let error: ResponseError<_> = (
+                StatusCode::BAD_REQUEST,
+                GetInventoryHistoryErrors::InvalidDateRange,
+            )
+                .into();
+            Err(error)?
+        }
+    }
+
+    tracing::debug!("{} Getting events from DB...", tracing_prefix);
+    let events = timed_query(
+        "get_inventory_history",
+        conn.query(
+            "SELECT * FROM sf_inventory_event WHERE household_id=$1 AND ($2::TEXT IS NULL OR ingredient_id=$2) AND ($3::TEXT IS NULL OR LOWER(category)=LOWER($3)) AND ($4::TIMESTAMPTZ IS NULL OR event_date >= $4) AND ($5::TIMESTAMPTZ IS NULL OR event_date < $5) ORDER BY event_date DESC",
+            &[
+                &household.household_id,
+                &ingredient_id.map(|id| id.to_string()),
+                &category,
+                &from_date,
+                &to_date,
+            ],
+        ),
+    )
+    .await
+    .map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` while trying to get the history of household `{}`",
+            tracing_prefix,
+            err,
+            household.household_id
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            GetInventoryHistoryErrors::CouldntRetrieveEventsFromDB,
+        )
+            .into();
+        error
+    })?
+    .iter()
+    .map(|row| {
+        parse_db_inventory_event(row, &tracing_prefix).ok_or_else(|| {
+            let error: ResponseError<_> = (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                GetInventoryHistoryErrors::InvalidEventFormatFromDB,
+            )
+                .into();
+            error
+        })
+    })
+    .collect::<Result<Vec<InventoryEvent>, ResponseError<GetInventoryHistoryErrors>>>()?;
+    tracing::debug!("{} Got {} events!", tracing_prefix, events.len());
+
+    tracing::debug!("{} DONE", tracing_prefix);
+    Ok(Json(events))
+}
@@ -0,0 +1,250 @@
+use std::{fmt::Display, sync::Arc};
+
+use axum::{response::IntoResponse, Json};
+use chrono::NaiveDate;
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::Client;
+
+use utoipa::ToSchema;
+
+use crate::{
+    dietary::flag_dietary_violations, extract_jwt, is_session_valid, metrics::timed_query,
+    models::MealPlanEntry, parse_db_meal_plan_entry, recipe_cache::get_cached_recipes,
+    recipe_ratings::fill_user_ratings, request_id::current_request_id, responses::ResponseError,
+    APP_SECRET,
+};
+
+#[derive(Debug, Serialize)]
+pub enum GetMealPlanErrors {
+    InvalidPayload { payload: String },
+    InvalidJWT,
+    NoDBConnectionFound,
+    ErrorCheckingIfSessionIsValid,
+    JWTExpired,
+    CouldntRetrieveMealPlanFromDB,
+    InvalidMealPlanFormatFromDB,
+    CouldntCheckDietaryRestrictions,
+}
+
+impl Display for GetMealPlanErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct GetMealPlanPayload {
+    token: String,
+    from: NaiveDate,
+    to: NaiveDate,
+}
+
+/// Route to get the planned meals between two dates, both inclusive.
+#[utoipa::path(
+    post,
+    path = "/v1/meal-plan",
+    tag = "Meal plan",
+    request_body = GetMealPlanPayload,
+    responses(
+        (status = 200, description = "The planned meals", body = [MealPlanEntry]),
+        (status = "4XX", description = "The `GetMealPlanErrors` that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The `GetMealPlanErrors` that made the request fail", body = String, content_type = "text/plain"),
+    )
+)]
+pub async fn get_meal_plan(
+    payload: Json<serde_json::Value>,
+    client: Arc<Option<Client>>,
+) -> Result<impl IntoResponse, ResponseError<GetMealPlanErrors>> {
+    let tracing_prefix = format!("/meal-plan - {}:", current_request_id());
+
+    tracing::debug!("{} START", tracing_prefix);
+
+    tracing::debug!("{} Parsing payload...", tracing_prefix);
+    let GetMealPlanPayload { token, from, to } = match serde_json::from_value(payload.0.clone()) {
+        Ok(p) => p,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred parsing payload `{}`",
+                tracing_prefix,
+                err,
+                payload.0
+            );
+            let error: ResponseError<_> = (
+                StatusCode::BAD_REQUEST,
+                GetMealPlanErrors::InvalidPayload {
+                    payload: payload.0.to_string(),
+                },
+            )
+                .into();
+            Err(error)?
+        }
+    };
+    tracing::debug!("{} Payload parsed successfully!", tracing_prefix);
+
+    tracing::debug!("{} Extracting JWT...", tracing_prefix);
+    let token_info = match extract_jwt(APP_SECRET, &token) {
+        Ok(t) => t,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred while extracting the JWT `{}`",
+                tracing_prefix,
+                err,
+                token
+            );
+            let error: ResponseError<_> =
+                (StatusCode::BAD_REQUEST, GetMealPlanErrors::InvalidJWT).into();
+            Err(error)?
+        }
+    };
+    let user_id = token_info.user_id.clone();
+    tracing::debug!("{} JWT extracted successfully!", tracing_prefix);
+
+    tracing::debug!("{} Checking for DB connection...", tracing_prefix);
+    let conn = client.as_ref().as_ref().ok_or_else(|| {
+        tracing::error!("{} No DB connection found!", tracing_prefix);
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            GetMealPlanErrors::NoDBConnectionFound,
+        )
+            .into();
+        error
+    })?;
+    tracing::debug!("{} DB Connection found!", tracing_prefix);
+
+    tracing::debug!("{} Checking if session is valid...", tracing_prefix);
+    if let Err(err) = is_session_valid(token_info, conn).await {
+        tracing::error!(
+            "{} An error `{:?}` occurred while checking if session is valid!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = match err {
+            crate::IsSessionValidErrors::InternalDBError(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                GetMealPlanErrors::ErrorCheckingIfSessionIsValid,
+            ),
+            crate::IsSessionValidErrors::InvalidSessionData {
+                current_date: _,
+                db_expire_date: _,
+            } => (StatusCode::UNAUTHORIZED, GetMealPlanErrors::JWTExpired),
+            _ => (
+                StatusCode::BAD_REQUEST,
+                GetMealPlanErrors::ErrorCheckingIfSessionIsValid,
+            ),
+        }
+        .into();
+
+        Err(error)?
+    }
+    tracing::debug!("{} Session is valid!", tracing_prefix);
+
+    tracing::debug!("{} Getting meal plan from DB...", tracing_prefix);
+    let mut entries = get_meal_plan_entries(conn, &user_id, from, to, &tracing_prefix)
+        .await
+        .map_err(|err| {
+            tracing::error!(
+                "{} An error `{:?}` occurred while getting the meal plan of user `{}`!",
+                tracing_prefix,
+                err,
+                user_id
+            );
+            let error: ResponseError<_> = match err {
+                GetMealPlanEntriesErrors::InternalDBError(_) => (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    GetMealPlanErrors::CouldntRetrieveMealPlanFromDB,
+                ),
+                GetMealPlanEntriesErrors::InvalidMealPlanFormatFromDB => (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    GetMealPlanErrors::InvalidMealPlanFormatFromDB,
+                ),
+            }
+            .into();
+            error
+        })?;
+    tracing::debug!("{} Got {} planned meals!", tracing_prefix, entries.len());
+
+    tracing::debug!("{} Checking dietary restrictions...", tracing_prefix);
+    flag_dietary_violations(
+        conn,
+        &user_id,
+        entries.iter_mut().filter_map(|e| e.recipe.as_mut()),
+        &tracing_prefix,
+    )
+    .await
+    .map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while checking the user's dietary restrictions!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            GetMealPlanErrors::CouldntCheckDietaryRestrictions,
+        )
+            .into();
+        error
+    })?;
+
+    tracing::debug!("{} Adding the user's ratings...", tracing_prefix);
+    if let Err(err) = fill_user_ratings(
+        conn,
+        &user_id,
+        entries.iter_mut().filter_map(|e| e.recipe.as_mut()),
+    )
+    .await
+    {
+        tracing::error!(
+            "{} An error `{:?}` occurred while getting the user's ratings!",
+            tracing_prefix,
+            err
+        );
+    }
+
+    tracing::debug!("{} DONE", tracing_prefix);
+    Ok(Json(entries))
+}
+
+#[allow(dead_code)]
+#[derive(Debug)]
+pub(crate) enum GetMealPlanEntriesErrors {
+    InternalDBError(tokio_postgres::Error),
+    InvalidMealPlanFormatFromDB,
+}
+
+/// Gets the planned meals of the user between two dates, both inclusive,
+/// with the stored copy of their recipes.
+pub(crate) async fn get_meal_plan_entries(
+    conn: &Client,
+    user_id: &str,
+    from: NaiveDate,
+    to: NaiveDate,
+    tracing_prefix: &str,
+) -> Result<Vec<MealPlanEntry>, GetMealPlanEntriesErrors> {
+    let mut entries = timed_query(
+        "get_meal_plan_entries",
+        conn.query(
+            "SELECT * FROM sf_meal_plan WHERE user_id=$1 AND date BETWEEN $2 AND $3 ORDER BY date",
+            &[&user_id, &from, &to],
+        ),
+    )
+    .await
+    .map_err(GetMealPlanEntriesErrors::InternalDBError)?
+    .iter()
+    .map(|row| parse_db_meal_plan_entry(row, tracing_prefix))
+    .collect::<Option<Vec<MealPlanEntry>>>()
+    .ok_or(GetMealPlanEntriesErrors::InvalidMealPlanFormatFromDB)?;
+
+    let recipe_ids: Vec<String> = entries.iter().map(|e| e.recipe_id.clone()).collect();
+    let recipes = get_cached_recipes(conn, &recipe_ids, tracing_prefix)
+        .await
+        .map_err(GetMealPlanEntriesErrors::InternalDBError)?;
+    for entry in entries.iter_mut() {
+        entry.recipe = recipes
+            .iter()
+            .find(|r| r.recipe_id == entry.recipe_id)
+            .cloned();
+    }
+
+    Ok(entries)
+}
@@ -0,0 +1,175 @@
+use std::{fmt::Display, sync::Arc};
+
+use axum::{response::IntoResponse, Json};
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::Client;
+
+use utoipa::ToSchema;
+
+use crate::{
+    extract_jwt, is_session_valid, metrics::timed_query, models::RecipeRating,
+    parse_db_recipe_rating, request_id::current_request_id, responses::ResponseError, APP_SECRET,
+};
+
+#[derive(Debug, Serialize)]
+pub enum GetRecipeRatingsErrors {
+    InvalidPayload { payload: String },
+    InvalidJWT,
+    NoDBConnectionFound,
+    ErrorCheckingIfSessionIsValid,
+    JWTExpired,
+    CouldntRetrieveRatingsFromDB,
+    InvalidRatingFormatFromDB,
+}
+
+impl Display for GetRecipeRatingsErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct GetRecipeRatingsPayload {
+    token: String,
+}
+
+/// Route to get the ratings the user gave, the most recent first.
+#[utoipa::path(
+    post,
+    path = "/v1/recipes/ratings",
+    tag = "Recipes",
+    request_body = GetRecipeRatingsPayload,
+    responses(
+        (status = 200, description = "The ratings of the user", body = [RecipeRating]),
+        (status = "4XX", description = "The `GetRecipeRatingsErrors` that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The `GetRecipeRatingsErrors` that made the request fail", body = String, content_type = "text/plain"),
+    )
+)]
+pub async fn get_recipe_ratings(
+    payload: Json<serde_json::Value>,
+    client: Arc<Option<Client>>,
+) -> Result<impl IntoResponse, ResponseError<GetRecipeRatingsErrors>> {
+    let tracing_prefix = format!("/recipes/ratings - {}:", current_request_id());
+
+    tracing::debug!("{} START", tracing_prefix);
+
+    tracing::debug!("{} Parsing payload...", tracing_prefix);
+    let GetRecipeRatingsPayload { token } = match serde_json::from_value(payload.0.clone()) {
+        Ok(p) => p,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred parsing payload `{}`",
+                tracing_prefix,
+                err,
+                payload.0
+            );
+            let error: ResponseError<_> = (
+                StatusCode::BAD_REQUEST,
+                GetRecipeRatingsErrors::InvalidPayload {
+                    payload: payload.0.to_string(),
+                },
+            )
+                .into();
+            Err(error)?
+        }
+    };
+    tracing::debug!("{} Payload parsed successfully!", tracing_prefix);
+
+    tracing::debug!("{} Extracting JWT...", tracing_prefix);
+    let token_info = match extract_jwt(APP_SECRET, &token) {
+        Ok(t) => t,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred while extracting the JWT `{}`",
+                tracing_prefix,
+                err,
+                token
+            );
+            let error: ResponseError<_> =
+                (StatusCode::BAD_REQUEST, GetRecipeRatingsErrors::InvalidJWT).into();
+            Err(error)?
+        }
+    };
+    let user_id = token_info.user_id.clone();
+    tracing::debug!("{} JWT extracted successfully!", tracing_prefix);
+
+    tracing::debug!("{} Checking for DB connection...", tracing_prefix);
+    let conn = client.as_ref().as_ref().ok_or_else(|| {
+        tracing::error!("{} No DB connection found!", tracing_prefix);
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            GetRecipeRatingsErrors::NoDBConnectionFound,
+        )
+            .into();
+        error
+    })?;
+    tracing::debug!("{} DB Connection found!", tracing_prefix);
+
+    tracing::debug!("{} Checking if session is valid...", tracing_prefix);
+    if let Err(err) = is_session_valid(token_info, conn).await {
+        tracing::error!(
+            "{} An error `{:?}` occurred while checking if session is valid!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = match err {
+            crate::IsSessionValidErrors::InternalDBError(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                GetRecipeRatingsErrors::ErrorCheckingIfSessionIsValid,
+            ),
+            crate::IsSessionValidErrors::InvalidSessionData {
+                current_date: _,
+                db_expire_date: _,
+            } => (StatusCode::UNAUTHORIZED, GetRecipeRatingsErrors::JWTExpired),
+            _ => (
+                StatusCode::BAD_REQUEST,
+                GetRecipeRatingsErrors::ErrorCheckingIfSessionIsValid,
+            ),
+        }
+        .into();
+
+        Err(error)?
+    }
+    tracing::debug!("{} Session is valid!", tracing_prefix);
+
+    tracing::debug!("{} Getting ratings from DB...", tracing_prefix);
+    let ratings = timed_query(
+        "get_recipe_ratings",
+        conn.query(
+            "SELECT * FROM sf_recipe_rating WHERE user_id=$1 ORDER BY rated_date DESC",
+            &[&user_id],
+        ),
+    )
+    .await
+    .map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` while trying to get ratings for user `{}`",
+            tracing_prefix,
+            err,
+            user_id
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            GetRecipeRatingsErrors::CouldntRetrieveRatingsFromDB,
+        )
+            .into();
+        error
+    })?
+    .iter()
+    .map(|row| {
+        parse_db_recipe_rating(row, &tracing_prefix).ok_or_else(|| {
+            let error: ResponseError<_> = (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                GetRecipeRatingsErrors::InvalidRatingFormatFromDB,
+            )
+                .into();
+            error
+        })
+    })
+    .collect::<Result<Vec<RecipeRating>, ResponseError<GetRecipeRatingsErrors>>>()?;
+    tracing::debug!("{} Got {} ratings!", tracing_prefix, ratings.len());
+
+    tracing::debug!("{} DONE", tracing_prefix);
+    Ok(Json(ratings))
+}
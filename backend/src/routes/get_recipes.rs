@@ -1,7 +1,4 @@
-use std::{
-    fmt::Display,
-    sync::{atomic::AtomicUsize, Arc},
-};
+use std::{fmt::Display, sync::Arc, time::Instant};
 
 use axum::{response::IntoResponse, Json};
 
@@ -10,9 +7,23 @@ use serde::Deserialize;
 use serde_json::Value;
 use tokio_postgres::Client;
 
+use utoipa::ToSchema;
+
 use crate::{
-    extract_jwt, is_session_valid, models::Recipe, parse_api_recipe_from_value,
-    responses::ResponseError, Params, APP_SECRET,
+    custom_recipes::{find_custom_recipes, sort_by_fridge_match, GetCustomRecipesErrors},
+    dietary::flag_dietary_violations,
+    extract_jwt,
+    households::{get_current_household, GetCurrentHouseholdErrors},
+    is_session_valid,
+    metrics::record_recipe_api_call,
+    metrics::timed_query,
+    models::{Ingredient, Recipe},
+    parse_api_recipe_from_value, parse_db_ingredient,
+    recipe_cache::cache_recipes,
+    recipe_ratings::fill_user_ratings,
+    request_id::current_request_id,
+    responses::ResponseError,
+    Params, APP_SECRET,
 };
 
 #[derive(Debug)]
@@ -23,6 +34,13 @@ pub enum GetRecipesErrors {
     NoDBConnectionFound,
     ErrorCheckingIfSessionIsValid,
     JWTExpired,
+    CouldntRetrieveIngredientsFromDB,
+    InvalidIngredientFormatFromDB,
+    CouldntRetrieveCustomRecipesFromDB,
+    InvalidCustomRecipeFormatFromDB,
+    ErrorGettingHousehold,
+    NoHouseholdSelected,
+    CouldntCheckDietaryRestrictions,
 }
 
 impl Display for GetRecipesErrors {
@@ -31,25 +49,44 @@ impl Display for GetRecipesErrors {
     }
 }
 
-#[derive(Debug, Deserialize)]
-struct GetRecipesPayload {
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct GetRecipesPayload {
     token: String,
+    /// Whether to leave out the recipes that go against the dietary restrictions of the user,
+    /// otherwise they're only flagged.
+    #[serde(default)]
+    hide_restricted: bool,
 }
 
-static ID: AtomicUsize = AtomicUsize::new(0);
-
+/// Route to get recommended recipes.
+///
+/// The recipes written by the user come first,
+/// sorted by how many of their ingredients are in the fridge.
+#[utoipa::path(
+    post,
+    path = "/v1/recipes",
+    tag = "Recipes",
+    request_body = GetRecipesPayload,
+    responses(
+        (status = 200, description = "The recommended recipes", body = [Recipe]),
+        (status = "4XX", description = "The `GetRecipesErrors` that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The `GetRecipesErrors` that made the request fail", body = String, content_type = "text/plain"),
+    )
+)]
 pub async fn get_recipes(
     payload: Json<serde_json::Value>,
     client: Arc<Option<Client>>,
     params: Arc<Params>,
 ) -> Result<impl IntoResponse, ResponseError<GetRecipesErrors>> {
-    let id = ID.fetch_add(1, std::sync::atomic::Ordering::AcqRel);
-    let tracing_prefix = format!("/recipes - {}:", id);
+    let tracing_prefix = format!("/recipes - {}:", current_request_id());
 
     tracing::debug!("{} START", tracing_prefix);
 
     tracing::debug!("{} Parsing payload...", tracing_prefix);
-    let GetRecipesPayload { token } = match serde_json::from_value(payload.0.clone()) {
+    let GetRecipesPayload {
+        token,
+        hide_restricted,
+    } = match serde_json::from_value(payload.0.clone()) {
         Ok(p) => p,
         Err(err) => {
             tracing::error!(
@@ -89,6 +126,7 @@ pub async fn get_recipes(
         tracing_prefix,
         token_info
     );
+    let user_id = token_info.user_id.clone();
 
     tracing::debug!("{} Checking if DB connection exists...", tracing_prefix);
     let conn = client.as_ref().as_ref().ok_or_else(|| {
@@ -145,10 +183,140 @@ pub async fn get_recipes(
             error
         })?;
 
+    tracing::debug!("{} Caching recipes...", tracing_prefix);
+    if let Err(err) = cache_recipes(conn, &recipes).await {
+        tracing::error!(
+            "{} An error `{:?}` occurred while caching the recipes!",
+            tracing_prefix,
+            err
+        );
+    }
+
+    tracing::debug!("{} Getting current household...", tracing_prefix);
+    let household = get_current_household(conn, &user_id).await.map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while getting the household of user `{}`!",
+            tracing_prefix,
+            err,
+            user_id
+        );
+        let error: ResponseError<_> = match err {
+            GetCurrentHouseholdErrors::NoHouseholdSelected => (
+                StatusCode::BAD_REQUEST,
+                GetRecipesErrors::NoHouseholdSelected,
+            ),
+            _ => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                GetRecipesErrors::ErrorGettingHousehold,
+            ),
+        }
+        .into();
+        error
+    })?;
+    tracing::debug!(
+        "{} Working on household `{}`!",
+        tracing_prefix,
+        household.household_id
+    );
+
+    tracing::debug!("{} Getting ingredients from DB...", tracing_prefix);
+    let fridge = timed_query(
+        "get_recipes",
+        conn.query(
+            "SELECT * FROM sf_ingredient WHERE household_id=$1 AND deleted_date IS NULL",
+            &[&household.household_id],
+        ),
+    )
+    .await
+    .map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` while trying to get ingredients for user `{}`",
+            tracing_prefix,
+            err,
+            user_id
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            GetRecipesErrors::CouldntRetrieveIngredientsFromDB,
+        )
+            .into();
+        error
+    })?
+    .iter()
+    .map(|row| {
+        parse_db_ingredient(row, &tracing_prefix).ok_or_else(|| {
+            let error: ResponseError<_> = (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                GetRecipesErrors::InvalidIngredientFormatFromDB,
+            )
+                .into();
+            error
+        })
+    })
+    .collect::<Result<Vec<Ingredient>, ResponseError<GetRecipesErrors>>>()?;
+    tracing::debug!("{} Got ingredients from user!", tracing_prefix);
+
+    tracing::debug!("{} Getting custom recipes from DB...", tracing_prefix);
+    let mut custom_recipes = find_custom_recipes(conn, &user_id, None, &tracing_prefix)
+        .await
+        .map_err(|err| {
+            tracing::error!(
+                "{} An error `{:?}` while trying to get custom recipes for user `{}`",
+                tracing_prefix,
+                err,
+                user_id
+            );
+            let error: ResponseError<_> = match err {
+                GetCustomRecipesErrors::InternalDBError(_) => (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    GetRecipesErrors::CouldntRetrieveCustomRecipesFromDB,
+                ),
+                GetCustomRecipesErrors::InvalidCustomRecipeFormatFromDB => (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    GetRecipesErrors::InvalidCustomRecipeFormatFromDB,
+                ),
+            }
+            .into();
+            error
+        })?;
+    sort_by_fridge_match(&mut custom_recipes, &fridge);
+    custom_recipes.extend(recipes);
+    tracing::debug!("{} Got custom recipes!", tracing_prefix);
+
+    tracing::debug!("{} Checking dietary restrictions...", tracing_prefix);
+    flag_dietary_violations(conn, &user_id, custom_recipes.iter_mut(), &tracing_prefix)
+        .await
+        .map_err(|err| {
+            tracing::error!(
+                "{} An error `{:?}` occurred while checking the user's dietary restrictions!",
+                tracing_prefix,
+                err
+            );
+            let error: ResponseError<_> = (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                GetRecipesErrors::CouldntCheckDietaryRestrictions,
+            )
+                .into();
+            error
+        })?;
+    if hide_restricted {
+        custom_recipes.retain(|r| r.dietary_violations.is_empty());
+    }
+
+    tracing::debug!("{} Adding the user's ratings...", tracing_prefix);
+    if let Err(err) = fill_user_ratings(conn, &user_id, custom_recipes.iter_mut()).await {
+        tracing::error!(
+            "{} An error `{:?}` occurred while getting the user's ratings!",
+            tracing_prefix,
+            err
+        );
+    }
+
     tracing::debug!("{} DONE", tracing_prefix);
-    Ok(Json(recipes))
+    Ok(Json(custom_recipes))
 }
 
+#[allow(dead_code)]
 #[derive(Debug)]
 enum GetRecipesFromAPIErrors {
     APIFormatHaschanged {
@@ -169,12 +337,15 @@ async fn get_recipes_from_api(
     api_host: &str,
 ) -> Result<Vec<Recipe>, GetRecipesFromAPIErrors> {
     let client = reqwest::Client::new();
+    let start = Instant::now();
     let response = client
         .get("https://worldwide-recipes1.p.rapidapi.com/api/explore")
         .header("X-RapidAPI-Key", api_key)
         .header("X-RapidAPI-Host", api_host)
         .send()
-        .await
+        .await;
+    record_recipe_api_call("explore", start.elapsed(), &response);
+    let response = response
         .map_err(|err| GetRecipesFromAPIErrors::APIError { error: err })?
         .text()
         .await
@@ -0,0 +1,215 @@
+use std::{fmt::Display, sync::Arc};
+
+use axum::{response::IntoResponse, Json};
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::Client;
+
+use utoipa::ToSchema;
+
+use crate::{
+    dietary::flag_dietary_violations, extract_jwt, is_session_valid, metrics::timed_query,
+    models::SavedRecipe, parse_db_saved_recipe, recipe_ratings::fill_user_ratings,
+    request_id::current_request_id, responses::ResponseError, APP_SECRET,
+};
+
+#[derive(Debug, Serialize)]
+pub enum GetSavedRecipesErrors {
+    InvalidPayload { payload: String },
+    InvalidJWT,
+    NoDBConnectionFound,
+    ErrorCheckingIfSessionIsValid,
+    JWTExpired,
+    CouldntRetrieveSavedRecipesFromDB,
+    InvalidSavedRecipeFormatFromDB,
+    CouldntCheckDietaryRestrictions,
+}
+
+impl Display for GetSavedRecipesErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// When a tag is supplied only the recipes the user tagged with it are returned.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct GetSavedRecipesPayload {
+    token: String,
+    tag: Option<String>,
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/recipes/saved",
+    tag = "Recipes",
+    request_body = GetSavedRecipesPayload,
+    responses(
+        (status = 200, description = "The saved recipes", body = [SavedRecipe]),
+        (status = "4XX", description = "The `GetSavedRecipesErrors` that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The `GetSavedRecipesErrors` that made the request fail", body = String, content_type = "text/plain"),
+    )
+)]
+pub async fn get_saved_recipes(
+    payload: Json<serde_json::Value>,
+    client: Arc<Option<Client>>,
+) -> Result<impl IntoResponse, ResponseError<GetSavedRecipesErrors>> {
+    let tracing_prefix = format!("/recipes/saved - {}:", current_request_id());
+
+    tracing::debug!("{} START", tracing_prefix);
+
+    tracing::debug!("{} Parsing payload...", tracing_prefix);
+    let GetSavedRecipesPayload { token, tag } = match serde_json::from_value(payload.0.clone()) {
+        Ok(p) => p,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred parsing payload `{}`",
+                tracing_prefix,
+                err,
+                payload.0
+            );
+            let error: ResponseError<_> = (
+                StatusCode::BAD_REQUEST,
+                GetSavedRecipesErrors::InvalidPayload {
+                    payload: payload.0.to_string(),
+                },
+            )
+                .into();
+            Err(error)?
+        }
+    };
+    tracing::debug!("{} Payload parsed successfully!", tracing_prefix);
+
+    tracing::debug!("{} Extracting JWT...", tracing_prefix);
+    let token_info = match extract_jwt(APP_SECRET, &token) {
+        Ok(t) => t,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred while extracting the JWT `{}`",
+                tracing_prefix,
+                err,
+                token
+            );
+            let error: ResponseError<_> =
+                (StatusCode::BAD_REQUEST, GetSavedRecipesErrors::InvalidJWT).into();
+            Err(error)?
+        }
+    };
+    let user_id = token_info.user_id.clone();
+    tracing::debug!("{} JWT extracted successfully!", tracing_prefix);
+
+    tracing::debug!("{} Checking for DB connection...", tracing_prefix);
+    let conn = client.as_ref().as_ref().ok_or_else(|| {
+        tracing::error!("{} No DB connection found!", tracing_prefix);
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            GetSavedRecipesErrors::NoDBConnectionFound,
+        )
+            .into();
+        error
+    })?;
+    tracing::debug!("{} DB Connection found!", tracing_prefix);
+
+    tracing::debug!("{} Checking if session is valid...", tracing_prefix);
+    if let Err(err) = is_session_valid(token_info, conn).await {
+        tracing::error!(
+            "{} An error `{:?}` occurred while checking if session is valid!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = match err {
+            crate::IsSessionValidErrors::InternalDBError(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                GetSavedRecipesErrors::ErrorCheckingIfSessionIsValid,
+            ),
+            crate::IsSessionValidErrors::InvalidSessionData {
+                current_date: _,
+                db_expire_date: _,
+            } => (StatusCode::UNAUTHORIZED, GetSavedRecipesErrors::JWTExpired),
+            _ => (
+                StatusCode::BAD_REQUEST,
+                GetSavedRecipesErrors::ErrorCheckingIfSessionIsValid,
+            ),
+        }
+        .into();
+
+        Err(error)?
+    }
+    tracing::debug!("{} Session is valid!", tracing_prefix);
+
+    tracing::debug!("{} Getting saved recipes from DB...", tracing_prefix);
+    let db_result = timed_query(
+        "get_saved_recipes",
+        conn.query(
+            "SELECT * FROM sf_saved_recipe WHERE user_id=$1 AND ($2::TEXT IS NULL OR $2 = ANY(tags)) ORDER BY saved_date DESC",
+            &[&user_id, &tag],
+        ),
+    )
+    .await
+    .map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` while trying to get saved recipes for user `{}`",
+            tracing_prefix,
+            err,
+            user_id
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            GetSavedRecipesErrors::CouldntRetrieveSavedRecipesFromDB,
+        )
+            .into();
+        error
+    })?;
+    tracing::debug!("{} Got saved recipes from user!", tracing_prefix);
+
+    tracing::debug!("{} Parsing saved recipes from db...", tracing_prefix);
+    let mut recipes = db_result
+        .iter()
+        .map(|row| {
+            parse_db_saved_recipe(row, &tracing_prefix).ok_or_else(|| {
+                let error: ResponseError<_> = (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    GetSavedRecipesErrors::InvalidSavedRecipeFormatFromDB,
+                )
+                    .into();
+                error
+            })
+        })
+        .collect::<Result<Vec<SavedRecipe>, ResponseError<GetSavedRecipesErrors>>>()?;
+    tracing::debug!("{} Saved recipes parsed!", tracing_prefix);
+
+    tracing::debug!("{} Checking dietary restrictions...", tracing_prefix);
+    flag_dietary_violations(
+        conn,
+        &user_id,
+        recipes.iter_mut().map(|r| &mut r.recipe),
+        &tracing_prefix,
+    )
+    .await
+    .map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while checking the user's dietary restrictions!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            GetSavedRecipesErrors::CouldntCheckDietaryRestrictions,
+        )
+            .into();
+        error
+    })?;
+
+    tracing::debug!("{} Adding the user's ratings...", tracing_prefix);
+    if let Err(err) =
+        fill_user_ratings(conn, &user_id, recipes.iter_mut().map(|r| &mut r.recipe)).await
+    {
+        tracing::error!(
+            "{} An error `{:?}` occurred while getting the user's ratings!",
+            tracing_prefix,
+            err
+        );
+    }
+
+    tracing::debug!("{} DONE", tracing_prefix);
+    Ok(Json(recipes))
+}
@@ -0,0 +1,187 @@
+use std::{fmt::Display, sync::Arc};
+
+use axum::{response::IntoResponse, Json};
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::Client;
+
+use utoipa::ToSchema;
+
+use crate::{
+    extract_jwt, is_session_valid, metrics::timed_query, models::UserSettings,
+    request_id::current_request_id, responses::ResponseError, APP_SECRET,
+};
+
+#[derive(Debug, Serialize)]
+pub enum GetSettingsErrors {
+    InvalidPayload { payload: String },
+    InvalidJWT,
+    NoDBConnectionFound,
+    ErrorCheckingIfSessionIsValid,
+    JWTExpired,
+    CouldntRetrieveUserSettings,
+    UserHasNoSettingsSaved,
+    UserSettingsCouldntBeParsed,
+}
+
+impl Display for GetSettingsErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct GetSettingsPayload {
+    token: String,
+}
+
+/// Route to get the settings of the user without logging in again.
+#[utoipa::path(
+    post,
+    path = "/v1/settings",
+    tag = "Settings",
+    request_body = GetSettingsPayload,
+    responses(
+        (status = 200, description = "The settings of the user", body = UserSettings),
+        (status = "4XX", description = "The `GetSettingsErrors` that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The `GetSettingsErrors` that made the request fail", body = String, content_type = "text/plain"),
+    )
+)]
+pub async fn get_settings(
+    payload: Json<serde_json::Value>,
+    client: Arc<Option<Client>>,
+) -> Result<impl IntoResponse, ResponseError<GetSettingsErrors>> {
+    let tracing_prefix = format!("/settings - {}:", current_request_id());
+
+    tracing::debug!("{} START", tracing_prefix);
+
+    tracing::debug!("{} Parsing payload...", tracing_prefix);
+    let GetSettingsPayload { token } = match serde_json::from_value(payload.0.clone()) {
+        Ok(p) => p,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred parsing payload `{}`",
+                tracing_prefix,
+                err,
+                payload.0
+            );
+            let error: ResponseError<_> = (
+                StatusCode::BAD_REQUEST,
+                GetSettingsErrors::InvalidPayload {
+                    payload: payload.0.to_string(),
+                },
+            )
+                .into();
+            Err(error)?
+        }
+    };
+    tracing::debug!("{} Payload parsed successfully!", tracing_prefix);
+
+    tracing::debug!("{} Extracting JWT...", tracing_prefix);
+    let token_info = match extract_jwt(APP_SECRET, &token) {
+        Ok(t) => t,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred while extracting the JWT `{}`",
+                tracing_prefix,
+                err,
+                token
+            );
+            let error: ResponseError<_> =
+                (StatusCode::BAD_REQUEST, GetSettingsErrors::InvalidJWT).into();
+            Err(error)?
+        }
+    };
+    let user_id = token_info.user_id.clone();
+    tracing::debug!("{} JWT extracted successfully!", tracing_prefix);
+
+    tracing::debug!("{} Checking for DB connection...", tracing_prefix);
+    let conn = client.as_ref().as_ref().ok_or_else(|| {
+        tracing::error!("{} No DB connection found!", tracing_prefix);
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            GetSettingsErrors::NoDBConnectionFound,
+        )
+            .into();
+        error
+    })?;
+    tracing::debug!("{} DB Connection found!", tracing_prefix);
+
+    tracing::debug!("{} Checking if session is valid...", tracing_prefix);
+    if let Err(err) = is_session_valid(token_info, conn).await {
+        tracing::error!(
+            "{} An error `{:?}` occurred while checking if session is valid!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = match err {
+            crate::IsSessionValidErrors::InternalDBError(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                GetSettingsErrors::ErrorCheckingIfSessionIsValid,
+            ),
+            crate::IsSessionValidErrors::InvalidSessionData {
+                current_date: _,
+                db_expire_date: _,
+            } => (StatusCode::UNAUTHORIZED, GetSettingsErrors::JWTExpired),
+            _ => (
+                StatusCode::BAD_REQUEST,
+                GetSettingsErrors::ErrorCheckingIfSessionIsValid,
+            ),
+        }
+        .into();
+
+        Err(error)?
+    }
+    tracing::debug!("{} Session is valid!", tracing_prefix);
+
+    tracing::debug!("{} Getting settings from DB...", tracing_prefix);
+    let row = timed_query(
+        "get_settings",
+        conn.query_opt("SELECT * FROM sf_settings WHERE user_id=$1", &[&user_id]),
+    )
+    .await
+    .map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while trying to retrieve user preferences!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            GetSettingsErrors::CouldntRetrieveUserSettings,
+        )
+            .into();
+        error
+    })?
+    .ok_or_else(|| {
+        tracing::error!(
+            "{} User `{}` has no settings in DB!",
+            tracing_prefix,
+            user_id
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            GetSettingsErrors::UserHasNoSettingsSaved,
+        )
+            .into();
+        error
+    })?;
+
+    let settings = UserSettings::try_from(&row).map_err(|e| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while trying to parse User Settings!",
+            tracing_prefix,
+            e
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            GetSettingsErrors::UserSettingsCouldntBeParsed,
+        )
+            .into();
+        error
+    })?;
+    tracing::debug!("{} Got settings!", tracing_prefix);
+
+    tracing::debug!("{} DONE", tracing_prefix);
+    Ok(Json(settings))
+}
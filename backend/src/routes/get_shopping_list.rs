@@ -0,0 +1,186 @@
+use std::{fmt::Display, sync::Arc};
+
+use axum::{response::IntoResponse, Json};
+
+use hyper::StatusCode;
+use serde::Deserialize;
+use tokio_postgres::Client;
+
+use utoipa::ToSchema;
+
+use crate::{
+    extract_jwt, is_session_valid, metrics::timed_query, models::ShoppingItem,
+    parse_db_shopping_item, request_id::current_request_id, responses::ResponseError, APP_SECRET,
+};
+
+#[derive(Debug)]
+pub enum GetShoppingListErrors {
+    InvalidPayloadFormat { payload: String },
+    InvalidJWT,
+    NoDBConnection,
+    InvalidSession,
+    ErrorCheckingIfSessionIsValid,
+    JWTExpired,
+    CouldntRetrieveItemsFromDB,
+    InvalidItemFormatFromDB,
+}
+
+impl Display for GetShoppingListErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct GetShoppingListPayload {
+    token: String,
+}
+
+#[utoipa::path(
+    post,
+    path = "/v1/shopping-list",
+    tag = "Shopping list",
+    request_body = GetShoppingListPayload,
+    responses(
+        (status = 200, description = "The items of the shopping list", body = [ShoppingItem]),
+        (status = "4XX", description = "The `GetShoppingListErrors` that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The `GetShoppingListErrors` that made the request fail", body = String, content_type = "text/plain"),
+    )
+)]
+pub async fn get_shopping_list(
+    payload: Json<serde_json::Value>,
+    client: Arc<Option<Client>>,
+) -> Result<impl IntoResponse, ResponseError<GetShoppingListErrors>> {
+    let tracing_prefix = format!("/shopping-list - {}:", current_request_id());
+
+    tracing::debug!("{} START", tracing_prefix);
+
+    tracing::debug!("{} Parsing payload...", tracing_prefix);
+    let GetShoppingListPayload { token } = match serde_json::from_value(payload.0.clone()) {
+        Ok(p) => p,
+        Err(err) => {
+            tracing::error!(
+                "{} An error {:?} occurred while parsing the payload `{}`",
+                tracing_prefix,
+                err,
+                payload.0
+            );
+            let error: ResponseError<_> = (
+                StatusCode::BAD_REQUEST,
+                GetShoppingListErrors::InvalidPayloadFormat {
+                    payload: payload.0.to_string(),
+                },
+            )
+                .into();
+            Err(error)?
+        }
+    };
+    tracing::debug!("{} Payload parsed successfully!", tracing_prefix);
+
+    tracing::debug!("{} Extracting JWT...", tracing_prefix);
+    let token_info = match extract_jwt(APP_SECRET, &token) {
+        Ok(t) => t,
+        Err(_) => {
+            tracing::error!(
+                "{} An error occurred while extracting the JWT `{}`",
+                tracing_prefix,
+                token
+            );
+            let error: ResponseError<_> =
+                (StatusCode::BAD_REQUEST, GetShoppingListErrors::InvalidJWT).into();
+            Err(error)?
+        }
+    };
+    let user_id = token_info.user_id.clone();
+    tracing::debug!("{} JWT extracted successfully!", tracing_prefix);
+
+    let conn = client.as_ref().as_ref().ok_or_else(|| {
+        tracing::error!("{} No DB connection found!", tracing_prefix);
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            GetShoppingListErrors::NoDBConnection,
+        )
+            .into();
+        error
+    })?;
+
+    tracing::debug!("{} DB Connection found!", tracing_prefix);
+
+    tracing::debug!("{} Checking if session is valid...", tracing_prefix);
+    match is_session_valid(token_info, conn).await {
+        Ok(_) => {}
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred while trying to check if session is valid!",
+                tracing_prefix,
+                err
+            );
+
+            let error: ResponseError<_> = match err {
+                crate::IsSessionValidErrors::InternalDBError(_) => (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    GetShoppingListErrors::ErrorCheckingIfSessionIsValid,
+                ),
+                crate::IsSessionValidErrors::InvalidSessionData {
+                    current_date: _,
+                    db_expire_date: _,
+                } => (
+                    StatusCode::UNAUTHORIZED,
+                    GetShoppingListErrors::InvalidSession,
+                ),
+                _ => (
+                    StatusCode::BAD_REQUEST,
+                    GetShoppingListErrors::ErrorCheckingIfSessionIsValid,
+                ),
+            }
+            .into();
+
+            Err(error)?
+        }
+    }
+    tracing::debug!("{} Session is valid!", tracing_prefix);
+
+    tracing::debug!("{} Getting shopping list from DB...", tracing_prefix);
+    let db_result = timed_query(
+        "get_shopping_list",
+        conn.query(
+            "SELECT * FROM sf_shopping_item WHERE user_id=$1 ORDER BY checked, name",
+            &[&user_id.to_string()],
+        ),
+    )
+    .await
+    .map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` while trying to get the shopping list for user `{}`",
+            tracing_prefix,
+            err,
+            user_id
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            GetShoppingListErrors::CouldntRetrieveItemsFromDB,
+        )
+            .into();
+        error
+    })?;
+    tracing::debug!("{} Got shopping list from user!", tracing_prefix);
+
+    tracing::debug!("{} Parsing items from db...", tracing_prefix);
+    let items = db_result
+        .iter()
+        .map(|row| {
+            parse_db_shopping_item(row, &tracing_prefix).ok_or_else(|| {
+                let error: ResponseError<_> = (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    GetShoppingListErrors::InvalidItemFormatFromDB,
+                )
+                    .into();
+                error
+            })
+        })
+        .collect::<Result<Vec<ShoppingItem>, ResponseError<GetShoppingListErrors>>>()?;
+    tracing::debug!("{} Items parsed!", tracing_prefix);
+
+    tracing::debug!("{} DONE", tracing_prefix);
+    Ok(Json(items))
+}
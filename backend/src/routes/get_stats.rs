@@ -0,0 +1,250 @@
+use std::{fmt::Display, sync::Arc};
+
+use axum::{response::IntoResponse, Json};
+use chrono::{DateTime, Utc};
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::Client;
+
+use utoipa::ToSchema;
+
+use crate::{
+    extract_jwt,
+    households::{get_current_household, GetCurrentHouseholdErrors},
+    is_session_valid,
+    metrics::timed_query,
+    models::{InventoryEvent, InventoryEventType, StatsPeriod},
+    parse_db_inventory_event,
+    request_id::current_request_id,
+    responses::ResponseError,
+    stats::{default_from_date, waste_stats},
+    APP_SECRET,
+};
+
+#[derive(Debug, Serialize)]
+pub enum GetStatsErrors {
+    InvalidPayload { payload: String },
+    InvalidJWT,
+    NoDBConnectionFound,
+    ErrorCheckingIfSessionIsValid,
+    JWTExpired,
+    ErrorGettingHousehold,
+    NoHouseholdSelected,
+    InvalidDateRange,
+    CouldntRetrieveEventsFromDB,
+    InvalidEventFormatFromDB,
+}
+
+impl Display for GetStatsErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct GetStatsPayload {
+    token: String,
+    #[serde(default)]
+    period: StatsPeriod,
+    /// When missing the statistics cover the last periods until `to_date`.
+    from_date: Option<DateTime<Utc>>,
+    /// When missing the statistics cover until now.
+    to_date: Option<DateTime<Utc>>,
+}
+
+/// Route to get statistics about the food the current household adds, eats and wastes.
+///
+/// The statistics are built from the inventory history, grouped by week or month.
+/// Removals undone by restoring the ingredient from the trash aren't counted.
+#[utoipa::path(
+    post,
+    path = "/v1/stats",
+    tag = "Statistics",
+    request_body = GetStatsPayload,
+    responses(
+        (status = 200, description = "The statistics of the household", body = WasteStats),
+        (status = "4XX", description = "The `GetStatsErrors` that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The `GetStatsErrors` that made the request fail", body = String, content_type = "text/plain"),
+    )
+)]
+pub async fn get_stats(
+    payload: Json<serde_json::Value>,
+    client: Arc<Option<Client>>,
+) -> Result<impl IntoResponse, ResponseError<GetStatsErrors>> {
+    let tracing_prefix = format!("/stats - {}:", current_request_id());
+
+    tracing::debug!("{} START", tracing_prefix);
+
+    tracing::debug!("{} Parsing payload...", tracing_prefix);
+    let GetStatsPayload {
+        token,
+        period,
+        from_date,
+        to_date,
+    } = match serde_json::from_value(payload.0.clone()) {
+        Ok(p) => p,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred parsing payload `{}`",
+                tracing_prefix,
+                err,
+                payload.0
+            );
+            let error: ResponseError<_> = (
+                StatusCode::BAD_REQUEST,
+                GetStatsErrors::InvalidPayload {
+                    payload: payload.0.to_string(),
+                },
+            )
+                .into();
+            Err(error)?
+        }
+    };
+    tracing::debug!("{} Payload parsed successfully!", tracing_prefix);
+
+    tracing::debug!("{} Extracting JWT...", tracing_prefix);
+    let token_info = match extract_jwt(APP_SECRET, &token) {
+        Ok(t) => t,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred while extracting the JWT `{}`",
+                tracing_prefix,
+                err,
+                token
+            );
+            let error: ResponseError<_> =
+                (StatusCode::BAD_REQUEST, GetStatsErrors::InvalidJWT).into();
+            Err(error)?
+        }
+    };
+    let user_id = token_info.user_id.clone();
+    tracing::debug!("{} JWT extracted successfully!", tracing_prefix);
+
+    tracing::debug!("{} Checking for DB connection...", tracing_prefix);
+    let conn = client.as_ref().as_ref().ok_or_else(|| {
+        tracing::error!("{} No DB connection found!", tracing_prefix);
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            GetStatsErrors::NoDBConnectionFound,
+        )
+            .into();
+        error
+    })?;
+    tracing::debug!("{} DB Connection found!", tracing_prefix);
+
+    tracing::debug!("{} Checking if session is valid...", tracing_prefix);
+    if let Err(err) = is_session_valid(token_info, conn).await {
+        tracing::error!(
+            "{} An error `{:?}` occurred while checking if session is valid!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = match err {
+            crate::IsSessionValidErrors::InternalDBError(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                GetStatsErrors::ErrorCheckingIfSessionIsValid,
+            ),
+            crate::IsSessionValidErrors::InvalidSessionData {
+                current_date: _,
+                db_expire_date: _,
+            } => (StatusCode::UNAUTHORIZED, GetStatsErrors::JWTExpired),
+            _ => (
+                StatusCode::BAD_REQUEST,
+                GetStatsErrors::ErrorCheckingIfSessionIsValid,
+            ),
+        }
+        .into();
+
+        Err(error)?
+    }
+    tracing::debug!("{} Session is valid!", tracing_prefix);
+
+    tracing::debug!("{} Getting current household...", tracing_prefix);
+    let household = get_current_household(conn, &user_id).await.map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while getting the household of user `{}`!",
+            tracing_prefix,
+            err,
+            user_id
+        );
+        let error: ResponseError<_> = match err {
+            GetCurrentHouseholdErrors::NoHouseholdSelected => {
+                (StatusCode::BAD_REQUEST, GetStatsErrors::NoHouseholdSelected)
+            }
+            _ => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                GetStatsErrors::ErrorGettingHousehold,
+            ),
+        }
+        .into();
+        error
+    })?;
+    tracing::debug!(
+        "{} Working on household `{}`!",
+        tracing_prefix,
+        household.household_id
+    );
+
+    let to_date = to_date.unwrap_or_else(Utc::now);
+    let from_date = from_date.unwrap_or_else(|| default_from_date(period, to_date));
+    if from_date >= to_date {
+        tracing::error!(
+            "{} The range `{}` - `{}` is invalid!",
+            tracing_prefix,
+            from_date,
+            to_date
+        );
+        let error: ResponseError<_> =
+            (StatusCode::BAD_REQUEST, GetStatsErrors::InvalidDateRange).into();
+        Err(error)?
+    }
+
+    tracing::debug!("{} Getting events from DB...", tracing_prefix);
+    let events = timed_query(
+        "get_stats",
+        conn.query(
+            "SELECT * FROM sf_inventory_event WHERE household_id=$1 AND event_date >= $2 AND (event_date < $3 OR event_type=$4)",
+            &[
+                &household.household_id,
+                &from_date,
+                &to_date,
+                &format!("{:?}", InventoryEventType::Restored),
+            ],
+        ),
+    )
+    .await
+    .map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` while trying to get the history of household `{}`",
+            tracing_prefix,
+            err,
+            household.household_id
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            GetStatsErrors::CouldntRetrieveEventsFromDB,
+        )
+            .into();
+        error
+    })?
+    .iter()
+    .map(|row| {
+        parse_db_inventory_event(row, &tracing_prefix).ok_or_else(|| {
+            let error: ResponseError<_> = (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                GetStatsErrors::InvalidEventFormatFromDB,
+            )
+                .into();
+            error
+        })
+    })
+    .collect::<Result<Vec<InventoryEvent>, ResponseError<GetStatsErrors>>>()?;
+    tracing::debug!("{} Got {} events!", tracing_prefix, events.len());
+
+    tracing::debug!("{} Computing statistics...", tracing_prefix);
+    let stats = waste_stats(&events, period, from_date, to_date);
+    tracing::debug!("{} Statistics computed!", tracing_prefix);
+
+    tracing::debug!("{} DONE", tracing_prefix);
+    Ok(Json(stats))
+}
@@ -0,0 +1,222 @@
+use std::{fmt::Display, sync::Arc};
+
+use axum::{response::IntoResponse, Json};
+use chrono::Duration;
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::Client;
+
+use utoipa::ToSchema;
+
+use crate::{
+    extract_jwt,
+    households::{get_current_household, GetCurrentHouseholdErrors},
+    is_session_valid,
+    metrics::timed_query,
+    models::TrashedIngredient,
+    parse_db_ingredient,
+    request_id::current_request_id,
+    responses::ResponseError,
+    Params, APP_SECRET,
+};
+
+#[derive(Debug, Serialize)]
+pub enum GetTrashErrors {
+    InvalidPayload { payload: String },
+    InvalidJWT,
+    NoDBConnectionFound,
+    ErrorCheckingIfSessionIsValid,
+    JWTExpired,
+    ErrorGettingHousehold,
+    NoHouseholdSelected,
+    CouldntRetrieveIngredientsFromDB,
+    InvalidIngredientFormatFromDB,
+}
+
+impl Display for GetTrashErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct GetTrashPayload {
+    token: String,
+}
+
+/// Route to get the ingredients of the current household that are in the trash, the most recently removed first.
+#[utoipa::path(
+    post,
+    path = "/v1/ingredients/trash",
+    tag = "Ingredients",
+    request_body = GetTrashPayload,
+    responses(
+        (status = 200, description = "The ingredients in the trash", body = [TrashedIngredient]),
+        (status = "4XX", description = "The `GetTrashErrors` that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The `GetTrashErrors` that made the request fail", body = String, content_type = "text/plain"),
+    )
+)]
+pub async fn get_trash(
+    payload: Json<serde_json::Value>,
+    client: Arc<Option<Client>>,
+    params: Arc<Params>,
+) -> Result<impl IntoResponse, ResponseError<GetTrashErrors>> {
+    let tracing_prefix = format!("/ingredients/trash - {}:", current_request_id());
+
+    tracing::debug!("{} START", tracing_prefix);
+
+    tracing::debug!("{} Parsing payload...", tracing_prefix);
+    let GetTrashPayload { token } = match serde_json::from_value(payload.0.clone()) {
+        Ok(p) => p,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred parsing payload `{}`",
+                tracing_prefix,
+                err,
+                payload.0
+            );
+            let error: ResponseError<_> = (
+                StatusCode::BAD_REQUEST,
+                GetTrashErrors::InvalidPayload {
+                    payload: payload.0.to_string(),
+                },
+            )
+                .into();
+            Err(error)?
+        }
+    };
+    tracing::debug!("{} Payload parsed successfully!", tracing_prefix);
+
+    tracing::debug!("{} Extracting JWT...", tracing_prefix);
+    let token_info = match extract_jwt(APP_SECRET, &token) {
+        Ok(t) => t,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred while extracting the JWT `{}`",
+                tracing_prefix,
+                err,
+                token
+            );
+            let error: ResponseError<_> =
+                (StatusCode::BAD_REQUEST, GetTrashErrors::InvalidJWT).into();
+            Err(error)?
+        }
+    };
+    let user_id = token_info.user_id.clone();
+    tracing::debug!("{} JWT extracted successfully!", tracing_prefix);
+
+    tracing::debug!("{} Checking for DB connection...", tracing_prefix);
+    let conn = client.as_ref().as_ref().ok_or_else(|| {
+        tracing::error!("{} No DB connection found!", tracing_prefix);
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            GetTrashErrors::NoDBConnectionFound,
+        )
+            .into();
+        error
+    })?;
+    tracing::debug!("{} DB Connection found!", tracing_prefix);
+
+    tracing::debug!("{} Checking if session is valid...", tracing_prefix);
+    if let Err(err) = is_session_valid(token_info, conn).await {
+        tracing::error!(
+            "{} An error `{:?}` occurred while checking if session is valid!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = match err {
+            crate::IsSessionValidErrors::InternalDBError(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                GetTrashErrors::ErrorCheckingIfSessionIsValid,
+            ),
+            crate::IsSessionValidErrors::InvalidSessionData {
+                current_date: _,
+                db_expire_date: _,
+            } => (StatusCode::UNAUTHORIZED, GetTrashErrors::JWTExpired),
+            _ => (
+                StatusCode::BAD_REQUEST,
+                GetTrashErrors::ErrorCheckingIfSessionIsValid,
+            ),
+        }
+        .into();
+
+        Err(error)?
+    }
+    tracing::debug!("{} Session is valid!", tracing_prefix);
+
+    tracing::debug!("{} Getting current household...", tracing_prefix);
+    let household = get_current_household(conn, &user_id).await.map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while getting the household of user `{}`!",
+            tracing_prefix,
+            err,
+            user_id
+        );
+        let error: ResponseError<_> = match err {
+            GetCurrentHouseholdErrors::NoHouseholdSelected => {
+                (StatusCode::BAD_REQUEST, GetTrashErrors::NoHouseholdSelected)
+            }
+            _ => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                GetTrashErrors::ErrorGettingHousehold,
+            ),
+        }
+        .into();
+        error
+    })?;
+    tracing::debug!(
+        "{} Working on household `{}`!",
+        tracing_prefix,
+        household.household_id
+    );
+
+    tracing::debug!("{} Getting trashed ingredients from DB...", tracing_prefix);
+    let ingredients = timed_query(
+        "get_trash",
+        conn.query(
+            "SELECT * FROM sf_ingredient WHERE household_id=$1 AND deleted_date IS NOT NULL ORDER BY deleted_date DESC",
+            &[&household.household_id],
+        ),
+    )
+    .await
+    .map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` while trying to get the trash of household `{}`",
+            tracing_prefix,
+            err,
+            household.household_id
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            GetTrashErrors::CouldntRetrieveIngredientsFromDB,
+        )
+            .into();
+        error
+    })?
+    .iter()
+    .map(|row| {
+        let ingredient = parse_db_ingredient(row, &tracing_prefix).ok_or_else(|| {
+            let error: ResponseError<_> = (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                GetTrashErrors::InvalidIngredientFormatFromDB,
+            )
+                .into();
+            error
+        })?;
+        let deleted_date = row.get("deleted_date");
+        Ok(TrashedIngredient {
+            ingredient,
+            deleted_date,
+            purge_date: deleted_date + Duration::days(params.trash_retention_days),
+        })
+    })
+    .collect::<Result<Vec<TrashedIngredient>, ResponseError<GetTrashErrors>>>()?;
+    tracing::debug!(
+        "{} Got {} trashed ingredients!",
+        tracing_prefix,
+        ingredients.len()
+    );
+
+    tracing::debug!("{} DONE", tracing_prefix);
+    Ok(Json(ingredients))
+}
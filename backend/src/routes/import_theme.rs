@@ -0,0 +1,248 @@
+use std::{fmt::Display, sync::Arc};
+
+use axum::{response::IntoResponse, Json};
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::Client;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    extract_jwt, generate_share_code, is_session_valid, metrics::timed_query, models::CustomTheme,
+    parse_db_custom_theme, request_id::current_request_id, responses::ResponseError,
+    themes::ThemePalette, APP_SECRET,
+};
+
+#[derive(Debug, Serialize)]
+pub enum ImportThemeErrors {
+    InvalidPayload { payload: String },
+    InvalidJWT,
+    NoDBConnectionFound,
+    ErrorCheckingIfSessionIsValid,
+    JWTExpired,
+    ErrorGettingSharedTheme,
+    ThemeNotFound,
+    InvalidThemeFormatFromDB,
+    ErrorImportingTheme,
+}
+
+impl Display for ImportThemeErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ImportThemePayload {
+    token: String,
+    share_code: String,
+}
+
+/// Route to copy the theme of another user with its share code.
+///
+/// The copy gets its own share code and doesn't change when the original does.
+#[utoipa::path(
+    post,
+    path = "/v1/themes/import",
+    tag = "Themes",
+    request_body = ImportThemePayload,
+    responses(
+        (status = 200, description = "The copy of the shared theme", body = CustomTheme),
+        (status = "4XX", description = "The `ImportThemeErrors` that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The `ImportThemeErrors` that made the request fail", body = String, content_type = "text/plain"),
+    )
+)]
+pub async fn import_theme(
+    payload: Json<serde_json::Value>,
+    client: Arc<Option<Client>>,
+) -> Result<impl IntoResponse, ResponseError<ImportThemeErrors>> {
+    let tracing_prefix = format!("/themes/import - {}:", current_request_id());
+
+    tracing::debug!("{} START", tracing_prefix);
+
+    tracing::debug!("{} Parsing payload...", tracing_prefix);
+    let ImportThemePayload { token, share_code } = match serde_json::from_value(payload.0.clone()) {
+        Ok(p) => p,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred parsing payload `{}`",
+                tracing_prefix,
+                err,
+                payload.0
+            );
+            let error: ResponseError<_> = (
+                StatusCode::BAD_REQUEST,
+                ImportThemeErrors::InvalidPayload {
+                    payload: payload.0.to_string(),
+                },
+            )
+                .into();
+            Err(error)?
+        }
+    };
+    tracing::debug!("{} Payload parsed successfully!", tracing_prefix);
+
+    tracing::debug!("{} Extracting JWT...", tracing_prefix);
+    let token_info = match extract_jwt(APP_SECRET, &token) {
+        Ok(t) => t,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred while extracting the JWT `{}`",
+                tracing_prefix,
+                err,
+                token
+            );
+            let error: ResponseError<_> =
+                (StatusCode::BAD_REQUEST, ImportThemeErrors::InvalidJWT).into();
+            Err(error)?
+        }
+    };
+    let user_id = token_info.user_id.clone();
+    tracing::debug!("{} JWT extracted successfully!", tracing_prefix);
+
+    tracing::debug!("{} Checking for DB connection...", tracing_prefix);
+    let conn = client.as_ref().as_ref().ok_or_else(|| {
+        tracing::error!("{} No DB connection found!", tracing_prefix);
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ImportThemeErrors::NoDBConnectionFound,
+        )
+            .into();
+        error
+    })?;
+    tracing::debug!("{} DB Connection found!", tracing_prefix);
+
+    tracing::debug!("{} Checking if session is valid...", tracing_prefix);
+    if let Err(err) = is_session_valid(token_info, conn).await {
+        tracing::error!(
+            "{} An error `{:?}` occurred while checking if session is valid!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = match err {
+            crate::IsSessionValidErrors::InternalDBError(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ImportThemeErrors::ErrorCheckingIfSessionIsValid,
+            ),
+            crate::IsSessionValidErrors::InvalidSessionData {
+                current_date: _,
+                db_expire_date: _,
+            } => (StatusCode::UNAUTHORIZED, ImportThemeErrors::JWTExpired),
+            _ => (
+                StatusCode::BAD_REQUEST,
+                ImportThemeErrors::ErrorCheckingIfSessionIsValid,
+            ),
+        }
+        .into();
+
+        Err(error)?
+    }
+    tracing::debug!("{} Session is valid!", tracing_prefix);
+
+    tracing::debug!("{} Getting shared theme...", tracing_prefix);
+    let row = timed_query(
+        "import_theme",
+        conn.query_opt(
+            "SELECT * FROM sf_theme WHERE share_code=$1",
+            &[&share_code.trim().to_uppercase()],
+        ),
+    )
+    .await
+    .map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while getting theme with code `{}`!",
+            tracing_prefix,
+            err,
+            share_code
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ImportThemeErrors::ErrorGettingSharedTheme,
+        )
+            .into();
+        error
+    })?
+    .ok_or_else(|| {
+        tracing::error!(
+            "{} No theme found with code `{}`!",
+            tracing_prefix,
+            share_code
+        );
+        let error: ResponseError<_> =
+            (StatusCode::NOT_FOUND, ImportThemeErrors::ThemeNotFound).into();
+        error
+    })?;
+    let CustomTheme {
+        name,
+        primary,
+        secondary,
+        background,
+        surface,
+        ..
+    } = parse_db_custom_theme(&row, &tracing_prefix).ok_or_else(|| {
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ImportThemeErrors::InvalidThemeFormatFromDB,
+        )
+            .into();
+        error
+    })?;
+    tracing::debug!("{} Got theme `{}`!", tracing_prefix, name);
+
+    let palette = ThemePalette {
+        primary,
+        secondary,
+        background,
+        surface,
+    };
+    let theme = CustomTheme {
+        theme_id: Uuid::new_v4(),
+        name,
+        primary: palette.primary,
+        secondary: palette.secondary,
+        background: palette.background,
+        surface: palette.surface,
+        share_code: generate_share_code(),
+    };
+
+    tracing::debug!("{} Copying theme `{}`...", tracing_prefix, theme.name);
+    if let Err(err) = timed_query(
+        "import_theme",
+        conn.execute(
+            "INSERT INTO sf_theme (theme_id, user_id, name, primary_color, secondary_color, background_color, surface_color, share_code) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+            &[
+                &theme.theme_id.to_string(),
+                &user_id,
+                &theme.name,
+                &theme.primary,
+                &theme.secondary,
+                &theme.background,
+                &theme.surface,
+                &theme.share_code,
+            ],
+        ),
+    )
+    .await
+    {
+        tracing::error!(
+            "{} An error `{:?}` occurred while saving theme `{}`!",
+            tracing_prefix,
+            err,
+            theme.name
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            ImportThemeErrors::ErrorImportingTheme,
+        )
+            .into();
+        Err(error)?
+    }
+    tracing::debug!(
+        "{} Theme saved with id `{}`!",
+        tracing_prefix,
+        theme.theme_id
+    );
+
+    tracing::debug!("{} DONE", tracing_prefix);
+    Ok(Json(theme))
+}
@@ -0,0 +1,246 @@
+use std::{fmt::Display, sync::Arc};
+
+use axum::{response::IntoResponse, Json};
+use chrono::{Duration, Utc};
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::Client;
+
+use utoipa::ToSchema;
+
+use crate::{
+    extract_jwt, generate_share_code,
+    households::{get_current_household, GetCurrentHouseholdErrors},
+    is_session_valid,
+    metrics::timed_query,
+    models::{HouseholdInvitation, HouseholdRole},
+    request_id::current_request_id,
+    responses::ResponseError,
+    APP_SECRET,
+};
+
+#[derive(Debug, Serialize)]
+pub enum InviteToHouseholdErrors {
+    InvalidPayload { payload: String },
+    InvalidJWT,
+    NoDBConnectionFound,
+    ErrorCheckingIfSessionIsValid,
+    JWTExpired,
+    ErrorGettingHousehold,
+    NoHouseholdSelected,
+    NotHouseholdOwner,
+    InvalidRole,
+    ErrorCreatingInvitation,
+}
+
+impl Display for InviteToHouseholdErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// When no role is supplied the users joining are members.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct InviteToHouseholdPayload {
+    token: String,
+    role: Option<HouseholdRole>,
+}
+
+/// How long the invitation codes can be used.
+const INVITATION_DAYS: i64 = 7;
+
+/// Route to create a code other users can use to join the current household.
+///
+/// Only the owner can invite and nobody can join as owner.
+#[utoipa::path(
+    post,
+    path = "/v1/households/invite",
+    tag = "Households",
+    request_body = InviteToHouseholdPayload,
+    responses(
+        (status = 200, description = "The invitation to share", body = HouseholdInvitation),
+        (status = "4XX", description = "The `InviteToHouseholdErrors` that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The `InviteToHouseholdErrors` that made the request fail", body = String, content_type = "text/plain"),
+    )
+)]
+pub async fn invite_to_household(
+    payload: Json<serde_json::Value>,
+    client: Arc<Option<Client>>,
+) -> Result<impl IntoResponse, ResponseError<InviteToHouseholdErrors>> {
+    let tracing_prefix = format!("/households/invite - {}:", current_request_id());
+
+    tracing::debug!("{} START", tracing_prefix);
+
+    tracing::debug!("{} Parsing payload...", tracing_prefix);
+    let InviteToHouseholdPayload { token, role } = match serde_json::from_value(payload.0.clone()) {
+        Ok(p) => p,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred parsing payload `{}`",
+                tracing_prefix,
+                err,
+                payload.0
+            );
+            let error: ResponseError<_> = (
+                StatusCode::BAD_REQUEST,
+                InviteToHouseholdErrors::InvalidPayload {
+                    payload: payload.0.to_string(),
+                },
+            )
+                .into();
+            Err(error)?
+        }
+    };
+    tracing::debug!("{} Payload parsed successfully!", tracing_prefix);
+
+    tracing::debug!("{} Extracting JWT...", tracing_prefix);
+    let token_info = match extract_jwt(APP_SECRET, &token) {
+        Ok(t) => t,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred while extracting the JWT `{}`",
+                tracing_prefix,
+                err,
+                token
+            );
+            let error: ResponseError<_> =
+                (StatusCode::BAD_REQUEST, InviteToHouseholdErrors::InvalidJWT).into();
+            Err(error)?
+        }
+    };
+    let user_id = token_info.user_id.clone();
+    tracing::debug!("{} JWT extracted successfully!", tracing_prefix);
+
+    tracing::debug!("{} Checking for DB connection...", tracing_prefix);
+    let conn = client.as_ref().as_ref().ok_or_else(|| {
+        tracing::error!("{} No DB connection found!", tracing_prefix);
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            InviteToHouseholdErrors::NoDBConnectionFound,
+        )
+            .into();
+        error
+    })?;
+    tracing::debug!("{} DB Connection found!", tracing_prefix);
+
+    tracing::debug!("{} Checking if session is valid...", tracing_prefix);
+    if let Err(err) = is_session_valid(token_info, conn).await {
+        tracing::error!(
+            "{} An error `{:?}` occurred while checking if session is valid!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = match err {
+            crate::IsSessionValidErrors::InternalDBError(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                InviteToHouseholdErrors::ErrorCheckingIfSessionIsValid,
+            ),
+            crate::IsSessionValidErrors::InvalidSessionData {
+                current_date: _,
+                db_expire_date: _,
+            } => (
+                StatusCode::UNAUTHORIZED,
+                InviteToHouseholdErrors::JWTExpired,
+            ),
+            _ => (
+                StatusCode::BAD_REQUEST,
+                InviteToHouseholdErrors::ErrorCheckingIfSessionIsValid,
+            ),
+        }
+        .into();
+
+        Err(error)?
+    }
+    tracing::debug!("{} Session is valid!", tracing_prefix);
+
+    tracing::debug!("{} Getting current household...", tracing_prefix);
+    let household = get_current_household(conn, &user_id).await.map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while getting the household of user `{}`!",
+            tracing_prefix,
+            err,
+            user_id
+        );
+        let error: ResponseError<_> = match err {
+            GetCurrentHouseholdErrors::NoHouseholdSelected => (
+                StatusCode::BAD_REQUEST,
+                InviteToHouseholdErrors::NoHouseholdSelected,
+            ),
+            _ => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                InviteToHouseholdErrors::ErrorGettingHousehold,
+            ),
+        }
+        .into();
+        error
+    })?;
+    if household.role != HouseholdRole::Owner {
+        tracing::error!(
+            "{} The user isn't the owner of household `{}`!",
+            tracing_prefix,
+            household.household_id
+        );
+        let error: ResponseError<_> = (
+            StatusCode::FORBIDDEN,
+            InviteToHouseholdErrors::NotHouseholdOwner,
+        )
+            .into();
+        Err(error)?
+    }
+    tracing::debug!(
+        "{} Working on household `{}`!",
+        tracing_prefix,
+        household.household_id
+    );
+
+    let role = role.unwrap_or(HouseholdRole::Member);
+    if role == HouseholdRole::Owner {
+        tracing::error!("{} Nobody can join as owner!", tracing_prefix);
+        let error: ResponseError<_> = (
+            StatusCode::BAD_REQUEST,
+            InviteToHouseholdErrors::InvalidRole,
+        )
+            .into();
+        Err(error)?
+    }
+
+    let invitation = HouseholdInvitation {
+        code: generate_share_code(),
+        role,
+        expire_date: Utc::now() + Duration::days(INVITATION_DAYS),
+    };
+
+    tracing::debug!("{} Creating invitation...", tracing_prefix);
+    if let Err(err) = timed_query(
+        "invite_to_household",
+        conn.execute(
+            "INSERT INTO sf_household_invitation (code, household_id, role, created_by, expire_date) VALUES ($1, $2, $3, $4, $5)",
+            &[
+                &invitation.code,
+                &household.household_id,
+                &format!("{:?}", invitation.role),
+                &user_id,
+                &invitation.expire_date,
+            ],
+        ),
+    )
+    .await
+    {
+        tracing::error!(
+            "{} An error `{:?}` occurred while creating an invitation to household `{}`!",
+            tracing_prefix,
+            err,
+            household.household_id
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            InviteToHouseholdErrors::ErrorCreatingInvitation,
+        )
+            .into();
+        Err(error)?
+    }
+    tracing::debug!("{} Invitation created!", tracing_prefix);
+
+    tracing::debug!("{} DONE", tracing_prefix);
+    Ok(Json(invitation))
+}
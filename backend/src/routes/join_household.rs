@@ -0,0 +1,188 @@
+use std::{fmt::Display, sync::Arc};
+
+use axum::{response::IntoResponse, Json};
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::Client;
+
+use utoipa::ToSchema;
+
+use crate::{
+    extract_jwt, is_session_valid, metrics::timed_query, request_id::current_request_id,
+    responses::ResponseError, APP_SECRET,
+};
+
+#[derive(Debug, Serialize)]
+pub enum JoinHouseholdErrors {
+    InvalidPayload { payload: String },
+    InvalidJWT,
+    NoDBConnectionFound,
+    ErrorCheckingIfSessionIsValid,
+    JWTExpired,
+    InvitationNotFound,
+    ErrorJoiningHousehold,
+}
+
+impl Display for JoinHouseholdErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct JoinHouseholdPayload {
+    token: String,
+    code: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct JoinHouseholdResponse {
+    #[serde(rename = "HouseholdId")]
+    pub household_id: String,
+}
+
+/// Route to join a household with an invitation code.
+///
+/// The household becomes the one the ingredient routes work on,
+/// users that already belonged to it keep their role.
+#[utoipa::path(
+    post,
+    path = "/v1/households/join",
+    tag = "Households",
+    request_body = JoinHouseholdPayload,
+    responses(
+        (status = 200, description = "The user joined the household", body = JoinHouseholdResponse),
+        (status = "4XX", description = "The `JoinHouseholdErrors` that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The `JoinHouseholdErrors` that made the request fail", body = String, content_type = "text/plain"),
+    )
+)]
+pub async fn join_household(
+    payload: Json<serde_json::Value>,
+    client: Arc<Option<Client>>,
+) -> Result<impl IntoResponse, ResponseError<JoinHouseholdErrors>> {
+    let tracing_prefix = format!("/households/join - {}:", current_request_id());
+
+    tracing::debug!("{} START", tracing_prefix);
+
+    tracing::debug!("{} Parsing payload...", tracing_prefix);
+    let JoinHouseholdPayload { token, code } = match serde_json::from_value(payload.0.clone()) {
+        Ok(p) => p,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred parsing payload `{}`",
+                tracing_prefix,
+                err,
+                payload.0
+            );
+            let error: ResponseError<_> = (
+                StatusCode::BAD_REQUEST,
+                JoinHouseholdErrors::InvalidPayload {
+                    payload: payload.0.to_string(),
+                },
+            )
+                .into();
+            Err(error)?
+        }
+    };
+    tracing::debug!("{} Payload parsed successfully!", tracing_prefix);
+
+    tracing::debug!("{} Extracting JWT...", tracing_prefix);
+    let token_info = match extract_jwt(APP_SECRET, &token) {
+        Ok(t) => t,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred while extracting the JWT `{}`",
+                tracing_prefix,
+                err,
+                token
+            );
+            let error: ResponseError<_> =
+                (StatusCode::BAD_REQUEST, JoinHouseholdErrors::InvalidJWT).into();
+            Err(error)?
+        }
+    };
+    let user_id = token_info.user_id.clone();
+    tracing::debug!("{} JWT extracted successfully!", tracing_prefix);
+
+    tracing::debug!("{} Checking for DB connection...", tracing_prefix);
+    let conn = client.as_ref().as_ref().ok_or_else(|| {
+        tracing::error!("{} No DB connection found!", tracing_prefix);
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            JoinHouseholdErrors::NoDBConnectionFound,
+        )
+            .into();
+        error
+    })?;
+    tracing::debug!("{} DB Connection found!", tracing_prefix);
+
+    tracing::debug!("{} Checking if session is valid...", tracing_prefix);
+    if let Err(err) = is_session_valid(token_info, conn).await {
+        tracing::error!(
+            "{} An error `{:?}` occurred while checking if session is valid!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = match err {
+            crate::IsSessionValidErrors::InternalDBError(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                JoinHouseholdErrors::ErrorCheckingIfSessionIsValid,
+            ),
+            crate::IsSessionValidErrors::InvalidSessionData {
+                current_date: _,
+                db_expire_date: _,
+            } => (StatusCode::UNAUTHORIZED, JoinHouseholdErrors::JWTExpired),
+            _ => (
+                StatusCode::BAD_REQUEST,
+                JoinHouseholdErrors::ErrorCheckingIfSessionIsValid,
+            ),
+        }
+        .into();
+
+        Err(error)?
+    }
+    tracing::debug!("{} Session is valid!", tracing_prefix);
+
+    tracing::debug!(
+        "{} Joining household with code `{}`...",
+        tracing_prefix,
+        code
+    );
+    let row = timed_query(
+        "join_household",
+        conn.query_opt(
+            "WITH invitation AS (SELECT household_id, role FROM sf_household_invitation WHERE code=$1 AND expire_date > NOW()), member AS (INSERT INTO sf_household_member (household_id, user_id, role) SELECT household_id, $2, role FROM invitation ON CONFLICT DO NOTHING) UPDATE sf_user SET current_household_id=(SELECT household_id FROM invitation) WHERE user_id=$2 AND EXISTS (SELECT 1 FROM invitation) RETURNING current_household_id",
+            &[&code.trim().to_uppercase(), &user_id],
+        ),
+    )
+    .await
+    .map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while joining household with code `{}`!",
+            tracing_prefix,
+            err,
+            code
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            JoinHouseholdErrors::ErrorJoiningHousehold,
+        )
+            .into();
+        error
+    })?
+    .ok_or_else(|| {
+        tracing::error!(
+            "{} No valid invitation found with code `{}`!",
+            tracing_prefix,
+            code
+        );
+        let error: ResponseError<_> =
+            (StatusCode::NOT_FOUND, JoinHouseholdErrors::InvitationNotFound).into();
+        error
+    })?;
+    let household_id: String = row.get("current_household_id");
+    tracing::debug!("{} Joined household `{}`!", tracing_prefix, household_id);
+
+    tracing::debug!("{} DONE", tracing_prefix);
+    Ok(Json(JoinHouseholdResponse { household_id }))
+}
@@ -0,0 +1,216 @@
+use std::{fmt::Display, sync::Arc};
+
+use axum::{response::IntoResponse, Json};
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::Client;
+
+use utoipa::ToSchema;
+
+use crate::{
+    extract_jwt, households::remove_membership, is_session_valid, metrics::timed_query,
+    models::HouseholdRole, request_id::current_request_id, responses::ResponseError, APP_SECRET,
+};
+
+#[derive(Debug, Serialize)]
+pub enum LeaveHouseholdErrors {
+    InvalidPayload { payload: String },
+    InvalidJWT,
+    NoDBConnectionFound,
+    ErrorCheckingIfSessionIsValid,
+    JWTExpired,
+    ErrorCheckingMembership,
+    NotAHouseholdMember,
+    OwnerCantLeave,
+    ErrorLeavingHousehold,
+}
+
+impl Display for LeaveHouseholdErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct LeaveHouseholdPayload {
+    token: String,
+    household_id: String,
+}
+
+/// Route to leave a household.
+///
+/// Owners can't leave their households. If the user was working on it
+/// the ingredient routes go back to another household, preferably one they own.
+#[utoipa::path(
+    post,
+    path = "/v1/households/leave",
+    tag = "Households",
+    request_body = LeaveHouseholdPayload,
+    responses(
+        (status = 200, description = "The user left the household"),
+        (status = "4XX", description = "The `LeaveHouseholdErrors` that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The `LeaveHouseholdErrors` that made the request fail", body = String, content_type = "text/plain"),
+    )
+)]
+pub async fn leave_household(
+    payload: Json<serde_json::Value>,
+    client: Arc<Option<Client>>,
+) -> Result<impl IntoResponse, ResponseError<LeaveHouseholdErrors>> {
+    let tracing_prefix = format!("/households/leave - {}:", current_request_id());
+
+    tracing::debug!("{} START", tracing_prefix);
+
+    tracing::debug!("{} Parsing payload...", tracing_prefix);
+    let LeaveHouseholdPayload {
+        token,
+        household_id,
+    } = match serde_json::from_value(payload.0.clone()) {
+        Ok(p) => p,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred parsing payload `{}`",
+                tracing_prefix,
+                err,
+                payload.0
+            );
+            let error: ResponseError<_> = (
+                StatusCode::BAD_REQUEST,
+                LeaveHouseholdErrors::InvalidPayload {
+                    payload: payload.0.to_string(),
+                },
+            )
+                .into();
+            Err(error)?
+        }
+    };
+    tracing::debug!("{} Payload parsed successfully!", tracing_prefix);
+
+    tracing::debug!("{} Extracting JWT...", tracing_prefix);
+    let token_info = match extract_jwt(APP_SECRET, &token) {
+        Ok(t) => t,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred while extracting the JWT `{}`",
+                tracing_prefix,
+                err,
+                token
+            );
+            let error: ResponseError<_> =
+                (StatusCode::BAD_REQUEST, LeaveHouseholdErrors::InvalidJWT).into();
+            Err(error)?
+        }
+    };
+    let user_id = token_info.user_id.clone();
+    tracing::debug!("{} JWT extracted successfully!", tracing_prefix);
+
+    tracing::debug!("{} Checking for DB connection...", tracing_prefix);
+    let conn = client.as_ref().as_ref().ok_or_else(|| {
+        tracing::error!("{} No DB connection found!", tracing_prefix);
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            LeaveHouseholdErrors::NoDBConnectionFound,
+        )
+            .into();
+        error
+    })?;
+    tracing::debug!("{} DB Connection found!", tracing_prefix);
+
+    tracing::debug!("{} Checking if session is valid...", tracing_prefix);
+    if let Err(err) = is_session_valid(token_info, conn).await {
+        tracing::error!(
+            "{} An error `{:?}` occurred while checking if session is valid!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = match err {
+            crate::IsSessionValidErrors::InternalDBError(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                LeaveHouseholdErrors::ErrorCheckingIfSessionIsValid,
+            ),
+            crate::IsSessionValidErrors::InvalidSessionData {
+                current_date: _,
+                db_expire_date: _,
+            } => (StatusCode::UNAUTHORIZED, LeaveHouseholdErrors::JWTExpired),
+            _ => (
+                StatusCode::BAD_REQUEST,
+                LeaveHouseholdErrors::ErrorCheckingIfSessionIsValid,
+            ),
+        }
+        .into();
+
+        Err(error)?
+    }
+    tracing::debug!("{} Session is valid!", tracing_prefix);
+
+    tracing::debug!("{} Checking membership...", tracing_prefix);
+    let role: String = timed_query(
+        "leave_household",
+        conn.query_opt(
+            "SELECT role FROM sf_household_member WHERE household_id=$1 AND user_id=$2",
+            &[&household_id, &user_id],
+        ),
+    )
+    .await
+    .map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while checking membership of household `{}`!",
+            tracing_prefix,
+            err,
+            household_id
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            LeaveHouseholdErrors::ErrorCheckingMembership,
+        )
+            .into();
+        error
+    })?
+    .ok_or_else(|| {
+        tracing::error!(
+            "{} The user isn't a member of household `{}`!",
+            tracing_prefix,
+            household_id
+        );
+        let error: ResponseError<_> = (
+            StatusCode::NOT_FOUND,
+            LeaveHouseholdErrors::NotAHouseholdMember,
+        )
+            .into();
+        error
+    })?
+    .get("role");
+
+    if role == format!("{:?}", HouseholdRole::Owner) {
+        tracing::error!(
+            "{} The user owns household `{}`!",
+            tracing_prefix,
+            household_id
+        );
+        let error: ResponseError<_> = (
+            StatusCode::BAD_REQUEST,
+            LeaveHouseholdErrors::OwnerCantLeave,
+        )
+            .into();
+        Err(error)?
+    }
+
+    tracing::debug!("{} Leaving household `{}`...", tracing_prefix, household_id);
+    if let Err(err) = remove_membership(conn, &household_id, &user_id).await {
+        tracing::error!(
+            "{} An error `{:?}` occurred while leaving household `{}`!",
+            tracing_prefix,
+            err,
+            household_id
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            LeaveHouseholdErrors::ErrorLeavingHousehold,
+        )
+            .into();
+        Err(error)?
+    }
+    tracing::debug!("{} Household left!", tracing_prefix);
+
+    tracing::debug!("{} DONE", tracing_prefix);
+    Ok(StatusCode::OK)
+}
@@ -1,7 +1,4 @@
-use std::{
-    fmt::Display,
-    sync::{atomic::AtomicUsize, Arc},
-};
+use std::{fmt::Display, sync::Arc};
 
 use axum::{response::IntoResponse, Json};
 
@@ -9,12 +6,15 @@ use chrono::{Duration, Utc};
 use hyper::StatusCode;
 use serde::{Deserialize, Serialize};
 use tokio_postgres::Client;
+use utoipa::ToSchema;
 use uuid::Uuid;
 
 use crate::{
     encrypt_password_with_salt, generate_jwt,
+    metrics::timed_query,
     models::{JWT_Token, UserSettings},
     obtain_salt,
+    request_id::current_request_id,
     responses::ResponseError,
     APP_SECRET,
 };
@@ -40,7 +40,7 @@ impl Display for LoginUserErrors {
     }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct LoginUserResponse {
     /// The JWT token that was evaluated
     pub token: String,
@@ -48,20 +48,28 @@ pub struct LoginUserResponse {
     pub preferences: UserSettings,
 }
 
-#[derive(Debug, Deserialize)]
-struct LoginUserPayload {
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct LoginUserPayload {
     username: String,
     password: String,
 }
 
-static ID: AtomicUsize = AtomicUsize::new(0);
-
+#[utoipa::path(
+    post,
+    path = "/v1/user/login",
+    tag = "Users",
+    request_body = LoginUserPayload,
+    responses(
+        (status = 200, description = "The session token and the settings of the user", body = LoginUserResponse),
+        (status = "4XX", description = "The `LoginUserErrors` that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The `LoginUserErrors` that made the request fail", body = String, content_type = "text/plain"),
+    )
+)]
 pub async fn login_user(
     payload: Json<serde_json::Value>,
     client: Arc<Option<Client>>,
 ) -> Result<impl IntoResponse, ResponseError<LoginUserErrors>> {
-    let id = ID.fetch_add(1, std::sync::atomic::Ordering::AcqRel);
-    let tracing_prefix = format!("/user/login - {}:", id);
+    let tracing_prefix = format!("/user/login - {}:", current_request_id());
 
     tracing::debug!("{} START", tracing_prefix);
 
@@ -100,12 +108,14 @@ pub async fn login_user(
     let conn = client.as_ref().as_ref().unwrap();
 
     tracing::debug!("{} Retrieving user `{}` salt...", tracing_prefix, username);
-    let (db_password, user_id): (String, String) = match conn
-        .query(
+    let (db_password, user_id): (String, String) = match timed_query(
+        "login_user",
+        conn.query(
             "SELECT password,user_id FROM sf_user WHERE username=$1",
             &[&username],
-        )
-        .await
+        ),
+    )
+    .await
     {
         Ok(r) => {
             if r.is_empty() {
@@ -121,7 +131,7 @@ pub async fn login_user(
                     .into();
                 Err(error)?
             }
-            (r.get(0).unwrap().get(0), r.get(0).unwrap().get(1))
+            (r.first().unwrap().get(0), r.first().unwrap().get(1))
         }
         Err(err) => {
             tracing::error!(
@@ -169,9 +179,11 @@ pub async fn login_user(
 
     tracing::debug!("{} Passwords match! Getting preferences...", tracing_prefix);
 
-    let preferences = match conn
-        .query("SELECT * FROM sf_settings WHERE user_id=$1", &[&user_id])
-        .await
+    let preferences = match timed_query(
+        "login_user",
+        conn.query("SELECT * FROM sf_settings WHERE user_id=$1", &[&user_id]),
+    )
+    .await
     {
         Ok(rows) => {
             if rows.is_empty() {
@@ -188,7 +200,7 @@ pub async fn login_user(
                 Err(error)?
             }
 
-            let row = rows.get(0).unwrap();
+            let row = rows.first().unwrap();
             UserSettings::try_from(row).map_err(|e| {
                 tracing::error!(
                     "{} An error `{:?}` occurred while trying to parse User Settings!",
@@ -225,12 +237,14 @@ pub async fn login_user(
     } else {
         Utc::now() + Duration::days(7)
     };
-    if let Err(err) = conn
-        .execute(
+    if let Err(err) = timed_query(
+        "login_user",
+        conn.execute(
             "INSERT INTO sf_session(session_id, user_id, expire_date) VALUES ($1, $2, $3)",
             &[&session_id, &user_id, &expire_date],
-        )
-        .await
+        ),
+    )
+    .await
     {
         tracing::error!(
             "{} An error `{}` occurred while trying to create session for user `{}`!",
@@ -1,7 +1,4 @@
-use std::{
-    fmt::Display,
-    sync::{atomic::AtomicUsize, Arc},
-};
+use std::{fmt::Display, sync::Arc};
 
 use axum::{response::IntoResponse, Json};
 use chrono::{Duration, Utc};
@@ -9,7 +6,12 @@ use hyper::StatusCode;
 use serde::Deserialize;
 use tokio_postgres::Client;
 
-use crate::{extract_jwt, models::JWT_Token, responses::ResponseError};
+use utoipa::ToSchema;
+
+use crate::{
+    extract_jwt, metrics::timed_query, models::JWT_Token, request_id::current_request_id,
+    responses::ResponseError,
+};
 
 #[derive(Debug)]
 pub enum LogoutUserErrors {
@@ -24,19 +26,27 @@ impl Display for LogoutUserErrors {
     }
 }
 
-#[derive(Debug, Deserialize)]
-struct LogoutUserPayload {
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct LogoutUserPayload {
     token: String,
 }
 
-static ID: AtomicUsize = AtomicUsize::new(0);
-
+#[utoipa::path(
+    post,
+    path = "/v1/user/logout",
+    tag = "Users",
+    request_body = LogoutUserPayload,
+    responses(
+        (status = 200, description = "The session was closed"),
+        (status = "4XX", description = "The `LogoutUserErrors` that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The `LogoutUserErrors` that made the request fail", body = String, content_type = "text/plain"),
+    )
+)]
 pub async fn logout(
     payload: Json<serde_json::Value>,
     client: Arc<Option<Client>>,
 ) -> Result<impl IntoResponse, ResponseError<LogoutUserErrors>> {
-    let id = ID.fetch_add(1, std::sync::atomic::Ordering::AcqRel);
-    let tracing_prefix = format!("/user/logout - {}:", id);
+    let tracing_prefix = format!("/user/logout - {}:", current_request_id());
 
     tracing::debug!("{} START", tracing_prefix);
 
@@ -95,12 +105,14 @@ pub async fn logout(
     let conn = client.as_ref().as_ref().unwrap();
     let new_expire_date = Utc::now() - Duration::seconds(3);
 
-    if let Err(err) = conn
-        .execute(
+    if let Err(err) = timed_query(
+        "logout",
+        conn.execute(
             "UPDATE sf_session SET expire_date=$1 WHERE session_id=$2",
             &[&new_expire_date, &session_id],
-        )
-        .await
+        ),
+    )
+    .await
     {
         tracing::error!(
             "{} An error `{:?}` occurred while updating session with id `{}`",
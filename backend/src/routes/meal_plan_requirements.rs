@@ -0,0 +1,364 @@
+use std::{fmt::Display, sync::Arc};
+
+use axum::{response::IntoResponse, Json};
+use chrono::{DateTime, NaiveDate, Utc};
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::Client;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    extract_jwt,
+    households::{get_current_household, GetCurrentHouseholdErrors},
+    inventory::{aggregate_required, available_quantity, expired_by, RequiredIngredient},
+    is_session_valid,
+    metrics::timed_query,
+    models::{Ingredient, MealSlot},
+    parse_db_ingredient,
+    request_id::current_request_id,
+    responses::ResponseError,
+    APP_SECRET,
+};
+
+use super::get_meal_plan::{get_meal_plan_entries, GetMealPlanEntriesErrors};
+
+#[derive(Debug, Serialize)]
+pub enum MealPlanRequirementsErrors {
+    InvalidPayload { payload: String },
+    InvalidJWT,
+    NoDBConnectionFound,
+    ErrorCheckingIfSessionIsValid,
+    JWTExpired,
+    CouldntRetrieveMealPlanFromDB,
+    InvalidMealPlanFormatFromDB,
+    CouldntRetrieveIngredientsFromDB,
+    InvalidIngredientFormatFromDB,
+    ErrorGettingHousehold,
+    NoHouseholdSelected,
+}
+
+impl Display for MealPlanRequirementsErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct MealPlanRequirementsPayload {
+    token: String,
+    from: NaiveDate,
+    to: NaiveDate,
+}
+
+/// Represents the total amount of an ingredient the planned meals need.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct IngredientRequirement {
+    #[serde(rename = "Name")]
+    pub name: String,
+
+    #[serde(rename = "Quantity")]
+    pub quantity: f32,
+
+    #[serde(rename = "Unit")]
+    pub unit: String,
+
+    /// How much the fridge has in the same unit,
+    /// `None` if the fridge has it in units that can't be compared.
+    #[serde(rename = "Available")]
+    pub available: Option<f32>,
+
+    #[serde(rename = "Missing")]
+    pub missing: f32,
+}
+
+/// Represents a planned meal that relies on an ingredient that will be expired by then.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ExpiryWarning {
+    #[serde(rename = "PlanId")]
+    pub plan_id: Uuid,
+
+    #[serde(rename = "RecipeId")]
+    pub recipe_id: String,
+
+    #[serde(rename = "Date")]
+    pub date: NaiveDate,
+
+    #[serde(rename = "Slot")]
+    pub slot: MealSlot,
+
+    #[serde(rename = "IngredientId")]
+    pub ingredient_id: Uuid,
+
+    #[serde(rename = "Ingredient")]
+    pub ingredient: String,
+
+    #[serde(rename = "ExpireDate")]
+    pub expire_date: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MealPlanRequirementsResponse {
+    #[serde(rename = "Requirements")]
+    pub requirements: Vec<IngredientRequirement>,
+
+    #[serde(rename = "Warnings")]
+    pub warnings: Vec<ExpiryWarning>,
+}
+
+/// Route to compute the ingredients the meals planned between two dates need,
+/// compared against what's currently in the fridge.
+///
+/// It also warns about planned meals whose ingredients will have expired by the day they're planned for.
+#[utoipa::path(
+    post,
+    path = "/v1/meal-plan/requirements",
+    tag = "Meal plan",
+    request_body = MealPlanRequirementsPayload,
+    responses(
+        (status = 200, description = "The ingredients needed for the planned meals", body = MealPlanRequirementsResponse),
+        (status = "4XX", description = "The `MealPlanRequirementsErrors` that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The `MealPlanRequirementsErrors` that made the request fail", body = String, content_type = "text/plain"),
+    )
+)]
+pub async fn meal_plan_requirements(
+    payload: Json<serde_json::Value>,
+    client: Arc<Option<Client>>,
+) -> Result<impl IntoResponse, ResponseError<MealPlanRequirementsErrors>> {
+    let tracing_prefix = format!("/meal-plan/requirements - {}:", current_request_id());
+
+    tracing::debug!("{} START", tracing_prefix);
+
+    tracing::debug!("{} Parsing payload...", tracing_prefix);
+    let MealPlanRequirementsPayload { token, from, to } =
+        match serde_json::from_value(payload.0.clone()) {
+            Ok(p) => p,
+            Err(err) => {
+                tracing::error!(
+                    "{} An error `{:?}` occurred parsing payload `{}`",
+                    tracing_prefix,
+                    err,
+                    payload.0
+                );
+                let error: ResponseError<_> = (
+                    StatusCode::BAD_REQUEST,
+                    MealPlanRequirementsErrors::InvalidPayload {
+                        payload: payload.0.to_string(),
+                    },
+                )
+                    .into();
+                Err(error)?
+            }
+        };
+    tracing::debug!("{} Payload parsed successfully!", tracing_prefix);
+
+    tracing::debug!("{} Extracting JWT...", tracing_prefix);
+    let token_info = match extract_jwt(APP_SECRET, &token) {
+        Ok(t) => t,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred while extracting the JWT `{}`",
+                tracing_prefix,
+                err,
+                token
+            );
+            let error: ResponseError<_> = (
+                StatusCode::BAD_REQUEST,
+                MealPlanRequirementsErrors::InvalidJWT,
+            )
+                .into();
+            Err(error)?
+        }
+    };
+    let user_id = token_info.user_id.clone();
+    tracing::debug!("{} JWT extracted successfully!", tracing_prefix);
+
+    tracing::debug!("{} Checking for DB connection...", tracing_prefix);
+    let conn = client.as_ref().as_ref().ok_or_else(|| {
+        tracing::error!("{} No DB connection found!", tracing_prefix);
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            MealPlanRequirementsErrors::NoDBConnectionFound,
+        )
+            .into();
+        error
+    })?;
+    tracing::debug!("{} DB Connection found!", tracing_prefix);
+
+    tracing::debug!("{} Checking if session is valid...", tracing_prefix);
+    if let Err(err) = is_session_valid(token_info, conn).await {
+        tracing::error!(
+            "{} An error `{:?}` occurred while checking if session is valid!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = match err {
+            crate::IsSessionValidErrors::InternalDBError(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                MealPlanRequirementsErrors::ErrorCheckingIfSessionIsValid,
+            ),
+            crate::IsSessionValidErrors::InvalidSessionData {
+                current_date: _,
+                db_expire_date: _,
+            } => (
+                StatusCode::UNAUTHORIZED,
+                MealPlanRequirementsErrors::JWTExpired,
+            ),
+            _ => (
+                StatusCode::BAD_REQUEST,
+                MealPlanRequirementsErrors::ErrorCheckingIfSessionIsValid,
+            ),
+        }
+        .into();
+
+        Err(error)?
+    }
+    tracing::debug!("{} Session is valid!", tracing_prefix);
+
+    tracing::debug!("{} Getting meal plan from DB...", tracing_prefix);
+    let entries = get_meal_plan_entries(conn, &user_id, from, to, &tracing_prefix)
+        .await
+        .map_err(|err| {
+            tracing::error!(
+                "{} An error `{:?}` occurred while getting the meal plan of user `{}`!",
+                tracing_prefix,
+                err,
+                user_id
+            );
+            let error: ResponseError<_> = match err {
+                GetMealPlanEntriesErrors::InternalDBError(_) => (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    MealPlanRequirementsErrors::CouldntRetrieveMealPlanFromDB,
+                ),
+                GetMealPlanEntriesErrors::InvalidMealPlanFormatFromDB => (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    MealPlanRequirementsErrors::InvalidMealPlanFormatFromDB,
+                ),
+            }
+            .into();
+            error
+        })?;
+    tracing::debug!("{} Got {} planned meals!", tracing_prefix, entries.len());
+
+    tracing::debug!("{} Getting current household...", tracing_prefix);
+    let household = get_current_household(conn, &user_id).await.map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while getting the household of user `{}`!",
+            tracing_prefix,
+            err,
+            user_id
+        );
+        let error: ResponseError<_> = match err {
+            GetCurrentHouseholdErrors::NoHouseholdSelected => (
+                StatusCode::BAD_REQUEST,
+                MealPlanRequirementsErrors::NoHouseholdSelected,
+            ),
+            _ => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                MealPlanRequirementsErrors::ErrorGettingHousehold,
+            ),
+        }
+        .into();
+        error
+    })?;
+    tracing::debug!(
+        "{} Working on household `{}`!",
+        tracing_prefix,
+        household.household_id
+    );
+
+    tracing::debug!("{} Getting ingredients from DB...", tracing_prefix);
+    let fridge = timed_query(
+        "meal_plan_requirements",
+        conn.query(
+            "SELECT * FROM sf_ingredient WHERE household_id=$1 AND deleted_date IS NULL",
+            &[&household.household_id],
+        ),
+    )
+    .await
+    .map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` while trying to get ingredients for user `{}`",
+            tracing_prefix,
+            err,
+            user_id
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            MealPlanRequirementsErrors::CouldntRetrieveIngredientsFromDB,
+        )
+            .into();
+        error
+    })?
+    .iter()
+    .map(|row| {
+        parse_db_ingredient(row, &tracing_prefix).ok_or_else(|| {
+            let error: ResponseError<_> = (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                MealPlanRequirementsErrors::InvalidIngredientFormatFromDB,
+            )
+                .into();
+            error
+        })
+    })
+    .collect::<Result<Vec<Ingredient>, ResponseError<MealPlanRequirementsErrors>>>()?;
+    tracing::debug!("{} Got ingredients from user!", tracing_prefix);
+
+    tracing::debug!("{} Computing requirements...", tracing_prefix);
+    let mut required = vec![];
+    let mut warnings = vec![];
+    for entry in &entries {
+        let recipe = match &entry.recipe {
+            Some(r) => r,
+            None => {
+                tracing::error!(
+                    "{} No stored recipe found for planned meal `{}`!",
+                    tracing_prefix,
+                    entry.plan_id
+                );
+                continue;
+            }
+        };
+
+        let meal_date = entry.date.and_time(chrono::NaiveTime::MIN).and_utc();
+        for ingredient in recipe.ingredients.iter().map(RequiredIngredient::from) {
+            warnings.extend(expired_by(&ingredient, &fridge, meal_date).into_iter().map(
+                |expired| ExpiryWarning {
+                    plan_id: entry.plan_id,
+                    recipe_id: entry.recipe_id.clone(),
+                    date: entry.date,
+                    slot: entry.slot,
+                    ingredient_id: expired.ingredient_id,
+                    ingredient: expired.name.clone(),
+                    expire_date: expired.expire_date,
+                },
+            ));
+            required.push(ingredient);
+        }
+    }
+
+    let requirements = aggregate_required(required)
+        .into_iter()
+        .map(|required| {
+            let available = available_quantity(&required, &fridge);
+            let missing = match available {
+                Some(available) => (required.quantity - available).max(0.0),
+                None => 0.0,
+            };
+            IngredientRequirement {
+                name: required.name,
+                quantity: required.quantity,
+                unit: required.unit,
+                available,
+                missing,
+            }
+        })
+        .collect();
+    tracing::debug!("{} Requirements computed!", tracing_prefix);
+
+    tracing::debug!("{} DONE", tracing_prefix);
+    Ok(Json(MealPlanRequirementsResponse {
+        requirements,
+        warnings,
+    }))
+}
@@ -8,9 +8,70 @@ pub mod search_recipes;
 pub mod get_ingredients;
 pub mod search_ingredients;
 
+pub mod get_settings;
 pub mod recipe_details;
 pub mod save_settings;
 
 pub mod add_ingredient;
 pub mod edit_ingredient;
 pub mod remove_ingredient;
+
+pub mod add_recipe_to_shopping_list;
+pub mod add_shopping_item;
+pub mod buy_shopping_items;
+pub mod edit_shopping_item;
+pub mod get_shopping_list;
+pub mod remove_shopping_item;
+
+pub mod assign_meal;
+pub mod get_meal_plan;
+pub mod meal_plan_requirements;
+pub mod remove_meal;
+
+pub mod get_saved_recipes;
+pub mod save_recipe;
+pub mod unsave_recipe;
+
+pub mod add_custom_recipe;
+pub mod edit_custom_recipe;
+pub mod get_custom_recipes;
+pub mod remove_custom_recipe;
+
+pub mod get_cook_history;
+pub mod get_recipe_ratings;
+pub mod rate_recipe;
+pub mod record_cooked_recipe;
+
+pub mod add_theme;
+pub mod get_themes;
+pub mod import_theme;
+pub mod remove_theme;
+
+pub mod create_household;
+pub mod get_households;
+pub mod invite_to_household;
+pub mod join_household;
+pub mod leave_household;
+pub mod remove_household_member;
+pub mod set_member_role;
+pub mod switch_household;
+
+pub mod add_storage_location;
+pub mod get_storage_locations;
+pub mod move_ingredients;
+pub mod remove_storage_location;
+
+pub mod get_ingredient;
+pub mod get_trash;
+pub mod restore_ingredient;
+
+pub mod get_inventory_history;
+
+pub mod get_stats;
+
+pub mod bulk_add_ingredients;
+pub mod bulk_edit_ingredients;
+pub mod bulk_remove_ingredients;
+pub mod consume_ingredient;
+pub mod stream_changes;
+pub mod sync_changes;
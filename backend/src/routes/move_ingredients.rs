@@ -0,0 +1,516 @@
+use std::{collections::HashMap, fmt::Display, sync::Arc};
+
+use axum::{response::IntoResponse, Json};
+use chrono::{DateTime, Utc};
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::Client;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    bulk_ingredients::TransactionPool,
+    extract_jwt,
+    households::{get_current_household, GetCurrentHouseholdErrors},
+    ingredient_lots::{get_lots, lot_totals},
+    is_session_valid,
+    metrics::timed_query,
+    models::{Ingredient, InventoryEventType, StorageKind},
+    parse_db_ingredient,
+    request_id::current_request_id,
+    responses::ResponseError,
+    shelf_life::adjust_expire_date,
+    storage::{get_storage_location, GetStorageLocationErrors},
+    APP_SECRET,
+};
+
+#[derive(Debug, Serialize)]
+pub enum MoveIngredientsErrors {
+    InvalidPayload {
+        payload: String,
+    },
+    InvalidJWT,
+    NoDBConnectionFound,
+    ErrorCheckingIfSessionIsValid,
+    JWTExpired,
+    ErrorGettingHousehold,
+    NoHouseholdSelected,
+    NotAllowedInHousehold,
+    LocationNotFound,
+    ErrorGettingStorageLocation,
+    NoIngredientsSelected,
+    CouldntRetrieveIngredientsFromDB,
+    InvalidIngredientFormatFromDB,
+    IngredientsNotFound,
+    /// Every ingredient sent had already expired and they can't be frozen.
+    IngredientsExpired,
+    /// An ingredient changed since the client got it, the current version is returned.
+    VersionConflict {
+        index: usize,
+        current: Box<Ingredient>,
+    },
+    ErrorStartingTransaction,
+    ErrorMovingIngredients,
+    ErrorCommittingTransaction,
+}
+
+impl Display for MoveIngredientsErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct MoveIngredientsPayload {
+    token: String,
+    ingredients: Vec<MovedIngredientPayload>,
+    location_id: Uuid,
+}
+
+/// Represents an ingredient that will be moved to another storage location.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct MovedIngredientPayload {
+    #[serde(rename = "IngredientId")]
+    pub ingredient_id: Uuid,
+
+    /// The version of the ingredient the client moved.
+    #[serde(rename = "Version")]
+    pub version: i32,
+}
+
+/// Route to move ingredients of the current household to another storage location.
+///
+/// Moving them into a freezer extends their expire date and taking them out shortens it,
+/// based on how long their category lasts. Ingredients that already expired aren't frozen
+/// and are left where they are. The moved ingredients are returned.
+///
+/// If one of them changed since the client got it none of them are moved.
+#[utoipa::path(
+    post,
+    path = "/v1/ingredients/move",
+    tag = "Ingredients",
+    request_body = MoveIngredientsPayload,
+    responses(
+        (status = 200, description = "The moved ingredients", body = [Ingredient]),
+        (status = 409, description = "An ingredient changed since the client got it, it's returned as it is now", body = Ingredient),
+        (status = "4XX", description = "The `MoveIngredientsErrors` that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The `MoveIngredientsErrors` that made the request fail", body = String, content_type = "text/plain"),
+    )
+)]
+pub async fn move_ingredients(
+    payload: Json<serde_json::Value>,
+    client: Arc<Option<Client>>,
+    transactions: Arc<TransactionPool>,
+) -> Result<impl IntoResponse, ResponseError<MoveIngredientsErrors>> {
+    let tracing_prefix = format!("/ingredients/move - {}:", current_request_id());
+
+    tracing::debug!("{} START", tracing_prefix);
+
+    tracing::debug!("{} Parsing payload...", tracing_prefix);
+    let MoveIngredientsPayload {
+        token,
+        ingredients: moved_ingredients,
+        location_id,
+    } = match serde_json::from_value(payload.0.clone()) {
+        Ok(p) => p,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred parsing payload `{}`",
+                tracing_prefix,
+                err,
+                payload.0
+            );
+            let error: ResponseError<_> = (
+                StatusCode::BAD_REQUEST,
+                MoveIngredientsErrors::InvalidPayload {
+                    payload: payload.0.to_string(),
+                },
+            )
+                .into();
+            Err(error)?
+        }
+    };
+    tracing::debug!("{} Payload parsed successfully!", tracing_prefix);
+
+    tracing::debug!("{} Extracting JWT...", tracing_prefix);
+    let token_info = match extract_jwt(APP_SECRET, &token) {
+        Ok(t) => t,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred while extracting the JWT `{}`",
+                tracing_prefix,
+                err,
+                token
+            );
+            let error: ResponseError<_> =
+                (StatusCode::BAD_REQUEST, MoveIngredientsErrors::InvalidJWT).into();
+            Err(error)?
+        }
+    };
+    let user_id = token_info.user_id.clone();
+    tracing::debug!("{} JWT extracted successfully!", tracing_prefix);
+
+    tracing::debug!("{} Checking for DB connection...", tracing_prefix);
+    let conn = client.as_ref().as_ref().ok_or_else(|| {
+        tracing::error!("{} No DB connection found!", tracing_prefix);
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            MoveIngredientsErrors::NoDBConnectionFound,
+        )
+            .into();
+        error
+    })?;
+    tracing::debug!("{} DB Connection found!", tracing_prefix);
+
+    tracing::debug!("{} Checking if session is valid...", tracing_prefix);
+    if let Err(err) = is_session_valid(token_info, conn).await {
+        tracing::error!(
+            "{} An error `{:?}` occurred while checking if session is valid!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = match err {
+            crate::IsSessionValidErrors::InternalDBError(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                MoveIngredientsErrors::ErrorCheckingIfSessionIsValid,
+            ),
+            crate::IsSessionValidErrors::InvalidSessionData {
+                current_date: _,
+                db_expire_date: _,
+            } => (StatusCode::UNAUTHORIZED, MoveIngredientsErrors::JWTExpired),
+            _ => (
+                StatusCode::BAD_REQUEST,
+                MoveIngredientsErrors::ErrorCheckingIfSessionIsValid,
+            ),
+        }
+        .into();
+
+        Err(error)?
+    }
+    tracing::debug!("{} Session is valid!", tracing_prefix);
+
+    tracing::debug!("{} Getting current household...", tracing_prefix);
+    let household = get_current_household(conn, &user_id).await.map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while getting the household of user `{}`!",
+            tracing_prefix,
+            err,
+            user_id
+        );
+        let error: ResponseError<_> = match err {
+            GetCurrentHouseholdErrors::NoHouseholdSelected => (
+                StatusCode::BAD_REQUEST,
+                MoveIngredientsErrors::NoHouseholdSelected,
+            ),
+            _ => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                MoveIngredientsErrors::ErrorGettingHousehold,
+            ),
+        }
+        .into();
+        error
+    })?;
+    if !household.role.can_edit_ingredients() {
+        tracing::error!(
+            "{} The user can't change the ingredients of household `{}`!",
+            tracing_prefix,
+            household.household_id
+        );
+        let error: ResponseError<_> = (
+            StatusCode::FORBIDDEN,
+            MoveIngredientsErrors::NotAllowedInHousehold,
+        )
+            .into();
+        Err(error)?
+    }
+    tracing::debug!(
+        "{} Working on household `{}`!",
+        tracing_prefix,
+        household.household_id
+    );
+
+    if moved_ingredients.is_empty() {
+        tracing::error!("{} No ingredients to move!", tracing_prefix);
+        let error: ResponseError<_> = (
+            StatusCode::BAD_REQUEST,
+            MoveIngredientsErrors::NoIngredientsSelected,
+        )
+            .into();
+        Err(error)?
+    }
+
+    tracing::debug!("{} Getting storage location...", tracing_prefix);
+    let location = get_storage_location(
+        conn,
+        &household.household_id,
+        Some(&location_id.to_string()),
+    )
+    .await
+    .map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while getting the storage location!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = match err {
+            GetStorageLocationErrors::LocationNotFound => (
+                StatusCode::NOT_FOUND,
+                MoveIngredientsErrors::LocationNotFound,
+            ),
+            _ => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                MoveIngredientsErrors::ErrorGettingStorageLocation,
+            ),
+        }
+        .into();
+        error
+    })?;
+    tracing::debug!(
+        "{} Got location `{}`!",
+        tracing_prefix,
+        location.location_id
+    );
+
+    tracing::debug!("{} Starting transaction...", tracing_prefix);
+    let mut tx_client = transactions.get().await.map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while getting a connection for the transaction!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            MoveIngredientsErrors::ErrorStartingTransaction,
+        )
+            .into();
+        error
+    })?;
+    let transaction = tx_client.transaction().await.map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while starting the transaction!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            MoveIngredientsErrors::ErrorStartingTransaction,
+        )
+            .into();
+        error
+    })?;
+    tracing::debug!("{} Transaction started!", tracing_prefix);
+
+    // The ingredients are locked until the move is committed, so a concurrent change
+    // can't happen between reading their lots and writing the adjusted expire dates.
+    tracing::debug!("{} Getting ingredients from DB...", tracing_prefix);
+    let ingredient_ids: Vec<String> = moved_ingredients
+        .iter()
+        .map(|i| i.ingredient_id.to_string())
+        .collect();
+    let expected_versions: HashMap<Uuid, (usize, i32)> = moved_ingredients
+        .iter()
+        .enumerate()
+        .map(|(index, i)| (i.ingredient_id, (index, i.version)))
+        .collect();
+    let rows = timed_query(
+        "move_ingredients",
+        transaction.query(
+            "SELECT i.*, l.kind AS location_kind FROM sf_ingredient i JOIN sf_storage_location l ON l.location_id=i.location_id WHERE i.ingredient_id = ANY($1) AND i.household_id=$2 AND i.deleted_date IS NULL FOR UPDATE OF i",
+            &[&ingredient_ids, &household.household_id],
+        ),
+    )
+    .await
+    .map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` while trying to get the ingredients to move!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            MoveIngredientsErrors::CouldntRetrieveIngredientsFromDB,
+        )
+            .into();
+        error
+    })?;
+
+    if rows.is_empty() {
+        tracing::error!("{} None of the ingredients were found!", tracing_prefix);
+        let error: ResponseError<_> = (
+            StatusCode::NOT_FOUND,
+            MoveIngredientsErrors::IngredientsNotFound,
+        )
+            .into();
+        Err(error)?
+    }
+
+    tracing::debug!("{} Getting lots...", tracing_prefix);
+    let mut lots = get_lots(&transaction, &ingredient_ids)
+        .await
+        .map_err(|err| {
+            tracing::error!(
+                "{} An error `{:?}` occurred while getting the lots!",
+                tracing_prefix,
+                err
+            );
+            let error: ResponseError<_> = (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                MoveIngredientsErrors::CouldntRetrieveIngredientsFromDB,
+            )
+                .into();
+            error
+        })?;
+
+    let moved_date = Utc::now();
+    let mut ingredients = vec![];
+    for row in rows {
+        let invalid_format = || {
+            let error: ResponseError<_> = (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                MoveIngredientsErrors::InvalidIngredientFormatFromDB,
+            )
+                .into();
+            error
+        };
+        let mut ingredient =
+            parse_db_ingredient(&row, &tracing_prefix).ok_or_else(invalid_format)?;
+        let from: StorageKind = row.get::<_, &str>("location_kind").parse().map_err(|err| {
+            tracing::error!(
+                "{} An error `{:?}` occurred while parsing the location kind!",
+                tracing_prefix,
+                err
+            );
+            invalid_format()
+        })?;
+
+        if let Some((index, version)) = expected_versions.get(&ingredient.ingredient_id) {
+            if ingredient.version != *version {
+                tracing::error!(
+                    "{} Ingredient `{}` is on version {} but version {} was moved!",
+                    tracing_prefix,
+                    ingredient.ingredient_id,
+                    ingredient.version,
+                    version
+                );
+                ingredient.lots = lots.remove(&ingredient.ingredient_id).unwrap_or_default();
+                let error = ResponseError::from((
+                    StatusCode::CONFLICT,
+                    MoveIngredientsErrors::VersionConflict {
+                        index: *index,
+                        current: Box::new(ingredient.clone()),
+                    },
+                ))
+                .with_json(&ingredient);
+                Err(error)?
+            }
+        }
+
+        // Freezing food that already went bad doesn't make it good again.
+        if location.kind == StorageKind::Freezer
+            && from != StorageKind::Freezer
+            && ingredient.expire_date <= moved_date
+        {
+            tracing::debug!(
+                "{} Ingredient `{}` expired on {}, it won't be frozen!",
+                tracing_prefix,
+                ingredient.ingredient_id,
+                ingredient.expire_date
+            );
+            continue;
+        }
+
+        let adjust = |expire_date| {
+            adjust_expire_date(
+                &ingredient.category,
+                expire_date,
+                from,
+                location.kind,
+                moved_date,
+            )
+        };
+        let mut ingredient_lots = lots.remove(&ingredient.ingredient_id).unwrap_or_default();
+        for lot in ingredient_lots.iter_mut() {
+            lot.expire_date = adjust(lot.expire_date);
+        }
+        ingredient.expire_date = lot_totals(&ingredient_lots)
+            .1
+            .unwrap_or_else(|| adjust(ingredient.expire_date));
+        ingredient.lots = ingredient_lots;
+        ingredient.location_id = location.location_id;
+        ingredients.push(ingredient);
+    }
+    if ingredients.is_empty() {
+        tracing::error!("{} All the ingredients already expired!", tracing_prefix);
+        let error: ResponseError<_> = (
+            StatusCode::BAD_REQUEST,
+            MoveIngredientsErrors::IngredientsExpired,
+        )
+            .into();
+        Err(error)?
+    }
+    tracing::debug!("{} Got {} ingredients!", tracing_prefix, ingredients.len());
+
+    tracing::debug!("{} Moving ingredients...", tracing_prefix);
+    let expire_dates: Vec<DateTime<Utc>> = ingredients.iter().map(|i| i.expire_date).collect();
+    let moved_ids: Vec<String> = ingredients
+        .iter()
+        .map(|i| i.ingredient_id.to_string())
+        .collect();
+    let lot_ids: Vec<String> = ingredients
+        .iter()
+        .flat_map(|i| i.lots.iter().map(|l| l.lot_id.to_string()))
+        .collect();
+    let lot_expire_dates: Vec<DateTime<Utc>> = ingredients
+        .iter()
+        .flat_map(|i| i.lots.iter().map(|l| l.expire_date))
+        .collect();
+    if let Err(err) = timed_query(
+        "move_ingredients",
+        transaction.execute(
+            "WITH moved AS (UPDATE sf_ingredient i SET location_id=$1, expire_date=m.expire_date, updated_at=NOW(), version=version + 1 FROM UNNEST($2::TEXT[], $3::TIMESTAMPTZ[]) AS m(ingredient_id, expire_date) WHERE i.ingredient_id=m.ingredient_id AND i.household_id=$4 AND i.deleted_date IS NULL RETURNING i.*), lots AS (UPDATE sf_ingredient_lot l SET expire_date=u.expire_date FROM UNNEST($7::TEXT[], $8::TIMESTAMPTZ[]) AS u(lot_id, expire_date) WHERE l.lot_id=u.lot_id AND l.ingredient_id IN (SELECT ingredient_id FROM moved)) INSERT INTO sf_inventory_event (household_id, user_id, ingredient_id, name, category, quantity, unit, expire_date, event_type, reason) SELECT household_id, $5, ingredient_id, name, category, quantity, unit, expire_date, $6, NULL FROM moved",
+            &[
+                &location.location_id.to_string(),
+                &moved_ids,
+                &expire_dates,
+                &household.household_id,
+                &user_id,
+                &format!("{:?}", InventoryEventType::Moved),
+                &lot_ids,
+                &lot_expire_dates,
+            ],
+        ),
+    )
+    .await
+    {
+        tracing::error!(
+            "{} An error `{:?}` occurred while moving the ingredients! Rolling back...",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            MoveIngredientsErrors::ErrorMovingIngredients,
+        )
+            .into();
+        Err(error)?
+    }
+
+    tracing::debug!("{} Committing transaction...", tracing_prefix);
+    transaction.commit().await.map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while committing the transaction!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            MoveIngredientsErrors::ErrorCommittingTransaction,
+        )
+            .into();
+        error
+    })?;
+    tracing::debug!("{} Ingredients moved!", tracing_prefix);
+
+    tracing::debug!("{} DONE", tracing_prefix);
+    Ok(Json(ingredients))
+}
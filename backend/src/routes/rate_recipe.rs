@@ -0,0 +1,189 @@
+use std::{fmt::Display, sync::Arc};
+
+use axum::{response::IntoResponse, Json};
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::Client;
+
+use utoipa::ToSchema;
+
+use crate::{
+    extract_jwt, is_session_valid, metrics::timed_query, request_id::current_request_id,
+    responses::ResponseError, APP_SECRET,
+};
+
+#[derive(Debug, Serialize)]
+pub enum RateRecipeErrors {
+    InvalidPayload { payload: String },
+    InvalidJWT,
+    NoDBConnectionFound,
+    ErrorCheckingIfSessionIsValid,
+    JWTExpired,
+    InvalidRating,
+    ErrorRatingRecipe,
+}
+
+impl Display for RateRecipeErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RateRecipePayload {
+    token: String,
+    recipe_id: String,
+    rating: i16,
+    comment: Option<String>,
+}
+
+/// Route to rate a recipe from 1 to 5 stars with an optional comment.
+///
+/// A new rating replaces the previous one the user gave to the recipe.
+#[utoipa::path(
+    post,
+    path = "/v1/recipes/rate",
+    tag = "Recipes",
+    request_body = RateRecipePayload,
+    responses(
+        (status = 200, description = "The rating was saved"),
+        (status = "4XX", description = "The `RateRecipeErrors` that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The `RateRecipeErrors` that made the request fail", body = String, content_type = "text/plain"),
+    )
+)]
+pub async fn rate_recipe(
+    payload: Json<serde_json::Value>,
+    client: Arc<Option<Client>>,
+) -> Result<impl IntoResponse, ResponseError<RateRecipeErrors>> {
+    let tracing_prefix = format!("/recipes/rate - {}:", current_request_id());
+
+    tracing::debug!("{} START", tracing_prefix);
+
+    tracing::debug!("{} Parsing payload...", tracing_prefix);
+    let RateRecipePayload {
+        token,
+        recipe_id,
+        rating,
+        comment,
+    } = match serde_json::from_value(payload.0.clone()) {
+        Ok(p) => p,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred parsing payload `{}`",
+                tracing_prefix,
+                err,
+                payload.0
+            );
+            let error: ResponseError<_> = (
+                StatusCode::BAD_REQUEST,
+                RateRecipeErrors::InvalidPayload {
+                    payload: payload.0.to_string(),
+                },
+            )
+                .into();
+            Err(error)?
+        }
+    };
+    tracing::debug!("{} Payload parsed successfully!", tracing_prefix);
+
+    tracing::debug!("{} Extracting JWT...", tracing_prefix);
+    let token_info = match extract_jwt(APP_SECRET, &token) {
+        Ok(t) => t,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred while extracting the JWT `{}`",
+                tracing_prefix,
+                err,
+                token
+            );
+            let error: ResponseError<_> =
+                (StatusCode::BAD_REQUEST, RateRecipeErrors::InvalidJWT).into();
+            Err(error)?
+        }
+    };
+    let user_id = token_info.user_id.clone();
+    tracing::debug!("{} JWT extracted successfully!", tracing_prefix);
+
+    tracing::debug!("{} Checking for DB connection...", tracing_prefix);
+    let conn = client.as_ref().as_ref().ok_or_else(|| {
+        tracing::error!("{} No DB connection found!", tracing_prefix);
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            RateRecipeErrors::NoDBConnectionFound,
+        )
+            .into();
+        error
+    })?;
+    tracing::debug!("{} DB Connection found!", tracing_prefix);
+
+    tracing::debug!("{} Checking if session is valid...", tracing_prefix);
+    if let Err(err) = is_session_valid(token_info, conn).await {
+        tracing::error!(
+            "{} An error `{:?}` occurred while checking if session is valid!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = match err {
+            crate::IsSessionValidErrors::InternalDBError(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                RateRecipeErrors::ErrorCheckingIfSessionIsValid,
+            ),
+            crate::IsSessionValidErrors::InvalidSessionData {
+                current_date: _,
+                db_expire_date: _,
+            } => (StatusCode::UNAUTHORIZED, RateRecipeErrors::JWTExpired),
+            _ => (
+                StatusCode::BAD_REQUEST,
+                RateRecipeErrors::ErrorCheckingIfSessionIsValid,
+            ),
+        }
+        .into();
+
+        Err(error)?
+    }
+    tracing::debug!("{} Session is valid!", tracing_prefix);
+
+    if !(1..=5).contains(&rating) {
+        tracing::error!(
+            "{} The rating `{}` isn't between 1 and 5!",
+            tracing_prefix,
+            rating
+        );
+        let error: ResponseError<_> =
+            (StatusCode::BAD_REQUEST, RateRecipeErrors::InvalidRating).into();
+        Err(error)?
+    }
+
+    tracing::debug!(
+        "{} Rating recipe `{}` with {} stars...",
+        tracing_prefix,
+        recipe_id,
+        rating
+    );
+    if let Err(err) = timed_query(
+        "rate_recipe",
+        conn.execute(
+            "INSERT INTO sf_recipe_rating (user_id, recipe_id, rating, comment) VALUES ($1, $2, $3, $4) ON CONFLICT (user_id, recipe_id) DO UPDATE SET rating=EXCLUDED.rating, comment=EXCLUDED.comment, rated_date=NOW()",
+            &[&user_id, &recipe_id, &rating, &comment],
+        ),
+    )
+    .await
+    {
+        tracing::error!(
+            "{} An error `{:?}` occurred while rating recipe `{}`!",
+            tracing_prefix,
+            err,
+            recipe_id
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            RateRecipeErrors::ErrorRatingRecipe,
+        )
+            .into();
+        Err(error)?
+    }
+    tracing::debug!("{} Recipe rated!", tracing_prefix);
+
+    tracing::debug!("{} DONE", tracing_prefix);
+    Ok(StatusCode::OK)
+}
@@ -1,7 +1,4 @@
-use std::{
-    fmt::Display,
-    sync::{atomic::AtomicUsize, Arc},
-};
+use std::{fmt::Display, sync::Arc};
 
 use axum::{response::IntoResponse, Json};
 
@@ -9,9 +6,12 @@ use hyper::StatusCode;
 use serde::Deserialize;
 use tokio_postgres::Client;
 
+use utoipa::ToSchema;
+
 use crate::{
     extract_jwt,
     models::{Recipe, RecipeIngredient},
+    request_id::current_request_id,
     responses::ResponseError,
     APP_SECRET,
 };
@@ -28,21 +28,31 @@ impl Display for RecipeDetailsErrors {
     }
 }
 
-#[derive(Debug, Deserialize)]
-struct RecipeDetailsPayload {
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RecipeDetailsPayload {
     token: String,
+    #[allow(dead_code)]
     #[serde(rename(deserialize = "recipeId"))]
+    #[schema(rename = "recipeId")]
     recipe_id: String,
 }
 
-static ID: AtomicUsize = AtomicUsize::new(0);
-
+#[utoipa::path(
+    post,
+    path = "/v1/recipes/details",
+    tag = "Recipes",
+    request_body = RecipeDetailsPayload,
+    responses(
+        (status = 200, description = "The recipe", body = Recipe),
+        (status = "4XX", description = "The `RecipeDetailsErrors` that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The `RecipeDetailsErrors` that made the request fail", body = String, content_type = "text/plain"),
+    )
+)]
 pub async fn recipe_details(
     payload: Json<serde_json::Value>,
     _client: Arc<Option<Client>>,
 ) -> Result<impl IntoResponse, ResponseError<RecipeDetailsErrors>> {
-    let id = ID.fetch_add(1, std::sync::atomic::Ordering::AcqRel);
-    let tracing_prefix = format!("/recipes/details - {}:", id);
+    let tracing_prefix = format!("/recipes/details - {}:", current_request_id());
 
     tracing::debug!("{} START", tracing_prefix);
 
@@ -96,8 +106,12 @@ pub async fn recipe_details(
             title: "Test Recipe #3".to_string(),
             banner: "https://lh3.googleusercontent.com/efGuFTcoR-Atb8-OgBL8PMCVbPwRQANTX0ZVgllhlzBkVc92d0G9LkapW1TiNmTL4iZJNlPIkyGKS1ODOUNCOxM".to_string(),
             tags: vec!["Breakfast".to_string(), "Egg".to_string()],
-            ingredients: vec![RecipeIngredient { display: "½ cup riced sweet potato".to_owned(), name: "Lime Juice".to_owned() }],
+            ingredients: vec![RecipeIngredient::from_display(Some("Lime Juice".to_owned()), "½ cup riced sweet potato".to_owned())],
             source: "http://www.yummly.com/recipe/Plant-Based-Breakfast-Bowl-9118197".to_string(),
+            steps: vec![],
+            user_rating: None,
+            last_cooked_date: None,
+            dietary_violations: vec![],
         };
 
     tracing::debug!("{} DONE", tracing_prefix);
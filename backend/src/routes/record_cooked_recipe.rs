@@ -0,0 +1,190 @@
+use std::{fmt::Display, sync::Arc};
+
+use axum::{response::IntoResponse, Json};
+use chrono::{DateTime, Utc};
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::Client;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    extract_jwt, is_session_valid, metrics::timed_query, request_id::current_request_id,
+    responses::ResponseError, APP_SECRET,
+};
+
+#[derive(Debug, Serialize)]
+pub enum RecordCookedRecipeErrors {
+    InvalidPayload { payload: String },
+    InvalidJWT,
+    NoDBConnectionFound,
+    ErrorCheckingIfSessionIsValid,
+    JWTExpired,
+    ErrorRecordingCookedRecipe,
+}
+
+impl Display for RecordCookedRecipeErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// When no date is supplied the recipe is recorded as cooked right now.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RecordCookedRecipePayload {
+    token: String,
+    recipe_id: String,
+    cooked_date: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RecordCookedRecipeResponse {
+    #[serde(rename = "CookId")]
+    pub cook_id: Uuid,
+}
+
+/// Route to record that the user cooked a recipe.
+#[utoipa::path(
+    post,
+    path = "/v1/recipes/cooked",
+    tag = "Recipes",
+    request_body = RecordCookedRecipePayload,
+    responses(
+        (status = 200, description = "The recipe was recorded as cooked", body = RecordCookedRecipeResponse),
+        (status = "4XX", description = "The `RecordCookedRecipeErrors` that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The `RecordCookedRecipeErrors` that made the request fail", body = String, content_type = "text/plain"),
+    )
+)]
+pub async fn record_cooked_recipe(
+    payload: Json<serde_json::Value>,
+    client: Arc<Option<Client>>,
+) -> Result<impl IntoResponse, ResponseError<RecordCookedRecipeErrors>> {
+    let tracing_prefix = format!("/recipes/cooked - {}:", current_request_id());
+
+    tracing::debug!("{} START", tracing_prefix);
+
+    tracing::debug!("{} Parsing payload...", tracing_prefix);
+    let RecordCookedRecipePayload {
+        token,
+        recipe_id,
+        cooked_date,
+    } = match serde_json::from_value(payload.0.clone()) {
+        Ok(p) => p,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred parsing payload `{}`",
+                tracing_prefix,
+                err,
+                payload.0
+            );
+            let error: ResponseError<_> = (
+                StatusCode::BAD_REQUEST,
+                RecordCookedRecipeErrors::InvalidPayload {
+                    payload: payload.0.to_string(),
+                },
+            )
+                .into();
+            Err(error)?
+        }
+    };
+    tracing::debug!("{} Payload parsed successfully!", tracing_prefix);
+
+    tracing::debug!("{} Extracting JWT...", tracing_prefix);
+    let token_info = match extract_jwt(APP_SECRET, &token) {
+        Ok(t) => t,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred while extracting the JWT `{}`",
+                tracing_prefix,
+                err,
+                token
+            );
+            let error: ResponseError<_> = (
+                StatusCode::BAD_REQUEST,
+                RecordCookedRecipeErrors::InvalidJWT,
+            )
+                .into();
+            Err(error)?
+        }
+    };
+    let user_id = token_info.user_id.clone();
+    tracing::debug!("{} JWT extracted successfully!", tracing_prefix);
+
+    tracing::debug!("{} Checking for DB connection...", tracing_prefix);
+    let conn = client.as_ref().as_ref().ok_or_else(|| {
+        tracing::error!("{} No DB connection found!", tracing_prefix);
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            RecordCookedRecipeErrors::NoDBConnectionFound,
+        )
+            .into();
+        error
+    })?;
+    tracing::debug!("{} DB Connection found!", tracing_prefix);
+
+    tracing::debug!("{} Checking if session is valid...", tracing_prefix);
+    if let Err(err) = is_session_valid(token_info, conn).await {
+        tracing::error!(
+            "{} An error `{:?}` occurred while checking if session is valid!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = match err {
+            crate::IsSessionValidErrors::InternalDBError(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                RecordCookedRecipeErrors::ErrorCheckingIfSessionIsValid,
+            ),
+            crate::IsSessionValidErrors::InvalidSessionData {
+                current_date: _,
+                db_expire_date: _,
+            } => (
+                StatusCode::UNAUTHORIZED,
+                RecordCookedRecipeErrors::JWTExpired,
+            ),
+            _ => (
+                StatusCode::BAD_REQUEST,
+                RecordCookedRecipeErrors::ErrorCheckingIfSessionIsValid,
+            ),
+        }
+        .into();
+
+        Err(error)?
+    }
+    tracing::debug!("{} Session is valid!", tracing_prefix);
+
+    let cook_id = Uuid::new_v4();
+    let cooked_date = cooked_date.unwrap_or_else(Utc::now);
+
+    tracing::debug!(
+        "{} Recording recipe `{}` as cooked on {}...",
+        tracing_prefix,
+        recipe_id,
+        cooked_date
+    );
+    if let Err(err) = timed_query(
+        "record_cooked_recipe",
+        conn.execute(
+            "INSERT INTO sf_cook_history (cook_id, user_id, recipe_id, cooked_date) VALUES ($1, $2, $3, $4)",
+            &[&cook_id.to_string(), &user_id, &recipe_id, &cooked_date],
+        ),
+    )
+    .await
+    {
+        tracing::error!(
+            "{} An error `{:?}` occurred while recording recipe `{}` as cooked!",
+            tracing_prefix,
+            err,
+            recipe_id
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            RecordCookedRecipeErrors::ErrorRecordingCookedRecipe,
+        )
+            .into();
+        Err(error)?
+    }
+    tracing::debug!("{} Recorded with id `{}`!", tracing_prefix, cook_id);
+
+    tracing::debug!("{} DONE", tracing_prefix);
+    Ok(Json(RecordCookedRecipeResponse { cook_id }))
+}
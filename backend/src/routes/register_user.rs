@@ -1,15 +1,20 @@
-use std::{
-    fmt::Display,
-    sync::{atomic::AtomicUsize, Arc},
-};
+use std::{fmt::Display, sync::Arc};
 
 use axum::{response::IntoResponse, Json};
 use hyper::StatusCode;
 use serde::Deserialize;
 use tokio_postgres::Client;
+use utoipa::ToSchema;
 use uuid::Uuid;
 
-use crate::{encrypt_password, models::AppThemes, responses::ResponseError};
+use crate::{
+    encrypt_password,
+    households::create_household,
+    metrics::timed_query,
+    models::{AppThemes, SETTINGS_SCHEMA_VERSION},
+    request_id::current_request_id,
+    responses::ResponseError,
+};
 
 #[derive(Debug)]
 pub enum RegisterUserErrors {
@@ -21,6 +26,7 @@ pub enum RegisterUserErrors {
     NoUserInserted,
     ErrorInsertingSettingsIntoDB,
     NoSettingsInserted,
+    ErrorCreatingHousehold,
 }
 
 impl Display for RegisterUserErrors {
@@ -29,20 +35,28 @@ impl Display for RegisterUserErrors {
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct RegisterUserPayload {
     username: String,
     password: String,
 }
 
-static ID: AtomicUsize = AtomicUsize::new(0);
-
+#[utoipa::path(
+    post,
+    path = "/v1/user/register",
+    tag = "Users",
+    request_body = RegisterUserPayload,
+    responses(
+        (status = 200, description = "The user was registered"),
+        (status = "4XX", description = "The `RegisterUserErrors` that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The `RegisterUserErrors` that made the request fail", body = String, content_type = "text/plain"),
+    )
+)]
 pub async fn register_user(
     payload: Json<serde_json::Value>,
     client: Arc<Option<Client>>,
 ) -> Result<impl IntoResponse, ResponseError<RegisterUserErrors>> {
-    let id = ID.fetch_add(1, std::sync::atomic::Ordering::AcqRel);
-    let tracing_prefix = format!("/user/register {}:", id);
+    let tracing_prefix = format!("/user/register {}:", current_request_id());
 
     tracing::debug!("{} START", tracing_prefix);
 
@@ -72,12 +86,14 @@ pub async fn register_user(
     tracing::debug!("{} Connecting to DB...", tracing_prefix);
     let username_exists = match client.as_ref() {
         Some(conn) => {
-            match conn
-                .query(
+            match timed_query(
+                "register_user",
+                conn.query(
                     "SELECT user_id FROM sf_user WHERE username=$1",
                     &[&username],
-                )
-                .await
+                ),
+            )
+            .await
             {
                 Ok(r) => !r.is_empty(),
                 Err(err) => {
@@ -137,12 +153,14 @@ pub async fn register_user(
                 tracing_prefix,
                 username
             );
-            let result = conn
-                .execute(
+            let result = timed_query(
+                "register_user",
+                conn.execute(
                     "INSERT INTO sf_user VALUES ($1, $2, $3)",
                     &[&user_id, &username, &encrypted],
-                )
-                .await;
+                ),
+            )
+            .await;
 
             match result {
                 Ok(rows_modified) => {
@@ -207,12 +225,14 @@ pub async fn register_user(
             let theme = format!("{:?}", AppThemes::default());
 
             tracing::debug!("{} Inserting settings...", tracing_prefix);
-            match conn
-                .execute(
-                    "INSERT INTO sf_settings VALUES ($1, $2, $3)",
-                    &[&settings_id, &user_id, &theme],
-                )
-                .await
+            match timed_query(
+                "register_user",
+                conn.execute(
+                    "INSERT INTO sf_settings (settings_id, user_id, theme, schema_version) VALUES ($1, $2, $3, $4)",
+                    &[&settings_id, &user_id, &theme, &SETTINGS_SCHEMA_VERSION],
+                ),
+            )
+            .await
             {
                 Ok(rows_modified) => {
                     if rows_modified > 0 {
@@ -253,6 +273,26 @@ pub async fn register_user(
         }
     }
 
+    tracing::debug!("{} Creating household...", tracing_prefix);
+    if let Some(conn) = client.as_ref() {
+        let name = format!("{}'s household", username);
+        if let Err(err) = create_household(conn, &user_id, &name).await {
+            tracing::error!(
+                "{} An error `{:?}` occurred while creating the household of user `{}`!",
+                tracing_prefix,
+                err,
+                user_id
+            );
+            let error: ResponseError<_> = (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                RegisterUserErrors::ErrorCreatingHousehold,
+            )
+                .into();
+            Err(error)?
+        }
+        tracing::debug!("{} Household created!", tracing_prefix);
+    }
+
     tracing::debug!("{} DONE", tracing_prefix);
     Ok((StatusCode::OK, ""))
 }
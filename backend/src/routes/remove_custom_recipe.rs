@@ -0,0 +1,187 @@
+use std::{fmt::Display, sync::Arc};
+
+use axum::{response::IntoResponse, Json};
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::Client;
+
+use utoipa::ToSchema;
+
+use crate::{
+    extract_jwt, is_session_valid, metrics::timed_query, request_id::current_request_id,
+    responses::ResponseError, APP_SECRET,
+};
+
+#[derive(Debug, Serialize)]
+pub enum RemoveCustomRecipeErrors {
+    InvalidPayload { payload: String },
+    InvalidJWT,
+    NoDBConnectionFound,
+    ErrorCheckingIfSessionIsValid,
+    JWTExpired,
+    RecipeNotFound,
+    ErrorRemovingCustomRecipe,
+}
+
+impl Display for RemoveCustomRecipeErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RemoveCustomRecipePayload {
+    token: String,
+    recipe_id: String,
+}
+
+/// Route to remove a recipe written by the user.
+///
+/// The stored copy is kept so the meal plans and saved recipes using it still work.
+#[utoipa::path(
+    post,
+    path = "/v1/recipes/custom/remove",
+    tag = "Recipes",
+    request_body = RemoveCustomRecipePayload,
+    responses(
+        (status = 200, description = "The recipe was removed"),
+        (status = "4XX", description = "The `RemoveCustomRecipeErrors` that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The `RemoveCustomRecipeErrors` that made the request fail", body = String, content_type = "text/plain"),
+    )
+)]
+pub async fn remove_custom_recipe(
+    payload: Json<serde_json::Value>,
+    client: Arc<Option<Client>>,
+) -> Result<impl IntoResponse, ResponseError<RemoveCustomRecipeErrors>> {
+    let tracing_prefix = format!("/recipes/custom/remove - {}:", current_request_id());
+
+    tracing::debug!("{} START", tracing_prefix);
+
+    tracing::debug!("{} Parsing payload...", tracing_prefix);
+    let RemoveCustomRecipePayload { token, recipe_id } =
+        match serde_json::from_value(payload.0.clone()) {
+            Ok(p) => p,
+            Err(err) => {
+                tracing::error!(
+                    "{} An error `{:?}` occurred parsing payload `{}`",
+                    tracing_prefix,
+                    err,
+                    payload.0
+                );
+                let error: ResponseError<_> = (
+                    StatusCode::BAD_REQUEST,
+                    RemoveCustomRecipeErrors::InvalidPayload {
+                        payload: payload.0.to_string(),
+                    },
+                )
+                    .into();
+                Err(error)?
+            }
+        };
+    tracing::debug!("{} Payload parsed successfully!", tracing_prefix);
+
+    tracing::debug!("{} Extracting JWT...", tracing_prefix);
+    let token_info = match extract_jwt(APP_SECRET, &token) {
+        Ok(t) => t,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred while extracting the JWT `{}`",
+                tracing_prefix,
+                err,
+                token
+            );
+            let error: ResponseError<_> = (
+                StatusCode::BAD_REQUEST,
+                RemoveCustomRecipeErrors::InvalidJWT,
+            )
+                .into();
+            Err(error)?
+        }
+    };
+    let user_id = token_info.user_id.clone();
+    tracing::debug!("{} JWT extracted successfully!", tracing_prefix);
+
+    tracing::debug!("{} Checking for DB connection...", tracing_prefix);
+    let conn = client.as_ref().as_ref().ok_or_else(|| {
+        tracing::error!("{} No DB connection found!", tracing_prefix);
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            RemoveCustomRecipeErrors::NoDBConnectionFound,
+        )
+            .into();
+        error
+    })?;
+    tracing::debug!("{} DB Connection found!", tracing_prefix);
+
+    tracing::debug!("{} Checking if session is valid...", tracing_prefix);
+    if let Err(err) = is_session_valid(token_info, conn).await {
+        tracing::error!(
+            "{} An error `{:?}` occurred while checking if session is valid!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = match err {
+            crate::IsSessionValidErrors::InternalDBError(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                RemoveCustomRecipeErrors::ErrorCheckingIfSessionIsValid,
+            ),
+            crate::IsSessionValidErrors::InvalidSessionData {
+                current_date: _,
+                db_expire_date: _,
+            } => (
+                StatusCode::UNAUTHORIZED,
+                RemoveCustomRecipeErrors::JWTExpired,
+            ),
+            _ => (
+                StatusCode::BAD_REQUEST,
+                RemoveCustomRecipeErrors::ErrorCheckingIfSessionIsValid,
+            ),
+        }
+        .into();
+
+        Err(error)?
+    }
+    tracing::debug!("{} Session is valid!", tracing_prefix);
+
+    tracing::debug!("{} Removing recipe `{}`...", tracing_prefix, recipe_id);
+    let removed = timed_query(
+        "remove_custom_recipe",
+        conn.execute(
+            "DELETE FROM sf_custom_recipe WHERE recipe_id=$1 AND user_id=$2",
+            &[&recipe_id, &user_id],
+        ),
+    )
+    .await
+    .map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while removing recipe `{}`!",
+            tracing_prefix,
+            err,
+            recipe_id
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            RemoveCustomRecipeErrors::ErrorRemovingCustomRecipe,
+        )
+            .into();
+        error
+    })?;
+
+    if removed == 0 {
+        tracing::error!(
+            "{} No recipe `{}` found for the user!",
+            tracing_prefix,
+            recipe_id
+        );
+        let error: ResponseError<_> = (
+            StatusCode::NOT_FOUND,
+            RemoveCustomRecipeErrors::RecipeNotFound,
+        )
+            .into();
+        Err(error)?
+    }
+    tracing::debug!("{} Recipe removed!", tracing_prefix);
+
+    tracing::debug!("{} DONE", tracing_prefix);
+    Ok(StatusCode::OK)
+}
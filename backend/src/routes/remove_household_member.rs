@@ -0,0 +1,230 @@
+use std::{fmt::Display, sync::Arc};
+
+use axum::{response::IntoResponse, Json};
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::Client;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    extract_jwt,
+    households::{get_current_household, remove_membership, GetCurrentHouseholdErrors},
+    is_session_valid,
+    models::HouseholdRole,
+    request_id::current_request_id,
+    responses::ResponseError,
+    APP_SECRET,
+};
+
+#[derive(Debug, Serialize)]
+pub enum RemoveHouseholdMemberErrors {
+    InvalidPayload { payload: String },
+    InvalidJWT,
+    NoDBConnectionFound,
+    ErrorCheckingIfSessionIsValid,
+    JWTExpired,
+    ErrorGettingHousehold,
+    NoHouseholdSelected,
+    NotHouseholdOwner,
+    MemberNotFound,
+    ErrorRemovingMember,
+}
+
+impl Display for RemoveHouseholdMemberErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RemoveHouseholdMemberPayload {
+    token: String,
+    user_id: Uuid,
+}
+
+/// Route for the owner to remove a member of the current household.
+#[utoipa::path(
+    post,
+    path = "/v1/households/members/remove",
+    tag = "Households",
+    request_body = RemoveHouseholdMemberPayload,
+    responses(
+        (status = 200, description = "The member was removed"),
+        (status = "4XX", description = "The `RemoveHouseholdMemberErrors` that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The `RemoveHouseholdMemberErrors` that made the request fail", body = String, content_type = "text/plain"),
+    )
+)]
+pub async fn remove_household_member(
+    payload: Json<serde_json::Value>,
+    client: Arc<Option<Client>>,
+) -> Result<impl IntoResponse, ResponseError<RemoveHouseholdMemberErrors>> {
+    let tracing_prefix = format!("/households/members/remove - {}:", current_request_id());
+
+    tracing::debug!("{} START", tracing_prefix);
+
+    tracing::debug!("{} Parsing payload...", tracing_prefix);
+    let RemoveHouseholdMemberPayload { token, user_id } =
+        match serde_json::from_value(payload.0.clone()) {
+            Ok(p) => p,
+            Err(err) => {
+                tracing::error!(
+                    "{} An error `{:?}` occurred parsing payload `{}`",
+                    tracing_prefix,
+                    err,
+                    payload.0
+                );
+                let error: ResponseError<_> = (
+                    StatusCode::BAD_REQUEST,
+                    RemoveHouseholdMemberErrors::InvalidPayload {
+                        payload: payload.0.to_string(),
+                    },
+                )
+                    .into();
+                Err(error)?
+            }
+        };
+    tracing::debug!("{} Payload parsed successfully!", tracing_prefix);
+
+    tracing::debug!("{} Extracting JWT...", tracing_prefix);
+    let token_info = match extract_jwt(APP_SECRET, &token) {
+        Ok(t) => t,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred while extracting the JWT `{}`",
+                tracing_prefix,
+                err,
+                token
+            );
+            let error: ResponseError<_> = (
+                StatusCode::BAD_REQUEST,
+                RemoveHouseholdMemberErrors::InvalidJWT,
+            )
+                .into();
+            Err(error)?
+        }
+    };
+    let owner_id = token_info.user_id.clone();
+    tracing::debug!("{} JWT extracted successfully!", tracing_prefix);
+
+    tracing::debug!("{} Checking for DB connection...", tracing_prefix);
+    let conn = client.as_ref().as_ref().ok_or_else(|| {
+        tracing::error!("{} No DB connection found!", tracing_prefix);
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            RemoveHouseholdMemberErrors::NoDBConnectionFound,
+        )
+            .into();
+        error
+    })?;
+    tracing::debug!("{} DB Connection found!", tracing_prefix);
+
+    tracing::debug!("{} Checking if session is valid...", tracing_prefix);
+    if let Err(err) = is_session_valid(token_info, conn).await {
+        tracing::error!(
+            "{} An error `{:?}` occurred while checking if session is valid!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = match err {
+            crate::IsSessionValidErrors::InternalDBError(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                RemoveHouseholdMemberErrors::ErrorCheckingIfSessionIsValid,
+            ),
+            crate::IsSessionValidErrors::InvalidSessionData {
+                current_date: _,
+                db_expire_date: _,
+            } => (
+                StatusCode::UNAUTHORIZED,
+                RemoveHouseholdMemberErrors::JWTExpired,
+            ),
+            _ => (
+                StatusCode::BAD_REQUEST,
+                RemoveHouseholdMemberErrors::ErrorCheckingIfSessionIsValid,
+            ),
+        }
+        .into();
+
+        Err(error)?
+    }
+    tracing::debug!("{} Session is valid!", tracing_prefix);
+
+    let member_id = user_id.to_string();
+    tracing::debug!("{} Getting current household...", tracing_prefix);
+    let household = get_current_household(conn, &owner_id)
+        .await
+        .map_err(|err| {
+            tracing::error!(
+                "{} An error `{:?}` occurred while getting the household of user `{}`!",
+                tracing_prefix,
+                err,
+                owner_id
+            );
+            let error: ResponseError<_> = match err {
+                GetCurrentHouseholdErrors::NoHouseholdSelected => (
+                    StatusCode::BAD_REQUEST,
+                    RemoveHouseholdMemberErrors::NoHouseholdSelected,
+                ),
+                _ => (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    RemoveHouseholdMemberErrors::ErrorGettingHousehold,
+                ),
+            }
+            .into();
+            error
+        })?;
+    if household.role != HouseholdRole::Owner {
+        tracing::error!(
+            "{} The user isn't the owner of household `{}`!",
+            tracing_prefix,
+            household.household_id
+        );
+        let error: ResponseError<_> = (
+            StatusCode::FORBIDDEN,
+            RemoveHouseholdMemberErrors::NotHouseholdOwner,
+        )
+            .into();
+        Err(error)?
+    }
+    tracing::debug!(
+        "{} Working on household `{}`!",
+        tracing_prefix,
+        household.household_id
+    );
+
+    tracing::debug!("{} Removing member `{}`...", tracing_prefix, member_id);
+    let removed = remove_membership(conn, &household.household_id, &member_id)
+        .await
+        .map_err(|err| {
+            tracing::error!(
+                "{} An error `{:?}` occurred while removing member `{}`!",
+                tracing_prefix,
+                err,
+                member_id
+            );
+            let error: ResponseError<_> = (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                RemoveHouseholdMemberErrors::ErrorRemovingMember,
+            )
+                .into();
+            error
+        })?;
+
+    if removed == 0 {
+        tracing::error!(
+            "{} No member `{}` that isn't the owner found!",
+            tracing_prefix,
+            member_id
+        );
+        let error: ResponseError<_> = (
+            StatusCode::NOT_FOUND,
+            RemoveHouseholdMemberErrors::MemberNotFound,
+        )
+            .into();
+        Err(error)?
+    }
+    tracing::debug!("{} Member removed!", tracing_prefix);
+
+    tracing::debug!("{} DONE", tracing_prefix);
+    Ok(StatusCode::OK)
+}
@@ -1,24 +1,41 @@
-use std::{
-    fmt::Display,
-    sync::{atomic::AtomicUsize, Arc},
-};
+use std::{fmt::Display, sync::Arc};
 
 use axum::{response::IntoResponse, Json};
 use hyper::StatusCode;
 use serde::{Deserialize, Serialize};
 use tokio_postgres::Client;
+use utoipa::ToSchema;
 use uuid::Uuid;
 
-use crate::{extract_jwt, is_session_valid, responses::ResponseError, APP_SECRET};
+use crate::{
+    extract_jwt, get_ingredient,
+    households::{get_current_household, GetCurrentHouseholdErrors},
+    is_session_valid,
+    metrics::timed_query,
+    models::{Ingredient, InventoryEventType, RemovalReason},
+    request_id::current_request_id,
+    responses::ResponseError,
+    APP_SECRET,
+};
 
 #[derive(Debug, Serialize)]
 pub enum RemoveIngredientErrors {
-    InvalidPayload { payload: String },
+    InvalidPayload {
+        payload: String,
+    },
     InvalidJWT,
     NoDBConnectionFound,
     ErrorCheckingIfSessionIsValid,
     JWTExpired,
     ErrorRemovingIngredient,
+    ErrorGettingHousehold,
+    NoHouseholdSelected,
+    NotAllowedInHousehold,
+    IngredientNotFound,
+    /// The ingredient changed since the client got it, the current version is returned.
+    VersionConflict {
+        current: Box<Ingredient>,
+    },
 }
 
 impl Display for RemoveIngredientErrors {
@@ -27,20 +44,37 @@ impl Display for RemoveIngredientErrors {
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct RemoveIngredientPayload {
     token: String,
     ingredient_id: Uuid,
+    /// Why the ingredient was removed, it's used for the waste statistics.
+    reason: Option<RemovalReason>,
+    /// The version of the ingredient the client removed.
+    version: i32,
 }
 
-static ID: AtomicUsize = AtomicUsize::new(0);
-
+/// Route to move an ingredient to the trash.
+///
+/// Trashed ingredients can be restored until they are purged after the retention period.
+/// The removal is rejected with a conflict if the ingredient changed since the client got it.
+#[utoipa::path(
+    post,
+    path = "/v1/ingredients/remove",
+    tag = "Ingredients",
+    request_body = RemoveIngredientPayload,
+    responses(
+        (status = 200, description = "The ingredient was moved to the trash"),
+        (status = 409, description = "The ingredient changed since the client got it, it's returned as it is now", body = Ingredient),
+        (status = "4XX", description = "The `RemoveIngredientErrors` that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The `RemoveIngredientErrors` that made the request fail", body = String, content_type = "text/plain"),
+    )
+)]
 pub async fn remove_ingredient(
     payload: Json<serde_json::Value>,
     client: Arc<Option<Client>>,
 ) -> Result<impl IntoResponse, ResponseError<RemoveIngredientErrors>> {
-    let id = ID.fetch_add(1, std::sync::atomic::Ordering::AcqRel);
-    let tracing_prefix = format!("/ingredients/remove - {}:", id);
+    let tracing_prefix = format!("/ingredients/remove - {}:", current_request_id());
 
     tracing::debug!("{} START", tracing_prefix);
 
@@ -48,6 +82,8 @@ pub async fn remove_ingredient(
     let RemoveIngredientPayload {
         token,
         ingredient_id,
+        reason,
+        version,
     } = match serde_json::from_value(payload.0.clone()) {
         Ok(p) => p,
         Err(err) => {
@@ -84,6 +120,7 @@ pub async fn remove_ingredient(
             Err(error)?
         }
     };
+    let user_id = token_info.user_id.clone();
     tracing::debug!("{} JWT extracted successfully!", tracing_prefix);
 
     let conn = client.as_ref().as_ref().ok_or_else(|| {
@@ -125,16 +162,65 @@ pub async fn remove_ingredient(
     }
     tracing::debug!("{} Session is valid!", tracing_prefix);
 
-    tracing::debug!("{} Removing ingredient...", tracing_prefix);
-    if let Err(err) = conn
-        .execute(
-            "DELETE FROM sf_ingredient WHERE ingredient_id=$1",
-            &[&ingredient_id.to_string()],
+    tracing::debug!("{} Getting current household...", tracing_prefix);
+    let household = get_current_household(conn, &user_id).await.map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while getting the household of user `{}`!",
+            tracing_prefix,
+            err,
+            user_id
+        );
+        let error: ResponseError<_> = match err {
+            GetCurrentHouseholdErrors::NoHouseholdSelected => (
+                StatusCode::BAD_REQUEST,
+                RemoveIngredientErrors::NoHouseholdSelected,
+            ),
+            _ => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                RemoveIngredientErrors::ErrorGettingHousehold,
+            ),
+        }
+        .into();
+        error
+    })?;
+    if !household.role.can_edit_ingredients() {
+        tracing::error!(
+            "{} The user can't change the ingredients of household `{}`!",
+            tracing_prefix,
+            household.household_id
+        );
+        let error: ResponseError<_> = (
+            StatusCode::FORBIDDEN,
+            RemoveIngredientErrors::NotAllowedInHousehold,
         )
-        .await
-    {
+            .into();
+        Err(error)?
+    }
+    tracing::debug!(
+        "{} Working on household `{}`!",
+        tracing_prefix,
+        household.household_id
+    );
+
+    tracing::debug!("{} Moving ingredient to the trash...", tracing_prefix);
+    let rows_modified = timed_query(
+        "remove_ingredient",
+        conn.execute(
+            "WITH removed AS (UPDATE sf_ingredient SET deleted_date=NOW(), updated_at=NOW(), version=version + 1 WHERE ingredient_id=$1 AND household_id=$2 AND deleted_date IS NULL AND version=$6 RETURNING *) INSERT INTO sf_inventory_event (household_id, user_id, ingredient_id, name, category, quantity, unit, expire_date, event_type, reason) SELECT household_id, $3, ingredient_id, name, category, quantity, unit, expire_date, $4, $5 FROM removed",
+            &[
+                &ingredient_id.to_string(),
+                &household.household_id,
+                &user_id,
+                &format!("{:?}", InventoryEventType::Removed),
+                &reason.map(|r| format!("{:?}", r)),
+                &version,
+            ],
+        ),
+    )
+    .await
+    .map_err(|err| {
         tracing::error!(
-            "{} An error `{:?}` occurred while deleting ingredient from DB!",
+            "{} An error `{:?}` occurred while moving ingredient to the trash!",
             tracing_prefix,
             err
         );
@@ -143,10 +229,64 @@ pub async fn remove_ingredient(
             RemoveIngredientErrors::ErrorRemovingIngredient,
         )
             .into();
+        error
+    })?;
+    if rows_modified == 0 {
+        let ingredient_id = ingredient_id.to_string();
+        let current = get_ingredient(
+            conn,
+            &household.household_id,
+            &ingredient_id,
+            &tracing_prefix,
+        )
+        .await
+        .map_err(|err| {
+            tracing::error!(
+                "{} An error `{:?}` occurred while getting the ingredient `{}`!",
+                tracing_prefix,
+                err,
+                ingredient_id
+            );
+            let error: ResponseError<_> = (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                RemoveIngredientErrors::ErrorRemovingIngredient,
+            )
+                .into();
+            error
+        })?;
+        let error: ResponseError<_> = match current {
+            Some(current) => {
+                tracing::error!(
+                    "{} The ingredient is on version {} but version {} was removed!",
+                    tracing_prefix,
+                    current.version,
+                    version
+                );
+                ResponseError::from((
+                    StatusCode::CONFLICT,
+                    RemoveIngredientErrors::VersionConflict {
+                        current: Box::new(current.clone()),
+                    },
+                ))
+                .with_json(&current)
+            }
+            None => {
+                tracing::error!(
+                    "{} The ingredient `{}` wasn't found!",
+                    tracing_prefix,
+                    ingredient_id
+                );
+                (
+                    StatusCode::NOT_FOUND,
+                    RemoveIngredientErrors::IngredientNotFound,
+                )
+                    .into()
+            }
+        };
         Err(error)?
     }
     tracing::debug!(
-        "{} Ingredient with id `{}` removed",
+        "{} Ingredient with id `{}` moved to the trash",
         tracing_prefix,
         ingredient_id
     );
@@ -0,0 +1,244 @@
+use std::{fmt::Display, sync::Arc};
+
+use axum::{response::IntoResponse, Json};
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::Client;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    extract_jwt,
+    households::{get_current_household, GetCurrentHouseholdErrors},
+    is_session_valid,
+    metrics::timed_query,
+    models::InventoryEventType,
+    request_id::current_request_id,
+    responses::ResponseError,
+    APP_SECRET,
+};
+
+#[derive(Debug, Serialize)]
+pub enum RemoveStorageLocationErrors {
+    InvalidPayload { payload: String },
+    InvalidJWT,
+    NoDBConnectionFound,
+    ErrorCheckingIfSessionIsValid,
+    JWTExpired,
+    ErrorGettingHousehold,
+    NoHouseholdSelected,
+    NotAllowedInHousehold,
+    LocationNotFound,
+    ErrorRemovingLocation,
+}
+
+impl Display for RemoveStorageLocationErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RemoveStorageLocationPayload {
+    token: String,
+    location_id: Uuid,
+}
+
+/// Route to remove a storage location of the current household.
+///
+/// Default locations can't be removed, the ingredients of the location
+/// are moved to the default location of the same kind.
+#[utoipa::path(
+    post,
+    path = "/v1/locations/remove",
+    tag = "Locations",
+    request_body = RemoveStorageLocationPayload,
+    responses(
+        (status = 200, description = "The location was removed"),
+        (status = "4XX", description = "The `RemoveStorageLocationErrors` that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The `RemoveStorageLocationErrors` that made the request fail", body = String, content_type = "text/plain"),
+    )
+)]
+pub async fn remove_storage_location(
+    payload: Json<serde_json::Value>,
+    client: Arc<Option<Client>>,
+) -> Result<impl IntoResponse, ResponseError<RemoveStorageLocationErrors>> {
+    let tracing_prefix = format!("/locations/remove - {}:", current_request_id());
+
+    tracing::debug!("{} START", tracing_prefix);
+
+    tracing::debug!("{} Parsing payload...", tracing_prefix);
+    let RemoveStorageLocationPayload { token, location_id } =
+        match serde_json::from_value(payload.0.clone()) {
+            Ok(p) => p,
+            Err(err) => {
+                tracing::error!(
+                    "{} An error `{:?}` occurred parsing payload `{}`",
+                    tracing_prefix,
+                    err,
+                    payload.0
+                );
+                let error: ResponseError<_> = (
+                    StatusCode::BAD_REQUEST,
+                    RemoveStorageLocationErrors::InvalidPayload {
+                        payload: payload.0.to_string(),
+                    },
+                )
+                    .into();
+                Err(error)?
+            }
+        };
+    tracing::debug!("{} Payload parsed successfully!", tracing_prefix);
+
+    tracing::debug!("{} Extracting JWT...", tracing_prefix);
+    let token_info = match extract_jwt(APP_SECRET, &token) {
+        Ok(t) => t,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred while extracting the JWT `{}`",
+                tracing_prefix,
+                err,
+                token
+            );
+            let error: ResponseError<_> = (
+                StatusCode::BAD_REQUEST,
+                RemoveStorageLocationErrors::InvalidJWT,
+            )
+                .into();
+            Err(error)?
+        }
+    };
+    let user_id = token_info.user_id.clone();
+    tracing::debug!("{} JWT extracted successfully!", tracing_prefix);
+
+    tracing::debug!("{} Checking for DB connection...", tracing_prefix);
+    let conn = client.as_ref().as_ref().ok_or_else(|| {
+        tracing::error!("{} No DB connection found!", tracing_prefix);
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            RemoveStorageLocationErrors::NoDBConnectionFound,
+        )
+            .into();
+        error
+    })?;
+    tracing::debug!("{} DB Connection found!", tracing_prefix);
+
+    tracing::debug!("{} Checking if session is valid...", tracing_prefix);
+    if let Err(err) = is_session_valid(token_info, conn).await {
+        tracing::error!(
+            "{} An error `{:?}` occurred while checking if session is valid!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = match err {
+            crate::IsSessionValidErrors::InternalDBError(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                RemoveStorageLocationErrors::ErrorCheckingIfSessionIsValid,
+            ),
+            crate::IsSessionValidErrors::InvalidSessionData {
+                current_date: _,
+                db_expire_date: _,
+            } => (
+                StatusCode::UNAUTHORIZED,
+                RemoveStorageLocationErrors::JWTExpired,
+            ),
+            _ => (
+                StatusCode::BAD_REQUEST,
+                RemoveStorageLocationErrors::ErrorCheckingIfSessionIsValid,
+            ),
+        }
+        .into();
+
+        Err(error)?
+    }
+    tracing::debug!("{} Session is valid!", tracing_prefix);
+
+    tracing::debug!("{} Getting current household...", tracing_prefix);
+    let household = get_current_household(conn, &user_id).await.map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while getting the household of user `{}`!",
+            tracing_prefix,
+            err,
+            user_id
+        );
+        let error: ResponseError<_> = match err {
+            GetCurrentHouseholdErrors::NoHouseholdSelected => (
+                StatusCode::BAD_REQUEST,
+                RemoveStorageLocationErrors::NoHouseholdSelected,
+            ),
+            _ => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                RemoveStorageLocationErrors::ErrorGettingHousehold,
+            ),
+        }
+        .into();
+        error
+    })?;
+    if !household.role.can_edit_ingredients() {
+        tracing::error!(
+            "{} The user can't change the ingredients of household `{}`!",
+            tracing_prefix,
+            household.household_id
+        );
+        let error: ResponseError<_> = (
+            StatusCode::FORBIDDEN,
+            RemoveStorageLocationErrors::NotAllowedInHousehold,
+        )
+            .into();
+        Err(error)?
+    }
+    tracing::debug!(
+        "{} Working on household `{}`!",
+        tracing_prefix,
+        household.household_id
+    );
+
+    tracing::debug!("{} Removing location `{}`...", tracing_prefix, location_id);
+    // Moving the ingredients in the same statement keeps them from being left without a location,
+    // the ones that aren't in the trash get a `Moved` event like the ones moved by the user.
+    let removed = timed_query(
+        "remove_storage_location",
+        conn.execute(
+            "WITH location AS (SELECT location_id, kind FROM sf_storage_location WHERE location_id=$1 AND household_id=$2 AND NOT is_default), moved AS (UPDATE sf_ingredient i SET location_id=d.location_id, updated_at=NOW(), version=version + 1 FROM location l JOIN sf_storage_location d ON d.household_id=$2 AND d.kind=l.kind AND d.is_default WHERE i.location_id=l.location_id RETURNING i.*), events AS (INSERT INTO sf_inventory_event (household_id, user_id, ingredient_id, name, category, quantity, unit, expire_date, event_type, reason) SELECT household_id, $3, ingredient_id, name, category, quantity, unit, expire_date, $4, NULL FROM moved WHERE deleted_date IS NULL) DELETE FROM sf_storage_location WHERE location_id IN (SELECT location_id FROM location)",
+            &[
+                &location_id.to_string(),
+                &household.household_id,
+                &user_id,
+                &format!("{:?}", InventoryEventType::Moved),
+            ],
+        ),
+    )
+    .await
+    .map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while removing location `{}`!",
+            tracing_prefix,
+            err,
+            location_id
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            RemoveStorageLocationErrors::ErrorRemovingLocation,
+        )
+            .into();
+        error
+    })?;
+
+    if removed == 0 {
+        tracing::error!(
+            "{} No location `{}` that isn't a default found!",
+            tracing_prefix,
+            location_id
+        );
+        let error: ResponseError<_> = (
+            StatusCode::NOT_FOUND,
+            RemoveStorageLocationErrors::LocationNotFound,
+        )
+            .into();
+        Err(error)?
+    }
+    tracing::debug!("{} Location removed!", tracing_prefix);
+
+    tracing::debug!("{} DONE", tracing_prefix);
+    Ok(StatusCode::OK)
+}
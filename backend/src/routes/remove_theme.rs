@@ -0,0 +1,182 @@
+use std::{fmt::Display, sync::Arc};
+
+use axum::{response::IntoResponse, Json};
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::Client;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    extract_jwt, is_session_valid, metrics::timed_query, models::AppThemes,
+    request_id::current_request_id, responses::ResponseError, APP_SECRET,
+};
+
+#[derive(Debug, Serialize)]
+pub enum RemoveThemeErrors {
+    InvalidPayload { payload: String },
+    InvalidJWT,
+    NoDBConnectionFound,
+    ErrorCheckingIfSessionIsValid,
+    JWTExpired,
+    ThemeNotFound,
+    ErrorRemovingTheme,
+}
+
+impl Display for RemoveThemeErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct RemoveThemePayload {
+    token: String,
+    theme_id: Uuid,
+}
+
+/// Route to remove a theme of the user.
+///
+/// If the user had it selected the default theme is selected instead,
+/// the copies other users imported are kept.
+#[utoipa::path(
+    post,
+    path = "/v1/themes/remove",
+    tag = "Themes",
+    request_body = RemoveThemePayload,
+    responses(
+        (status = 200, description = "The theme was removed"),
+        (status = "4XX", description = "The `RemoveThemeErrors` that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The `RemoveThemeErrors` that made the request fail", body = String, content_type = "text/plain"),
+    )
+)]
+pub async fn remove_theme(
+    payload: Json<serde_json::Value>,
+    client: Arc<Option<Client>>,
+) -> Result<impl IntoResponse, ResponseError<RemoveThemeErrors>> {
+    let tracing_prefix = format!("/themes/remove - {}:", current_request_id());
+
+    tracing::debug!("{} START", tracing_prefix);
+
+    tracing::debug!("{} Parsing payload...", tracing_prefix);
+    let RemoveThemePayload { token, theme_id } = match serde_json::from_value(payload.0.clone()) {
+        Ok(p) => p,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred parsing payload `{}`",
+                tracing_prefix,
+                err,
+                payload.0
+            );
+            let error: ResponseError<_> = (
+                StatusCode::BAD_REQUEST,
+                RemoveThemeErrors::InvalidPayload {
+                    payload: payload.0.to_string(),
+                },
+            )
+                .into();
+            Err(error)?
+        }
+    };
+    tracing::debug!("{} Payload parsed successfully!", tracing_prefix);
+
+    tracing::debug!("{} Extracting JWT...", tracing_prefix);
+    let token_info = match extract_jwt(APP_SECRET, &token) {
+        Ok(t) => t,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred while extracting the JWT `{}`",
+                tracing_prefix,
+                err,
+                token
+            );
+            let error: ResponseError<_> =
+                (StatusCode::BAD_REQUEST, RemoveThemeErrors::InvalidJWT).into();
+            Err(error)?
+        }
+    };
+    let user_id = token_info.user_id.clone();
+    tracing::debug!("{} JWT extracted successfully!", tracing_prefix);
+
+    tracing::debug!("{} Checking for DB connection...", tracing_prefix);
+    let conn = client.as_ref().as_ref().ok_or_else(|| {
+        tracing::error!("{} No DB connection found!", tracing_prefix);
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            RemoveThemeErrors::NoDBConnectionFound,
+        )
+            .into();
+        error
+    })?;
+    tracing::debug!("{} DB Connection found!", tracing_prefix);
+
+    tracing::debug!("{} Checking if session is valid...", tracing_prefix);
+    if let Err(err) = is_session_valid(token_info, conn).await {
+        tracing::error!(
+            "{} An error `{:?}` occurred while checking if session is valid!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = match err {
+            crate::IsSessionValidErrors::InternalDBError(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                RemoveThemeErrors::ErrorCheckingIfSessionIsValid,
+            ),
+            crate::IsSessionValidErrors::InvalidSessionData {
+                current_date: _,
+                db_expire_date: _,
+            } => (StatusCode::UNAUTHORIZED, RemoveThemeErrors::JWTExpired),
+            _ => (
+                StatusCode::BAD_REQUEST,
+                RemoveThemeErrors::ErrorCheckingIfSessionIsValid,
+            ),
+        }
+        .into();
+
+        Err(error)?
+    }
+    tracing::debug!("{} Session is valid!", tracing_prefix);
+
+    tracing::debug!("{} Removing theme `{}`...", tracing_prefix, theme_id);
+    let removed = timed_query(
+        "remove_theme",
+        conn.execute(
+            "WITH reset AS (UPDATE sf_settings SET theme=$3, custom_theme_id=NULL, updated_at=NOW() WHERE user_id=$2 AND custom_theme_id=$1) DELETE FROM sf_theme WHERE theme_id=$1 AND user_id=$2",
+            &[
+                &theme_id.to_string(),
+                &user_id,
+                &format!("{:?}", AppThemes::default()),
+            ],
+        ),
+    )
+    .await
+    .map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while removing theme `{}`!",
+            tracing_prefix,
+            err,
+            theme_id
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            RemoveThemeErrors::ErrorRemovingTheme,
+        )
+            .into();
+        error
+    })?;
+
+    if removed == 0 {
+        tracing::error!(
+            "{} No theme `{}` found for the user!",
+            tracing_prefix,
+            theme_id
+        );
+        let error: ResponseError<_> =
+            (StatusCode::NOT_FOUND, RemoveThemeErrors::ThemeNotFound).into();
+        Err(error)?
+    }
+    tracing::debug!("{} Theme removed!", tracing_prefix);
+
+    tracing::debug!("{} DONE", tracing_prefix);
+    Ok(StatusCode::OK)
+}
@@ -0,0 +1,216 @@
+use std::{fmt::Display, sync::Arc};
+
+use axum::{response::IntoResponse, Json};
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::Client;
+
+use utoipa::ToSchema;
+
+use crate::{
+    extract_jwt, is_session_valid, metrics::timed_query, models::Recipe,
+    recipe_cache::get_cached_recipes, request_id::current_request_id, responses::ResponseError,
+    APP_SECRET,
+};
+
+#[derive(Debug, Serialize)]
+pub enum SaveRecipeErrors {
+    InvalidPayload { payload: String },
+    InvalidJWT,
+    NoDBConnectionFound,
+    ErrorCheckingIfSessionIsValid,
+    JWTExpired,
+    ErrorRetrievingRecipe,
+    RecipeNotFound,
+    ErrorSavingRecipe,
+}
+
+impl Display for SaveRecipeErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// The recipe is optional, when it isn't supplied the copy the backend stored
+/// when it returned the recipe is used.
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SaveRecipePayload {
+    token: String,
+    recipe_id: String,
+    recipe: Option<Recipe>,
+    #[serde(default)]
+    notes: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+/// Route to save a recipe to the user's saved recipes.
+///
+/// If the recipe was already saved only the notes and tags are updated,
+/// the saved copy of the recipe never changes.
+#[utoipa::path(
+    post,
+    path = "/v1/recipes/save",
+    tag = "Recipes",
+    request_body = SaveRecipePayload,
+    responses(
+        (status = 200, description = "The recipe was saved"),
+        (status = "4XX", description = "The `SaveRecipeErrors` that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The `SaveRecipeErrors` that made the request fail", body = String, content_type = "text/plain"),
+    )
+)]
+pub async fn save_recipe(
+    payload: Json<serde_json::Value>,
+    client: Arc<Option<Client>>,
+) -> Result<impl IntoResponse, ResponseError<SaveRecipeErrors>> {
+    let tracing_prefix = format!("/recipes/save - {}:", current_request_id());
+
+    tracing::debug!("{} START", tracing_prefix);
+
+    tracing::debug!("{} Parsing payload...", tracing_prefix);
+    let SaveRecipePayload {
+        token,
+        recipe_id,
+        recipe,
+        notes,
+        tags,
+    } = match serde_json::from_value(payload.0.clone()) {
+        Ok(p) => p,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred parsing payload `{}`",
+                tracing_prefix,
+                err,
+                payload.0
+            );
+            let error: ResponseError<_> = (
+                StatusCode::BAD_REQUEST,
+                SaveRecipeErrors::InvalidPayload {
+                    payload: payload.0.to_string(),
+                },
+            )
+                .into();
+            Err(error)?
+        }
+    };
+    tracing::debug!("{} Payload parsed successfully!", tracing_prefix);
+
+    tracing::debug!("{} Extracting JWT...", tracing_prefix);
+    let token_info = match extract_jwt(APP_SECRET, &token) {
+        Ok(t) => t,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred while extracting the JWT `{}`",
+                tracing_prefix,
+                err,
+                token
+            );
+            let error: ResponseError<_> =
+                (StatusCode::BAD_REQUEST, SaveRecipeErrors::InvalidJWT).into();
+            Err(error)?
+        }
+    };
+    let user_id = token_info.user_id.clone();
+    tracing::debug!("{} JWT extracted successfully!", tracing_prefix);
+
+    tracing::debug!("{} Checking for DB connection...", tracing_prefix);
+    let conn = client.as_ref().as_ref().ok_or_else(|| {
+        tracing::error!("{} No DB connection found!", tracing_prefix);
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            SaveRecipeErrors::NoDBConnectionFound,
+        )
+            .into();
+        error
+    })?;
+    tracing::debug!("{} DB Connection found!", tracing_prefix);
+
+    tracing::debug!("{} Checking if session is valid...", tracing_prefix);
+    if let Err(err) = is_session_valid(token_info, conn).await {
+        tracing::error!(
+            "{} An error `{:?}` occurred while checking if session is valid!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = match err {
+            crate::IsSessionValidErrors::InternalDBError(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                SaveRecipeErrors::ErrorCheckingIfSessionIsValid,
+            ),
+            crate::IsSessionValidErrors::InvalidSessionData {
+                current_date: _,
+                db_expire_date: _,
+            } => (StatusCode::UNAUTHORIZED, SaveRecipeErrors::JWTExpired),
+            _ => (
+                StatusCode::BAD_REQUEST,
+                SaveRecipeErrors::ErrorCheckingIfSessionIsValid,
+            ),
+        }
+        .into();
+
+        Err(error)?
+    }
+    tracing::debug!("{} Session is valid!", tracing_prefix);
+
+    let recipe = match recipe {
+        Some(recipe) => recipe,
+        None => {
+            tracing::debug!("{} Getting stored copy of the recipe...", tracing_prefix);
+            get_cached_recipes(conn, std::slice::from_ref(&recipe_id), &tracing_prefix)
+                .await
+                .map_err(|err| {
+                    tracing::error!(
+                        "{} An error `{:?}` occurred while getting recipe `{}`!",
+                        tracing_prefix,
+                        err,
+                        recipe_id
+                    );
+                    let error: ResponseError<_> = (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        SaveRecipeErrors::ErrorRetrievingRecipe,
+                    )
+                        .into();
+                    error
+                })?
+                .pop()
+                .ok_or_else(|| {
+                    tracing::error!(
+                        "{} No recipe found with id `{}`!",
+                        tracing_prefix,
+                        recipe_id
+                    );
+                    let error: ResponseError<_> =
+                        (StatusCode::NOT_FOUND, SaveRecipeErrors::RecipeNotFound).into();
+                    error
+                })?
+        }
+    };
+
+    tracing::debug!("{} Saving recipe `{}`...", tracing_prefix, recipe_id);
+    if let Err(err) = timed_query(
+        "save_recipe",
+        conn.execute(
+            "INSERT INTO sf_saved_recipe (user_id, recipe_id, recipe, notes, tags) VALUES ($1, $2, $3, $4, $5) ON CONFLICT (user_id, recipe_id) DO UPDATE SET notes=EXCLUDED.notes, tags=EXCLUDED.tags",
+            &[&user_id, &recipe_id, &tokio_postgres::types::Json(&recipe), &notes, &tags],
+        ),
+    )
+    .await
+    {
+        tracing::error!(
+            "{} An error `{:?}` occurred while saving recipe `{}`!",
+            tracing_prefix,
+            err,
+            recipe_id
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            SaveRecipeErrors::ErrorSavingRecipe,
+        )
+            .into();
+        Err(error)?
+    }
+    tracing::debug!("{} Recipe saved!", tracing_prefix);
+
+    tracing::debug!("{} DONE", tracing_prefix);
+    Ok(StatusCode::OK)
+}
@@ -1,16 +1,19 @@
-use std::{
-    fmt::Display,
-    sync::{atomic::AtomicUsize, Arc},
-};
+use std::{fmt::Display, sync::Arc};
 
 use axum::{response::IntoResponse, Json};
 use hyper::StatusCode;
 use serde::{Deserialize, Serialize};
 use tokio_postgres::Client;
+use utoipa::ToSchema;
 use uuid::Uuid;
 
 use crate::{
-    extract_jwt, is_session_valid, models::AppThemes, responses::ResponseError, APP_SECRET,
+    extract_jwt, is_session_valid,
+    metrics::timed_query,
+    models::{AppThemes, DietaryPreference, UnitSystem, SETTINGS_SCHEMA_VERSION},
+    request_id::current_request_id,
+    responses::ResponseError,
+    APP_SECRET,
 };
 
 #[derive(Debug)]
@@ -21,6 +24,12 @@ pub enum SaveSettingsErrors {
     ErrorCheckingIfSessionIsValid,
     JWTExpired,
     ErrorSavingSettings,
+    UnsupportedSchemaVersion { version: i32 },
+    InvalidSetting { setting: String },
+    SettingsNotFound,
+    MissingCustomThemeId,
+    ErrorCheckingIfCustomThemeExists,
+    CustomThemeNotFound,
 }
 
 impl Display for SaveSettingsErrors {
@@ -29,30 +38,124 @@ impl Display for SaveSettingsErrors {
     }
 }
 
-#[derive(Debug, Deserialize)]
-struct SaveSettingsPayload {
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SaveSettingsPayload {
     token: String,
     settings: UserSettingsPayload,
 }
 
 /// Contains the theme id and all the options the user is allowed to change.
-#[derive(Debug, Serialize, Deserialize)]
-struct UserSettingsPayload {
+///
+/// Missing options keep the value the user already had,
+/// so clients with an older schema version only change the options they know about.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct UserSettingsPayload {
     #[serde(rename = "SettingsId")]
     settings_id: Uuid,
 
+    /// The first clients didn't send the version.
+    #[serde(rename = "SchemaVersion", default = "first_schema_version")]
+    schema_version: i32,
+
     #[serde(rename = "Theme")]
-    theme: AppThemes,
+    theme: Option<AppThemes>,
+
+    /// Required when the theme is `Custom`, it must be one of the user's themes.
+    #[serde(rename = "CustomThemeId")]
+    custom_theme_id: Option<Uuid>,
+
+    /// When missing the diets the user already had are kept.
+    #[serde(rename = "Diets")]
+    diets: Option<Vec<DietaryPreference>>,
+
+    /// When missing the excluded ingredients the user already had are kept.
+    #[serde(rename = "ExcludedIngredients")]
+    excluded_ingredients: Option<Vec<String>>,
+
+    /// A language tag like "en" or "es-GT".
+    #[serde(rename = "Language")]
+    language: Option<String>,
+
+    #[serde(rename = "UnitSystem")]
+    unit_system: Option<UnitSystem>,
+
+    #[serde(rename = "ExpiryWarningDays")]
+    expiry_warning_days: Option<i32>,
+
+    /// Only the notifications sent are changed.
+    #[serde(rename = "Notifications")]
+    notifications: Option<NotificationPreferencesPayload>,
+
+    #[serde(rename = "DefaultServings")]
+    default_servings: Option<i32>,
+}
+
+fn first_schema_version() -> i32 {
+    1
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct NotificationPreferencesPayload {
+    #[serde(
+        rename = "ExpiringIngredients",
+        skip_serializing_if = "Option::is_none"
+    )]
+    expiring_ingredients: Option<bool>,
+
+    #[serde(rename = "MealPlanReminders", skip_serializing_if = "Option::is_none")]
+    meal_plan_reminders: Option<bool>,
+
+    #[serde(
+        rename = "ShoppingListUpdates",
+        skip_serializing_if = "Option::is_none"
+    )]
+    shopping_list_updates: Option<bool>,
 }
 
-static ID: AtomicUsize = AtomicUsize::new(0);
+/// Returns the name of the first option with a value out of its range.
+fn invalid_setting(settings: &UserSettingsPayload) -> Option<&'static str> {
+    if let Some(language) = &settings.language {
+        let is_tag = !language.is_empty()
+            && language.len() <= 16
+            && language
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-');
+        if !is_tag {
+            return Some("Language");
+        }
+    }
+
+    if let Some(days) = settings.expiry_warning_days {
+        if !(0..=60).contains(&days) {
+            return Some("ExpiryWarningDays");
+        }
+    }
+
+    if let Some(servings) = settings.default_servings {
+        if !(1..=50).contains(&servings) {
+            return Some("DefaultServings");
+        }
+    }
+
+    None
+}
 
+#[utoipa::path(
+    post,
+    path = "/v1/settings/save",
+    tag = "Settings",
+    request_body = SaveSettingsPayload,
+    responses(
+        (status = 200, description = "The settings were saved"),
+        (status = "4XX", description = "The `SaveSettingsErrors` that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The `SaveSettingsErrors` that made the request fail", body = String, content_type = "text/plain"),
+    )
+)]
 pub async fn save_settings(
     payload: Json<serde_json::Value>,
     client: Arc<Option<Client>>,
 ) -> Result<impl IntoResponse, ResponseError<SaveSettingsErrors>> {
-    let id = ID.fetch_add(1, std::sync::atomic::Ordering::AcqRel);
-    let tracing_prefix = format!("/settings/save - {}:", id);
+    let tracing_prefix = format!("/settings/save - {}:", current_request_id());
 
     tracing::debug!("{} START", tracing_prefix);
 
@@ -92,6 +195,7 @@ pub async fn save_settings(
             Err(error)?
         }
     };
+    let user_id = token_info.user_id.clone();
     tracing::debug!("{} JWT extracted successfully!", tracing_prefix);
 
     tracing::debug!("{} Checking DB connection...", tracing_prefix);
@@ -132,15 +236,119 @@ pub async fn save_settings(
     };
     tracing::debug!("{} Session is valid!", tracing_prefix);
 
-    tracing::debug!("{} Saving settings in DB...", tracing_prefix);
-    let theme = format!("{:?}", settings.theme);
-    if let Err(err) = conn
-        .execute(
-            "UPDATE sf_settings SET theme=$2 WHERE settings_id=$1",
-            &[&settings.settings_id.to_string(), &theme],
+    tracing::debug!("{} Validating settings...", tracing_prefix);
+    if settings.schema_version > SETTINGS_SCHEMA_VERSION {
+        tracing::error!(
+            "{} The settings schema version `{}` isn't supported!",
+            tracing_prefix,
+            settings.schema_version
+        );
+        let error: ResponseError<_> = (
+            StatusCode::BAD_REQUEST,
+            SaveSettingsErrors::UnsupportedSchemaVersion {
+                version: settings.schema_version,
+            },
         )
-        .await
-    {
+            .into();
+        Err(error)?
+    }
+    if let Some(setting) = invalid_setting(&settings) {
+        tracing::error!("{} The setting `{}` is invalid!", tracing_prefix, setting);
+        let error: ResponseError<_> = (
+            StatusCode::BAD_REQUEST,
+            SaveSettingsErrors::InvalidSetting {
+                setting: setting.to_string(),
+            },
+        )
+            .into();
+        Err(error)?
+    }
+    let custom_theme_id = match settings.theme {
+        Some(AppThemes::Custom) => {
+            let Some(theme_id) = settings.custom_theme_id.map(|id| id.to_string()) else {
+                tracing::error!("{} No custom theme was selected!", tracing_prefix);
+                let error: ResponseError<_> = (
+                    StatusCode::BAD_REQUEST,
+                    SaveSettingsErrors::MissingCustomThemeId,
+                )
+                    .into();
+                Err(error)?
+            };
+
+            let theme_exists = !timed_query(
+                "save_settings",
+                conn.query(
+                    "SELECT theme_id FROM sf_theme WHERE theme_id=$1 AND user_id=$2",
+                    &[&theme_id, &user_id],
+                ),
+            )
+            .await
+            .map_err(|err| {
+                tracing::error!(
+                    "{} An error `{:?}` occurred while checking if theme `{}` exists!",
+                    tracing_prefix,
+                    err,
+                    theme_id
+                );
+                let error: ResponseError<_> = (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    SaveSettingsErrors::ErrorCheckingIfCustomThemeExists,
+                )
+                    .into();
+                error
+            })?
+            .is_empty();
+            if !theme_exists {
+                tracing::error!("{} The user has no theme `{}`!", tracing_prefix, theme_id);
+                let error: ResponseError<_> = (
+                    StatusCode::NOT_FOUND,
+                    SaveSettingsErrors::CustomThemeNotFound,
+                )
+                    .into();
+                Err(error)?
+            }
+            Some(theme_id)
+        }
+        _ => None,
+    };
+    tracing::debug!("{} Settings are valid!", tracing_prefix);
+
+    tracing::debug!("{} Saving settings in DB...", tracing_prefix);
+    let theme = settings.theme.map(|t| format!("{:?}", t));
+    let unit_system = settings.unit_system.map(|u| format!("{:?}", u));
+    let notifications = settings.notifications.map(tokio_postgres::types::Json);
+    let diets: Option<Vec<String>> = settings
+        .diets
+        .map(|diets| diets.iter().map(|d| format!("{:?}", d)).collect());
+    let excluded_ingredients: Option<Vec<String>> = settings.excluded_ingredients.map(|excluded| {
+        excluded
+            .iter()
+            .map(|e| e.trim().to_string())
+            .filter(|e| !e.is_empty())
+            .collect()
+    });
+    let updated = timed_query(
+        "save_settings",
+        conn.execute(
+            "UPDATE sf_settings SET schema_version=GREATEST(schema_version, $3), theme=COALESCE($4, theme), custom_theme_id=CASE WHEN $4::TEXT IS NULL THEN custom_theme_id ELSE $12 END, diets=COALESCE($5, diets), excluded_ingredients=COALESCE($6, excluded_ingredients), language=COALESCE($7, language), unit_system=COALESCE($8, unit_system), expiry_warning_days=COALESCE($9, expiry_warning_days), notifications=notifications || COALESCE($10, '{}'::JSONB), default_servings=COALESCE($11, default_servings), updated_at=NOW() WHERE settings_id=$1 AND user_id=$2",
+            &[
+                &settings.settings_id.to_string(),
+                &user_id,
+                &settings.schema_version,
+                &theme,
+                &diets,
+                &excluded_ingredients,
+                &settings.language,
+                &unit_system,
+                &settings.expiry_warning_days,
+                &notifications,
+                &settings.default_servings,
+                &custom_theme_id,
+            ],
+        ),
+    )
+    .await
+    .map_err(|err| {
         tracing::error!(
             "{} An error `{:?}` occurred while updating settings `{}`",
             tracing_prefix,
@@ -152,6 +360,17 @@ pub async fn save_settings(
             SaveSettingsErrors::ErrorSavingSettings,
         )
             .into();
+        error
+    })?;
+
+    if updated == 0 {
+        tracing::error!(
+            "{} No settings `{}` found for the user!",
+            tracing_prefix,
+            settings.settings_id
+        );
+        let error: ResponseError<_> =
+            (StatusCode::NOT_FOUND, SaveSettingsErrors::SettingsNotFound).into();
         Err(error)?
     }
 
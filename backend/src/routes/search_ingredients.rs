@@ -1,18 +1,24 @@
-use std::{
-    fmt::Display,
-    sync::{atomic::AtomicUsize, Arc},
-};
+use std::{fmt::Display, sync::Arc};
 
 use axum::{response::IntoResponse, Json};
 
 use hyper::StatusCode;
 use serde::Deserialize;
 use tokio_postgres::Client;
-use uuid::Uuid;
+
+use utoipa::ToSchema;
 
 use crate::{
-    extract_jwt, is_session_valid, models::Ingredient, parse_db_ingredient,
-    responses::ResponseError, APP_SECRET,
+    extract_jwt,
+    households::{get_current_household, GetCurrentHouseholdErrors},
+    ingredient_lots::get_lots,
+    is_session_valid,
+    metrics::timed_query,
+    models::Ingredient,
+    parse_db_ingredient,
+    request_id::current_request_id,
+    responses::ResponseError,
+    APP_SECRET,
 };
 
 #[derive(Debug)]
@@ -24,6 +30,9 @@ pub enum SearchIngredientErrors {
     JWTExpired,
     ErrorRetrievingIngredients,
     InvalidIngredientFormatFromDB,
+    ErrorGettingHousehold,
+    NoHouseholdSelected,
+    ErrorGettingLots,
 }
 
 impl Display for SearchIngredientErrors {
@@ -32,30 +41,34 @@ impl Display for SearchIngredientErrors {
     }
 }
 
-#[derive(Debug, Deserialize)]
-struct SearchIngredientsPayload {
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SearchIngredientsPayload {
     token: String,
-    user_id: Uuid,
     query: String,
 }
 
-static ID: AtomicUsize = AtomicUsize::new(0);
-
+#[utoipa::path(
+    post,
+    path = "/v1/ingredients/search",
+    tag = "Ingredients",
+    request_body = SearchIngredientsPayload,
+    responses(
+        (status = 200, description = "The ingredients found", body = [Ingredient]),
+        (status = "4XX", description = "The `SearchIngredientErrors` that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The `SearchIngredientErrors` that made the request fail", body = String, content_type = "text/plain"),
+    )
+)]
 pub async fn search_ingredients(
     payload: Json<serde_json::Value>,
     client: Arc<Option<Client>>,
 ) -> Result<impl IntoResponse, ResponseError<SearchIngredientErrors>> {
-    let id = ID.fetch_add(1, std::sync::atomic::Ordering::AcqRel);
-    let tracing_prefix = format!("/ingredients/search - {}:", id);
+    let tracing_prefix = format!("/ingredients/search - {}:", current_request_id());
 
     tracing::debug!("{} START", tracing_prefix);
 
     tracing::debug!("{} Parsing payload...", tracing_prefix);
-    let SearchIngredientsPayload {
-        token,
-        user_id,
-        query,
-    } = match serde_json::from_value(payload.0.clone()) {
+    let SearchIngredientsPayload { token, query } = match serde_json::from_value(payload.0.clone())
+    {
         Ok(p) => p,
         Err(err) => {
             tracing::error!(
@@ -91,6 +104,7 @@ pub async fn search_ingredients(
             Err(error)?
         }
     };
+    let user_id = token_info.user_id.clone();
     tracing::debug!("{} JWT extracted successfully!", tracing_prefix);
 
     tracing::debug!("{} Checking DB connection...", tracing_prefix);
@@ -133,30 +147,59 @@ pub async fn search_ingredients(
     }
     tracing::debug!("{} Session is valid!", tracing_prefix);
 
+    tracing::debug!("{} Getting current household...", tracing_prefix);
+    let household = get_current_household(conn, &user_id).await.map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while getting the household of user `{}`!",
+            tracing_prefix,
+            err,
+            user_id
+        );
+        let error: ResponseError<_> = match err {
+            GetCurrentHouseholdErrors::NoHouseholdSelected => (
+                StatusCode::BAD_REQUEST,
+                SearchIngredientErrors::NoHouseholdSelected,
+            ),
+            _ => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                SearchIngredientErrors::ErrorGettingHousehold,
+            ),
+        }
+        .into();
+        error
+    })?;
+    tracing::debug!(
+        "{} Working on household `{}`!",
+        tracing_prefix,
+        household.household_id
+    );
+
     tracing::debug!("{} Getting ingredients from API...", tracing_prefix);
-    let ingredients = conn
-        .query(
-            "SELECT * FROM sf_ingredients WHERE user_id=$1 AND ( name % $2 OR category % $2 )",
-            &[&user_id.to_string(), &query],
+    let ingredients = timed_query(
+        "search_ingredients",
+        conn.query(
+            "SELECT * FROM sf_ingredient WHERE household_id=$1 AND deleted_date IS NULL AND ( name % $2 OR category % $2 )",
+            &[&household.household_id, &query],
+        ),
+    )
+    .await
+    .map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while trying to query ingredients from DB!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            SearchIngredientErrors::ErrorRetrievingIngredients,
         )
-        .await
-        .map_err(|err| {
-            tracing::error!(
-                "{} An error `{:?}` occurred while trying to query ingredients from DB!",
-                tracing_prefix,
-                err
-            );
-            let error: ResponseError<_> = (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                SearchIngredientErrors::ErrorRetrievingIngredients,
-            )
-                .into();
-            error
-        })?;
+            .into();
+        error
+    })?;
     tracing::debug!("{} Done getting ingredients!", tracing_prefix);
 
     tracing::debug!("{} Parsing ingredients...", tracing_prefix);
-    let ingredients = ingredients
+    let mut ingredients = ingredients
         .iter()
         .map(|row| {
             parse_db_ingredient(row, &tracing_prefix).ok_or_else(|| {
@@ -169,6 +212,28 @@ pub async fn search_ingredients(
             })
         })
         .collect::<Result<Vec<Ingredient>, ResponseError<SearchIngredientErrors>>>()?;
+
+    tracing::debug!("{} Getting lots...", tracing_prefix);
+    let ingredient_ids: Vec<String> = ingredients
+        .iter()
+        .map(|i| i.ingredient_id.to_string())
+        .collect();
+    let mut lots = get_lots(conn, &ingredient_ids).await.map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while getting the lots!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            SearchIngredientErrors::ErrorGettingLots,
+        )
+            .into();
+        error
+    })?;
+    for ingredient in ingredients.iter_mut() {
+        ingredient.lots = lots.remove(&ingredient.ingredient_id).unwrap_or_default();
+    }
     tracing::debug!("{} DONE", tracing_prefix);
 
     Ok(Json(ingredients))
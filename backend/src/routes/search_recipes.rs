@@ -1,7 +1,4 @@
-use std::{
-    fmt::Display,
-    sync::{atomic::AtomicUsize, Arc},
-};
+use std::{fmt::Display, sync::Arc, time::Instant};
 
 use axum::{response::IntoResponse, Json};
 
@@ -10,9 +7,20 @@ use serde::Deserialize;
 use serde_json::Value;
 use tokio_postgres::Client;
 
+use utoipa::ToSchema;
+
 use crate::{
-    extract_jwt, is_session_valid, models::Recipe, parse_api_recipe_from_value,
-    responses::ResponseError, Params, APP_SECRET,
+    custom_recipes::{find_custom_recipes, GetCustomRecipesErrors},
+    dietary::flag_dietary_violations,
+    extract_jwt, is_session_valid,
+    metrics::record_recipe_api_call,
+    models::Recipe,
+    parse_api_recipe_from_value,
+    recipe_cache::cache_recipes,
+    recipe_ratings::fill_user_ratings,
+    request_id::current_request_id,
+    responses::ResponseError,
+    Params, APP_SECRET,
 };
 
 #[derive(Debug)]
@@ -23,6 +31,9 @@ pub enum SearchRecipesErrors {
     ErrorCheckingIfSessionIsValid,
     JWTExpired,
     ErrorGettingRecipesFromAPI,
+    CouldntRetrieveCustomRecipesFromDB,
+    InvalidCustomRecipeFormatFromDB,
+    CouldntCheckDietaryRestrictions,
 }
 
 impl Display for SearchRecipesErrors {
@@ -31,26 +42,42 @@ impl Display for SearchRecipesErrors {
     }
 }
 
-#[derive(Debug, Deserialize)]
-struct SearchRecipesPayload {
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SearchRecipesPayload {
     token: String,
     query: String,
+    /// Whether to leave out the recipes that go against the dietary restrictions of the user,
+    /// otherwise they're only flagged.
+    #[serde(default)]
+    hide_restricted: bool,
 }
 
-static ID: AtomicUsize = AtomicUsize::new(0);
-
+#[utoipa::path(
+    post,
+    path = "/v1/recipes/search",
+    tag = "Recipes",
+    request_body = SearchRecipesPayload,
+    responses(
+        (status = 200, description = "The recipes found", body = [Recipe]),
+        (status = "4XX", description = "The `SearchRecipesErrors` that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The `SearchRecipesErrors` that made the request fail", body = String, content_type = "text/plain"),
+    )
+)]
 pub async fn search_recipes(
     payload: Json<serde_json::Value>,
     client: Arc<Option<Client>>,
     _params: Arc<Params>,
 ) -> Result<impl IntoResponse, ResponseError<SearchRecipesErrors>> {
-    let id = ID.fetch_add(1, std::sync::atomic::Ordering::AcqRel);
-    let tracing_prefix = format!("/recipes/search - {}:", id);
+    let tracing_prefix = format!("/recipes/search - {}:", current_request_id());
 
     tracing::debug!("{} START", tracing_prefix);
 
     tracing::debug!("{} Parsing payload...", tracing_prefix);
-    let SearchRecipesPayload { token, query } = match serde_json::from_value(payload.0.clone()) {
+    let SearchRecipesPayload {
+        token,
+        query,
+        hide_restricted,
+    } = match serde_json::from_value(payload.0.clone()) {
         Ok(p) => p,
         Err(err) => {
             tracing::error!(
@@ -85,6 +112,7 @@ pub async fn search_recipes(
             Err(error)?
         }
     };
+    let user_id = token_info.user_id.clone();
     tracing::debug!("{} JWT extracted successfully!", tracing_prefix);
 
     tracing::debug!("{} Checking if DB connection exists...", tracing_prefix);
@@ -125,6 +153,35 @@ pub async fn search_recipes(
     }
     tracing::debug!("{} Session is valid!", tracing_prefix);
 
+    tracing::debug!("{} Searching custom recipes...", tracing_prefix);
+    let mut custom_recipes = find_custom_recipes(conn, &user_id, Some(&query), &tracing_prefix)
+        .await
+        .map_err(|err| {
+            tracing::error!(
+                "{} An error `{:?}` occurred while searching custom recipes of user `{}`!",
+                tracing_prefix,
+                err,
+                user_id
+            );
+            let error: ResponseError<_> = match err {
+                GetCustomRecipesErrors::InternalDBError(_) => (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    SearchRecipesErrors::CouldntRetrieveCustomRecipesFromDB,
+                ),
+                GetCustomRecipesErrors::InvalidCustomRecipeFormatFromDB => (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    SearchRecipesErrors::InvalidCustomRecipeFormatFromDB,
+                ),
+            }
+            .into();
+            error
+        })?;
+    tracing::debug!(
+        "{} Found {} custom recipes!",
+        tracing_prefix,
+        custom_recipes.len()
+    );
+
     tracing::debug!("{} Querying API for recipes...", tracing_prefix);
 
     let recipes = get_recipes_from_api(&_params.rapid_api_key, &_params.rapid_api_host, &query)
@@ -143,10 +200,51 @@ pub async fn search_recipes(
             error
         })?;
 
+    tracing::debug!("{} Caching recipes...", tracing_prefix);
+    if let Err(err) = cache_recipes(conn, &recipes).await {
+        tracing::error!(
+            "{} An error `{:?}` occurred while caching the recipes!",
+            tracing_prefix,
+            err
+        );
+    }
+
+    custom_recipes.extend(recipes);
+
+    tracing::debug!("{} Checking dietary restrictions...", tracing_prefix);
+    flag_dietary_violations(conn, &user_id, custom_recipes.iter_mut(), &tracing_prefix)
+        .await
+        .map_err(|err| {
+            tracing::error!(
+                "{} An error `{:?}` occurred while checking the user's dietary restrictions!",
+                tracing_prefix,
+                err
+            );
+            let error: ResponseError<_> = (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                SearchRecipesErrors::CouldntCheckDietaryRestrictions,
+            )
+                .into();
+            error
+        })?;
+    if hide_restricted {
+        custom_recipes.retain(|r| r.dietary_violations.is_empty());
+    }
+
+    tracing::debug!("{} Adding the user's ratings...", tracing_prefix);
+    if let Err(err) = fill_user_ratings(conn, &user_id, custom_recipes.iter_mut()).await {
+        tracing::error!(
+            "{} An error `{:?}` occurred while getting the user's ratings!",
+            tracing_prefix,
+            err
+        );
+    }
+
     tracing::debug!("{} DONE", tracing_prefix);
-    Ok(Json(recipes))
+    Ok(Json(custom_recipes))
 }
 
+#[allow(dead_code)]
 #[derive(Debug)]
 enum GetRecipesFromAPIErrors {
     APIFormatHaschanged {
@@ -168,13 +266,16 @@ async fn get_recipes_from_api(
     query: &str,
 ) -> Result<Vec<Recipe>, GetRecipesFromAPIErrors> {
     let client = reqwest::Client::new();
+    let start = Instant::now();
     let response = client
         .get("https://worldwide-recipes1.p.rapidapi.com/api/search")
         .query(&[("q", query)])
         .header("X-RapidAPI-Key", api_key)
         .header("X-RapidAPI-Host", api_host)
         .send()
-        .await
+        .await;
+    record_recipe_api_call("search", start.elapsed(), &response);
+    let response = response
         .map_err(|err| GetRecipesFromAPIErrors::APIError { error: err })?
         .text()
         .await
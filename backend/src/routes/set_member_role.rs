@@ -0,0 +1,252 @@
+use std::{fmt::Display, sync::Arc};
+
+use axum::{response::IntoResponse, Json};
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::Client;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    extract_jwt,
+    households::{get_current_household, GetCurrentHouseholdErrors},
+    is_session_valid,
+    metrics::timed_query,
+    models::HouseholdRole,
+    request_id::current_request_id,
+    responses::ResponseError,
+    APP_SECRET,
+};
+
+#[derive(Debug, Serialize)]
+pub enum SetMemberRoleErrors {
+    InvalidPayload { payload: String },
+    InvalidJWT,
+    NoDBConnectionFound,
+    ErrorCheckingIfSessionIsValid,
+    JWTExpired,
+    ErrorGettingHousehold,
+    NoHouseholdSelected,
+    NotHouseholdOwner,
+    InvalidRole,
+    MemberNotFound,
+    ErrorSettingRole,
+}
+
+impl Display for SetMemberRoleErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SetMemberRolePayload {
+    token: String,
+    user_id: Uuid,
+    role: HouseholdRole,
+}
+
+/// Route for the owner to change the role of a member of the current household.
+///
+/// The owner's role can't be changed and nobody can be made owner.
+#[utoipa::path(
+    post,
+    path = "/v1/households/members/role",
+    tag = "Households",
+    request_body = SetMemberRolePayload,
+    responses(
+        (status = 200, description = "The role was changed"),
+        (status = "4XX", description = "The `SetMemberRoleErrors` that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The `SetMemberRoleErrors` that made the request fail", body = String, content_type = "text/plain"),
+    )
+)]
+pub async fn set_member_role(
+    payload: Json<serde_json::Value>,
+    client: Arc<Option<Client>>,
+) -> Result<impl IntoResponse, ResponseError<SetMemberRoleErrors>> {
+    let tracing_prefix = format!("/households/members/role - {}:", current_request_id());
+
+    tracing::debug!("{} START", tracing_prefix);
+
+    tracing::debug!("{} Parsing payload...", tracing_prefix);
+    let SetMemberRolePayload {
+        token,
+        user_id,
+        role,
+    } = match serde_json::from_value(payload.0.clone()) {
+        Ok(p) => p,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred parsing payload `{}`",
+                tracing_prefix,
+                err,
+                payload.0
+            );
+            let error: ResponseError<_> = (
+                StatusCode::BAD_REQUEST,
+                SetMemberRoleErrors::InvalidPayload {
+                    payload: payload.0.to_string(),
+                },
+            )
+                .into();
+            Err(error)?
+        }
+    };
+    tracing::debug!("{} Payload parsed successfully!", tracing_prefix);
+
+    tracing::debug!("{} Extracting JWT...", tracing_prefix);
+    let token_info = match extract_jwt(APP_SECRET, &token) {
+        Ok(t) => t,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred while extracting the JWT `{}`",
+                tracing_prefix,
+                err,
+                token
+            );
+            let error: ResponseError<_> =
+                (StatusCode::BAD_REQUEST, SetMemberRoleErrors::InvalidJWT).into();
+            Err(error)?
+        }
+    };
+    let owner_id = token_info.user_id.clone();
+    tracing::debug!("{} JWT extracted successfully!", tracing_prefix);
+
+    tracing::debug!("{} Checking for DB connection...", tracing_prefix);
+    let conn = client.as_ref().as_ref().ok_or_else(|| {
+        tracing::error!("{} No DB connection found!", tracing_prefix);
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            SetMemberRoleErrors::NoDBConnectionFound,
+        )
+            .into();
+        error
+    })?;
+    tracing::debug!("{} DB Connection found!", tracing_prefix);
+
+    tracing::debug!("{} Checking if session is valid...", tracing_prefix);
+    if let Err(err) = is_session_valid(token_info, conn).await {
+        tracing::error!(
+            "{} An error `{:?}` occurred while checking if session is valid!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = match err {
+            crate::IsSessionValidErrors::InternalDBError(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                SetMemberRoleErrors::ErrorCheckingIfSessionIsValid,
+            ),
+            crate::IsSessionValidErrors::InvalidSessionData {
+                current_date: _,
+                db_expire_date: _,
+            } => (StatusCode::UNAUTHORIZED, SetMemberRoleErrors::JWTExpired),
+            _ => (
+                StatusCode::BAD_REQUEST,
+                SetMemberRoleErrors::ErrorCheckingIfSessionIsValid,
+            ),
+        }
+        .into();
+
+        Err(error)?
+    }
+    tracing::debug!("{} Session is valid!", tracing_prefix);
+
+    let member_id = user_id.to_string();
+    tracing::debug!("{} Getting current household...", tracing_prefix);
+    let household = get_current_household(conn, &owner_id)
+        .await
+        .map_err(|err| {
+            tracing::error!(
+                "{} An error `{:?}` occurred while getting the household of user `{}`!",
+                tracing_prefix,
+                err,
+                owner_id
+            );
+            let error: ResponseError<_> = match err {
+                GetCurrentHouseholdErrors::NoHouseholdSelected => (
+                    StatusCode::BAD_REQUEST,
+                    SetMemberRoleErrors::NoHouseholdSelected,
+                ),
+                _ => (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    SetMemberRoleErrors::ErrorGettingHousehold,
+                ),
+            }
+            .into();
+            error
+        })?;
+    if household.role != HouseholdRole::Owner {
+        tracing::error!(
+            "{} The user isn't the owner of household `{}`!",
+            tracing_prefix,
+            household.household_id
+        );
+        let error: ResponseError<_> = (
+            StatusCode::FORBIDDEN,
+            SetMemberRoleErrors::NotHouseholdOwner,
+        )
+            .into();
+        Err(error)?
+    }
+    tracing::debug!(
+        "{} Working on household `{}`!",
+        tracing_prefix,
+        household.household_id
+    );
+
+    if role == HouseholdRole::Owner {
+        tracing::error!("{} Nobody can be made owner!", tracing_prefix);
+        let error: ResponseError<_> =
+            (StatusCode::BAD_REQUEST, SetMemberRoleErrors::InvalidRole).into();
+        Err(error)?
+    }
+
+    tracing::debug!(
+        "{} Setting role of `{}` to {:?}...",
+        tracing_prefix,
+        member_id,
+        role
+    );
+    let updated = timed_query(
+        "set_member_role",
+        conn.execute(
+            "UPDATE sf_household_member SET role=$3 WHERE household_id=$1 AND user_id=$2 AND role<>$4",
+            &[
+                &household.household_id,
+                &member_id,
+                &format!("{:?}", role),
+                &format!("{:?}", HouseholdRole::Owner),
+            ],
+        ),
+    )
+    .await
+    .map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while setting the role of `{}`!",
+            tracing_prefix,
+            err,
+            member_id
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            SetMemberRoleErrors::ErrorSettingRole,
+        )
+            .into();
+        error
+    })?;
+
+    if updated == 0 {
+        tracing::error!(
+            "{} No member `{}` that isn't the owner found!",
+            tracing_prefix,
+            member_id
+        );
+        let error: ResponseError<_> =
+            (StatusCode::NOT_FOUND, SetMemberRoleErrors::MemberNotFound).into();
+        Err(error)?
+    }
+    tracing::debug!("{} Role set!", tracing_prefix);
+
+    tracing::debug!("{} DONE", tracing_prefix);
+    Ok(StatusCode::OK)
+}
@@ -0,0 +1,250 @@
+use std::{fmt::Display, sync::Arc};
+
+use axum::{
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse,
+    },
+    Json,
+};
+use chrono::Utc;
+use futures_util::StreamExt;
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::Client;
+
+use utoipa::ToSchema;
+
+use crate::{
+    change_feed::{ChangeFeed, ChangeFeedItem},
+    extract_jwt,
+    households::{get_current_household, GetCurrentHouseholdErrors},
+    is_session_valid,
+    models::ChangeNotification,
+    request_id::current_request_id,
+    responses::ResponseError,
+    APP_SECRET,
+};
+
+#[derive(Debug, Serialize)]
+pub enum StreamChangesErrors {
+    InvalidPayload { payload: String },
+    InvalidJWT,
+    NoDBConnectionFound,
+    ErrorCheckingIfSessionIsValid,
+    JWTExpired,
+    ErrorGettingHousehold,
+    NoHouseholdSelected,
+}
+
+impl Display for StreamChangesErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct StreamChangesPayload {
+    token: String,
+}
+
+/// Route to receive the changes of the ingredients of the current household and the settings of the user as they happen.
+///
+/// The changes are sent as Server-Sent Events until the session expires or is closed,
+/// or the user no longer has the household selected, which is checked every minute.
+/// A `Lagged` event means some changes were missed and the client should sync.
+/// Switching households requires connecting again.
+///
+/// This route is a POST, so a browser `EventSource` can't connect to it, use `GET /v2/changes` instead.
+#[utoipa::path(
+    post,
+    path = "/v1/changes",
+    tag = "Sync",
+    request_body = StreamChangesPayload,
+    responses(
+        (status = 200, description = "Server-sent events with the changes, until the session ends or the household is no longer selected", body = ChangeNotification, content_type = "text/event-stream"),
+        (status = "4XX", description = "The `StreamChangesErrors` that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The `StreamChangesErrors` that made the request fail", body = String, content_type = "text/plain"),
+    )
+)]
+pub async fn stream_changes(
+    payload: Json<serde_json::Value>,
+    client: Arc<Option<Client>>,
+    changes: Arc<ChangeFeed>,
+) -> Result<impl IntoResponse, ResponseError<StreamChangesErrors>> {
+    let tracing_prefix = format!("/changes - {}:", current_request_id());
+
+    tracing::debug!("{} START", tracing_prefix);
+
+    tracing::debug!("{} Parsing payload...", tracing_prefix);
+    let StreamChangesPayload { token } = match serde_json::from_value(payload.0.clone()) {
+        Ok(p) => p,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred parsing payload `{}`",
+                tracing_prefix,
+                err,
+                payload.0
+            );
+            let error: ResponseError<_> = (
+                StatusCode::BAD_REQUEST,
+                StreamChangesErrors::InvalidPayload {
+                    payload: payload.0.to_string(),
+                },
+            )
+                .into();
+            Err(error)?
+        }
+    };
+    tracing::debug!("{} Payload parsed successfully!", tracing_prefix);
+
+    tracing::debug!("{} Extracting JWT...", tracing_prefix);
+    let token_info = match extract_jwt(APP_SECRET, &token) {
+        Ok(t) => t,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred while extracting the JWT `{}`",
+                tracing_prefix,
+                err,
+                token
+            );
+            let error: ResponseError<_> =
+                (StatusCode::BAD_REQUEST, StreamChangesErrors::InvalidJWT).into();
+            Err(error)?
+        }
+    };
+    let user_id = token_info.user_id.clone();
+    let session = token_info.clone();
+    let session_expire_date = token_info.expire_date;
+    tracing::debug!("{} JWT extracted successfully!", tracing_prefix);
+
+    tracing::debug!("{} Checking for DB connection...", tracing_prefix);
+    let conn = client.as_ref().as_ref().ok_or_else(|| {
+        tracing::error!("{} No DB connection found!", tracing_prefix);
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            StreamChangesErrors::NoDBConnectionFound,
+        )
+            .into();
+        error
+    })?;
+    tracing::debug!("{} DB Connection found!", tracing_prefix);
+
+    tracing::debug!("{} Checking if session is valid...", tracing_prefix);
+    if let Err(err) = is_session_valid(token_info, conn).await {
+        tracing::error!(
+            "{} An error `{:?}` occurred while checking if session is valid!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = match err {
+            crate::IsSessionValidErrors::InternalDBError(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                StreamChangesErrors::ErrorCheckingIfSessionIsValid,
+            ),
+            crate::IsSessionValidErrors::InvalidSessionData {
+                current_date: _,
+                db_expire_date: _,
+            } => (StatusCode::UNAUTHORIZED, StreamChangesErrors::JWTExpired),
+            _ => (
+                StatusCode::BAD_REQUEST,
+                StreamChangesErrors::ErrorCheckingIfSessionIsValid,
+            ),
+        }
+        .into();
+
+        Err(error)?
+    }
+    tracing::debug!("{} Session is valid!", tracing_prefix);
+
+    tracing::debug!("{} Getting current household...", tracing_prefix);
+    let household = get_current_household(conn, &user_id).await.map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while getting the household of user `{}`!",
+            tracing_prefix,
+            err,
+            user_id
+        );
+        let error: ResponseError<_> = match err {
+            GetCurrentHouseholdErrors::NoHouseholdSelected => (
+                StatusCode::BAD_REQUEST,
+                StreamChangesErrors::NoHouseholdSelected,
+            ),
+            _ => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                StreamChangesErrors::ErrorGettingHousehold,
+            ),
+        }
+        .into();
+        error
+    })?;
+    tracing::debug!(
+        "{} Working on household `{}`!",
+        tracing_prefix,
+        household.household_id
+    );
+
+    // Checks the session and the household again while streaming, so a logout or leaving the household
+    // ends the stream instead of waiting for the session to expire.
+    let has_access = {
+        let household_id = household.household_id.clone();
+        let user_id = user_id.clone();
+        let tracing_prefix = tracing_prefix.clone();
+        move || {
+            let client = client.clone();
+            let session = session.clone();
+            let household_id = household_id.clone();
+            let user_id = user_id.clone();
+            let tracing_prefix = tracing_prefix.clone();
+            async move {
+                let Some(conn) = client.as_ref() else {
+                    return false;
+                };
+                if let Err(err) = is_session_valid(session, conn).await {
+                    tracing::debug!(
+                        "{} The session is no longer valid (`{:?}`), closing the stream!",
+                        tracing_prefix,
+                        err
+                    );
+                    return false;
+                }
+                match get_current_household(conn, &user_id).await {
+                    Ok(current) if current.household_id == household_id => true,
+                    current => {
+                        tracing::debug!(
+                            "{} The household `{}` is no longer selected (`{:?}`), closing the stream!",
+                            tracing_prefix,
+                            household_id,
+                            current
+                        );
+                        false
+                    }
+                }
+            }
+        }
+    };
+
+    // Subscribed before answering so no change made after the response is missed.
+    let session_end = tokio::time::Instant::now()
+        + (session_expire_date - Utc::now())
+            .to_std()
+            .unwrap_or_default();
+    let stream = changes
+        .subscribe(household.household_id, user_id, session_end, has_access)
+        .map(|item| match item {
+            ChangeFeedItem::Change(change) => {
+                let name = match change {
+                    ChangeNotification::Ingredient { .. } => "Ingredient",
+                    ChangeNotification::Settings { .. } => "Settings",
+                };
+                Event::default().event(name).json_data(&change)
+            }
+            ChangeFeedItem::Lagged(missed) => {
+                Ok(Event::default().event("Lagged").data(missed.to_string()))
+            }
+        });
+    tracing::debug!("{} Streaming changes...", tracing_prefix);
+
+    tracing::debug!("{} DONE", tracing_prefix);
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
@@ -0,0 +1,185 @@
+use std::{fmt::Display, sync::Arc};
+
+use axum::{response::IntoResponse, Json};
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::Client;
+
+use utoipa::ToSchema;
+
+use crate::{
+    extract_jwt, is_session_valid, metrics::timed_query, request_id::current_request_id,
+    responses::ResponseError, APP_SECRET,
+};
+
+#[derive(Debug, Serialize)]
+pub enum SwitchHouseholdErrors {
+    InvalidPayload { payload: String },
+    InvalidJWT,
+    NoDBConnectionFound,
+    ErrorCheckingIfSessionIsValid,
+    JWTExpired,
+    NotAHouseholdMember,
+    ErrorSwitchingHousehold,
+}
+
+impl Display for SwitchHouseholdErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SwitchHouseholdPayload {
+    token: String,
+    household_id: String,
+}
+
+/// Route to select the household the ingredient routes work on.
+#[utoipa::path(
+    post,
+    path = "/v1/households/switch",
+    tag = "Households",
+    request_body = SwitchHouseholdPayload,
+    responses(
+        (status = 200, description = "The household is now the current one"),
+        (status = "4XX", description = "The `SwitchHouseholdErrors` that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The `SwitchHouseholdErrors` that made the request fail", body = String, content_type = "text/plain"),
+    )
+)]
+pub async fn switch_household(
+    payload: Json<serde_json::Value>,
+    client: Arc<Option<Client>>,
+) -> Result<impl IntoResponse, ResponseError<SwitchHouseholdErrors>> {
+    let tracing_prefix = format!("/households/switch - {}:", current_request_id());
+
+    tracing::debug!("{} START", tracing_prefix);
+
+    tracing::debug!("{} Parsing payload...", tracing_prefix);
+    let SwitchHouseholdPayload {
+        token,
+        household_id,
+    } = match serde_json::from_value(payload.0.clone()) {
+        Ok(p) => p,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred parsing payload `{}`",
+                tracing_prefix,
+                err,
+                payload.0
+            );
+            let error: ResponseError<_> = (
+                StatusCode::BAD_REQUEST,
+                SwitchHouseholdErrors::InvalidPayload {
+                    payload: payload.0.to_string(),
+                },
+            )
+                .into();
+            Err(error)?
+        }
+    };
+    tracing::debug!("{} Payload parsed successfully!", tracing_prefix);
+
+    tracing::debug!("{} Extracting JWT...", tracing_prefix);
+    let token_info = match extract_jwt(APP_SECRET, &token) {
+        Ok(t) => t,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred while extracting the JWT `{}`",
+                tracing_prefix,
+                err,
+                token
+            );
+            let error: ResponseError<_> =
+                (StatusCode::BAD_REQUEST, SwitchHouseholdErrors::InvalidJWT).into();
+            Err(error)?
+        }
+    };
+    let user_id = token_info.user_id.clone();
+    tracing::debug!("{} JWT extracted successfully!", tracing_prefix);
+
+    tracing::debug!("{} Checking for DB connection...", tracing_prefix);
+    let conn = client.as_ref().as_ref().ok_or_else(|| {
+        tracing::error!("{} No DB connection found!", tracing_prefix);
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            SwitchHouseholdErrors::NoDBConnectionFound,
+        )
+            .into();
+        error
+    })?;
+    tracing::debug!("{} DB Connection found!", tracing_prefix);
+
+    tracing::debug!("{} Checking if session is valid...", tracing_prefix);
+    if let Err(err) = is_session_valid(token_info, conn).await {
+        tracing::error!(
+            "{} An error `{:?}` occurred while checking if session is valid!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = match err {
+            crate::IsSessionValidErrors::InternalDBError(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                SwitchHouseholdErrors::ErrorCheckingIfSessionIsValid,
+            ),
+            crate::IsSessionValidErrors::InvalidSessionData {
+                current_date: _,
+                db_expire_date: _,
+            } => (StatusCode::UNAUTHORIZED, SwitchHouseholdErrors::JWTExpired),
+            _ => (
+                StatusCode::BAD_REQUEST,
+                SwitchHouseholdErrors::ErrorCheckingIfSessionIsValid,
+            ),
+        }
+        .into();
+
+        Err(error)?
+    }
+    tracing::debug!("{} Session is valid!", tracing_prefix);
+
+    tracing::debug!(
+        "{} Switching to household `{}`...",
+        tracing_prefix,
+        household_id
+    );
+    let updated = timed_query(
+        "switch_household",
+        conn.execute(
+            "UPDATE sf_user SET current_household_id=$1 WHERE user_id=$2 AND EXISTS (SELECT 1 FROM sf_household_member WHERE household_id=$1 AND user_id=$2)",
+            &[&household_id, &user_id],
+        ),
+    )
+    .await
+    .map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while switching to household `{}`!",
+            tracing_prefix,
+            err,
+            household_id
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            SwitchHouseholdErrors::ErrorSwitchingHousehold,
+        )
+            .into();
+        error
+    })?;
+
+    if updated == 0 {
+        tracing::error!(
+            "{} The user isn't a member of household `{}`!",
+            tracing_prefix,
+            household_id
+        );
+        let error: ResponseError<_> = (
+            StatusCode::NOT_FOUND,
+            SwitchHouseholdErrors::NotAHouseholdMember,
+        )
+            .into();
+        Err(error)?
+    }
+    tracing::debug!("{} Switched household!", tracing_prefix);
+
+    tracing::debug!("{} DONE", tracing_prefix);
+    Ok(StatusCode::OK)
+}
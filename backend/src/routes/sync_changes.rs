@@ -0,0 +1,471 @@
+use std::{fmt::Display, sync::Arc};
+
+use axum::{response::IntoResponse, Json};
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use tokio_postgres::Client;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    bulk_ingredients::TransactionPool,
+    extract_jwt,
+    households::{get_current_household, GetCurrentHouseholdErrors},
+    ingredient_lots::get_lots,
+    is_session_valid,
+    metrics::timed_query,
+    models::{Ingredient, SyncChanges, SyncMutation, UserSettings},
+    parse_db_ingredient,
+    request_id::current_request_id,
+    responses::ResponseError,
+    sync::{apply_sync_mutation, MAX_SYNC_MUTATIONS},
+    APP_SECRET,
+};
+
+#[derive(Debug, Serialize)]
+pub enum SyncChangesErrors {
+    InvalidPayload { payload: String },
+    InvalidJWT,
+    NoDBConnectionFound,
+    ErrorCheckingIfSessionIsValid,
+    JWTExpired,
+    ErrorGettingHousehold,
+    NoHouseholdSelected,
+    NotAllowedInHousehold,
+    TooManyMutations { max: usize },
+    ErrorStartingTransaction,
+    ErrorApplyingMutation { index: usize },
+    ErrorCommittingTransaction,
+    CouldntRetrieveChangesFromDB,
+    InvalidIngredientFormatFromDB,
+    InvalidSettingsFormatFromDB,
+}
+
+impl Display for SyncChangesErrors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct SyncChangesPayload {
+    token: String,
+    /// The `Cursor` returned by the last sync, everything is returned when missing.
+    cursor: Option<i64>,
+    /// Changes made while offline, applied in order and all at once before getting the changes.
+    #[serde(default)]
+    mutations: Vec<SyncMutation>,
+}
+
+/// Route for offline clients to upload the changes they made and download what changed since their last sync.
+///
+/// Mutations that conflict with newer changes on the server aren't applied,
+/// the client gets the version of the server instead. None of the mutations are applied
+/// when one of them fails.
+#[utoipa::path(
+    post,
+    path = "/v1/sync",
+    tag = "Sync",
+    request_body = SyncChangesPayload,
+    responses(
+        (status = 200, description = "What changed since the cursor", body = SyncChanges),
+        (status = "4XX", description = "The `SyncChangesErrors` that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The `SyncChangesErrors` that made the request fail", body = String, content_type = "text/plain"),
+    )
+)]
+pub async fn sync_changes(
+    payload: Json<serde_json::Value>,
+    client: Arc<Option<Client>>,
+    transactions: Arc<TransactionPool>,
+) -> Result<impl IntoResponse, ResponseError<SyncChangesErrors>> {
+    let tracing_prefix = format!("/sync - {}:", current_request_id());
+
+    tracing::debug!("{} START", tracing_prefix);
+
+    tracing::debug!("{} Parsing payload...", tracing_prefix);
+    let SyncChangesPayload {
+        token,
+        cursor,
+        mutations,
+    } = match serde_json::from_value(payload.0.clone()) {
+        Ok(p) => p,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred parsing payload `{}`",
+                tracing_prefix,
+                err,
+                payload.0
+            );
+            let error: ResponseError<_> = (
+                StatusCode::BAD_REQUEST,
+                SyncChangesErrors::InvalidPayload {
+                    payload: payload.0.to_string(),
+                },
+            )
+                .into();
+            Err(error)?
+        }
+    };
+    tracing::debug!("{} Payload parsed successfully!", tracing_prefix);
+
+    tracing::debug!("{} Extracting JWT...", tracing_prefix);
+    let token_info = match extract_jwt(APP_SECRET, &token) {
+        Ok(t) => t,
+        Err(err) => {
+            tracing::error!(
+                "{} An error `{:?}` occurred while extracting the JWT `{}`",
+                tracing_prefix,
+                err,
+                token
+            );
+            let error: ResponseError<_> =
+                (StatusCode::BAD_REQUEST, SyncChangesErrors::InvalidJWT).into();
+            Err(error)?
+        }
+    };
+    let user_id = token_info.user_id.clone();
+    tracing::debug!("{} JWT extracted successfully!", tracing_prefix);
+
+    tracing::debug!("{} Checking for DB connection...", tracing_prefix);
+    let conn = client.as_ref().as_ref().ok_or_else(|| {
+        tracing::error!("{} No DB connection found!", tracing_prefix);
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            SyncChangesErrors::NoDBConnectionFound,
+        )
+            .into();
+        error
+    })?;
+    tracing::debug!("{} DB Connection found!", tracing_prefix);
+
+    tracing::debug!("{} Checking if session is valid...", tracing_prefix);
+    if let Err(err) = is_session_valid(token_info, conn).await {
+        tracing::error!(
+            "{} An error `{:?}` occurred while checking if session is valid!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = match err {
+            crate::IsSessionValidErrors::InternalDBError(_) => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                SyncChangesErrors::ErrorCheckingIfSessionIsValid,
+            ),
+            crate::IsSessionValidErrors::InvalidSessionData {
+                current_date: _,
+                db_expire_date: _,
+            } => (StatusCode::UNAUTHORIZED, SyncChangesErrors::JWTExpired),
+            _ => (
+                StatusCode::BAD_REQUEST,
+                SyncChangesErrors::ErrorCheckingIfSessionIsValid,
+            ),
+        }
+        .into();
+
+        Err(error)?
+    }
+    tracing::debug!("{} Session is valid!", tracing_prefix);
+
+    tracing::debug!("{} Getting current household...", tracing_prefix);
+    let household = get_current_household(conn, &user_id).await.map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while getting the household of user `{}`!",
+            tracing_prefix,
+            err,
+            user_id
+        );
+        let error: ResponseError<_> = match err {
+            GetCurrentHouseholdErrors::NoHouseholdSelected => (
+                StatusCode::BAD_REQUEST,
+                SyncChangesErrors::NoHouseholdSelected,
+            ),
+            _ => (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                SyncChangesErrors::ErrorGettingHousehold,
+            ),
+        }
+        .into();
+        error
+    })?;
+    tracing::debug!(
+        "{} Working on household `{}`!",
+        tracing_prefix,
+        household.household_id
+    );
+
+    if mutations.len() > MAX_SYNC_MUTATIONS {
+        tracing::error!(
+            "{} {} mutations are more than the {} allowed!",
+            tracing_prefix,
+            mutations.len(),
+            MAX_SYNC_MUTATIONS
+        );
+        let error: ResponseError<_> = (
+            StatusCode::BAD_REQUEST,
+            SyncChangesErrors::TooManyMutations {
+                max: MAX_SYNC_MUTATIONS,
+            },
+        )
+            .into();
+        Err(error)?
+    }
+    if !mutations.is_empty() && !household.role.can_edit_ingredients() {
+        tracing::error!(
+            "{} The user can't change the ingredients of household `{}`!",
+            tracing_prefix,
+            household.household_id
+        );
+        let error: ResponseError<_> = (
+            StatusCode::FORBIDDEN,
+            SyncChangesErrors::NotAllowedInHousehold,
+        )
+            .into();
+        Err(error)?
+    }
+
+    let mut results = vec![];
+    if !mutations.is_empty() {
+        tracing::debug!("{} Starting transaction...", tracing_prefix);
+        let mut tx_client = transactions.get().await.map_err(|err| {
+            tracing::error!(
+                "{} An error `{:?}` occurred while getting a connection for the transaction!",
+                tracing_prefix,
+                err
+            );
+            let error: ResponseError<_> = (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                SyncChangesErrors::ErrorStartingTransaction,
+            )
+                .into();
+            error
+        })?;
+        let transaction = tx_client.transaction().await.map_err(|err| {
+            tracing::error!(
+                "{} An error `{:?}` occurred while starting the transaction!",
+                tracing_prefix,
+                err
+            );
+            let error: ResponseError<_> = (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                SyncChangesErrors::ErrorStartingTransaction,
+            )
+                .into();
+            error
+        })?;
+        tracing::debug!("{} Transaction started!", tracing_prefix);
+
+        tracing::debug!(
+            "{} Applying {} mutations...",
+            tracing_prefix,
+            mutations.len()
+        );
+        // The transaction is rolled back when dropped, so a failed mutation undoes the ones before it.
+        for (index, mutation) in mutations.iter().enumerate() {
+            let result = apply_sync_mutation(
+                &transaction,
+                &household.household_id,
+                &user_id,
+                mutation,
+                &tracing_prefix,
+            )
+            .await
+            .map_err(|err| {
+                tracing::error!(
+                    "{} An error `{:?}` occurred while applying mutation {}!",
+                    tracing_prefix,
+                    err,
+                    index
+                );
+                let error: ResponseError<_> = (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    SyncChangesErrors::ErrorApplyingMutation { index },
+                )
+                    .into();
+                error
+            })?;
+            tracing::debug!(
+                "{} Mutation `{}` is {:?}!",
+                tracing_prefix,
+                result.mutation_id,
+                result.status
+            );
+            results.push(result);
+        }
+
+        tracing::debug!("{} Committing transaction...", tracing_prefix);
+        transaction.commit().await.map_err(|err| {
+            tracing::error!(
+                "{} An error `{:?}` occurred while committing the transaction!",
+                tracing_prefix,
+                err
+            );
+            let error: ResponseError<_> = (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                SyncChangesErrors::ErrorCommittingTransaction,
+            )
+                .into();
+            error
+        })?;
+        tracing::debug!("{} Mutations applied!", tracing_prefix);
+    }
+
+    // The oldest transaction still running, taken before reading the changes. Every change the reads
+    // below don't see was made by it or a newer one, so the next sync asks for the changes made from it
+    // on. The ones that were seen are sent again and the client keeps the one with the highest version.
+    tracing::debug!("{} Getting changes from DB...", tracing_prefix);
+    let new_cursor: i64 = timed_query(
+        "sync_changes",
+        conn.query_one(
+            "SELECT pg_snapshot_xmin(pg_current_snapshot())::TEXT::BIGINT AS cursor",
+            &[],
+        ),
+    )
+    .await
+    .map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while getting the cursor!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            SyncChangesErrors::CouldntRetrieveChangesFromDB,
+        )
+            .into();
+        error
+    })?
+    .get("cursor");
+
+    let mut ingredients = timed_query(
+        "sync_changes",
+        conn.query(
+            "SELECT * FROM sf_ingredient WHERE household_id=$1 AND deleted_date IS NULL AND ($2::BIGINT IS NULL OR change_xid >= $2)",
+            &[&household.household_id, &cursor],
+        ),
+    )
+    .await
+    .map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while getting the changed ingredients!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            SyncChangesErrors::CouldntRetrieveChangesFromDB,
+        )
+            .into();
+        error
+    })?
+    .iter()
+    .map(|row| {
+        parse_db_ingredient(row, &tracing_prefix).ok_or_else(|| {
+            let error: ResponseError<_> = (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                SyncChangesErrors::InvalidIngredientFormatFromDB,
+            )
+                .into();
+            error
+        })
+    })
+    .collect::<Result<Vec<Ingredient>, ResponseError<SyncChangesErrors>>>()?;
+
+    let ingredient_ids: Vec<String> = ingredients
+        .iter()
+        .map(|i| i.ingredient_id.to_string())
+        .collect();
+    let mut lots = get_lots(conn, &ingredient_ids).await.map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while getting the lots!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            SyncChangesErrors::CouldntRetrieveChangesFromDB,
+        )
+            .into();
+        error
+    })?;
+    for ingredient in ingredients.iter_mut() {
+        ingredient.lots = lots.remove(&ingredient.ingredient_id).unwrap_or_default();
+    }
+
+    // Nothing is deleted on a client without a cursor, it replaces all of its ingredients.
+    let deleted_ingredient_ids: Vec<Uuid> = timed_query(
+        "sync_changes",
+        conn.query(
+            "SELECT ingredient_id FROM sf_ingredient WHERE household_id=$1 AND deleted_date IS NOT NULL AND change_xid >= $2::BIGINT UNION SELECT ingredient_id FROM sf_ingredient_tombstone WHERE household_id=$1 AND change_xid >= $2::BIGINT",
+            &[&household.household_id, &cursor],
+        ),
+    )
+    .await
+    .map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while getting the deleted ingredients!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            SyncChangesErrors::CouldntRetrieveChangesFromDB,
+        )
+            .into();
+        error
+    })?
+    .iter()
+    .filter_map(|row| row.get::<_, &str>("ingredient_id").parse().ok())
+    .collect();
+
+    let settings = timed_query(
+        "sync_changes",
+        conn.query_opt(
+            "SELECT * FROM sf_settings WHERE user_id=$1 AND ($2::BIGINT IS NULL OR change_xid >= $2)",
+            &[&user_id, &cursor],
+        ),
+    )
+    .await
+    .map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while getting the settings!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            SyncChangesErrors::CouldntRetrieveChangesFromDB,
+        )
+            .into();
+        error
+    })?
+    .map(|row| UserSettings::try_from(&row))
+    .transpose()
+    .map_err(|err| {
+        tracing::error!(
+            "{} An error `{:?}` occurred while parsing the settings!",
+            tracing_prefix,
+            err
+        );
+        let error: ResponseError<_> = (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            SyncChangesErrors::InvalidSettingsFormatFromDB,
+        )
+            .into();
+        error
+    })?;
+    tracing::debug!(
+        "{} {} ingredients changed and {} were deleted!",
+        tracing_prefix,
+        ingredients.len(),
+        deleted_ingredient_ids.len()
+    );
+
+    tracing::debug!("{} DONE", tracing_prefix);
+    Ok(Json(SyncChanges {
+        cursor: new_cursor,
+        household_id: household.household_id,
+        ingredients,
+        deleted_ingredient_ids,
+        settings,
+        results,
+    }))
+}
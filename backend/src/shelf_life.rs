@@ -0,0 +1,155 @@
+//! Estimations of how long food lasts, used when the user doesn't supply an expire date.
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::models::StorageKind;
+
+/// Category used for items when we can't tell where they belong.
+pub const DEFAULT_CATEGORY: &str = "Packaged Foods";
+
+/// Returns how many days an ingredient of the given category usually lasts in the fridge.
+///
+/// Categories are the ones the client app offers when adding an ingredient.
+pub fn shelf_life_days(category: &str) -> i64 {
+    match category.trim().to_lowercase().as_str() {
+        "seafood" => 2,
+        "meat" => 3,
+        "herbs" => 5,
+        "fruits" | "vegetables" => 7,
+        "dairy & alternatives" => 10,
+        "sauces" => 60,
+        "sweets and desserts" => 90,
+        "grains and cereals" | "beverages" | "condiments" | "packaged foods" => 180,
+        "oils and fats" | "baking supplies" => 365,
+        _ => 7,
+    }
+}
+
+/// Estimates the expire date of an ingredient of the given category bought on `bought_date`.
+pub fn estimate_expire_date(category: &str, bought_date: DateTime<Utc>) -> DateTime<Utc> {
+    bought_date + Duration::days(shelf_life_days(category))
+}
+
+/// Returns how many days an ingredient of the given category usually lasts in the freezer.
+pub fn freezer_shelf_life_days(category: &str) -> i64 {
+    match category.trim().to_lowercase().as_str() {
+        "seafood" => 90,
+        "meat" => 180,
+        "herbs" => 180,
+        "fruits" | "vegetables" => 240,
+        "dairy & alternatives" => 90,
+        "sauces" => 120,
+        "sweets and desserts" => 180,
+        "grains and cereals" | "baking supplies" => 365,
+        // Freezing doesn't help the rest of them.
+        _ => shelf_life_days(category),
+    }
+}
+
+/// Adjusts the expire date of an ingredient moved between kinds of storage on `moved_date`.
+///
+/// Freezing extends the date to the freezer shelf life if that's later,
+/// thawing shortens it to the fridge shelf life if that's sooner.
+pub fn adjust_expire_date(
+    category: &str,
+    expire_date: DateTime<Utc>,
+    from: StorageKind,
+    to: StorageKind,
+    moved_date: DateTime<Utc>,
+) -> DateTime<Utc> {
+    match (from, to) {
+        (StorageKind::Freezer, StorageKind::Freezer) => expire_date,
+        (_, StorageKind::Freezer) => {
+            expire_date.max(moved_date + Duration::days(freezer_shelf_life_days(category)))
+        }
+        (StorageKind::Freezer, _) => {
+            expire_date.min(moved_date + Duration::days(shelf_life_days(category)))
+        }
+        _ => expire_date,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn estimates_shelf_life() {
+        let cases = [
+            ("Seafood", 2, 90),
+            ("meat", 3, 180),
+            ("Herbs", 5, 180),
+            ("Fruits", 7, 240),
+            (" vegetables ", 7, 240),
+            ("Dairy & Alternatives", 10, 90),
+            ("Sauces", 60, 120),
+            ("Sweets and Desserts", 90, 180),
+            ("Grains and Cereals", 180, 365),
+            ("Beverages", 180, 180),
+            ("Condiments", 180, 180),
+            ("Packaged Foods", 180, 180),
+            ("Oils and Fats", 365, 365),
+            ("Baking Supplies", 365, 365),
+            ("Unknown", 7, 7),
+            ("", 7, 7),
+        ];
+
+        let bought_date = Utc.with_ymd_and_hms(2024, 2, 27, 12, 0, 0).unwrap();
+        for (category, days, freezer_days) in cases {
+            assert_eq!(shelf_life_days(category), days, "days of `{}`", category);
+            assert_eq!(
+                freezer_shelf_life_days(category),
+                freezer_days,
+                "freezer days of `{}`",
+                category
+            );
+            assert_eq!(
+                estimate_expire_date(category, bought_date),
+                bought_date + Duration::days(days),
+                "expire date of `{}`",
+                category
+            );
+        }
+    }
+
+    #[test]
+    fn adjusts_expire_dates_when_moved() {
+        use StorageKind::*;
+
+        let moved_date = Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap();
+        let days = |days| moved_date + Duration::days(days);
+
+        #[rustfmt::skip]
+        let cases = [
+            // Freezing extends the date
+            ("Meat", days(2), Fridge, Freezer, days(180)),
+            ("Seafood", days(1), Pantry, Freezer, days(90)),
+            // ...unless it was already later
+            ("Meat", days(400), Fridge, Freezer, days(400)),
+            ("Beverages", days(200), Fridge, Freezer, days(200)),
+            // Thawing shortens the date
+            ("Meat", days(150), Freezer, Fridge, days(3)),
+            ("Vegetables", days(200), Freezer, Pantry, days(7)),
+            // ...unless it was already sooner
+            ("Meat", days(1), Freezer, Fridge, days(1)),
+            // Moves that don't freeze nor thaw keep it
+            ("Meat", days(2), Fridge, Pantry, days(2)),
+            ("Meat", days(2), Pantry, Fridge, days(2)),
+            ("Meat", days(100), Freezer, Freezer, days(100)),
+        ];
+
+        for (category, expire_date, from, to, expected) in cases {
+            assert_eq!(
+                adjust_expire_date(category, expire_date, from, to, moved_date),
+                expected,
+                "`{}` expiring on {} moved from {:?} to {:?}",
+                category,
+                expire_date,
+                from,
+                to
+            );
+        }
+    }
+}
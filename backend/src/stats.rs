@@ -0,0 +1,273 @@
+//! Statistics of what happens to the ingredients of a household, mostly how much of them is wasted.
+
+use std::collections::{HashMap, HashSet};
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
+
+use crate::{
+    models::{
+        CategoryWaste, InventoryEvent, InventoryEventType, RemovalReason, StatsPeriod,
+        StatsTrendPoint, WasteStats, WastedItem,
+    },
+    units::to_base_unit,
+};
+use uuid::Uuid;
+
+/// How many periods the statistics cover when the client doesn't supply a range.
+pub const DEFAULT_TREND_PERIODS: i32 = 6;
+
+/// How many ingredients the most wasted ranking has.
+const MOST_WASTED_LIMIT: usize = 5;
+
+/// Represents what happened to a removed ingredient.
+#[derive(Debug, PartialEq, Eq)]
+enum RemovalOutcome {
+    Consumed,
+    Discarded,
+    Unspecified,
+}
+
+/// Classifies a removal, the ones without a reason count as discarded if the ingredient had expired.
+fn removal_outcome(event: &InventoryEvent) -> RemovalOutcome {
+    match event.reason {
+        Some(RemovalReason::Eaten) => RemovalOutcome::Consumed,
+        Some(RemovalReason::Wasted) | Some(RemovalReason::Expired) => RemovalOutcome::Discarded,
+        None => match event.expire_date {
+            Some(expire_date) if expire_date <= event.event_date => RemovalOutcome::Discarded,
+            _ => RemovalOutcome::Unspecified,
+        },
+    }
+}
+
+/// Returns the ids of the removals undone by restoring the ingredient afterwards.
+///
+/// A restore undoes the last removal of the ingredient before it.
+fn restored_removals(events: &[InventoryEvent]) -> HashSet<Uuid> {
+    let mut events: Vec<&InventoryEvent> = events.iter().collect();
+    events.sort_by_key(|e| e.event_date);
+
+    let mut last_removal: HashMap<Uuid, Uuid> = HashMap::new();
+    let mut restored = HashSet::new();
+    for event in events {
+        match event.event_type {
+            InventoryEventType::Removed => {
+                last_removal.insert(event.ingredient_id, event.event_id);
+            }
+            InventoryEventType::Restored => {
+                if let Some(removal) = last_removal.remove(&event.ingredient_id) {
+                    restored.insert(removal);
+                }
+            }
+            _ => {}
+        }
+    }
+    restored
+}
+
+/// Returns the first day of the month `months` after the month of `date`.
+fn add_months(date: NaiveDate, months: i32) -> NaiveDate {
+    let month = date.year() * 12 + date.month0() as i32 + months;
+    NaiveDate::from_ymd_opt(month.div_euclid(12), month.rem_euclid(12) as u32 + 1, 1)
+        .expect("The first day of a month is always valid")
+}
+
+/// Returns the start of the period `periods` after `start`, it can be negative.
+fn shift_period(period: StatsPeriod, start: DateTime<Utc>, periods: i32) -> DateTime<Utc> {
+    match period {
+        StatsPeriod::Week => start + Duration::weeks(periods as i64),
+        StatsPeriod::Month => add_months(start.date_naive(), periods)
+            .and_hms_opt(0, 0, 0)
+            .expect("Midnight is always valid")
+            .and_utc(),
+    }
+}
+
+/// Returns the start of the period that contains `date`, weeks start on Monday.
+pub fn period_start(period: StatsPeriod, date: DateTime<Utc>) -> DateTime<Utc> {
+    let day = date.date_naive();
+    let start = match period {
+        StatsPeriod::Week => day - Duration::days(day.weekday().num_days_from_monday() as i64),
+        StatsPeriod::Month => add_months(day, 0),
+    };
+    start
+        .and_hms_opt(0, 0, 0)
+        .expect("Midnight is always valid")
+        .and_utc()
+}
+
+/// Returns the start of the range that shows the last `DEFAULT_TREND_PERIODS` periods until `to_date`.
+pub fn default_from_date(period: StatsPeriod, to_date: DateTime<Utc>) -> DateTime<Utc> {
+    shift_period(
+        period,
+        period_start(period, to_date),
+        1 - DEFAULT_TREND_PERIODS,
+    )
+}
+
+/// Computes the statistics of the events that happened between `from_date` and `to_date`.
+///
+/// Removals of ingredients that were restored later aren't counted,
+/// so `events` should include the restores after `to_date` too.
+pub fn waste_stats(
+    events: &[InventoryEvent],
+    period: StatsPeriod,
+    from_date: DateTime<Utc>,
+    to_date: DateTime<Utc>,
+) -> WasteStats {
+    let mut trend = vec![];
+    let mut start = period_start(period, from_date);
+    while start < to_date {
+        trend.push(StatsTrendPoint {
+            period_start: start,
+            added: 0,
+            consumed: 0,
+            discarded: 0,
+        });
+        start = shift_period(period, start, 1);
+    }
+
+    let mut stats = WasteStats {
+        from_date,
+        to_date,
+        period,
+        items_added: 0,
+        items_consumed: 0,
+        items_discarded: 0,
+        items_unspecified: 0,
+        wasted_by_category: vec![],
+        most_wasted: vec![],
+        trend: vec![],
+    };
+    let mut wasted_by_category: HashMap<(String, String), f32> = HashMap::new();
+    let mut most_wasted: HashMap<String, WastedItem> = HashMap::new();
+
+    let restored = restored_removals(events);
+    let events = events
+        .iter()
+        .filter(|e| e.event_date >= from_date && e.event_date < to_date)
+        .filter(|e| !restored.contains(&e.event_id));
+    for event in events {
+        let point = trend
+            .iter()
+            .rposition(|p| p.period_start <= event.event_date)
+            .map(|i| &mut trend[i]);
+
+        match event.event_type {
+            InventoryEventType::Added => {
+                stats.items_added += 1;
+                if let Some(point) = point {
+                    point.added += 1;
+                }
+            }
+            InventoryEventType::Removed => match removal_outcome(event) {
+                RemovalOutcome::Consumed => {
+                    stats.items_consumed += 1;
+                    if let Some(point) = point {
+                        point.consumed += 1;
+                    }
+                }
+                RemovalOutcome::Discarded => {
+                    stats.items_discarded += 1;
+                    if let Some(point) = point {
+                        point.discarded += 1;
+                    }
+
+                    let (quantity, unit) = to_base_unit(event.quantity, &event.unit);
+                    *wasted_by_category
+                        .entry((event.category.clone(), unit))
+                        .or_default() += quantity;
+
+                    most_wasted
+                        .entry(event.name.trim().to_lowercase())
+                        .or_insert_with(|| WastedItem {
+                            name: event.name.trim().to_string(),
+                            times: 0,
+                        })
+                        .times += 1;
+                }
+                RemovalOutcome::Unspecified => stats.items_unspecified += 1,
+            },
+            _ => {}
+        }
+    }
+
+    stats.wasted_by_category = wasted_by_category
+        .into_iter()
+        .map(|((category, unit), quantity)| CategoryWaste {
+            category,
+            quantity,
+            unit,
+        })
+        .collect();
+    stats
+        .wasted_by_category
+        .sort_by(|a, b| a.category.cmp(&b.category).then(a.unit.cmp(&b.unit)));
+
+    stats.most_wasted = most_wasted.into_values().collect();
+    stats
+        .most_wasted
+        .sort_by(|a, b| b.times.cmp(&a.times).then(a.name.cmp(&b.name)));
+    stats.most_wasted.truncate(MOST_WASTED_LIMIT);
+
+    stats.trend = trend;
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn event(
+        ingredient: u128,
+        event_type: InventoryEventType,
+        reason: Option<RemovalReason>,
+        day: u32,
+    ) -> InventoryEvent {
+        InventoryEvent {
+            event_id: Uuid::new_v4(),
+            ingredient_id: Uuid::from_u128(ingredient),
+            user_id: Uuid::nil(),
+            name: format!("Ingredient {}", ingredient),
+            category: "Vegetables".to_string(),
+            quantity: 1.0,
+            unit: "kg".to_string(),
+            event_type,
+            reason,
+            expire_date: None,
+            event_date: Utc.with_ymd_and_hms(2024, 3, day, 12, 0, 0).unwrap(),
+        }
+    }
+
+    #[test]
+    fn doesnt_count_restored_removals() {
+        use InventoryEventType::*;
+        use RemovalReason::*;
+
+        let from_date = Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap();
+        let to_date = Utc.with_ymd_and_hms(2024, 3, 20, 0, 0, 0).unwrap();
+
+        #[rustfmt::skip]
+        let cases = [
+            ("removed", vec![event(1, Removed, Some(Wasted), 2)], 1, 0),
+            ("restored", vec![event(1, Removed, Some(Wasted), 2), event(1, Restored, None, 3)], 0, 0),
+            ("restored after the range", vec![event(1, Removed, Some(Eaten), 2), event(1, Restored, None, 25)], 0, 0),
+            ("removed again", vec![event(1, Removed, Some(Wasted), 2), event(1, Restored, None, 3), event(1, Removed, Some(Eaten), 4)], 0, 1),
+            ("other ingredient restored", vec![event(1, Removed, Some(Wasted), 2), event(2, Restored, None, 3)], 1, 0),
+            ("restored before removed", vec![event(1, Restored, None, 2), event(1, Removed, Some(Wasted), 3)], 1, 0),
+        ];
+
+        for (case, events, discarded, consumed) in cases {
+            let stats = waste_stats(&events, StatsPeriod::Month, from_date, to_date);
+            assert_eq!(stats.items_discarded, discarded, "discarded when {}", case);
+            assert_eq!(stats.items_consumed, consumed, "consumed when {}", case);
+            assert_eq!(
+                stats.wasted_by_category.len(),
+                discarded as usize,
+                "wasted categories when {}",
+                case
+            );
+        }
+    }
+}
@@ -0,0 +1,65 @@
+//! Places of a household where the ingredients are stored.
+
+use std::str::FromStr;
+
+use tokio_postgres::GenericClient;
+use uuid::Uuid;
+
+use crate::metrics::timed_query;
+use crate::models::StorageKind;
+
+/// Represents the location an ingredient is going to be stored in.
+#[derive(Debug)]
+pub struct LocationRef {
+    pub location_id: Uuid,
+    pub kind: StorageKind,
+}
+
+#[allow(dead_code)]
+#[derive(Debug)]
+pub enum GetStorageLocationErrors {
+    InternalDBError(tokio_postgres::Error),
+    LocationNotFound,
+    InvalidLocationFromDB(String),
+}
+
+/// Gets a location of the household, when no location is supplied it gets the default fridge.
+pub async fn get_storage_location(
+    conn: &impl GenericClient,
+    household_id: &str,
+    location_id: Option<&str>,
+) -> Result<LocationRef, GetStorageLocationErrors> {
+    let row = match location_id {
+        Some(location_id) => {
+            timed_query(
+                "get_storage_location",
+                conn.query_opt(
+                    "SELECT location_id, kind FROM sf_storage_location WHERE location_id=$1 AND household_id=$2",
+                    &[&location_id, &household_id],
+                ),
+            )
+            .await
+        }
+        None => {
+            timed_query(
+                "get_storage_location",
+                conn.query_opt(
+                    "SELECT location_id, kind FROM sf_storage_location WHERE household_id=$1 AND kind=$2 AND is_default",
+                    &[&household_id, &format!("{:?}", StorageKind::Fridge)],
+                ),
+            )
+            .await
+        }
+    }
+    .map_err(GetStorageLocationErrors::InternalDBError)?
+    .ok_or(GetStorageLocationErrors::LocationNotFound)?;
+
+    let location_id: String = row.get("location_id");
+    let kind: String = row.get("kind");
+    Ok(LocationRef {
+        location_id: Uuid::from_str(&location_id)
+            .map_err(|_| GetStorageLocationErrors::InvalidLocationFromDB(location_id))?,
+        kind: StorageKind::from_str(&kind)
+            .map_err(|_| GetStorageLocationErrors::InvalidLocationFromDB(kind))?,
+    })
+}
@@ -0,0 +1,232 @@
+//! Applies the changes offline clients upload when they sync.
+
+use chrono::Utc;
+use tokio_postgres::GenericClient;
+
+use crate::{
+    bulk_ingredients::invalid_ingredient_field,
+    ingredient_lots::{edit_lots, get_lots, save_lots, LotsUpdate},
+    metrics::timed_query,
+    models::{
+        InventoryEventType, SyncMutation, SyncMutationKind, SyncMutationResult, SyncMutationStatus,
+    },
+    parse_db_ingredient,
+    storage::{get_storage_location, GetStorageLocationErrors},
+};
+
+/// The most mutations a client can upload in a single sync.
+pub const MAX_SYNC_MUTATIONS: usize = 500;
+
+#[allow(dead_code)]
+#[derive(Debug)]
+pub enum ApplySyncMutationErrors {
+    InternalDBError(tokio_postgres::Error),
+    InvalidIngredientFromDB,
+    InvalidLocationFromDB(String),
+}
+
+impl From<tokio_postgres::Error> for ApplySyncMutationErrors {
+    fn from(value: tokio_postgres::Error) -> Self {
+        ApplySyncMutationErrors::InternalDBError(value)
+    }
+}
+
+/// Applies a mutation of a client to the ingredients of the household.
+///
+/// A mutation conflicts when the ingredient changed after the `BaseUpdatedAt` of the mutation,
+/// or when it's an upsert of an ingredient that was removed. Conflicting mutations aren't applied.
+pub async fn apply_sync_mutation(
+    conn: &impl GenericClient,
+    household_id: &str,
+    user_id: &str,
+    mutation: &SyncMutation,
+    tracing_prefix: &str,
+) -> Result<SyncMutationResult, ApplySyncMutationErrors> {
+    let result = |status, ingredient| SyncMutationResult {
+        mutation_id: mutation.mutation_id,
+        status,
+        ingredient,
+    };
+    let ingredient_id = mutation.ingredient_id.to_string();
+
+    let current = timed_query(
+        "apply_sync_mutation",
+        conn.query_opt(
+            "SELECT * FROM sf_ingredient WHERE ingredient_id=$1",
+            &[&ingredient_id],
+        ),
+    )
+    .await?;
+    let current = match current {
+        Some(row) if row.get::<_, Option<&str>>("household_id") != Some(household_id) => {
+            tracing::error!(
+                "{} The ingredient `{}` belongs to another household!",
+                tracing_prefix,
+                ingredient_id
+            );
+            return Ok(result(SyncMutationStatus::Rejected, None));
+        }
+        Some(row) => {
+            let deleted = row
+                .get::<_, Option<chrono::DateTime<Utc>>>("deleted_date")
+                .is_some();
+            let ingredient = parse_db_ingredient(&row, tracing_prefix)
+                .ok_or(ApplySyncMutationErrors::InvalidIngredientFromDB)?;
+            Some((ingredient, deleted))
+        }
+        None => {
+            let purged = timed_query(
+                "apply_sync_mutation",
+                conn.query_opt(
+                    "SELECT ingredient_id FROM sf_ingredient_tombstone WHERE ingredient_id=$1 AND household_id=$2",
+                    &[&ingredient_id, &household_id],
+                ),
+            )
+            .await?
+            .is_some();
+            if purged {
+                return Ok(match mutation.kind {
+                    SyncMutationKind::Upsert => result(SyncMutationStatus::Conflict, None),
+                    SyncMutationKind::Delete => result(SyncMutationStatus::Applied, None),
+                });
+            }
+            None
+        }
+    };
+
+    match (mutation.kind, current) {
+        (SyncMutationKind::Delete, None) => Ok(result(SyncMutationStatus::Rejected, None)),
+        (SyncMutationKind::Delete, Some((_, true))) => {
+            Ok(result(SyncMutationStatus::Applied, None))
+        }
+        (SyncMutationKind::Upsert, Some((_, true))) => {
+            Ok(result(SyncMutationStatus::Conflict, None))
+        }
+        (_, Some((ingredient, false)))
+            if mutation
+                .base_updated_at
+                .is_none_or(|base| ingredient.updated_at > base) =>
+        {
+            Ok(result(SyncMutationStatus::Conflict, Some(ingredient)))
+        }
+        (SyncMutationKind::Delete, Some((ingredient, _))) => {
+            let rows_modified = timed_query(
+                "apply_sync_mutation",
+                conn.execute(
+                    "WITH removed AS (UPDATE sf_ingredient SET deleted_date=NOW(), updated_at=NOW(), version=version + 1 WHERE ingredient_id=$1 AND household_id=$2 AND deleted_date IS NULL AND version=$6 RETURNING *) INSERT INTO sf_inventory_event (household_id, user_id, ingredient_id, name, category, quantity, unit, expire_date, event_type, reason) SELECT household_id, $3, ingredient_id, name, category, quantity, unit, expire_date, $4, $5 FROM removed",
+                    &[
+                        &ingredient_id,
+                        &household_id,
+                        &user_id,
+                        &format!("{:?}", InventoryEventType::Removed),
+                        &mutation.reason.map(|r| format!("{:?}", r)),
+                        &ingredient.version,
+                    ],
+                ),
+            )
+            .await?;
+            // It changed after it was read.
+            if rows_modified == 0 {
+                return Ok(result(SyncMutationStatus::Conflict, None));
+            }
+            Ok(result(SyncMutationStatus::Applied, None))
+        }
+        (SyncMutationKind::Upsert, current) => {
+            let (Some(name), Some(category), Some(quantity), Some(unit), Some(expire_date)) = (
+                &mutation.name,
+                &mutation.category,
+                mutation.quantity,
+                &mutation.unit,
+                mutation.expire_date,
+            ) else {
+                tracing::error!(
+                    "{} The mutation `{}` is missing fields of the ingredient!",
+                    tracing_prefix,
+                    mutation.mutation_id
+                );
+                return Ok(result(SyncMutationStatus::Rejected, None));
+            };
+            if let Some(field) = invalid_ingredient_field(name, quantity, unit) {
+                tracing::error!(
+                    "{} The mutation `{}` has an invalid `{}`!",
+                    tracing_prefix,
+                    mutation.mutation_id,
+                    field
+                );
+                return Ok(result(SyncMutationStatus::Rejected, None));
+            }
+
+            // New ingredients go to the default fridge, existing ones stay where they are.
+            let location_id = match (mutation.location_id, &current) {
+                (None, Some(_)) => None,
+                (location_id, _) => {
+                    let location_id = location_id.map(|id| id.to_string());
+                    match get_storage_location(conn, household_id, location_id.as_deref()).await {
+                        Ok(location) => Some(location.location_id.to_string()),
+                        Err(GetStorageLocationErrors::LocationNotFound) => {
+                            return Ok(result(SyncMutationStatus::Rejected, None))
+                        }
+                        Err(GetStorageLocationErrors::InternalDBError(err)) => Err(err)?,
+                        Err(GetStorageLocationErrors::InvalidLocationFromDB(location)) => {
+                            Err(ApplySyncMutationErrors::InvalidLocationFromDB(location))?
+                        }
+                    }
+                }
+            };
+
+            if current.is_some() {
+                let old_lots = get_lots(conn, std::slice::from_ref(&ingredient_id))
+                    .await?
+                    .remove(&mutation.ingredient_id)
+                    .unwrap_or_default();
+                let new_lots = edit_lots(&old_lots, quantity, expire_date, Utc::now());
+                let rows_modified = save_lots(
+                    conn,
+                    LotsUpdate {
+                        ingredient_id: &ingredient_id,
+                        household_id,
+                        user_id,
+                        old_lots: &old_lots,
+                        new_lots: &new_lots,
+                        name: Some(name),
+                        category: Some(category),
+                        unit: Some(unit),
+                        location_id,
+                        remove: false,
+                        event_type: InventoryEventType::Edited,
+                        reason: None,
+                        event_quantity: None,
+                        version: current.as_ref().map(|(ingredient, _)| ingredient.version),
+                    },
+                )
+                .await?;
+                // It changed after it was read.
+                if rows_modified == 0 {
+                    return Ok(result(SyncMutationStatus::Conflict, None));
+                }
+            } else {
+                timed_query(
+                    "apply_sync_mutation",
+                    conn.execute(
+                        "WITH added AS (INSERT INTO sf_ingredient (ingredient_id, user_id, name, expire_date, category, quantity, unit, household_id, location_id) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9) RETURNING *), lot AS (INSERT INTO sf_ingredient_lot (ingredient_id, quantity, expire_date) SELECT ingredient_id, quantity, expire_date FROM added) INSERT INTO sf_inventory_event (household_id, user_id, ingredient_id, name, category, quantity, unit, expire_date, event_type, reason) SELECT household_id, $2, ingredient_id, name, category, quantity, unit, expire_date, $10, NULL FROM added",
+                        &[
+                            &ingredient_id,
+                            &user_id,
+                            name,
+                            &expire_date,
+                            category,
+                            &quantity,
+                            unit,
+                            &household_id,
+                            &location_id,
+                            &format!("{:?}", InventoryEventType::Added),
+                        ],
+                    ),
+                )
+                .await?;
+            }
+
+            Ok(result(SyncMutationStatus::Applied, None))
+        }
+    }
+}
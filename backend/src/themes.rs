@@ -0,0 +1,96 @@
+//! Validation of the color themes defined by the users.
+//!
+//! The contrast is computed like the WCAG 2 contrast ratio, we require the ratio for
+//! graphical objects so the primary and secondary colors are visible over the background.
+
+/// The minimum contrast ratio between a color and the background it's drawn over.
+pub const MIN_CONTRAST_RATIO: f32 = 3.0;
+
+#[derive(Debug)]
+pub enum ThemeValidationErrors {
+    InvalidColor { color: String },
+    LowContrast { colors: String, ratio: f32 },
+}
+
+/// Represents the colors of a theme as hex values like "#1A2B3C".
+#[derive(Debug, Clone)]
+pub struct ThemePalette {
+    pub primary: String,
+    pub secondary: String,
+    pub background: String,
+    pub surface: String,
+}
+
+impl ThemePalette {
+    /// Checks the colors are valid hex values with enough contrast between them
+    /// and returns them in uppercase.
+    pub fn validate(self) -> Result<ThemePalette, ThemeValidationErrors> {
+        let palette = ThemePalette {
+            primary: normalize_hex_color(&self.primary)?,
+            secondary: normalize_hex_color(&self.secondary)?,
+            background: normalize_hex_color(&self.background)?,
+            surface: normalize_hex_color(&self.surface)?,
+        };
+
+        let pairs = [
+            (&palette.primary, &palette.background),
+            (&palette.secondary, &palette.background),
+            (&palette.primary, &palette.surface),
+            (&palette.secondary, &palette.surface),
+        ];
+        for (color, over) in pairs {
+            let ratio = contrast_ratio(color, over)?;
+            if ratio < MIN_CONTRAST_RATIO {
+                Err(ThemeValidationErrors::LowContrast {
+                    colors: format!("{} over {}", color, over),
+                    ratio,
+                })?
+            }
+        }
+
+        Ok(palette)
+    }
+}
+
+/// Returns the color as "#RRGGBB" in uppercase, the "#" can be omitted.
+fn normalize_hex_color(color: &str) -> Result<String, ThemeValidationErrors> {
+    let digits = color.trim().trim_start_matches('#');
+    if digits.len() != 6 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        Err(ThemeValidationErrors::InvalidColor {
+            color: color.to_string(),
+        })?
+    }
+    Ok(format!("#{}", digits.to_uppercase()))
+}
+
+/// Computes the relative luminance of a "#RRGGBB" color.
+fn relative_luminance(color: &str) -> Result<f32, ThemeValidationErrors> {
+    let invalid = || ThemeValidationErrors::InvalidColor {
+        color: color.to_string(),
+    };
+    let digits = color.strip_prefix('#').ok_or_else(invalid)?;
+
+    let mut channels = [0.0; 3];
+    for (i, channel) in channels.iter_mut().enumerate() {
+        let value = digits
+            .get(i * 2..i * 2 + 2)
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            .ok_or_else(invalid)?;
+        let value = value as f32 / 255.0;
+        *channel = if value <= 0.03928 {
+            value / 12.92
+        } else {
+            ((value + 0.055) / 1.055).powf(2.4)
+        };
+    }
+
+    Ok(0.2126 * channels[0] + 0.7152 * channels[1] + 0.0722 * channels[2])
+}
+
+/// Computes the contrast ratio between two colors, from 1 to 21.
+pub fn contrast_ratio(a: &str, b: &str) -> Result<f32, ThemeValidationErrors> {
+    let a = relative_luminance(a)?;
+    let b = relative_luminance(b)?;
+    let (lighter, darker) = if a > b { (a, b) } else { (b, a) };
+    Ok((lighter + 0.05) / (darker + 0.05))
+}
@@ -0,0 +1,49 @@
+//! Removed ingredients go to a trash where they can be restored until they are purged.
+
+use std::{sync::Arc, time::Duration};
+
+use tokio_postgres::Client;
+
+use crate::metrics::timed_query;
+
+/// Permanently deletes the ingredients that have been in the trash for longer than `retention_days`.
+/// A tombstone is left for each one so clients syncing later still remove them.
+///
+/// Returns how many ingredients were deleted.
+pub async fn purge_trash(conn: &Client, retention_days: i64) -> Result<u64, tokio_postgres::Error> {
+    timed_query(
+        "purge_trash",
+        conn.execute(
+            "WITH purged AS (DELETE FROM sf_ingredient WHERE deleted_date < NOW() - make_interval(days => $1::INT) RETURNING ingredient_id, household_id) INSERT INTO sf_ingredient_tombstone (ingredient_id, household_id) SELECT ingredient_id, household_id FROM purged ON CONFLICT (ingredient_id) DO UPDATE SET deleted_at=NOW()",
+            &[&(retention_days as i32)],
+        ),
+    )
+    .await
+}
+
+/// Spawns a task that purges the trash every `interval_minutes`.
+pub fn spawn_purge_job(client: Arc<Option<Client>>, retention_days: i64, interval_minutes: u64) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(interval_minutes.max(1) * 60));
+        loop {
+            interval.tick().await;
+
+            let Some(conn) = client.as_ref() else {
+                tracing::error!("Trash purge: No DB connection found!");
+                continue;
+            };
+
+            match purge_trash(conn, retention_days).await {
+                Ok(deleted) => tracing::debug!(
+                    "Trash purge: {} ingredients older than {} days deleted!",
+                    deleted,
+                    retention_days
+                ),
+                Err(err) => tracing::error!(
+                    "Trash purge: An error `{:?}` occurred while purging the trash!",
+                    err
+                ),
+            }
+        }
+    });
+}
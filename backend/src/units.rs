@@ -0,0 +1,147 @@
+//! Conversion between the units used by recipes and the ones the user stores in the fridge.
+
+use crate::ingredient_parser::normalize_unit;
+
+/// Represents what a unit measures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnitKind {
+    /// Base unit is grams.
+    Mass,
+    /// Base unit is milliliters.
+    Volume,
+}
+
+/// Returns the kind of the unit and the factor to convert it to its base unit.
+///
+/// Units that count things (cans, cloves, bags...) have no conversion and return `None`.
+fn unit_factor(unit: &str) -> Option<(UnitKind, f32)> {
+    let factor = match normalize_unit(unit)? {
+        "g" => (UnitKind::Mass, 1.0),
+        "kg" => (UnitKind::Mass, 1000.0),
+        "oz" => (UnitKind::Mass, 28.3495),
+        "lb" => (UnitKind::Mass, 453.592),
+        "ml" => (UnitKind::Volume, 1.0),
+        "l" => (UnitKind::Volume, 1000.0),
+        "tsp" => (UnitKind::Volume, 4.92892),
+        "tbsp" => (UnitKind::Volume, 14.7868),
+        "cup" => (UnitKind::Volume, 236.588),
+        "fl oz" => (UnitKind::Volume, 29.5735),
+        "pint" => (UnitKind::Volume, 473.176),
+        "quart" => (UnitKind::Volume, 946.353),
+        "gallon" => (UnitKind::Volume, 3785.41),
+        _ => None?,
+    };
+    Some(factor)
+}
+
+/// Returns the canonical name of the unit, unknown units are just lowercased.
+pub fn canonical_unit(unit: &str) -> String {
+    match normalize_unit(unit.trim()) {
+        Some(unit) => unit.to_string(),
+        None => unit.trim().to_lowercase(),
+    }
+}
+
+/// Converts a quantity from one unit to another.
+///
+/// Returns `None` if the units measure different things.
+pub fn convert_quantity(quantity: f32, from: &str, to: &str) -> Option<f32> {
+    match (unit_factor(from), unit_factor(to)) {
+        (Some((from_kind, from_factor)), Some((to_kind, to_factor))) if from_kind == to_kind => {
+            Some(quantity * from_factor / to_factor)
+        }
+        (None, None) if canonical_unit(from) == canonical_unit(to) => Some(quantity),
+        _ => None,
+    }
+}
+
+/// Checks if a quantity in one unit can be converted into the other.
+pub fn are_units_compatible(a: &str, b: &str) -> bool {
+    convert_quantity(1.0, a, b).is_some()
+}
+
+/// Converts a quantity to the base unit of what it measures so quantities of different units can be added.
+///
+/// Units that count things keep their canonical name.
+pub fn to_base_unit(quantity: f32, unit: &str) -> (f32, String) {
+    match unit_factor(unit) {
+        Some((UnitKind::Mass, factor)) => (quantity * factor, "g".to_string()),
+        Some((UnitKind::Volume, factor)) => (quantity * factor, "ml".to_string()),
+        None => (quantity, canonical_unit(unit)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_quantities() {
+        #[rustfmt::skip]
+        let cases = [
+            // Mass
+            (1.0, "kg", "g", Some(1000.0)),
+            (500.0, "g", "kg", Some(0.5)),
+            (1.0, "lb", "oz", Some(16.0)),
+            (2.0, "pounds", "g", Some(907.184)),
+            // Volume
+            (1.0, "cup", "ml", Some(236.588)),
+            (1.0, "tbsp", "tsp", Some(3.0)),
+            (2.0, "Cups", "pint", Some(1.0)),
+            (1.0, "gallon", "quart", Some(4.0)),
+            (1.0, "L", "mL", Some(1000.0)),
+            (8.0, "fl oz", "cup", Some(1.0)),
+            // Same unit, with aliases and casing
+            (3.0, "Tablespoons", "tbsp", Some(3.0)),
+            (2.0, "Bags", "bag", Some(2.0)),
+            (4.0, "handful", "Handful", Some(4.0)),
+            (1.0, "", "", Some(1.0)),
+            // Different kinds
+            (1.0, "kg", "ml", None),
+            (1.0, "cup", "g", None),
+            (1.0, "clove", "g", None),
+            (1.0, "can", "bag", None),
+            (1.0, "", "g", None),
+        ];
+
+        for (quantity, from, to, expected) in cases {
+            let converted = convert_quantity(quantity, from, to);
+            match (converted, expected) {
+                (Some(a), Some(b)) => {
+                    assert!((a - b).abs() < 1e-2, "{} {} in {}", quantity, from, to)
+                }
+                (a, b) => assert_eq!(a, b, "{} {} in {}", quantity, from, to),
+            }
+            assert_eq!(
+                are_units_compatible(from, to),
+                expected.is_some(),
+                "`{}` vs `{}`",
+                from,
+                to
+            );
+        }
+    }
+
+    #[test]
+    fn converts_to_base_units() {
+        let cases = [
+            (2.0, "kg", 2000.0, "g"),
+            (1.0, "oz", 28.3495, "g"),
+            (1.5, "l", 1500.0, "ml"),
+            (2.0, "tsp", 9.85784, "ml"),
+            (3.0, "Cloves", 3.0, "clove"),
+            (1.0, "Handful", 1.0, "handful"),
+        ];
+
+        for (quantity, unit, base_quantity, base_unit) in cases {
+            let (converted, converted_unit) = to_base_unit(quantity, unit);
+            assert!(
+                (converted - base_quantity).abs() < 1e-3,
+                "{} {} in the base unit",
+                quantity,
+                unit
+            );
+            assert_eq!(converted_unit, base_unit, "base unit of `{}`", unit);
+        }
+    }
+}
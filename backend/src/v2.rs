@@ -0,0 +1,2176 @@
+//! The v2 API, with resources in the paths, proper verbs and the token in an `Authorization: Bearer` header.
+//!
+//! The routes build the payload of the matching v1 route and run it, so both versions behave the same.
+//! Routes that create something answer `201 Created` and routes that don't return anything `204 No Content`.
+
+use std::{fmt::Display, sync::Arc};
+
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Path, Query, State},
+    handler::Handler,
+    http::{header::AUTHORIZATION, request::Parts},
+    middleware::from_fn_with_state,
+    response::{IntoResponse, Response},
+    routing::{delete, get, patch, post, put},
+    Json, Router,
+};
+use chrono::{DateTime, NaiveDate, Utc};
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use tokio_postgres::Client;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::{
+    bulk_ingredients::TransactionPool,
+    change_feed::ChangeFeed,
+    custom_recipes::CustomRecipeIngredient,
+    idempotency::{idempotency, IdempotencyState},
+    models::{
+        HouseholdRole, MealSlot, Recipe, RemovalReason, StatsPeriod, StorageKind, SyncMutation,
+    },
+    responses::ResponseError,
+    routes::{
+        add_custom_recipe::add_custom_recipe,
+        add_ingredient::{
+            add_ingredient, AddIngredientResponse, DuplicateIngredientAction,
+            IngredientPayload as NewIngredientPayload,
+        },
+        add_recipe_to_shopping_list::add_recipe_to_shopping_list,
+        add_shopping_item::{add_shopping_item, ShoppingItemPayload as NewShoppingItemPayload},
+        add_storage_location::add_storage_location,
+        add_theme::add_theme,
+        assign_meal::assign_meal,
+        bulk_add_ingredients::bulk_add_ingredients,
+        bulk_edit_ingredients::bulk_edit_ingredients,
+        bulk_remove_ingredients::{bulk_remove_ingredients, RemovedIngredientPayload},
+        buy_shopping_items::buy_shopping_items,
+        consume_ingredient::consume_ingredient,
+        create_household::create_household_route,
+        edit_custom_recipe::edit_custom_recipe,
+        edit_ingredient::{edit_ingredient, IngredientPayload as EditedIngredientPayload},
+        edit_shopping_item::edit_shopping_item,
+        get_cook_history::get_cook_history,
+        get_custom_recipes::get_custom_recipes,
+        get_households::get_households,
+        get_ingredient::get_ingredient_route,
+        get_ingredients::get_ingredients,
+        get_inventory_history::get_inventory_history,
+        get_meal_plan::get_meal_plan,
+        get_recipe_ratings::get_recipe_ratings,
+        get_recipes::get_recipes,
+        get_saved_recipes::get_saved_recipes,
+        get_settings::get_settings,
+        get_shopping_list::get_shopping_list,
+        get_stats::get_stats,
+        get_storage_locations::get_storage_locations,
+        get_themes::get_themes,
+        get_trash::get_trash,
+        import_theme::import_theme,
+        invite_to_household::invite_to_household,
+        join_household::join_household,
+        leave_household::leave_household,
+        login_user::login_user,
+        logout::logout,
+        meal_plan_requirements::meal_plan_requirements,
+        move_ingredients::{move_ingredients, MovedIngredientPayload},
+        rate_recipe::rate_recipe,
+        recipe_details::recipe_details,
+        record_cooked_recipe::record_cooked_recipe,
+        register_user::register_user,
+        remove_custom_recipe::remove_custom_recipe,
+        remove_household_member::remove_household_member,
+        remove_ingredient::remove_ingredient,
+        remove_meal::remove_meal,
+        remove_shopping_item::remove_shopping_item,
+        remove_storage_location::remove_storage_location,
+        remove_theme::remove_theme,
+        restore_ingredient::restore_ingredient,
+        save_recipe::save_recipe,
+        save_settings::{save_settings, UserSettingsPayload},
+        search_ingredients::search_ingredients,
+        search_recipes::search_recipes,
+        set_member_role::set_member_role,
+        stream_changes::stream_changes,
+        switch_household::switch_household,
+        sync_changes::sync_changes,
+        unsave_recipe::unsave_recipe,
+    },
+    Params,
+};
+
+#[derive(Debug, Serialize)]
+pub enum ApiV2Errors {
+    MissingBearerToken,
+    /// The body or query string of the request couldn't be turned into a v1 payload.
+    InvalidPayloadFields,
+}
+
+impl Display for ApiV2Errors {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+type ApiV2Result = Result<Response, ResponseError<ApiV2Errors>>;
+
+#[derive(Clone)]
+pub struct ApiV2State {
+    pub client: Arc<Option<Client>>,
+    pub changes: Arc<ChangeFeed>,
+    pub params: Arc<Params>,
+    pub transactions: Arc<TransactionPool>,
+}
+
+/// The session token sent in the `Authorization: Bearer <token>` header.
+pub struct BearerToken(pub String);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for BearerToken
+where
+    S: Send + Sync,
+{
+    type Rejection = ResponseError<ApiV2Errors>;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts
+            .headers
+            .get(AUTHORIZATION)
+            .and_then(|header| header.to_str().ok())
+            .and_then(|header| header.strip_prefix("Bearer "))
+            .map(str::trim)
+            .filter(|token| !token.is_empty())
+            .map(|token| BearerToken(token.to_string()))
+            .ok_or_else(|| {
+                tracing::error!("{} - v2: The bearer token is missing!", parts.uri.path());
+                (StatusCode::UNAUTHORIZED, ApiV2Errors::MissingBearerToken).into()
+            })
+    }
+}
+
+/// Serializes the body or query string of a request into the fields of a v1 payload.
+fn fields(value: impl Serialize) -> Result<Map<String, Value>, ResponseError<ApiV2Errors>> {
+    match serde_json::to_value(value) {
+        Ok(Value::Object(fields)) => Ok(fields),
+        other => {
+            tracing::error!(
+                "v2: The request couldn't be turned into the fields of a v1 payload, it became `{:?}`!",
+                other
+            );
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                ApiV2Errors::InvalidPayloadFields,
+            )
+                .into())
+        }
+    }
+}
+
+/// Builds the payload of a v1 route by adding the token to `fields`.
+fn v1_payload(token: String, fields: Map<String, Value>) -> Json<Value> {
+    let mut fields = fields;
+    fields.insert("token".to_string(), Value::String(token));
+    Json(Value::Object(fields))
+}
+
+/// Same as [`v1_payload`] for the fields built with `json!`.
+fn v1_json(token: String, value: Value) -> Result<Json<Value>, ResponseError<ApiV2Errors>> {
+    Ok(v1_payload(token, fields(value)?))
+}
+
+/// Turns the response of a v1 route into a v2 one, answering `status` when it succeeded.
+fn respond(response: impl IntoResponse, status: StatusCode) -> ApiV2Result {
+    let mut response = response.into_response();
+    if response.status() != StatusCode::OK {
+        return Ok(response);
+    }
+    if status == StatusCode::NO_CONTENT {
+        return Ok(StatusCode::NO_CONTENT.into_response());
+    }
+    *response.status_mut() = status;
+    Ok(response)
+}
+
+// Bodies, with the same fields as the payloads of the v1 routes except the ones in the path.
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct Credentials {
+    username: String,
+    password: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct NewHousehold {
+    name: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct CurrentHousehold {
+    household_id: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct NewInvitation {
+    /// Members when missing.
+    role: Option<HouseholdRole>,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct MemberRole {
+    role: HouseholdRole,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct NewTheme {
+    name: String,
+    /// All the colors are hex values like "#1A2B3C".
+    primary: String,
+    secondary: String,
+    background: String,
+    surface: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct SharedTheme {
+    share_code: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct SavedRecipeChanges {
+    /// Required the first time the recipe is saved.
+    recipe: Option<Recipe>,
+    notes: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct CustomRecipe {
+    title: String,
+    image_url: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    ingredients: Vec<CustomRecipeIngredient>,
+    #[serde(default)]
+    steps: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct CookedRecipe {
+    /// Now when missing.
+    cooked_date: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct Rating {
+    /// From 1 to 5 stars.
+    rating: i16,
+    comment: Option<String>,
+}
+
+/// Represents the new values of an ingredient, see [`EditedIngredientPayload`].
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct IngredientChanges {
+    #[serde(rename = "ExpireDate")]
+    expire_date: DateTime<Utc>,
+
+    #[serde(rename = "Name")]
+    name: String,
+
+    #[serde(rename = "Category")]
+    category: String,
+
+    #[serde(rename = "Quantity")]
+    quantity: f32,
+
+    #[serde(rename = "Unit")]
+    unit: String,
+
+    /// When missing the ingredient stays where it is.
+    #[serde(rename = "LocationId", default)]
+    location_id: Option<Uuid>,
+
+    /// The version of the ingredient the client edited.
+    #[serde(rename = "Version")]
+    version: i32,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct IngredientsMove {
+    ingredients: Vec<MovedIngredientPayload>,
+    location_id: Uuid,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct Consumption {
+    quantity: f32,
+    /// The unit of the ingredient when missing.
+    unit: Option<String>,
+    /// The version of the ingredient the client consumed.
+    version: i32,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct NewLocation {
+    name: String,
+    kind: StorageKind,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct SyncRequest {
+    /// The `Cursor` of the last sync, everything is returned when missing.
+    cursor: Option<i64>,
+    #[serde(default)]
+    mutations: Vec<SyncMutation>,
+}
+
+/// Represents the new values of a shopping list item, see [`EditedShoppingItemPayload`].
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct ShoppingItemChanges {
+    #[serde(rename = "Name")]
+    name: String,
+
+    #[serde(rename = "Category")]
+    category: String,
+
+    #[serde(rename = "Quantity")]
+    quantity: f32,
+
+    #[serde(rename = "Unit")]
+    unit: String,
+
+    #[serde(rename = "Checked")]
+    checked: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct RecipeToBuy {
+    recipe: Recipe,
+}
+
+#[derive(Debug, Deserialize, Serialize, ToSchema)]
+pub struct NewMeal {
+    recipe_id: String,
+    date: NaiveDate,
+    slot: MealSlot,
+}
+
+// Query strings.
+
+#[derive(Debug, Deserialize, Serialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct RecipesQuery {
+    /// Whether to leave out the recipes that go against the dietary restrictions of the user.
+    #[serde(default)]
+    hide_restricted: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SearchRecipesQuery {
+    query: String,
+    /// Whether to leave out the recipes that go against the dietary restrictions of the user.
+    #[serde(default)]
+    hide_restricted: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SavedRecipesQuery {
+    /// Only returns the recipes the user gave this tag.
+    tag: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct CookHistoryQuery {
+    /// Only returns the times this recipe was cooked.
+    recipe_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct IngredientsQuery {
+    /// Only returns the ingredients stored in this location.
+    location_id: Option<Uuid>,
+    /// Only returns the ingredients stored in locations of this kind.
+    kind: Option<StorageKind>,
+}
+
+#[derive(Debug, Deserialize, Serialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SearchIngredientsQuery {
+    query: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AddIngredientQuery {
+    /// What to do when the household already has the ingredient in the same location.
+    #[serde(default)]
+    on_duplicate: DuplicateIngredientAction,
+}
+
+#[derive(Debug, Deserialize, Serialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct RemoveIngredientQuery {
+    /// The version of the ingredient the client removed.
+    version: i32,
+    /// Why the ingredient was removed, it's used for the waste statistics.
+    reason: Option<RemovalReason>,
+}
+
+#[derive(Debug, Deserialize, Serialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct InventoryHistoryQuery {
+    ingredient_id: Option<Uuid>,
+    category: Option<String>,
+    from_date: Option<DateTime<Utc>>,
+    to_date: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize, Serialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct StatsQuery {
+    #[serde(default)]
+    period: StatsPeriod,
+    from_date: Option<DateTime<Utc>>,
+    to_date: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize, Serialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct DateRangeQuery {
+    from: NaiveDate,
+    to: NaiveDate,
+}
+
+/// Router with all the v2 routes, to nest under `/v2`.
+pub fn router(state: ApiV2State, idempotency_state: IdempotencyState) -> Router {
+    // Mutating routes replay their response when retried with the same `Idempotency-Key`.
+    let idempotent = from_fn_with_state(idempotency_state, idempotency);
+
+    Router::new()
+        // Users
+        .route("/users", post(register.layer(idempotent.clone())))
+        .route("/sessions", post(login))
+        .route("/sessions/current", delete(end_session))
+        .route(
+            "/settings",
+            get(read_settings).patch(update_settings.layer(idempotent.clone())),
+        )
+        // Households
+        .route(
+            "/households",
+            get(list_households).post(create_household.layer(idempotent.clone())),
+        )
+        .route(
+            "/households/current",
+            put(select_household.layer(idempotent.clone())),
+        )
+        .route(
+            "/households/invites",
+            post(create_invite.layer(idempotent.clone())),
+        )
+        .route(
+            "/households/invites/:code/accept",
+            post(accept_invite.layer(idempotent.clone())),
+        )
+        .route(
+            "/households/members/:user_id",
+            patch(update_member.layer(idempotent.clone()))
+                .delete(delete_member.layer(idempotent.clone())),
+        )
+        .route(
+            "/households/:household_id/membership",
+            delete(leave.layer(idempotent.clone())),
+        )
+        // Themes
+        .route(
+            "/themes",
+            get(list_themes).post(create_theme.layer(idempotent.clone())),
+        )
+        .route(
+            "/themes/import",
+            post(import_shared_theme.layer(idempotent.clone())),
+        )
+        .route(
+            "/themes/:theme_id",
+            delete(delete_theme.layer(idempotent.clone())),
+        )
+        // Recipes
+        .route("/recipes", get(list_recipes))
+        .route("/recipes/search", get(find_recipes))
+        .route("/recipes/saved", get(list_saved_recipes))
+        .route(
+            "/recipes/saved/:recipe_id",
+            put(save_saved_recipe.layer(idempotent.clone()))
+                .delete(delete_saved_recipe.layer(idempotent.clone())),
+        )
+        .route(
+            "/recipes/custom",
+            get(list_custom_recipes).post(create_custom_recipe.layer(idempotent.clone())),
+        )
+        .route(
+            "/recipes/custom/:recipe_id",
+            put(update_custom_recipe.layer(idempotent.clone()))
+                .delete(delete_custom_recipe.layer(idempotent.clone())),
+        )
+        .route("/recipes/history", get(list_cook_history))
+        .route("/recipes/ratings", get(list_recipe_ratings))
+        .route("/recipes/:recipe_id", get(read_recipe))
+        .route(
+            "/recipes/:recipe_id/cooked",
+            post(create_cooked_entry.layer(idempotent.clone())),
+        )
+        .route(
+            "/recipes/:recipe_id/rating",
+            put(replace_rating.layer(idempotent.clone())),
+        )
+        // Ingredients
+        .route(
+            "/ingredients",
+            get(list_ingredients).post(create_ingredient.layer(idempotent.clone())),
+        )
+        .route("/ingredients/search", get(find_ingredients))
+        .route("/ingredients/trash", get(list_trash))
+        .route("/ingredients/history", get(list_inventory_history))
+        .route(
+            "/ingredients/move",
+            post(move_to_location.layer(idempotent.clone())),
+        )
+        .route(
+            "/ingredients/bulk",
+            post(create_ingredients.layer(idempotent.clone()))
+                .put(update_ingredients.layer(idempotent.clone()))
+                .delete(delete_ingredients.layer(idempotent.clone())),
+        )
+        .route(
+            "/ingredients/:ingredient_id",
+            get(read_ingredient)
+                .put(update_ingredient.layer(idempotent.clone()))
+                .delete(delete_ingredient.layer(idempotent.clone())),
+        )
+        .route(
+            "/ingredients/:ingredient_id/consume",
+            post(consume.layer(idempotent.clone())),
+        )
+        .route(
+            "/ingredients/:ingredient_id/restore",
+            post(restore.layer(idempotent.clone())),
+        )
+        // Storage locations
+        .route(
+            "/locations",
+            get(list_locations).post(create_location.layer(idempotent.clone())),
+        )
+        .route(
+            "/locations/:location_id",
+            delete(delete_location.layer(idempotent.clone())),
+        )
+        // Sync and statistics
+        .route("/sync", post(sync.layer(idempotent.clone())))
+        .route("/changes", get(changes))
+        .route("/stats", get(stats))
+        // Shopping list
+        .route("/shopping-list", get(list_shopping_items))
+        .route(
+            "/shopping-list/items",
+            post(create_shopping_item.layer(idempotent.clone())),
+        )
+        .route(
+            "/shopping-list/items/:item_id",
+            put(update_shopping_item.layer(idempotent.clone()))
+                .delete(delete_shopping_item.layer(idempotent.clone())),
+        )
+        .route(
+            "/shopping-list/recipes",
+            post(create_recipe_items.layer(idempotent.clone())),
+        )
+        .route(
+            "/shopping-list/bought",
+            post(buy_checked_items.layer(idempotent.clone())),
+        )
+        // Meal plan
+        .route(
+            "/meal-plan",
+            get(list_meals).post(create_meal.layer(idempotent.clone())),
+        )
+        .route("/meal-plan/requirements", get(list_meal_requirements))
+        .route("/meal-plan/:plan_id", delete(delete_meal.layer(idempotent)))
+        .with_state(state)
+}
+
+// Users
+
+#[utoipa::path(
+    post,
+    path = "/v2/users",
+    tag = "Users",
+    request_body = Credentials,
+    responses(
+        (status = 201, description = "The user was registered"),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    )
+)]
+async fn register(State(state): State<ApiV2State>, Json(body): Json<Credentials>) -> ApiV2Result {
+    respond(
+        register_user(Json(json!(body)), state.client).await,
+        StatusCode::CREATED,
+    )
+}
+
+#[utoipa::path(
+    post,
+    path = "/v2/sessions",
+    tag = "Users",
+    request_body = Credentials,
+    responses(
+        (status = 201, description = "The session token and the settings of the user", body = LoginUserResponse),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    )
+)]
+async fn login(State(state): State<ApiV2State>, Json(body): Json<Credentials>) -> ApiV2Result {
+    respond(
+        login_user(Json(json!(body)), state.client).await,
+        StatusCode::CREATED,
+    )
+}
+
+#[utoipa::path(
+    delete,
+    path = "/v2/sessions/current",
+    tag = "Users",
+    responses(
+        (status = 204, description = "The session was closed"),
+        (status = 401, description = "The bearer token is missing or the session expired", body = String, content_type = "text/plain"),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    ),
+    security(("bearer" = []))
+)]
+async fn end_session(
+    State(state): State<ApiV2State>,
+    BearerToken(token): BearerToken,
+) -> ApiV2Result {
+    respond(
+        logout(v1_json(token, json!({}))?, state.client).await,
+        StatusCode::NO_CONTENT,
+    )
+}
+
+#[utoipa::path(
+    get,
+    path = "/v2/settings",
+    tag = "Settings",
+    responses(
+        (status = 200, description = "The settings of the user", body = UserSettings),
+        (status = 401, description = "The bearer token is missing or the session expired", body = String, content_type = "text/plain"),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    ),
+    security(("bearer" = []))
+)]
+async fn read_settings(
+    State(state): State<ApiV2State>,
+    BearerToken(token): BearerToken,
+) -> ApiV2Result {
+    respond(
+        get_settings(v1_json(token, json!({}))?, state.client).await,
+        StatusCode::OK,
+    )
+}
+
+#[utoipa::path(
+    patch,
+    path = "/v2/settings",
+    tag = "Settings",
+    request_body = UserSettingsPayload,
+    responses(
+        (status = 204, description = "The settings in the body were saved, the ones missing are kept"),
+        (status = 401, description = "The bearer token is missing or the session expired", body = String, content_type = "text/plain"),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    ),
+    security(("bearer" = []))
+)]
+async fn update_settings(
+    State(state): State<ApiV2State>,
+    BearerToken(token): BearerToken,
+    Json(settings): Json<UserSettingsPayload>,
+) -> ApiV2Result {
+    respond(
+        save_settings(
+            v1_json(token, json!({ "settings": settings }))?,
+            state.client,
+        )
+        .await,
+        StatusCode::NO_CONTENT,
+    )
+}
+
+// Households
+
+#[utoipa::path(
+    get,
+    path = "/v2/households",
+    tag = "Households",
+    responses(
+        (status = 200, description = "The households of the user", body = [Household]),
+        (status = 401, description = "The bearer token is missing or the session expired", body = String, content_type = "text/plain"),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    ),
+    security(("bearer" = []))
+)]
+async fn list_households(
+    State(state): State<ApiV2State>,
+    BearerToken(token): BearerToken,
+) -> ApiV2Result {
+    respond(
+        get_households(v1_json(token, json!({}))?, state.client).await,
+        StatusCode::OK,
+    )
+}
+
+#[utoipa::path(
+    post,
+    path = "/v2/households",
+    tag = "Households",
+    request_body = NewHousehold,
+    responses(
+        (status = 201, description = "The household was created", body = CreateHouseholdResponse),
+        (status = 401, description = "The bearer token is missing or the session expired", body = String, content_type = "text/plain"),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    ),
+    security(("bearer" = []))
+)]
+async fn create_household(
+    State(state): State<ApiV2State>,
+    BearerToken(token): BearerToken,
+    Json(body): Json<NewHousehold>,
+) -> ApiV2Result {
+    respond(
+        create_household_route(v1_payload(token, fields(body)?), state.client).await,
+        StatusCode::CREATED,
+    )
+}
+
+#[utoipa::path(
+    put,
+    path = "/v2/households/current",
+    tag = "Households",
+    request_body = CurrentHousehold,
+    responses(
+        (status = 204, description = "The household is now the current one"),
+        (status = 401, description = "The bearer token is missing or the session expired", body = String, content_type = "text/plain"),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    ),
+    security(("bearer" = []))
+)]
+async fn select_household(
+    State(state): State<ApiV2State>,
+    BearerToken(token): BearerToken,
+    Json(body): Json<CurrentHousehold>,
+) -> ApiV2Result {
+    respond(
+        switch_household(v1_payload(token, fields(body)?), state.client).await,
+        StatusCode::NO_CONTENT,
+    )
+}
+
+#[utoipa::path(
+    post,
+    path = "/v2/households/invites",
+    tag = "Households",
+    request_body = NewInvitation,
+    responses(
+        (status = 201, description = "The invitation to share", body = HouseholdInvitation),
+        (status = 401, description = "The bearer token is missing or the session expired", body = String, content_type = "text/plain"),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    ),
+    security(("bearer" = []))
+)]
+async fn create_invite(
+    State(state): State<ApiV2State>,
+    BearerToken(token): BearerToken,
+    Json(body): Json<NewInvitation>,
+) -> ApiV2Result {
+    respond(
+        invite_to_household(v1_payload(token, fields(body)?), state.client).await,
+        StatusCode::CREATED,
+    )
+}
+
+#[utoipa::path(
+    post,
+    path = "/v2/households/invites/{code}/accept",
+    tag = "Households",
+    params(("code" = String, Path, description = "The code of the invitation")),
+    responses(
+        (status = 200, description = "The user joined the household", body = JoinHouseholdResponse),
+        (status = 401, description = "The bearer token is missing or the session expired", body = String, content_type = "text/plain"),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    ),
+    security(("bearer" = []))
+)]
+async fn accept_invite(
+    State(state): State<ApiV2State>,
+    BearerToken(token): BearerToken,
+    Path(code): Path<String>,
+) -> ApiV2Result {
+    respond(
+        join_household(v1_json(token, json!({ "code": code }))?, state.client).await,
+        StatusCode::OK,
+    )
+}
+
+#[utoipa::path(
+    patch,
+    path = "/v2/households/members/{user_id}",
+    tag = "Households",
+    params(("user_id" = Uuid, Path, description = "The member")),
+    request_body = MemberRole,
+    responses(
+        (status = 200, description = "The role was changed"),
+        (status = 401, description = "The bearer token is missing or the session expired", body = String, content_type = "text/plain"),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    ),
+    security(("bearer" = []))
+)]
+async fn update_member(
+    State(state): State<ApiV2State>,
+    BearerToken(token): BearerToken,
+    Path(user_id): Path<Uuid>,
+    Json(body): Json<MemberRole>,
+) -> ApiV2Result {
+    let mut body = fields(body)?;
+    body.insert("user_id".to_string(), json!(user_id));
+    respond(
+        set_member_role(v1_payload(token, body), state.client).await,
+        StatusCode::OK,
+    )
+}
+
+#[utoipa::path(
+    delete,
+    path = "/v2/households/members/{user_id}",
+    tag = "Households",
+    params(("user_id" = Uuid, Path, description = "The member")),
+    responses(
+        (status = 204, description = "The member was removed"),
+        (status = 401, description = "The bearer token is missing or the session expired", body = String, content_type = "text/plain"),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    ),
+    security(("bearer" = []))
+)]
+async fn delete_member(
+    State(state): State<ApiV2State>,
+    BearerToken(token): BearerToken,
+    Path(user_id): Path<Uuid>,
+) -> ApiV2Result {
+    respond(
+        remove_household_member(v1_json(token, json!({ "user_id": user_id }))?, state.client).await,
+        StatusCode::NO_CONTENT,
+    )
+}
+
+#[utoipa::path(
+    delete,
+    path = "/v2/households/{household_id}/membership",
+    tag = "Households",
+    params(("household_id" = String, Path, description = "The household to leave")),
+    responses(
+        (status = 204, description = "The user left the household"),
+        (status = 401, description = "The bearer token is missing or the session expired", body = String, content_type = "text/plain"),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    ),
+    security(("bearer" = []))
+)]
+async fn leave(
+    State(state): State<ApiV2State>,
+    BearerToken(token): BearerToken,
+    Path(household_id): Path<String>,
+) -> ApiV2Result {
+    respond(
+        leave_household(
+            v1_json(token, json!({ "household_id": household_id }))?,
+            state.client,
+        )
+        .await,
+        StatusCode::NO_CONTENT,
+    )
+}
+
+// Themes
+
+#[utoipa::path(
+    get,
+    path = "/v2/themes",
+    tag = "Themes",
+    responses(
+        (status = 200, description = "The themes of the user", body = [CustomTheme]),
+        (status = 401, description = "The bearer token is missing or the session expired", body = String, content_type = "text/plain"),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    ),
+    security(("bearer" = []))
+)]
+async fn list_themes(
+    State(state): State<ApiV2State>,
+    BearerToken(token): BearerToken,
+) -> ApiV2Result {
+    respond(
+        get_themes(v1_json(token, json!({}))?, state.client).await,
+        StatusCode::OK,
+    )
+}
+
+#[utoipa::path(
+    post,
+    path = "/v2/themes",
+    tag = "Themes",
+    request_body = NewTheme,
+    responses(
+        (status = 201, description = "The theme was added", body = CustomTheme),
+        (status = 401, description = "The bearer token is missing or the session expired", body = String, content_type = "text/plain"),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    ),
+    security(("bearer" = []))
+)]
+async fn create_theme(
+    State(state): State<ApiV2State>,
+    BearerToken(token): BearerToken,
+    Json(body): Json<NewTheme>,
+) -> ApiV2Result {
+    respond(
+        add_theme(v1_payload(token, fields(body)?), state.client).await,
+        StatusCode::CREATED,
+    )
+}
+
+#[utoipa::path(
+    post,
+    path = "/v2/themes/import",
+    tag = "Themes",
+    request_body = SharedTheme,
+    responses(
+        (status = 201, description = "The copy of the shared theme", body = CustomTheme),
+        (status = 401, description = "The bearer token is missing or the session expired", body = String, content_type = "text/plain"),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    ),
+    security(("bearer" = []))
+)]
+async fn import_shared_theme(
+    State(state): State<ApiV2State>,
+    BearerToken(token): BearerToken,
+    Json(body): Json<SharedTheme>,
+) -> ApiV2Result {
+    respond(
+        import_theme(v1_payload(token, fields(body)?), state.client).await,
+        StatusCode::CREATED,
+    )
+}
+
+#[utoipa::path(
+    delete,
+    path = "/v2/themes/{theme_id}",
+    tag = "Themes",
+    params(("theme_id" = Uuid, Path, description = "The theme")),
+    responses(
+        (status = 204, description = "The theme was removed"),
+        (status = 401, description = "The bearer token is missing or the session expired", body = String, content_type = "text/plain"),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    ),
+    security(("bearer" = []))
+)]
+async fn delete_theme(
+    State(state): State<ApiV2State>,
+    BearerToken(token): BearerToken,
+    Path(theme_id): Path<Uuid>,
+) -> ApiV2Result {
+    respond(
+        remove_theme(
+            v1_json(token, json!({ "theme_id": theme_id }))?,
+            state.client,
+        )
+        .await,
+        StatusCode::NO_CONTENT,
+    )
+}
+
+// Recipes
+
+#[utoipa::path(
+    get,
+    path = "/v2/recipes",
+    tag = "Recipes",
+    params(RecipesQuery),
+    responses(
+        (status = 200, description = "The recommended recipes", body = [Recipe]),
+        (status = 401, description = "The bearer token is missing or the session expired", body = String, content_type = "text/plain"),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    ),
+    security(("bearer" = []))
+)]
+async fn list_recipes(
+    State(state): State<ApiV2State>,
+    BearerToken(token): BearerToken,
+    Query(query): Query<RecipesQuery>,
+) -> ApiV2Result {
+    respond(
+        get_recipes(
+            v1_payload(token, fields(query)?),
+            state.client,
+            state.params,
+        )
+        .await,
+        StatusCode::OK,
+    )
+}
+
+#[utoipa::path(
+    get,
+    path = "/v2/recipes/search",
+    tag = "Recipes",
+    params(SearchRecipesQuery),
+    responses(
+        (status = 200, description = "The recipes found", body = [Recipe]),
+        (status = 401, description = "The bearer token is missing or the session expired", body = String, content_type = "text/plain"),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    ),
+    security(("bearer" = []))
+)]
+async fn find_recipes(
+    State(state): State<ApiV2State>,
+    BearerToken(token): BearerToken,
+    Query(query): Query<SearchRecipesQuery>,
+) -> ApiV2Result {
+    respond(
+        search_recipes(
+            v1_payload(token, fields(query)?),
+            state.client,
+            state.params,
+        )
+        .await,
+        StatusCode::OK,
+    )
+}
+
+#[utoipa::path(
+    get,
+    path = "/v2/recipes/{recipe_id}",
+    tag = "Recipes",
+    params(("recipe_id" = String, Path, description = "The recipe")),
+    responses(
+        (status = 200, description = "The recipe", body = Recipe),
+        (status = 401, description = "The bearer token is missing or the session expired", body = String, content_type = "text/plain"),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    ),
+    security(("bearer" = []))
+)]
+async fn read_recipe(
+    State(state): State<ApiV2State>,
+    BearerToken(token): BearerToken,
+    Path(recipe_id): Path<String>,
+) -> ApiV2Result {
+    respond(
+        recipe_details(
+            v1_json(token, json!({ "recipeId": recipe_id }))?,
+            state.client,
+        )
+        .await,
+        StatusCode::OK,
+    )
+}
+
+#[utoipa::path(
+    get,
+    path = "/v2/recipes/saved",
+    tag = "Recipes",
+    params(SavedRecipesQuery),
+    responses(
+        (status = 200, description = "The saved recipes", body = [SavedRecipe]),
+        (status = 401, description = "The bearer token is missing or the session expired", body = String, content_type = "text/plain"),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    ),
+    security(("bearer" = []))
+)]
+async fn list_saved_recipes(
+    State(state): State<ApiV2State>,
+    BearerToken(token): BearerToken,
+    Query(query): Query<SavedRecipesQuery>,
+) -> ApiV2Result {
+    respond(
+        get_saved_recipes(v1_payload(token, fields(query)?), state.client).await,
+        StatusCode::OK,
+    )
+}
+
+#[utoipa::path(
+    put,
+    path = "/v2/recipes/saved/{recipe_id}",
+    tag = "Recipes",
+    params(("recipe_id" = String, Path, description = "The recipe")),
+    request_body = SavedRecipeChanges,
+    responses(
+        (status = 200, description = "The recipe was saved"),
+        (status = 401, description = "The bearer token is missing or the session expired", body = String, content_type = "text/plain"),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    ),
+    security(("bearer" = []))
+)]
+async fn save_saved_recipe(
+    State(state): State<ApiV2State>,
+    BearerToken(token): BearerToken,
+    Path(recipe_id): Path<String>,
+    Json(body): Json<SavedRecipeChanges>,
+) -> ApiV2Result {
+    let mut body = fields(body)?;
+    body.insert("recipe_id".to_string(), Value::String(recipe_id));
+    respond(
+        save_recipe(v1_payload(token, body), state.client).await,
+        StatusCode::OK,
+    )
+}
+
+#[utoipa::path(
+    delete,
+    path = "/v2/recipes/saved/{recipe_id}",
+    tag = "Recipes",
+    params(("recipe_id" = String, Path, description = "The recipe")),
+    responses(
+        (status = 204, description = "The recipe is no longer saved"),
+        (status = 401, description = "The bearer token is missing or the session expired", body = String, content_type = "text/plain"),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    ),
+    security(("bearer" = []))
+)]
+async fn delete_saved_recipe(
+    State(state): State<ApiV2State>,
+    BearerToken(token): BearerToken,
+    Path(recipe_id): Path<String>,
+) -> ApiV2Result {
+    respond(
+        unsave_recipe(
+            v1_json(token, json!({ "recipe_id": recipe_id }))?,
+            state.client,
+        )
+        .await,
+        StatusCode::NO_CONTENT,
+    )
+}
+
+#[utoipa::path(
+    get,
+    path = "/v2/recipes/custom",
+    tag = "Recipes",
+    responses(
+        (status = 200, description = "The recipes written by the user", body = [Recipe]),
+        (status = 401, description = "The bearer token is missing or the session expired", body = String, content_type = "text/plain"),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    ),
+    security(("bearer" = []))
+)]
+async fn list_custom_recipes(
+    State(state): State<ApiV2State>,
+    BearerToken(token): BearerToken,
+) -> ApiV2Result {
+    respond(
+        get_custom_recipes(v1_json(token, json!({}))?, state.client).await,
+        StatusCode::OK,
+    )
+}
+
+#[utoipa::path(
+    post,
+    path = "/v2/recipes/custom",
+    tag = "Recipes",
+    request_body = CustomRecipe,
+    responses(
+        (status = 201, description = "The recipe was added", body = AddCustomRecipeResponse),
+        (status = 401, description = "The bearer token is missing or the session expired", body = String, content_type = "text/plain"),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    ),
+    security(("bearer" = []))
+)]
+async fn create_custom_recipe(
+    State(state): State<ApiV2State>,
+    BearerToken(token): BearerToken,
+    Json(body): Json<CustomRecipe>,
+) -> ApiV2Result {
+    respond(
+        add_custom_recipe(v1_payload(token, fields(body)?), state.client).await,
+        StatusCode::CREATED,
+    )
+}
+
+#[utoipa::path(
+    put,
+    path = "/v2/recipes/custom/{recipe_id}",
+    tag = "Recipes",
+    params(("recipe_id" = String, Path, description = "The recipe")),
+    request_body = CustomRecipe,
+    responses(
+        (status = 200, description = "The recipe was edited"),
+        (status = 401, description = "The bearer token is missing or the session expired", body = String, content_type = "text/plain"),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    ),
+    security(("bearer" = []))
+)]
+async fn update_custom_recipe(
+    State(state): State<ApiV2State>,
+    BearerToken(token): BearerToken,
+    Path(recipe_id): Path<String>,
+    Json(body): Json<CustomRecipe>,
+) -> ApiV2Result {
+    let mut body = fields(body)?;
+    body.insert("recipe_id".to_string(), Value::String(recipe_id));
+    respond(
+        edit_custom_recipe(v1_payload(token, body), state.client).await,
+        StatusCode::OK,
+    )
+}
+
+#[utoipa::path(
+    delete,
+    path = "/v2/recipes/custom/{recipe_id}",
+    tag = "Recipes",
+    params(("recipe_id" = String, Path, description = "The recipe")),
+    responses(
+        (status = 204, description = "The recipe was removed"),
+        (status = 401, description = "The bearer token is missing or the session expired", body = String, content_type = "text/plain"),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    ),
+    security(("bearer" = []))
+)]
+async fn delete_custom_recipe(
+    State(state): State<ApiV2State>,
+    BearerToken(token): BearerToken,
+    Path(recipe_id): Path<String>,
+) -> ApiV2Result {
+    respond(
+        remove_custom_recipe(
+            v1_json(token, json!({ "recipe_id": recipe_id }))?,
+            state.client,
+        )
+        .await,
+        StatusCode::NO_CONTENT,
+    )
+}
+
+#[utoipa::path(
+    post,
+    path = "/v2/recipes/{recipe_id}/cooked",
+    tag = "Recipes",
+    params(("recipe_id" = String, Path, description = "The recipe")),
+    request_body = CookedRecipe,
+    responses(
+        (status = 201, description = "The recipe was recorded as cooked", body = RecordCookedRecipeResponse),
+        (status = 401, description = "The bearer token is missing or the session expired", body = String, content_type = "text/plain"),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    ),
+    security(("bearer" = []))
+)]
+async fn create_cooked_entry(
+    State(state): State<ApiV2State>,
+    BearerToken(token): BearerToken,
+    Path(recipe_id): Path<String>,
+    Json(body): Json<CookedRecipe>,
+) -> ApiV2Result {
+    let mut body = fields(body)?;
+    body.insert("recipe_id".to_string(), Value::String(recipe_id));
+    respond(
+        record_cooked_recipe(v1_payload(token, body), state.client).await,
+        StatusCode::CREATED,
+    )
+}
+
+#[utoipa::path(
+    get,
+    path = "/v2/recipes/history",
+    tag = "Recipes",
+    params(CookHistoryQuery),
+    responses(
+        (status = 200, description = "The recipes the user cooked", body = [CookHistoryEntry]),
+        (status = 401, description = "The bearer token is missing or the session expired", body = String, content_type = "text/plain"),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    ),
+    security(("bearer" = []))
+)]
+async fn list_cook_history(
+    State(state): State<ApiV2State>,
+    BearerToken(token): BearerToken,
+    Query(query): Query<CookHistoryQuery>,
+) -> ApiV2Result {
+    respond(
+        get_cook_history(v1_payload(token, fields(query)?), state.client).await,
+        StatusCode::OK,
+    )
+}
+
+#[utoipa::path(
+    put,
+    path = "/v2/recipes/{recipe_id}/rating",
+    tag = "Recipes",
+    params(("recipe_id" = String, Path, description = "The recipe")),
+    request_body = Rating,
+    responses(
+        (status = 200, description = "The rating was saved"),
+        (status = 401, description = "The bearer token is missing or the session expired", body = String, content_type = "text/plain"),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    ),
+    security(("bearer" = []))
+)]
+async fn replace_rating(
+    State(state): State<ApiV2State>,
+    BearerToken(token): BearerToken,
+    Path(recipe_id): Path<String>,
+    Json(body): Json<Rating>,
+) -> ApiV2Result {
+    let mut body = fields(body)?;
+    body.insert("recipe_id".to_string(), Value::String(recipe_id));
+    respond(
+        rate_recipe(v1_payload(token, body), state.client).await,
+        StatusCode::OK,
+    )
+}
+
+#[utoipa::path(
+    get,
+    path = "/v2/recipes/ratings",
+    tag = "Recipes",
+    responses(
+        (status = 200, description = "The ratings of the user", body = [RecipeRating]),
+        (status = 401, description = "The bearer token is missing or the session expired", body = String, content_type = "text/plain"),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    ),
+    security(("bearer" = []))
+)]
+async fn list_recipe_ratings(
+    State(state): State<ApiV2State>,
+    BearerToken(token): BearerToken,
+) -> ApiV2Result {
+    respond(
+        get_recipe_ratings(v1_json(token, json!({}))?, state.client).await,
+        StatusCode::OK,
+    )
+}
+
+// Ingredients
+
+#[utoipa::path(
+    get,
+    path = "/v2/ingredients",
+    tag = "Ingredients",
+    params(IngredientsQuery),
+    responses(
+        (status = 200, description = "The ingredients of the household", body = [Ingredient]),
+        (status = 401, description = "The bearer token is missing or the session expired", body = String, content_type = "text/plain"),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    ),
+    security(("bearer" = []))
+)]
+async fn list_ingredients(
+    State(state): State<ApiV2State>,
+    BearerToken(token): BearerToken,
+    Query(query): Query<IngredientsQuery>,
+) -> ApiV2Result {
+    respond(
+        get_ingredients(v1_payload(token, fields(query)?), state.client).await,
+        StatusCode::OK,
+    )
+}
+
+#[utoipa::path(
+    get,
+    path = "/v2/ingredients/search",
+    tag = "Ingredients",
+    params(SearchIngredientsQuery),
+    responses(
+        (status = 200, description = "The ingredients found", body = [Ingredient]),
+        (status = 401, description = "The bearer token is missing or the session expired", body = String, content_type = "text/plain"),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    ),
+    security(("bearer" = []))
+)]
+async fn find_ingredients(
+    State(state): State<ApiV2State>,
+    BearerToken(token): BearerToken,
+    Query(query): Query<SearchIngredientsQuery>,
+) -> ApiV2Result {
+    respond(
+        search_ingredients(v1_payload(token, fields(query)?), state.client).await,
+        StatusCode::OK,
+    )
+}
+
+#[utoipa::path(
+    post,
+    path = "/v2/ingredients",
+    tag = "Ingredients",
+    params(AddIngredientQuery),
+    request_body = NewIngredientPayload,
+    responses(
+        (status = 201, description = "The ingredient was added", body = AddIngredientResponse),
+        (status = 200, description = "The ingredient was merged into an existing one", body = AddIngredientResponse),
+        (status = 409, description = "The ingredient is a duplicate and `on_duplicate` is `Conflict`, the ingredients it could be merged into are returned", body = [Ingredient]),
+        (status = 401, description = "The bearer token is missing or the session expired", body = String, content_type = "text/plain"),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    ),
+    security(("bearer" = []))
+)]
+async fn create_ingredient(
+    State(state): State<ApiV2State>,
+    BearerToken(token): BearerToken,
+    Query(query): Query<AddIngredientQuery>,
+    Json(ingredient): Json<NewIngredientPayload>,
+) -> ApiV2Result {
+    let mut payload = fields(query)?;
+    payload.insert("ingredient".to_string(), json!(ingredient));
+    let added = add_ingredient(v1_payload(token, payload), state.client).await;
+    let status = match &added {
+        Ok(Json(AddIngredientResponse { merged: true, .. })) => StatusCode::OK,
+        _ => StatusCode::CREATED,
+    };
+    respond(added, status)
+}
+
+#[utoipa::path(
+    get,
+    path = "/v2/ingredients/{ingredient_id}",
+    tag = "Ingredients",
+    params(("ingredient_id" = Uuid, Path, description = "The ingredient")),
+    responses(
+        (status = 200, description = "The ingredient with its lots", body = Ingredient),
+        (status = 401, description = "The bearer token is missing or the session expired", body = String, content_type = "text/plain"),
+        (status = 404, description = "The ingredient doesn't exist or it's in the trash", body = String, content_type = "text/plain"),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    ),
+    security(("bearer" = []))
+)]
+async fn read_ingredient(
+    State(state): State<ApiV2State>,
+    BearerToken(token): BearerToken,
+    Path(ingredient_id): Path<Uuid>,
+) -> ApiV2Result {
+    respond(
+        get_ingredient_route(
+            v1_json(token, json!({ "ingredient_id": ingredient_id }))?,
+            state.client,
+        )
+        .await,
+        StatusCode::OK,
+    )
+}
+
+#[utoipa::path(
+    put,
+    path = "/v2/ingredients/{ingredient_id}",
+    tag = "Ingredients",
+    params(("ingredient_id" = Uuid, Path, description = "The ingredient")),
+    request_body = IngredientChanges,
+    responses(
+        (status = 200, description = "The ingredient was edited"),
+        (status = 401, description = "The bearer token is missing or the session expired", body = String, content_type = "text/plain"),
+        (status = 409, description = "The ingredient changed since the client got it, it's returned as it is now", body = Ingredient),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    ),
+    security(("bearer" = []))
+)]
+async fn update_ingredient(
+    State(state): State<ApiV2State>,
+    BearerToken(token): BearerToken,
+    Path(ingredient_id): Path<Uuid>,
+    Json(ingredient): Json<IngredientChanges>,
+) -> ApiV2Result {
+    let mut ingredient = fields(ingredient)?;
+    ingredient.insert("IngredientId".to_string(), json!(ingredient_id));
+    respond(
+        edit_ingredient(
+            v1_json(token, json!({ "ingredient": ingredient }))?,
+            state.client,
+        )
+        .await,
+        StatusCode::OK,
+    )
+}
+
+#[utoipa::path(
+    delete,
+    path = "/v2/ingredients/{ingredient_id}",
+    tag = "Ingredients",
+    params(("ingredient_id" = Uuid, Path, description = "The ingredient"), RemoveIngredientQuery),
+    responses(
+        (status = 204, description = "The ingredient was moved to the trash"),
+        (status = 401, description = "The bearer token is missing or the session expired", body = String, content_type = "text/plain"),
+        (status = 409, description = "The ingredient changed since the client got it, it's returned as it is now", body = Ingredient),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    ),
+    security(("bearer" = []))
+)]
+async fn delete_ingredient(
+    State(state): State<ApiV2State>,
+    BearerToken(token): BearerToken,
+    Path(ingredient_id): Path<Uuid>,
+    Query(query): Query<RemoveIngredientQuery>,
+) -> ApiV2Result {
+    let mut payload = fields(query)?;
+    payload.insert("ingredient_id".to_string(), json!(ingredient_id));
+    respond(
+        remove_ingredient(v1_payload(token, payload), state.client).await,
+        StatusCode::NO_CONTENT,
+    )
+}
+
+#[utoipa::path(
+    post,
+    path = "/v2/ingredients/move",
+    tag = "Ingredients",
+    request_body = IngredientsMove,
+    responses(
+        (status = 200, description = "The moved ingredients", body = [Ingredient]),
+        (status = 401, description = "The bearer token is missing or the session expired", body = String, content_type = "text/plain"),
+        (status = 409, description = "An ingredient changed since the client got it, it's returned as it is now", body = Ingredient),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    ),
+    security(("bearer" = []))
+)]
+async fn move_to_location(
+    State(state): State<ApiV2State>,
+    BearerToken(token): BearerToken,
+    Json(body): Json<IngredientsMove>,
+) -> ApiV2Result {
+    respond(
+        move_ingredients(
+            v1_payload(token, fields(body)?),
+            state.client,
+            state.transactions,
+        )
+        .await,
+        StatusCode::OK,
+    )
+}
+
+#[utoipa::path(
+    post,
+    path = "/v2/ingredients/{ingredient_id}/consume",
+    tag = "Ingredients",
+    params(("ingredient_id" = Uuid, Path, description = "The ingredient")),
+    request_body = Consumption,
+    responses(
+        (status = 200, description = "What is left of the ingredient", body = ConsumeIngredientResponse),
+        (status = 401, description = "The bearer token is missing or the session expired", body = String, content_type = "text/plain"),
+        (status = 409, description = "The ingredient changed since the client got it, it's returned as it is now", body = Ingredient),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    ),
+    security(("bearer" = []))
+)]
+async fn consume(
+    State(state): State<ApiV2State>,
+    BearerToken(token): BearerToken,
+    Path(ingredient_id): Path<Uuid>,
+    Json(body): Json<Consumption>,
+) -> ApiV2Result {
+    let mut body = fields(body)?;
+    body.insert("ingredient_id".to_string(), json!(ingredient_id));
+    respond(
+        consume_ingredient(v1_payload(token, body), state.client).await,
+        StatusCode::OK,
+    )
+}
+
+#[utoipa::path(
+    post,
+    path = "/v2/ingredients/bulk",
+    tag = "Ingredients",
+    request_body = [NewIngredientPayload],
+    responses(
+        (status = 201, description = "The ingredients were added", body = [BulkIngredientResult]),
+        (status = 401, description = "The bearer token is missing or the session expired", body = String, content_type = "text/plain"),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    ),
+    security(("bearer" = []))
+)]
+async fn create_ingredients(
+    State(state): State<ApiV2State>,
+    BearerToken(token): BearerToken,
+    Json(ingredients): Json<Vec<NewIngredientPayload>>,
+) -> ApiV2Result {
+    respond(
+        bulk_add_ingredients(
+            v1_json(token, json!({ "ingredients": ingredients }))?,
+            state.client,
+            state.transactions,
+        )
+        .await,
+        StatusCode::CREATED,
+    )
+}
+
+#[utoipa::path(
+    put,
+    path = "/v2/ingredients/bulk",
+    tag = "Ingredients",
+    request_body = [EditedIngredientPayload],
+    responses(
+        (status = 200, description = "The ingredients were edited", body = [BulkIngredientResult]),
+        (status = 401, description = "The bearer token is missing or the session expired", body = String, content_type = "text/plain"),
+        (status = 409, description = "An ingredient changed since the client got it, it's returned as it is now", body = Ingredient),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    ),
+    security(("bearer" = []))
+)]
+async fn update_ingredients(
+    State(state): State<ApiV2State>,
+    BearerToken(token): BearerToken,
+    Json(ingredients): Json<Vec<EditedIngredientPayload>>,
+) -> ApiV2Result {
+    respond(
+        bulk_edit_ingredients(
+            v1_json(token, json!({ "ingredients": ingredients }))?,
+            state.client,
+            state.transactions,
+        )
+        .await,
+        StatusCode::OK,
+    )
+}
+
+#[utoipa::path(
+    delete,
+    path = "/v2/ingredients/bulk",
+    tag = "Ingredients",
+    request_body = [RemovedIngredientPayload],
+    responses(
+        (status = 200, description = "The ingredients were moved to the trash", body = [BulkIngredientResult]),
+        (status = 401, description = "The bearer token is missing or the session expired", body = String, content_type = "text/plain"),
+        (status = 409, description = "An ingredient changed since the client got it, it's returned as it is now", body = Ingredient),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    ),
+    security(("bearer" = []))
+)]
+/// Returns the result of each removal, so it answers `200 OK`.
+async fn delete_ingredients(
+    State(state): State<ApiV2State>,
+    BearerToken(token): BearerToken,
+    Json(ingredients): Json<Vec<RemovedIngredientPayload>>,
+) -> ApiV2Result {
+    respond(
+        bulk_remove_ingredients(
+            v1_json(token, json!({ "ingredients": ingredients }))?,
+            state.client,
+            state.transactions,
+        )
+        .await,
+        StatusCode::OK,
+    )
+}
+
+#[utoipa::path(
+    get,
+    path = "/v2/ingredients/trash",
+    tag = "Ingredients",
+    responses(
+        (status = 200, description = "The ingredients in the trash", body = [TrashedIngredient]),
+        (status = 401, description = "The bearer token is missing or the session expired", body = String, content_type = "text/plain"),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    ),
+    security(("bearer" = []))
+)]
+async fn list_trash(
+    State(state): State<ApiV2State>,
+    BearerToken(token): BearerToken,
+) -> ApiV2Result {
+    respond(
+        get_trash(v1_json(token, json!({}))?, state.client, state.params).await,
+        StatusCode::OK,
+    )
+}
+
+#[utoipa::path(
+    get,
+    path = "/v2/ingredients/history",
+    tag = "Ingredients",
+    params(InventoryHistoryQuery),
+    responses(
+        (status = 200, description = "The history of the ingredients", body = [InventoryEvent]),
+        (status = 401, description = "The bearer token is missing or the session expired", body = String, content_type = "text/plain"),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    ),
+    security(("bearer" = []))
+)]
+async fn list_inventory_history(
+    State(state): State<ApiV2State>,
+    BearerToken(token): BearerToken,
+    Query(query): Query<InventoryHistoryQuery>,
+) -> ApiV2Result {
+    respond(
+        get_inventory_history(v1_payload(token, fields(query)?), state.client).await,
+        StatusCode::OK,
+    )
+}
+
+#[utoipa::path(
+    post,
+    path = "/v2/ingredients/{ingredient_id}/restore",
+    tag = "Ingredients",
+    params(("ingredient_id" = Uuid, Path, description = "The ingredient")),
+    responses(
+        (status = 200, description = "The ingredient was restored"),
+        (status = 401, description = "The bearer token is missing or the session expired", body = String, content_type = "text/plain"),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    ),
+    security(("bearer" = []))
+)]
+async fn restore(
+    State(state): State<ApiV2State>,
+    BearerToken(token): BearerToken,
+    Path(ingredient_id): Path<Uuid>,
+) -> ApiV2Result {
+    respond(
+        restore_ingredient(
+            v1_json(token, json!({ "ingredient_id": ingredient_id }))?,
+            state.client,
+        )
+        .await,
+        StatusCode::OK,
+    )
+}
+
+// Storage locations
+
+#[utoipa::path(
+    get,
+    path = "/v2/locations",
+    tag = "Locations",
+    responses(
+        (status = 200, description = "The storage locations of the household", body = [StorageLocation]),
+        (status = 401, description = "The bearer token is missing or the session expired", body = String, content_type = "text/plain"),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    ),
+    security(("bearer" = []))
+)]
+async fn list_locations(
+    State(state): State<ApiV2State>,
+    BearerToken(token): BearerToken,
+) -> ApiV2Result {
+    respond(
+        get_storage_locations(v1_json(token, json!({}))?, state.client).await,
+        StatusCode::OK,
+    )
+}
+
+#[utoipa::path(
+    post,
+    path = "/v2/locations",
+    tag = "Locations",
+    request_body = NewLocation,
+    responses(
+        (status = 201, description = "The location was added", body = AddStorageLocationResponse),
+        (status = 401, description = "The bearer token is missing or the session expired", body = String, content_type = "text/plain"),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    ),
+    security(("bearer" = []))
+)]
+async fn create_location(
+    State(state): State<ApiV2State>,
+    BearerToken(token): BearerToken,
+    Json(body): Json<NewLocation>,
+) -> ApiV2Result {
+    respond(
+        add_storage_location(v1_payload(token, fields(body)?), state.client).await,
+        StatusCode::CREATED,
+    )
+}
+
+#[utoipa::path(
+    delete,
+    path = "/v2/locations/{location_id}",
+    tag = "Locations",
+    params(("location_id" = Uuid, Path, description = "The storage location")),
+    responses(
+        (status = 204, description = "The location was removed"),
+        (status = 401, description = "The bearer token is missing or the session expired", body = String, content_type = "text/plain"),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    ),
+    security(("bearer" = []))
+)]
+async fn delete_location(
+    State(state): State<ApiV2State>,
+    BearerToken(token): BearerToken,
+    Path(location_id): Path<Uuid>,
+) -> ApiV2Result {
+    respond(
+        remove_storage_location(
+            v1_json(token, json!({ "location_id": location_id }))?,
+            state.client,
+        )
+        .await,
+        StatusCode::NO_CONTENT,
+    )
+}
+
+// Sync and statistics
+
+#[utoipa::path(
+    post,
+    path = "/v2/sync",
+    tag = "Sync",
+    request_body = SyncRequest,
+    responses(
+        (status = 200, description = "What changed since the cursor", body = SyncChanges),
+        (status = 401, description = "The bearer token is missing or the session expired", body = String, content_type = "text/plain"),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    ),
+    security(("bearer" = []))
+)]
+async fn sync(
+    State(state): State<ApiV2State>,
+    BearerToken(token): BearerToken,
+    Json(body): Json<SyncRequest>,
+) -> ApiV2Result {
+    respond(
+        sync_changes(
+            v1_payload(token, fields(body)?),
+            state.client,
+            state.transactions,
+        )
+        .await,
+        StatusCode::OK,
+    )
+}
+
+#[utoipa::path(
+    get,
+    path = "/v2/changes",
+    tag = "Sync",
+    responses(
+        (status = 200, description = "Server-sent events with the changes, until the session ends or the household is no longer selected", body = ChangeNotification, content_type = "text/event-stream"),
+        (status = 401, description = "The bearer token is missing or the session expired", body = String, content_type = "text/plain"),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    ),
+    security(("bearer" = []))
+)]
+/// The stream of `POST /v1/changes` as a GET, so a browser `EventSource` can connect to it.
+async fn changes(State(state): State<ApiV2State>, BearerToken(token): BearerToken) -> ApiV2Result {
+    respond(
+        stream_changes(v1_json(token, json!({}))?, state.client, state.changes).await,
+        StatusCode::OK,
+    )
+}
+
+#[utoipa::path(
+    get,
+    path = "/v2/stats",
+    tag = "Statistics",
+    params(StatsQuery),
+    responses(
+        (status = 200, description = "The statistics of the household", body = WasteStats),
+        (status = 401, description = "The bearer token is missing or the session expired", body = String, content_type = "text/plain"),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    ),
+    security(("bearer" = []))
+)]
+async fn stats(
+    State(state): State<ApiV2State>,
+    BearerToken(token): BearerToken,
+    Query(query): Query<StatsQuery>,
+) -> ApiV2Result {
+    respond(
+        get_stats(v1_payload(token, fields(query)?), state.client).await,
+        StatusCode::OK,
+    )
+}
+
+// Shopping list
+
+#[utoipa::path(
+    get,
+    path = "/v2/shopping-list",
+    tag = "Shopping list",
+    responses(
+        (status = 200, description = "The items of the shopping list", body = [ShoppingItem]),
+        (status = 401, description = "The bearer token is missing or the session expired", body = String, content_type = "text/plain"),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    ),
+    security(("bearer" = []))
+)]
+async fn list_shopping_items(
+    State(state): State<ApiV2State>,
+    BearerToken(token): BearerToken,
+) -> ApiV2Result {
+    respond(
+        get_shopping_list(v1_json(token, json!({}))?, state.client).await,
+        StatusCode::OK,
+    )
+}
+
+#[utoipa::path(
+    post,
+    path = "/v2/shopping-list/items",
+    tag = "Shopping list",
+    request_body = NewShoppingItemPayload,
+    responses(
+        (status = 201, description = "The item was added or merged", body = AddShoppingItemResponse),
+        (status = 401, description = "The bearer token is missing or the session expired", body = String, content_type = "text/plain"),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    ),
+    security(("bearer" = []))
+)]
+async fn create_shopping_item(
+    State(state): State<ApiV2State>,
+    BearerToken(token): BearerToken,
+    Json(item): Json<NewShoppingItemPayload>,
+) -> ApiV2Result {
+    respond(
+        add_shopping_item(v1_json(token, json!({ "item": item }))?, state.client).await,
+        StatusCode::CREATED,
+    )
+}
+
+#[utoipa::path(
+    put,
+    path = "/v2/shopping-list/items/{item_id}",
+    tag = "Shopping list",
+    params(("item_id" = Uuid, Path, description = "The item of the shopping list")),
+    request_body = ShoppingItemChanges,
+    responses(
+        (status = 200, description = "The item was edited"),
+        (status = 401, description = "The bearer token is missing or the session expired", body = String, content_type = "text/plain"),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    ),
+    security(("bearer" = []))
+)]
+async fn update_shopping_item(
+    State(state): State<ApiV2State>,
+    BearerToken(token): BearerToken,
+    Path(item_id): Path<Uuid>,
+    Json(item): Json<ShoppingItemChanges>,
+) -> ApiV2Result {
+    let mut item = fields(item)?;
+    item.insert("ItemId".to_string(), json!(item_id));
+    respond(
+        edit_shopping_item(v1_json(token, json!({ "item": item }))?, state.client).await,
+        StatusCode::OK,
+    )
+}
+
+#[utoipa::path(
+    delete,
+    path = "/v2/shopping-list/items/{item_id}",
+    tag = "Shopping list",
+    params(("item_id" = Uuid, Path, description = "The item of the shopping list")),
+    responses(
+        (status = 204, description = "The item was removed"),
+        (status = 401, description = "The bearer token is missing or the session expired", body = String, content_type = "text/plain"),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    ),
+    security(("bearer" = []))
+)]
+async fn delete_shopping_item(
+    State(state): State<ApiV2State>,
+    BearerToken(token): BearerToken,
+    Path(item_id): Path<Uuid>,
+) -> ApiV2Result {
+    respond(
+        remove_shopping_item(v1_json(token, json!({ "item_id": item_id }))?, state.client).await,
+        StatusCode::NO_CONTENT,
+    )
+}
+
+#[utoipa::path(
+    post,
+    path = "/v2/shopping-list/recipes",
+    tag = "Shopping list",
+    request_body = RecipeToBuy,
+    responses(
+        (status = 201, description = "The items that were missing", body = [AddedShoppingItem]),
+        (status = 401, description = "The bearer token is missing or the session expired", body = String, content_type = "text/plain"),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    ),
+    security(("bearer" = []))
+)]
+async fn create_recipe_items(
+    State(state): State<ApiV2State>,
+    BearerToken(token): BearerToken,
+    Json(body): Json<RecipeToBuy>,
+) -> ApiV2Result {
+    respond(
+        add_recipe_to_shopping_list(v1_payload(token, fields(body)?), state.client).await,
+        StatusCode::CREATED,
+    )
+}
+
+#[utoipa::path(
+    post,
+    path = "/v2/shopping-list/bought",
+    tag = "Shopping list",
+    responses(
+        (status = 200, description = "The ingredients created from the checked items", body = [Ingredient]),
+        (status = 401, description = "The bearer token is missing or the session expired", body = String, content_type = "text/plain"),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    ),
+    security(("bearer" = []))
+)]
+async fn buy_checked_items(
+    State(state): State<ApiV2State>,
+    BearerToken(token): BearerToken,
+) -> ApiV2Result {
+    respond(
+        buy_shopping_items(v1_json(token, json!({}))?, state.client).await,
+        StatusCode::OK,
+    )
+}
+
+// Meal plan
+
+#[utoipa::path(
+    get,
+    path = "/v2/meal-plan",
+    tag = "Meal plan",
+    params(DateRangeQuery),
+    responses(
+        (status = 200, description = "The planned meals", body = [MealPlanEntry]),
+        (status = 401, description = "The bearer token is missing or the session expired", body = String, content_type = "text/plain"),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    ),
+    security(("bearer" = []))
+)]
+async fn list_meals(
+    State(state): State<ApiV2State>,
+    BearerToken(token): BearerToken,
+    Query(query): Query<DateRangeQuery>,
+) -> ApiV2Result {
+    respond(
+        get_meal_plan(v1_payload(token, fields(query)?), state.client).await,
+        StatusCode::OK,
+    )
+}
+
+#[utoipa::path(
+    post,
+    path = "/v2/meal-plan",
+    tag = "Meal plan",
+    request_body = NewMeal,
+    responses(
+        (status = 201, description = "The meal was planned", body = AssignMealResponse),
+        (status = 401, description = "The bearer token is missing or the session expired", body = String, content_type = "text/plain"),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    ),
+    security(("bearer" = []))
+)]
+async fn create_meal(
+    State(state): State<ApiV2State>,
+    BearerToken(token): BearerToken,
+    Json(body): Json<NewMeal>,
+) -> ApiV2Result {
+    respond(
+        assign_meal(v1_payload(token, fields(body)?), state.client).await,
+        StatusCode::CREATED,
+    )
+}
+
+#[utoipa::path(
+    get,
+    path = "/v2/meal-plan/requirements",
+    tag = "Meal plan",
+    params(DateRangeQuery),
+    responses(
+        (status = 200, description = "The ingredients needed for the planned meals", body = MealPlanRequirementsResponse),
+        (status = 401, description = "The bearer token is missing or the session expired", body = String, content_type = "text/plain"),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    ),
+    security(("bearer" = []))
+)]
+async fn list_meal_requirements(
+    State(state): State<ApiV2State>,
+    BearerToken(token): BearerToken,
+    Query(query): Query<DateRangeQuery>,
+) -> ApiV2Result {
+    respond(
+        meal_plan_requirements(v1_payload(token, fields(query)?), state.client).await,
+        StatusCode::OK,
+    )
+}
+
+#[utoipa::path(
+    delete,
+    path = "/v2/meal-plan/{plan_id}",
+    tag = "Meal plan",
+    params(("plan_id" = Uuid, Path, description = "The planned meal")),
+    responses(
+        (status = 204, description = "The meal was removed from the plan"),
+        (status = 401, description = "The bearer token is missing or the session expired", body = String, content_type = "text/plain"),
+        (status = "4XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+        (status = "5XX", description = "The errors of the v1 route that made the request fail", body = String, content_type = "text/plain"),
+    ),
+    security(("bearer" = []))
+)]
+async fn delete_meal(
+    State(state): State<ApiV2State>,
+    BearerToken(token): BearerToken,
+    Path(plan_id): Path<Uuid>,
+) -> ApiV2Result {
+    respond(
+        remove_meal(v1_json(token, json!({ "plan_id": plan_id }))?, state.client).await,
+        StatusCode::NO_CONTENT,
+    )
+}
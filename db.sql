@@ -3,6 +3,8 @@ DROP DATABASE IF EXISTS smart_fridge;
 CREATE DATABASE smart_fridge;
 \c smart_fridge;
 
+-- Base schema, the changes made since are in backend/migrations and the backend applies them when it starts.
+
 CREATE TABLE sf_user (
 	user_id varchar(64) UNIQUE NOT NULL,
 	username varchar(64) NOT NULL,